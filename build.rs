@@ -0,0 +1,8 @@
+fn main() {
+    // the lv2 host links lilv, found through pkg-config so one installed outside the default
+    // library path links too and a missing one fails here rather than at the link step
+    #[cfg(feature = "lv2")]
+    if let Err(e) = pkg_config::Config::new().probe("lilv-0") {
+        panic!("the lv2 feature needs lilv-0: {e}");
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::font;
+use crate::render::Renderer;
+
+// trials unless --trials says otherwise, enough for 12 right to mean something
+pub const DEFAULT_TRIALS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Source {
+    A,
+    B,
+    X,
+}
+
+// a blind ABX test between the main file (A) and the comparison file (B): every trial X is one
+// of them picked at random, the listener switches between A, B and X as often as they like and
+// then says which one X was. Nothing on screen gives X away until the last vote is in
+pub struct Abx {
+    // for every trial whether X is B, decided up front
+    x_is_b: Vec<bool>,
+    // whether each answered trial was answered right
+    answers: Vec<bool>,
+    pub hearing: Source,
+}
+
+impl Abx {
+    pub fn new(trials: usize) -> Self {
+        Self {
+            x_is_b: coin_flips(trials.max(1)),
+            answers: Vec::new(),
+            hearing: Source::A,
+        }
+    }
+
+    // whether the comparison file should be playing for what's being listened to
+    pub fn plays_b(&self) -> bool {
+        match self.hearing {
+            Source::A => false,
+            Source::B => true,
+            Source::X => self
+                .x_is_b
+                .get(self.answers.len())
+                .copied()
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.answers.len() == self.x_is_b.len()
+    }
+
+    // the listener's answer for the current trial, the next one starts back on A
+    pub fn vote(&mut self, says_b: bool) {
+        if self.done() {
+            return;
+        }
+        self.answers.push(self.x_is_b[self.answers.len()] == says_b);
+        self.hearing = Source::A;
+    }
+
+    fn correct(&self) -> usize {
+        self.answers.iter().filter(|&&right| right).count()
+    }
+
+    // the trials answered so far, how many of them right and how likely that is by guessing
+    pub fn results(&self) -> String {
+        let trials = self.answers.len();
+        let correct = self.correct();
+        let p = guess_probability(trials, correct);
+        let verdict = if trials == 0 {
+            "no trials answered"
+        } else if p < 0.05 {
+            "a difference was heard (p < 0.05)"
+        } else {
+            "no reliable difference heard"
+        };
+        format!("abx: {correct}/{trials} correct, p = {p:.4}, {verdict}")
+    }
+}
+
+// chance of getting at least `correct` of `trials` right by flipping a coin, the one-sided
+// binomial test every ABX tool reports
+fn guess_probability(trials: usize, correct: usize) -> f64 {
+    // C(trials, k) / 2^trials summed from k = correct up, built up term by term so nothing
+    // overflows
+    let mut term = 0.5f64.powi(trials as i32);
+    let mut p = 0.0;
+    for k in 0..=trials {
+        if k >= correct {
+            p += term;
+        }
+        term = term * (trials - k) as f64 / (k + 1) as f64;
+    }
+    p.min(1.0)
+}
+
+// xorshift seeded from the clock, nothing here needs to be better than the listener's ears
+fn coin_flips(count: usize) -> Vec<bool> {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |time| time.as_nanos() as u64)
+        | 1;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state >> 63 == 1
+        })
+        .collect()
+}
+
+// which trial it is and what's being listened to, the results once every trial is answered
+pub fn draw(canvas: &mut impl Renderer, abx: &Abx) {
+    let lines = if abx.done() {
+        vec![abx.results()]
+    } else {
+        let hearing = match abx.hearing {
+            Source::A => "A",
+            Source::B => "B",
+            Source::X => "X",
+        };
+        vec![
+            format!(
+                "abx trial {}/{}  hearing {hearing}",
+                abx.answers.len() + 1,
+                abx.x_is_b.len()
+            ),
+            "1 A  2 B  3 X".to_string(),
+            "shift+1 X is A  shift+2 X is B".to_string(),
+        ]
+    };
+
+    // below the playback status line
+    let top = 36;
+    let box_width = lines
+        .iter()
+        .map(|line| font::text_width(line, 2))
+        .max()
+        .unwrap_or(0)
+        + 8;
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(Rect::new(
+        4,
+        top,
+        box_width as u32,
+        lines.len() as u32 * 20 + 8,
+    ));
+    canvas.set_blend(false);
+    canvas.set_color(Color::RGB(255, 255, 255));
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(canvas, 8, top + 4 + i as i32 * 20, 2, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guessing_probability_matches_the_binomial_table() {
+        // the classic thresholds: 12 of 16 and 9 of 10 are just under 5%
+        assert!((guess_probability(16, 12) - 0.0384).abs() < 1e-4);
+        assert!((guess_probability(10, 9) - 0.0107).abs() < 1e-4);
+        assert_eq!(guess_probability(16, 0), 1.0);
+    }
+}
@@ -0,0 +1,535 @@
+use std::error;
+use std::io::{self, BufRead, Write};
+use std::ops::ControlFlow;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use sdl2::audio::AudioSpecDesired;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::notes::Notes;
+use crate::player::{PlaybackEvent, Player};
+use crate::{
+    AUDIO_BUFFER_FRAMES, MAX_VOLUME_DB, MIN_VOLUME_DB, dynamics, i18n, loudness, meter, timecode,
+    track_name, video,
+};
+use wave::WavFile;
+use wave::progress::CancellationToken;
+use wave::raw::RawSpec;
+
+// `wave --accessible`: the player without a window, for screen readers and braille displays.
+// Commands are typed a line at a time and every answer is a plain sentence on a line of its
+// own, times read out as minutes and seconds instead of clock digits. Nothing redraws or
+// moves the cursor, so a screen reader only reads what's new
+
+// each command with the key of what it does
+const HELP: &[(&str, &str)] = &[
+    ("p, play", "accessible.help.play"),
+    ("s, status", "accessible.help.status"),
+    ("l, levels", "accessible.help.levels"),
+    ("seek SECONDS", "accessible.help.seek"),
+    ("go TIME", "accessible.help.go"),
+    ("m, mark TEXT", "accessible.help.mark"),
+    ("markers", "accessible.help.markers"),
+    ("jump N", "accessible.help.jump"),
+    ("vol DB", "accessible.help.vol"),
+    ("mute", "accessible.help.mute"),
+    ("loop", "accessible.help.loop"),
+    ("speed RATE", "accessible.help.speed"),
+    ("info", "accessible.help.info"),
+    ("loudness", "accessible.help.loudness"),
+    ("n, next", "accessible.help.next"),
+    ("b, back", "accessible.help.back"),
+    ("h, help", "accessible.help.help"),
+    ("q, quit", "accessible.help.quit"),
+];
+
+// how long the loop waits for a command before looking at playback again
+const POLL: Duration = Duration::from_millis(50);
+
+enum Next {
+    Track(usize),
+    Quit,
+}
+
+pub fn run(
+    playlist: &[String],
+    raw: Option<RawSpec>,
+    args: &Args,
+    config: &Config,
+) -> Result<(), Box<dyn error::Error + 'static>> {
+    let sdl_context = sdl2::init()?;
+    let audio_subsystem = sdl_context.audio()?;
+    // stdin is read on a thread of its own so playback can move on to the next file while
+    // nothing is typed
+    let commands = read_commands();
+    say(i18n::text("accessible.welcome"));
+
+    let mut gain = match args.value("gain") {
+        Some(gain) => gain
+            .parse::<f32>()
+            .map_err(|_| i18n::fill("accessible.bad-gain", &[("value", gain.to_string())]))?,
+        None => 0.0,
+    };
+    let mut loop_file = args.flag("loop");
+    let mut track = 0;
+    loop {
+        let path = playlist[track].as_str();
+        let wav = match raw {
+            Some(spec) => WavFile::open_raw(
+                path,
+                spec,
+                &mut |_| ControlFlow::Continue(()),
+                &CancellationToken::new(),
+            )?,
+            None if video::is_video(path) => video::open(path)?,
+            None => WavFile::open(path)?,
+        };
+        say(&i18n::fill(
+            "accessible.file",
+            &[
+                ("track", (track + 1).to_string()),
+                ("tracks", playlist.len().to_string()),
+                ("name", track_name(path, &wav)),
+                ("duration", spoken_time(duration(&wav))),
+            ],
+        ));
+
+        let (mut player, callback) = Player::new(&wav, None);
+        player.set_output_delay(config.output_delay);
+        player.set_gain(10f32.powf(gain / 20.0));
+        player.set_loop_file(loop_file);
+        let events = player.subscribe();
+        player.play();
+        let desired_spec = AudioSpecDesired {
+            freq: Some(wav.header.sample_rate as i32),
+            channels: Some(wav.header.num_channels as u8),
+            samples: Some(AUDIO_BUFFER_FRAMES),
+        };
+        let device = audio_subsystem.open_playback(None, &desired_spec, |_spec| callback)?;
+        device.resume();
+
+        let mut session = Session {
+            path,
+            wav: &wav,
+            player,
+            notes: Notes::load(path, &wav),
+            handed_samples: 0,
+        };
+        let next = session.run(&events, &commands, playlist.len(), track);
+        gain = dynamics::to_db(session.player.settings().gain);
+        loop_file = session.player.settings().loop_file;
+        match next {
+            Next::Track(next) => track = next,
+            Next::Quit => return Ok(()),
+        }
+    }
+}
+
+// what's needed while one file plays
+struct Session<'a> {
+    path: &'a str,
+    wav: &'a WavFile,
+    player: Player,
+    notes: Notes,
+    // the position the callback last reported
+    handed_samples: usize,
+}
+
+impl Session<'_> {
+    fn run(
+        &mut self,
+        events: &Receiver<PlaybackEvent>,
+        commands: &Receiver<String>,
+        tracks: usize,
+        track: usize,
+    ) -> Next {
+        loop {
+            self.player.pump();
+            for event in events.try_iter() {
+                match event {
+                    PlaybackEvent::PositionChanged(position) => self.handed_samples = position,
+                    PlaybackEvent::EndOfStream if track + 1 < tracks => {
+                        say(i18n::text("accessible.end-of-file"));
+                        return Next::Track(track + 1);
+                    }
+                    PlaybackEvent::EndOfStream => say(i18n::text("accessible.end-of-last-file")),
+                    PlaybackEvent::DeviceChanged => say(i18n::text("accessible.device-changed")),
+                    _ => {}
+                }
+            }
+            let line = match commands.recv_timeout(POLL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                // stdin closed, nothing more can be asked for
+                Err(RecvTimeoutError::Disconnected) => return Next::Quit,
+            };
+            let (command, argument) = match line.trim().split_once(' ') {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line.trim(), ""),
+            };
+            match command {
+                "" => {}
+                "q" | "quit" => return Next::Quit,
+                "n" | "next" if track + 1 < tracks => return Next::Track(track + 1),
+                "n" | "next" => say(i18n::text("accessible.last-file")),
+                "b" | "back" if track > 0 => return Next::Track(track - 1),
+                "b" | "back" => say(i18n::text("accessible.first-file")),
+                command => {
+                    if let Err(e) = self.command(command, argument) {
+                        say(&e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str, argument: &str) -> Result<(), String> {
+        let sample_rate = self.wav.header.sample_rate;
+        let channels = self.wav.header.num_channels.max(1) as usize;
+        let frame = self.player.audible_position(self.handed_samples) / channels;
+        match command {
+            "h" | "help" => {
+                for (command, what) in HELP {
+                    say(&format!("{command}: {}", i18n::text(what)));
+                }
+            }
+            "p" | "play" if self.player.settings().playing => {
+                self.player.pause();
+                say(&i18n::fill(
+                    "accessible.paused-at",
+                    &[("time", spoken_frame(frame, sample_rate))],
+                ));
+            }
+            "p" | "play" => {
+                self.player.play();
+                say(i18n::text("accessible.playing"));
+            }
+            "s" | "status" => say(&self.status(frame)),
+            "l" | "levels" => {
+                let levels = meter::Levels::at(self.wav, frame * channels);
+                let names = self.wav.header.speakers();
+                for channel in 0..channels {
+                    let name = match names.get(channel) {
+                        Some(name) => name.to_string(),
+                        None => i18n::fill(
+                            "accessible.channel",
+                            &[("channel", (channel + 1).to_string())],
+                        ),
+                    };
+                    let key = if levels.clipped[channel] {
+                        "accessible.level-clipping"
+                    } else {
+                        "accessible.level"
+                    };
+                    say(&i18n::fill(
+                        key,
+                        &[
+                            ("name", name),
+                            ("peak", spoken_db(levels.peak_db[channel])),
+                            ("average", spoken_db(levels.rms_db[channel])),
+                        ],
+                    ));
+                }
+            }
+            "seek" => {
+                let seconds: f64 = argument.parse().map_err(|_| {
+                    i18n::fill("accessible.bad-seek", &[("value", argument.to_string())])
+                })?;
+                let frames = (seconds * sample_rate as f64).round() as isize;
+                self.seek(frame.saturating_add_signed(frames));
+            }
+            "go" => {
+                let frame = timecode::parse(argument, sample_rate)?;
+                self.seek(frame);
+            }
+            "m" | "mark" => {
+                let text = if argument.is_empty() {
+                    i18n::text("accessible.marker")
+                } else {
+                    argument
+                };
+                self.notes
+                    .add(frame, text, sample_rate)
+                    .map_err(|e| e.to_string())?;
+                say(&i18n::fill(
+                    "accessible.marked",
+                    &[("time", spoken_frame(frame, sample_rate))],
+                ));
+            }
+            "markers" => {
+                let markers = self.markers();
+                if markers.is_empty() {
+                    say(i18n::text("accessible.no-markers"));
+                }
+                for (i, (frame, label)) in markers.iter().enumerate() {
+                    say(&i18n::fill(
+                        "accessible.marker-at",
+                        &[
+                            ("number", (i + 1).to_string()),
+                            ("label", label.clone()),
+                            ("time", spoken_frame(*frame, sample_rate)),
+                        ],
+                    ));
+                }
+            }
+            "jump" => {
+                let markers = self.markers();
+                let (frame, label) = argument
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| markers.get(n.checked_sub(1)?))
+                    .ok_or_else(|| {
+                        i18n::fill(
+                            "accessible.no-marker",
+                            &[
+                                ("number", argument.to_string()),
+                                ("count", markers.len().to_string()),
+                            ],
+                        )
+                    })?;
+                say(label);
+                self.seek(*frame);
+            }
+            "vol" => {
+                let db: f32 = argument.parse().map_err(|_| {
+                    i18n::fill("accessible.bad-volume", &[("value", argument.to_string())])
+                })?;
+                let gain = dynamics::to_db(self.player.settings().gain) + db;
+                let gain = gain.clamp(MIN_VOLUME_DB, MAX_VOLUME_DB);
+                self.player.set_gain(10f32.powf(gain / 20.0));
+                say(&i18n::fill(
+                    "accessible.volume",
+                    &[("level", spoken_db(gain))],
+                ));
+            }
+            "mute" => {
+                let muted = !self.player.settings().muted;
+                self.player.set_muted(muted);
+                say(i18n::text(if muted {
+                    "accessible.muted"
+                } else {
+                    "accessible.unmuted"
+                }));
+            }
+            "loop" => {
+                let looping = !self.player.settings().loop_file;
+                self.player.set_loop_file(looping);
+                say(i18n::text(if looping {
+                    "accessible.looping-file"
+                } else {
+                    "accessible.not-looping"
+                }));
+            }
+            "speed" => {
+                let speed: f64 = argument
+                    .parse()
+                    .ok()
+                    .filter(|speed: &f64| *speed != 0.0 && speed.abs() <= 4.0)
+                    .ok_or_else(|| {
+                        i18n::fill("accessible.bad-speed", &[("value", argument.to_string())])
+                    })?;
+                self.player.set_speed(speed);
+                say(&i18n::fill(
+                    "accessible.speed",
+                    &[("speed", speed.to_string())],
+                ));
+            }
+            "info" => self.info(),
+            "loudness" => {
+                let loudness = loudness::integrated(&self.wav.data, channels, sample_rate);
+                say(&match loudness {
+                    Some(lufs) => {
+                        i18n::fill("accessible.loudness", &[("lufs", format!("{lufs:.1}"))])
+                    }
+                    None => i18n::text("accessible.no-loudness").to_string(),
+                });
+            }
+            command => {
+                return Err(i18n::fill(
+                    "accessible.no-command",
+                    &[("command", command.to_string())],
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn seek(&mut self, frame: usize) {
+        let channels = self.wav.header.num_channels.max(1) as usize;
+        let last = (self.wav.data.len() / channels).saturating_sub(1);
+        let frame = frame.min(last);
+        self.player.seek(frame * channels);
+        self.handed_samples = frame * channels;
+        say(&i18n::fill(
+            "accessible.at",
+            &[("time", spoken_frame(frame, self.wav.header.sample_rate))],
+        ));
+    }
+
+    fn status(&self, frame: usize) -> String {
+        let settings = self.player.settings();
+        let key = if settings.playing {
+            "accessible.status-playing"
+        } else {
+            "accessible.status-paused"
+        };
+        let mut parts = vec![i18n::fill(
+            key,
+            &[
+                ("time", spoken_frame(frame, self.wav.header.sample_rate)),
+                ("duration", spoken_time(duration(self.wav))),
+            ],
+        )];
+        if settings.muted {
+            parts.push(i18n::text("accessible.muted").to_string());
+        } else if settings.gain != 1.0 {
+            parts.push(i18n::fill(
+                "accessible.volume",
+                &[("level", spoken_db(dynamics::to_db(settings.gain)))],
+            ));
+        }
+        if settings.speed != 1.0 {
+            parts.push(i18n::fill(
+                "accessible.speed",
+                &[("speed", settings.speed.to_string())],
+            ));
+        }
+        if settings.loop_file {
+            parts.push(i18n::text("accessible.looping").to_string());
+        }
+        parts.join(", ")
+    }
+
+    fn info(&self) {
+        let header = &self.wav.header;
+        say(&track_name(self.path, self.wav));
+        say(&i18n::fill(
+            "accessible.format",
+            &[
+                ("channels", header.num_channels.to_string()),
+                ("rate", header.sample_rate.to_string()),
+                ("bits", header.bits_per_sample.to_string()),
+                ("duration", spoken_time(duration(self.wav))),
+            ],
+        ));
+        let metadata = &self.wav.metadata;
+        for (name, value) in [
+            ("metadata.artist", &metadata.artist),
+            ("metadata.album", &metadata.album),
+            ("metadata.comment", &metadata.comment),
+        ] {
+            if let Some(value) = value {
+                say(&format!("{}: {value}", i18n::text(name)));
+            }
+        }
+        let diagnostics = self.wav.diagnostics();
+        if diagnostics.is_empty() {
+            say(i18n::text("accessible.follows-spec"));
+        }
+        for diagnostic in diagnostics {
+            say(&diagnostic.to_string());
+        }
+    }
+
+    // the file's cue points and the notes, in the order they come up
+    fn markers(&self) -> Vec<(usize, String)> {
+        let cues = self.wav.cues().iter().map(|cue| {
+            let label = cue.label.clone();
+            (
+                cue.frame,
+                label
+                    .unwrap_or_else(|| i18n::fill("accessible.cue", &[("id", cue.id.to_string())])),
+            )
+        });
+        let notes = (self.notes.notes.iter()).map(|note| (note.frame, note.text.clone()));
+        let mut markers: Vec<_> = cues.chain(notes).collect();
+        markers.sort_by_key(|(frame, _)| *frame);
+        markers
+    }
+}
+
+fn read_commands() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// a line for the screen reader, flushed right away in case stdout isn't a terminal
+fn say(text: &str) {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{text}").ok();
+    stdout.flush().ok();
+}
+
+fn duration(wav: &WavFile) -> f64 {
+    let frames = wav.data.len() / wav.header.num_channels.max(1) as usize;
+    frames as f64 / wav.header.sample_rate.max(1) as f64
+}
+
+fn spoken_frame(frame: usize, sample_rate: u32) -> String {
+    spoken_time(frame as f64 / sample_rate.max(1) as f64)
+}
+
+// `1 hour 2 minutes 3.4 seconds`, leaving out what's zero and tenths past a minute
+fn spoken_time(seconds: f64) -> String {
+    let tenths = (seconds.max(0.0) * 10.0).round() as u64;
+    let (hours, minutes) = (tenths / 36_000, tenths / 600 % 60);
+    let seconds = (tenths % 600) as f64 / 10.0;
+    // `unit` is the key of one, `unit`s of more
+    let unit = |count: f64, unit: &str| match count {
+        1.0 => i18n::text(unit).to_string(),
+        count => i18n::fill(&format!("{unit}s"), &[("count", count.to_string())]),
+    };
+    let mut parts = Vec::new();
+    if hours > 0 {
+        parts.push(unit(hours as f64, "accessible.hour"));
+    }
+    if minutes > 0 {
+        parts.push(unit(minutes as f64, "accessible.minute"));
+    }
+    if hours + minutes > 0 {
+        if seconds.floor() > 0.0 {
+            parts.push(unit(seconds.floor(), "accessible.second"));
+        }
+    } else {
+        parts.push(unit(seconds, "accessible.second"));
+    }
+    parts.join(" ")
+}
+
+fn spoken_db(db: f32) -> String {
+    if db.is_infinite() {
+        return i18n::text("accessible.silent").to_string();
+    }
+    let db = (db * 10.0).round() / 10.0;
+    if db < 0.0 {
+        i18n::fill("accessible.minus-db", &[("db", (-db).to_string())])
+    } else {
+        i18n::fill("accessible.db", &[("db", db.to_string())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_read_as_words() {
+        assert_eq!(spoken_time(0.0), "0 seconds");
+        assert_eq!(spoken_time(1.0), "1 second");
+        assert_eq!(spoken_time(12.34), "12.3 seconds");
+        assert_eq!(spoken_time(61.5), "1 minute 1 second");
+        assert_eq!(spoken_time(120.0), "2 minutes");
+        assert_eq!(spoken_time(3725.0), "1 hour 2 minutes 5 seconds");
+    }
+}
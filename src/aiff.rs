@@ -0,0 +1,342 @@
+// https://www.mmsp.ece.mcgill.ca/Documents/AudioFormats/AIFF/Docs/AIFF-1.3.pdf and the AIFF-C
+// draft next to it. The same idea as RIFF/WAVE with everything big-endian: a FORM of chunks,
+// COMM describing the samples and SSND holding them
+
+use std::io::{self, BufReader, Read};
+use std::{error, str};
+
+use crate::cue::Cue;
+use crate::metadata::Metadata;
+use crate::reader::{Dither, read_all_or_nothing, read_body, skip, skip_padding, truncated};
+use crate::{AudioSource, Header, g711, id3};
+
+/// Reads an AIFF or AIFF-C file front to back like [`crate::WavReader`] reads a wav file.
+/// The header is filled in as if it were one: the AIFF-C compression type turns into the
+/// matching wav format code.
+pub struct AiffReader<R> {
+    reader: BufReader<R>,
+    header: Header,
+    data_size: u64,
+    remaining: u64,
+    encoding: Encoding,
+    dither: Dither,
+    metadata: Metadata,
+}
+
+impl<R: Read> AiffReader<R> {
+    /// Reads the header, walking the chunks up to `SSND`.
+    pub fn new(reader: R) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let mut reader = BufReader::new(reader);
+        let mut form = [0; 12];
+        reader.read_exact(&mut form).map_err(truncated)?;
+        if &form[0..4] != b"FORM" || !matches!(&form[8..12], b"AIFF" | b"AIFC") {
+            return Err("not an AIFF file".into());
+        }
+        let mut header = Header {
+            chunk_id: "FORM".into(),
+            chunk_size: be_u32(&form[4..8]),
+            format: str::from_utf8(&form[8..12])?.into(),
+            ..Header::default()
+        };
+
+        let mut encoding = None;
+        let mut metadata = Metadata::default();
+        let mut id3_metadata = Metadata::default();
+        loop {
+            let mut chunk_header = [0; 8];
+            if !read_all_or_nothing(&mut reader, &mut chunk_header)? {
+                return Err("no SSND chunk".into());
+            }
+            let id = str::from_utf8(&chunk_header[..4])?;
+            let size = be_u32(&chunk_header[4..]);
+            match id {
+                "COMM" if size >= 18 => {
+                    let body = read_body(&mut reader, size)?;
+                    encoding = Some(Encoding::of(&body, &mut header)?);
+                    header.subchunk1_id = id.into();
+                    header.subchunk1_size = size;
+                }
+                "SSND" => {
+                    let Some(encoding) = encoding else {
+                        return Err("SSND chunk before COMM".into());
+                    };
+                    // the offset of the first sample in the chunk after its 8 byte header,
+                    // for aligning blocks, which nothing writes anymore
+                    let mut offsets = [0; 8];
+                    reader.read_exact(&mut offsets).map_err(truncated)?;
+                    let offset = be_u32(&offsets[0..4]) as u64;
+                    skip(&mut reader, offset)?;
+                    header.subchunk2_id = id.into();
+                    let data_size = (size as u64).saturating_sub(8 + offset);
+                    header.subchunk2_size = data_size as u32;
+                    metadata.merge(id3_metadata);
+                    return Ok(Self {
+                        reader,
+                        data_size,
+                        remaining: data_size,
+                        encoding,
+                        dither: Dither::default(),
+                        header,
+                        metadata,
+                    });
+                }
+                _ => {
+                    let body = read_body(&mut reader, size)?;
+                    collect(id, &body, &mut metadata, &mut id3_metadata);
+                }
+            }
+        }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Size of the sound data in bytes.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Bytes of the sound data not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Decodes the next samples into `out` the way [`crate::WavReader::read_samples`] does.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        let width = self.encoding.bytes();
+        let count = (self.remaining / width as u64).min(out.len() as u64) as usize;
+        let mut bytes = vec![0; count * width];
+        self.reader.read_exact(&mut bytes).map_err(truncated)?;
+        self.remaining -= bytes.len() as u64;
+        for (sample, b) in out.iter_mut().zip(bytes.chunks_exact(width)) {
+            *sample = match self.encoding {
+                // 8-bit AIFF is signed, unlike 8-bit wav
+                Encoding::Int8 => (b[0] as i8 as i16) << 8,
+                Encoding::Int16 => i16::from_be_bytes([b[0], b[1]]),
+                Encoding::Int16Little => i16::from_le_bytes([b[0], b[1]]),
+                Encoding::Int24 => {
+                    let value = i32::from_be_bytes([b[0], b[1], b[2], 0]) >> 8;
+                    self.dither.quantize(value as f32 / 256.0)
+                }
+                Encoding::Int32 => {
+                    let value = i32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+                    self.dither.quantize(value as f32 / 65536.0)
+                }
+                Encoding::Float32 => {
+                    let value = f32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+                    let value = if value.is_nan() { 0.0 } else { value };
+                    self.dither.quantize(value * 32768.0)
+                }
+                Encoding::ALaw => g711::alaw_to_linear(b[0]),
+                Encoding::MuLaw => g711::ulaw_to_linear(b[0]),
+            };
+        }
+        Ok(count)
+    }
+
+    /// Skips whatever is left of the samples and reads the tags in the chunks after them.
+    pub fn finish(mut self) -> io::Result<Metadata> {
+        skip(&mut self.reader, self.remaining)?;
+        if self.data_size % 2 == 1 {
+            skip_padding(&mut self.reader)?;
+        }
+        let mut id3_metadata = Metadata::default();
+        loop {
+            let mut chunk_header = [0; 8];
+            if !read_all_or_nothing(&mut self.reader, &mut chunk_header)? {
+                break;
+            }
+            let Ok(id) = str::from_utf8(&chunk_header[..4]) else {
+                break;
+            };
+            let size = be_u32(&chunk_header[4..]);
+            let body = read_body(&mut self.reader, size)?;
+            collect(id, &body, &mut self.metadata, &mut id3_metadata);
+        }
+        self.metadata.merge(id3_metadata);
+        Ok(self.metadata)
+    }
+}
+
+impl<R: Read> AudioSource for AiffReader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Int8,
+    Int16,
+    // the `sowt` compression type of AIFF-C, what macOS writes for little-endian samples
+    Int16Little,
+    Int24,
+    Int32,
+    Float32,
+    ALaw,
+    MuLaw,
+}
+
+impl Encoding {
+    // fills in the header from a COMM body: channels, frame count, bits, the sample rate as an
+    // 80-bit float and for AIFF-C the compression type
+    fn of(comm: &[u8], header: &mut Header) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let channels = u16::from_be_bytes([comm[0], comm[1]]);
+        let bits = u16::from_be_bytes([comm[6], comm[7]]);
+        let sample_rate = extended(comm[8..18].try_into().unwrap());
+        let compression = if &*header.format == "AIFC" {
+            comm.get(18..22).unwrap_or(b"NONE")
+        } else {
+            b"NONE"
+        };
+        let (encoding, audio_format) = match (compression, bits) {
+            (b"NONE" | b"twos", 1..=8) => (Self::Int8, 1),
+            (b"NONE" | b"twos", 9..=16) => (Self::Int16, 1),
+            (b"NONE" | b"twos", 17..=24) => (Self::Int24, 1),
+            (b"NONE" | b"twos", 25..=32) => (Self::Int32, 1),
+            (b"sowt", 16) => (Self::Int16Little, 1),
+            (b"fl32" | b"FL32", _) => (Self::Float32, 3),
+            (b"alaw" | b"ALAW", _) => (Self::ALaw, 6),
+            (b"ulaw" | b"ULAW", _) => (Self::MuLaw, 7),
+            (b"NONE" | b"twos" | b"sowt", _) => {
+                return Err(format!("{bits}-bit samples aren't supported").into());
+            }
+            (compression, _) => {
+                return Err(format!(
+                    "AIFF-C compression `{}` isn't supported",
+                    String::from_utf8_lossy(compression)
+                )
+                .into());
+            }
+        };
+        let width = encoding.bytes() as u16;
+        header.audio_format = audio_format;
+        header.num_channels = channels;
+        header.sample_rate = sample_rate.round() as u32;
+        header.bits_per_sample = width * 8;
+        header.block_align = channels * width;
+        header.byte_rate = header.sample_rate * header.block_align as u32;
+        Ok(encoding)
+    }
+
+    fn bytes(self) -> usize {
+        match self {
+            Self::Int8 | Self::ALaw | Self::MuLaw => 1,
+            Self::Int16 | Self::Int16Little => 2,
+            Self::Int24 => 3,
+            Self::Int32 | Self::Float32 => 4,
+        }
+    }
+}
+
+// an IEEE 754 80-bit extended float: a sign bit, a 15-bit exponent and a 64-bit mantissa
+// whose integer bit is stored instead of implied
+fn extended(bytes: [u8; 10]) -> f64 {
+    let sign_exponent = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+    let exponent = (sign_exponent & 0x7fff) as i32 - 16383 - 63;
+    let value = mantissa as f64 * 2f64.powi(exponent);
+    if sign_exponent & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+// the text chunks, markers and id3 tag of an AIFF file
+fn collect(id: &str, body: &[u8], metadata: &mut Metadata, id3_metadata: &mut Metadata) {
+    let text = || {
+        Some(
+            String::from_utf8_lossy(body)
+                .trim_end_matches('\0')
+                .trim()
+                .to_string(),
+        )
+    };
+    match id {
+        "NAME" => metadata.title = text(),
+        "AUTH" => metadata.artist = text(),
+        "ANNO" if metadata.comment.is_none() => metadata.comment = text(),
+        "ID3 " | "id3 " => id3_metadata.merge(id3::parse(body)),
+        "MARK" if metadata.cues.is_empty() => {
+            metadata.cues = markers(body);
+            metadata.cues.sort_by_key(|cue| cue.frame);
+        }
+        _ => {}
+    }
+}
+
+// a count, then per marker its id, frame and name as a pascal string padded to an even size
+fn markers(body: &[u8]) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut rest = body.get(2..).unwrap_or_default();
+    while rest.len() >= 7 {
+        let id = u16::from_be_bytes([rest[0], rest[1]]) as u32;
+        let frame = be_u32(&rest[2..6]) as usize;
+        let length = rest[6] as usize;
+        let name = rest.get(7..7 + length).unwrap_or_default();
+        cues.push(Cue {
+            id,
+            frame,
+            label: (!name.is_empty()).then(|| String::from_utf8_lossy(name).into_owned()),
+        });
+        // the length byte and the text together take an even number of bytes
+        let taken = 7 + length + (length + 1) % 2;
+        rest = rest.get(taken..).unwrap_or_default();
+    }
+    cues
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{aiff, decode, signal};
+
+    #[test]
+    fn aiff_names_and_markers_are_read() {
+        let mut chunks = Vec::new();
+        chunks.extend(b"NAME");
+        chunks.extend(6u32.to_be_bytes());
+        chunks.extend(b"Corpus");
+        // two markers out of order, one named with an odd length and one with an even one
+        let mut mark = Vec::new();
+        mark.extend(2u16.to_be_bytes());
+        mark.extend(1u16.to_be_bytes());
+        mark.extend(300u32.to_be_bytes());
+        mark.extend(b"\x05Verse");
+        mark.extend(2u16.to_be_bytes());
+        mark.extend(100u32.to_be_bytes());
+        mark.extend(b"\x04Intr\0");
+        chunks.extend(b"MARK");
+        chunks.extend((mark.len() as u32).to_be_bytes());
+        chunks.extend(mark);
+        let wav = decode(&aiff(1, &signal(1, 44100, 500), None, &chunks)).unwrap();
+        assert_eq!(wav.metadata.title.as_deref(), Some("Corpus"));
+        let cues: Vec<_> = wav
+            .cues()
+            .iter()
+            .map(|cue| (cue.frame, cue.label.as_deref()))
+            .collect();
+        assert_eq!(cues, [(100, Some("Intr")), (300, Some("Verse"))]);
+    }
+}
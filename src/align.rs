@@ -0,0 +1,61 @@
+use crate::fft::fft;
+
+// frames of each signal compared, from the start. Stems of one take all begin within a bar
+// or so of each other, which this covers at any common rate
+const ANALYSIS_FRAMES: usize = 1 << 18;
+
+// how many frames later than `reference` the same sound happens in `other`, searched within
+// `max_lag` either way. Cross-correlation through the FFT: the inverse transform of one
+// spectrum times the conjugate of the other peaks at the lag where they line up best
+pub fn lag(reference: &[f32], other: &[f32], max_lag: usize) -> isize {
+    let frames = ANALYSIS_FRAMES.min(reference.len().max(other.len()));
+    // zero padded to twice the length so the correlation doesn't wrap around into itself
+    let size = (frames * 2).next_power_of_two();
+    let spectrum = |signal: &[f32]| {
+        let mut re = vec![0.0; size];
+        let mut im = vec![0.0; size];
+        let count = frames.min(signal.len());
+        re[..count].copy_from_slice(&signal[..count]);
+        fft(&mut re, &mut im);
+        (re, im)
+    };
+    let (a_re, a_im) = spectrum(reference);
+    let (b_re, b_im) = spectrum(other);
+
+    // conj(A) * B, then the inverse transform as the conjugate of a forward one
+    let mut re: Vec<f32> = (0..size)
+        .map(|i| a_re[i] * b_re[i] + a_im[i] * b_im[i])
+        .collect();
+    let mut im: Vec<f32> = (0..size)
+        .map(|i| -(a_re[i] * b_im[i] - a_im[i] * b_re[i]))
+        .collect();
+    fft(&mut re, &mut im);
+
+    // positive lags from the start, negative ones wrapped around from the end
+    let max_lag = max_lag.min(size / 2 - 1) as isize;
+    (-max_lag..=max_lag)
+        .max_by(|&x, &y| {
+            let at = |lag: isize| re[lag.rem_euclid(size as isize) as usize];
+            at(x).total_cmp(&at(y))
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_a_delay_either_way() {
+        let mut noise: u32 = 1;
+        let signal: Vec<f32> = (0..20000)
+            .map(|_| {
+                noise = noise.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (noise >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+        let late: Vec<f32> = [vec![0.0; 37], signal.clone()].concat();
+        assert_eq!(lag(&signal, &late, 1000), 37);
+        assert_eq!(lag(&late, &signal, 1000), -37);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::render::Renderer;
+use crate::{WavFile, dynamics, font, timecode};
+
+// where the verdicts go unless --report says otherwise
+pub const DEFAULT_REPORT: &str = "audition.txt";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Keep,
+    Reject,
+}
+
+impl Verdict {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+// sample triage: only the start of every file in the queue is played, and each one can be
+// kept or rejected with a key, which moves on to the next file right away
+pub struct Audition {
+    // how much of each file is played before moving on
+    pub seconds: f64,
+    report: PathBuf,
+    verdicts: Vec<Option<Verdict>>,
+}
+
+impl Audition {
+    pub fn new(seconds: f64, report: PathBuf, files: usize) -> Self {
+        Self {
+            seconds,
+            report,
+            verdicts: vec![None; files],
+        }
+    }
+
+    // interleaved samples of a file that get played
+    pub fn samples(&self, wav: &WavFile) -> usize {
+        let channels = wav.header.num_channels.max(1) as usize;
+        (self.seconds * wav.header.sample_rate as f64) as usize * channels
+    }
+
+    pub fn verdict(&self, track: usize) -> Option<Verdict> {
+        self.verdicts[track]
+    }
+
+    // going back with shift+N and tagging again replaces the earlier verdict
+    pub fn tag(&mut self, track: usize, verdict: Verdict) {
+        self.verdicts[track] = Some(verdict);
+    }
+
+    // the whole report is rewritten after every verdict, so quitting at any point leaves an
+    // up to date one behind. One `verdict<TAB>file` line per file, untagged ones as `skip`
+    pub fn write_report(&self, files: &[String]) -> io::Result<()> {
+        let mut out = io::BufWriter::new(fs::File::create(&self.report)?);
+        for (file, verdict) in files.iter().zip(&self.verdicts) {
+            let verdict = verdict.map_or("skip", Verdict::name);
+            writeln!(out, "{verdict}\t{file}")?;
+        }
+        out.flush()
+    }
+
+    pub fn report(&self) -> &PathBuf {
+        &self.report
+    }
+}
+
+// what's worth knowing about a sample at a glance
+pub struct Summary {
+    lines: Vec<String>,
+}
+
+impl Summary {
+    pub fn new(path: &str, wav: &WavFile) -> Self {
+        let header = &wav.header;
+        let channels = header.num_channels.max(1) as usize;
+        let frames = wav.data.len() / channels;
+        let peak = wav
+            .data
+            .iter()
+            .map(|&sample| (sample as i32).abs())
+            .max()
+            .unwrap_or(0);
+        Self {
+            lines: vec![
+                path.to_string(),
+                format!(
+                    "{} Hz  {} bit  {} ch",
+                    header.sample_rate, header.bits_per_sample, header.num_channels
+                ),
+                format!(
+                    "length {}",
+                    timecode::format(frames as f64 / header.sample_rate as f64)
+                ),
+                format!("peak {:.1} dBFS", dynamics::to_db(peak as f32 / 32768.0)),
+            ],
+        }
+    }
+}
+
+// the summary of the current file, its place in the queue and its verdict so far
+pub fn draw(
+    canvas: &mut impl Renderer,
+    summary: &Summary,
+    track: usize,
+    tracks: usize,
+    verdict: Option<Verdict>,
+) {
+    let mut lines = vec![format!(
+        "audition {}/{}  Y keep  R reject",
+        track + 1,
+        tracks
+    )];
+    lines.extend(summary.lines.iter().cloned());
+    let color = match verdict {
+        Some(Verdict::Keep) => Color::RGB(0, 220, 0),
+        Some(Verdict::Reject) => Color::RGB(255, 64, 64),
+        None => Color::RGB(255, 255, 255),
+    };
+    if let Some(verdict) = verdict {
+        lines.push(verdict.name().to_string());
+    }
+
+    // below the playback status line
+    let top = 36;
+    let box_width = lines
+        .iter()
+        .map(|line| font::text_width(line, 2))
+        .max()
+        .unwrap_or(0)
+        + 8;
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(Rect::new(
+        4,
+        top,
+        box_width as u32,
+        lines.len() as u32 * 20 + 8,
+    ));
+    canvas.set_blend(false);
+    canvas.set_color(color);
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(canvas, 8, top + 4 + i as i32 * 20, 2, line);
+    }
+}
@@ -0,0 +1,111 @@
+// https://tech.ebu.ch/docs/tech/tech3285.pdf, the broadcast extension chunk of a Broadcast
+// Wave file
+
+/// What a field recorder or broadcast system says about how and when a file was made.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bext {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    /// `yyyy-mm-dd`
+    pub origination_date: String,
+    /// `hh:mm:ss`, some recorders separate with dashes or dots instead
+    pub origination_time: String,
+    /// Frames since midnight at the first sample, for lining files up by timecode.
+    pub time_reference: u64,
+    pub version: u16,
+    /// Loudness of the programme from version 2 on, in LUFS, LU and dB.
+    pub loudness: Option<BextLoudness>,
+    /// One line per process the audio went through, oldest first.
+    pub coding_history: Vec<String>,
+}
+
+/// Each value is None when the recorder left it unset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BextLoudness {
+    pub integrated: Option<f32>,
+    pub range: Option<f32>,
+    pub max_true_peak: Option<f32>,
+    pub max_momentary: Option<f32>,
+    pub max_short_term: Option<f32>,
+}
+
+// the bext chunk body, None when it's shorter than the fixed fields of version 0
+pub fn parse(body: &[u8]) -> Option<Bext> {
+    // description, originator, reference, date and time, then the time reference, the
+    // version, the 64 byte UMID, five loudness values and 180 reserved bytes
+    if body.len() < 348 {
+        return None;
+    }
+    let version = u16::from_le_bytes([body[346], body[347]]);
+    let loudness = (version >= 2 && body.len() >= 422).then(|| {
+        // stored as hundredths, 0x7fff when unknown
+        let value = |offset: usize| {
+            let value = i16::from_le_bytes([body[412 + offset * 2], body[413 + offset * 2]]);
+            (value != i16::MAX).then(|| value as f32 / 100.0)
+        };
+        BextLoudness {
+            integrated: value(0),
+            range: value(1),
+            max_true_peak: value(2),
+            max_momentary: value(3),
+            max_short_term: value(4),
+        }
+    });
+    let coding_history = text(body.get(602..).unwrap_or_default())
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(Bext {
+        description: text(&body[0..256]),
+        originator: text(&body[256..288]),
+        originator_reference: text(&body[288..320]),
+        origination_date: text(&body[320..330]),
+        origination_time: text(&body[330..338]),
+        time_reference: u64::from_le_bytes(body[338..346].try_into().unwrap()),
+        version,
+        loudness,
+        coding_history,
+    })
+}
+
+// an ascii field, padded with nuls up to its fixed size
+fn text(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{decode, insert_chunk, signal, wav};
+
+    #[test]
+    fn bext_fields_and_coding_history_are_read() {
+        let mut bext = vec![0; 602];
+        bext[..12].copy_from_slice(b"Scene 4 take");
+        bext[256..262].copy_from_slice(b"Deva 5");
+        bext[320..338].copy_from_slice(b"2024-05-0112:30:00");
+        bext[338..346].copy_from_slice(&(48000u64 * 3600).to_le_bytes());
+        bext[346..348].copy_from_slice(&2u16.to_le_bytes());
+        bext[412..414].copy_from_slice(&(-2300i16).to_le_bytes());
+        bext[414..416].copy_from_slice(&i16::MAX.to_le_bytes());
+        bext.extend(b"A=PCM,F=48000,W=24,M=stereo\r\nA=PCM,F=48000,W=16\r\n");
+        let wav = decode(&insert_chunk(
+            &wav(2, 48000, &signal(2, 48000, 100), &[]),
+            0,
+            *b"bext",
+            bext,
+        ))
+        .unwrap();
+        let bext = wav.metadata.bext.unwrap();
+        assert_eq!(bext.description, "Scene 4 take");
+        assert_eq!(bext.originator, "Deva 5");
+        assert_eq!(bext.origination_time, "12:30:00");
+        assert_eq!(bext.time_reference, 48000 * 3600);
+        let loudness = bext.loudness.unwrap();
+        assert_eq!((loudness.integrated, loudness.range), (Some(-23.0), None));
+        assert_eq!(bext.coding_history.len(), 2);
+    }
+}
@@ -0,0 +1,230 @@
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, PI};
+
+use wave::Header;
+
+// radius of an average head and the speed of sound, in m and m/s
+const HEAD_RADIUS: f32 = 0.0875;
+const SPEED_OF_SOUND: f32 = 343.0;
+// longer than the largest delay around the head, about 0.7 ms, even at 352.8 kHz
+const DELAY_LINE: usize = 256;
+// level of the low frequency effects channel in both ears
+const LFE_GAIN: f32 = 0.5;
+
+// a speaker position: degrees clockwise from straight ahead, None for the LFE channel which
+// has no direction
+fn azimuth(speaker: &str) -> Option<f32> {
+    Some(match speaker {
+        "FL" | "TFL" => -30.0,
+        "FR" | "TFR" => 30.0,
+        "FLC" => -15.0,
+        "FRC" => 15.0,
+        "SL" => -90.0,
+        "SR" => 90.0,
+        "BL" | "TBL" => -135.0,
+        "BR" | "TBR" => 135.0,
+        "BC" | "TBC" => 180.0,
+        "LFE" => return None,
+        // FC, TC, TFC
+        _ => 0.0,
+    })
+}
+
+// the usual speakers for files without a channel mask, in the order wav puts them
+fn default_layout(channels: usize) -> &'static [&'static str] {
+    match channels {
+        1 => &["FC"],
+        2 => &["FL", "FR"],
+        3 => &["FL", "FR", "FC"],
+        4 => &["FL", "FR", "BL", "BR"],
+        5 => &["FL", "FR", "FC", "BL", "BR"],
+        6 => &["FL", "FR", "FC", "LFE", "BL", "BR"],
+        7 => &["FL", "FR", "FC", "LFE", "BC", "SL", "SR"],
+        8 => &["FL", "FR", "FC", "LFE", "BL", "BR", "SL", "SR"],
+        _ => &[],
+    }
+}
+
+// headphone monitoring of any number of channels: every channel is played from where its
+// speaker would be, through the spherical head model of Brown and Duda ("A structural model
+// for binaural sound synthesis", 1998). Each ear hears it delayed by the time around the head
+// and through a one pole shelf for the head's shadow. With `enabled` off it's a flat downmix
+// to compare against instead
+pub struct Binaural {
+    pub enabled: bool,
+    sources: Vec<Source>,
+    // where in the delay lines the current frame is written
+    position: usize,
+}
+
+struct Source {
+    // level in the downmix, the same with the head model
+    gain: f32,
+    // None for the LFE
+    ears: Option<[Ear; 2]>,
+    // what went into the flat downmix, left and right
+    flat: [f32; 2],
+    history: [f32; DELAY_LINE],
+}
+
+struct Ear {
+    delay: f32,
+    // the head shadow, a first order filter from the bilinear transform
+    b0: f32,
+    b1: f32,
+    a1: f32,
+    x1: f32,
+    y1: f32,
+}
+
+impl Ear {
+    // `angle` between the direction of the sound and the ear, in radians
+    fn new(sample_rate: u32, angle: f32) -> Self {
+        let w0 = SPEED_OF_SOUND / HEAD_RADIUS;
+        // the shadow is deepest a little off the far side, 150 degrees from the ear, where
+        // the high frequencies drop to a tenth
+        let (alpha_min, theta_min) = (0.1, 150f32.to_radians());
+        let alpha =
+            (1.0 + alpha_min / 2.0) + (1.0 - alpha_min / 2.0) * (angle / theta_min * PI).cos();
+        let k = 2.0 * sample_rate as f32;
+        let norm = 2.0 * w0 + k;
+        // the path to an ear facing the sound is shorter than to the center of the head, to
+        // one facing away it goes around it. Offset so the nearest ear isn't negative
+        let travel = if angle < FRAC_PI_2 {
+            -angle.cos()
+        } else {
+            angle - FRAC_PI_2
+        };
+        Self {
+            delay: (1.0 + travel) * HEAD_RADIUS / SPEED_OF_SOUND * sample_rate as f32,
+            b0: (2.0 * w0 + alpha * k) / norm,
+            b1: (2.0 * w0 - alpha * k) / norm,
+            a1: (2.0 * w0 - k) / norm,
+            x1: 0.0,
+            y1: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b1 * self.x1 - self.a1 * self.y1;
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+}
+
+impl Binaural {
+    // for the channels of a file with `header`, from its channel mask or else the usual layout
+    // for its channel count. Channels beyond that are spread evenly around the listener
+    pub fn new(header: &Header) -> Self {
+        let channels = header.num_channels.max(1) as usize;
+        let speakers = match header.speakers() {
+            speakers if speakers.len() == channels => speakers,
+            _ => default_layout(channels).to_vec(),
+        };
+        let sources = (0..channels)
+            .map(|channel| {
+                let azimuth = match speakers.get(channel) {
+                    Some(speaker) => azimuth(speaker),
+                    None => Some(channel as f32 * 360.0 / channels as f32 - 30.0),
+                };
+                let Some(azimuth) = azimuth else {
+                    return Source {
+                        gain: LFE_GAIN,
+                        ears: None,
+                        flat: [1.0, 1.0],
+                        history: [0.0; DELAY_LINE],
+                    };
+                };
+                let azimuth = (azimuth + 180.0).rem_euclid(360.0) - 180.0;
+                // fronts at full level, the center and the ones behind at -3 dB like the
+                // ITU downmix has them
+                let gain = if azimuth.abs() <= 15.0 || azimuth.abs() > 90.0 {
+                    FRAC_1_SQRT_2
+                } else {
+                    1.0
+                };
+                let flat = match azimuth {
+                    a if a.abs() <= 15.0 || a.abs() >= 165.0 => [FRAC_1_SQRT_2; 2],
+                    a if a < 0.0 => [1.0, 0.0],
+                    _ => [0.0, 1.0],
+                };
+                let ear = |side: f32| {
+                    let angle = (azimuth - side).to_radians().abs();
+                    let angle = if angle > PI { 2.0 * PI - angle } else { angle };
+                    Ear::new(header.sample_rate, angle)
+                };
+                Source {
+                    gain,
+                    ears: Some([ear(-90.0), ear(90.0)]),
+                    flat,
+                    history: [0.0; DELAY_LINE],
+                }
+            })
+            .collect();
+        Self {
+            enabled: true,
+            sources,
+            position: 0,
+        }
+    }
+
+    // interleaved frames of the file's channels into interleaved stereo
+    pub fn render(&mut self, input: &[i16], out: &mut [i16]) {
+        let channels = self.sources.len();
+        for (frame, output) in input.chunks_exact(channels).zip(out.chunks_exact_mut(2)) {
+            let mut sum = [0.0f32; 2];
+            for (source, &sample) in self.sources.iter_mut().zip(frame) {
+                let value = sample as f32 * source.gain;
+                source.history[self.position] = value;
+                match &mut source.ears {
+                    Some(ears) if self.enabled => {
+                        for (side, ear) in ears.iter_mut().enumerate() {
+                            // between the two samples of the history the delay falls between
+                            let whole = ear.delay as usize;
+                            let t = ear.delay - whole as f32;
+                            let at = |back: usize| {
+                                source.history[(self.position + DELAY_LINE - back) % DELAY_LINE]
+                            };
+                            let delayed = at(whole) + (at(whole + 1) - at(whole)) * t;
+                            sum[side] += ear.process(delayed);
+                        }
+                    }
+                    _ => {
+                        sum[0] += value * source.flat[0];
+                        sum[1] += value * source.flat[1];
+                    }
+                }
+            }
+            for (out, sum) in output.iter_mut().zip(sum) {
+                *out = sum.clamp(-32768.0, 32767.0) as i16;
+            }
+            self.position = (self.position + 1) % DELAY_LINE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_left_speaker_is_louder_and_earlier_in_the_left_ear() {
+        let header = Header {
+            num_channels: 2,
+            sample_rate: 48000,
+            ..Header::default()
+        };
+        let mut binaural = Binaural::new(&header);
+        // a click on the left channel only
+        let mut input = vec![0; 2 * 256];
+        input[0] = 20000;
+        let mut out = vec![0; 2 * 256];
+        binaural.render(&input, &mut out);
+        let ear = |side: usize| -> Vec<i16> { out.iter().skip(side).step_by(2).copied().collect() };
+        let (left, right) = (ear(0), ear(1));
+        let level = |ear: &[i16]| ear.iter().map(|&s| (s as f32).powi(2)).sum::<f32>().sqrt();
+        assert!(level(&left) > level(&right) * 1.2);
+        let arrival = |ear: &[i16]| ear.iter().position(|s| s.abs() > 100).unwrap();
+        assert!(arrival(&left) < arrival(&right));
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+use std::{error, fs};
+
+use memmap2::Mmap;
+
+use crate::cli::Args;
+use crate::{i18n, repair, riff};
+
+const USAGE: &str = "wave carve <image.bin> [--scan] [--output-dir DIR]";
+
+// `wave carve`: pulls wav files back out of a disk image or any other blob. Without --scan only
+// a file starting at the very beginning is looked for, with it every RIFF/WAVE signature in the
+// blob is
+pub fn carve(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["scan"])?;
+    let input = args.input(USAGE)?;
+    let output_dir = Path::new(args.value("output-dir").unwrap_or("."));
+    let stem = Path::new(input)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    // disk images run to gigabytes, mapping one leaves paging it in to the system instead of
+    // reading it all into memory, and every carved file is written straight out of the map
+    let file = fs::File::open(input)?;
+    // carving an image something else is still writing to isn't supported
+    let map = unsafe { Mmap::map(&file)? };
+    let bytes: &[u8] = &map;
+
+    let starts: Vec<usize> = if args.flag("scan") {
+        signatures(bytes)
+    } else {
+        signatures(&bytes[..bytes.len().min(12)])
+    };
+    if starts.is_empty() {
+        return Err(i18n::text("carve.no-signature").into());
+    }
+
+    let mut carved = 0;
+    for (i, &start) in starts.iter().enumerate() {
+        // a file ends where its header says, unless that runs into the next signature or the
+        // size was never written
+        let limit = starts.get(i + 1).copied().unwrap_or(bytes.len());
+        let riff_size = u32::from_le_bytes([
+            bytes[start + 4],
+            bytes[start + 5],
+            bytes[start + 6],
+            bytes[start + 7],
+        ]) as usize;
+        let end = match start + 8 + riff_size {
+            end if riff_size > 4 && end <= limit => end,
+            _ => limit,
+        };
+
+        println!(
+            "{}",
+            i18n::fill(
+                "carve.candidate",
+                &[
+                    ("offset", format!("{start:#x}")),
+                    ("bytes", (end - start).to_string()),
+                ]
+            )
+        );
+        let span = &bytes[start..end];
+        let scanned = repair::scan(&mut Cursor::new(span), span.len() as u64)?;
+        let usable = scanned
+            .chunks
+            .iter()
+            .any(|chunk| &chunk.id == b"fmt " && chunk.data.len() >= 16);
+        let Some(data) = scanned.data.filter(|_| usable) else {
+            println!("  {}", i18n::text("carve.skipped"));
+            continue;
+        };
+
+        let output = output_dir.join(format!("{stem}.{start:08x}.wav"));
+        let mut out = BufWriter::new(fs::File::create(&output)?);
+        let mut samples = &span[data.start as usize..data.end as usize];
+        let body = riff::DataBody {
+            source: &mut samples,
+            len: data.end - data.start,
+        };
+        riff::write_form_to(&mut out, b"RIFF", &scanned.chunks, Some(body))?;
+        out.flush()?;
+        println!(
+            "  {}",
+            i18n::fill("carve.wrote", &[("path", output.display().to_string())])
+        );
+        carved += 1;
+    }
+    println!(
+        "{}",
+        i18n::fill(
+            "carve.carved",
+            &[
+                ("carved", carved.to_string()),
+                ("candidates", starts.len().to_string()),
+            ]
+        )
+    );
+    Ok(())
+}
+
+// offsets of every `RIFF....WAVE` header in `bytes`
+fn signatures(bytes: &[u8]) -> Vec<usize> {
+    bytes
+        .windows(12)
+        .enumerate()
+        .filter(|(_, window)| &window[0..4] == b"RIFF" && &window[8..12] == b"WAVE")
+        .map(|(offset, _)| offset)
+        .collect()
+}
@@ -0,0 +1,418 @@
+// hosting CLAP effects, https://github.com/free-audio/clap. A plugin is a shared library
+// exporting `clap_entry`, whose factory makes instances that process blocks of float samples.
+// Only effects are of use here: the file goes into the plugin's main input port and its main
+// output port is what's played
+
+use std::error;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE, clap_event_header, clap_event_param_value,
+    clap_input_events, clap_output_events,
+};
+use clap_sys::ext::audio_ports::{
+    CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS, clap_audio_port_info, clap_plugin_audio_ports,
+};
+use clap_sys::ext::params::{
+    CLAP_EXT_PARAMS, CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_READONLY, CLAP_PARAM_IS_STEPPED,
+    clap_param_info, clap_plugin_params,
+};
+use clap_sys::factory::plugin_factory::{CLAP_PLUGIN_FACTORY_ID, clap_plugin_factory};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{CLAP_PROCESS_ERROR, clap_process};
+use clap_sys::version::{CLAP_VERSION, clap_version_is_compatible};
+use libloading::Library;
+
+use crate::plugin::{BLOCK, Blocks, Parameter};
+
+// a loaded plugin binary, the first plugin its factory offers is the one used
+pub struct PluginLibrary {
+    entry: *const clap_plugin_entry,
+    factory: *const clap_plugin_factory,
+    id: CString,
+    name: String,
+    // last so the code stays mapped until deinit has run
+    _library: Library,
+}
+
+// the entry and the factory are static data of the library, usable from any thread
+unsafe impl Send for PluginLibrary {}
+unsafe impl Sync for PluginLibrary {}
+
+impl PluginLibrary {
+    // a `.clap` file, or on macOS the bundle directory with the binary inside
+    pub fn open(path: &str) -> Result<Arc<Self>, Box<dyn error::Error + 'static>> {
+        let binary = match Path::new(path) {
+            bundle if bundle.is_dir() => bundle
+                .join("Contents/MacOS")
+                .join(bundle.file_stem().unwrap_or_default()),
+            file => file.to_path_buf(),
+        };
+        let library = unsafe { Library::new(&binary) }.map_err(|e| format!("{path}: {e}"))?;
+        let entry = unsafe { library.get::<*const clap_plugin_entry>(b"clap_entry\0") }
+            .map_err(|_| format!("{path}: not a CLAP plugin"))?;
+        let entry = *entry;
+        let (init, get_factory) = match unsafe { &*entry } {
+            entry if !clap_version_is_compatible(entry.clap_version) => {
+                return Err(format!("{path}: made for an unknown CLAP version").into());
+            }
+            clap_plugin_entry {
+                init: Some(init),
+                get_factory: Some(get_factory),
+                ..
+            } => (*init, *get_factory),
+            _ => return Err(format!("{path}: incomplete CLAP entry").into()),
+        };
+        let c_path = CString::new(path)?;
+        if !unsafe { init(c_path.as_ptr()) } {
+            return Err(format!("{path}: plugin failed to initialize").into());
+        }
+        // from here on dropping it runs deinit
+        let mut library = Self {
+            entry,
+            factory: ptr::null(),
+            id: CString::default(),
+            name: String::new(),
+            _library: library,
+        };
+        let factory = unsafe { get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) };
+        library.factory = factory.cast();
+        let descriptor = match unsafe { library.factory.as_ref() } {
+            Some(clap_plugin_factory {
+                get_plugin_count: Some(count),
+                get_plugin_descriptor: Some(descriptor),
+                ..
+            }) if unsafe { count(library.factory) } > 0 => unsafe {
+                descriptor(library.factory, 0).as_ref()
+            },
+            _ => None,
+        };
+        let descriptor = descriptor.ok_or_else(|| format!("{path}: contains no plugins"))?;
+        library.id = unsafe { CStr::from_ptr(descriptor.id) }.to_owned();
+        library.name = unsafe { text(descriptor.name) };
+        Ok(Arc::new(library))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // an instance activated for a file with `channels` at `sample_rate`, with the parameters
+    // it starts out with
+    pub fn instantiate(
+        self: &Arc<Self>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(Plugin, Vec<Parameter>), Box<dyn error::Error + 'static>> {
+        let host = Box::new(clap_host {
+            clap_version: CLAP_VERSION,
+            host_data: ptr::null_mut(),
+            name: c"wave".as_ptr(),
+            vendor: c"".as_ptr(),
+            url: c"".as_ptr(),
+            version: c"0.1.0".as_ptr(),
+            get_extension: Some(get_extension),
+            request_restart: Some(ignore_request),
+            request_process: Some(ignore_request),
+            request_callback: Some(ignore_request),
+        });
+        let create = unsafe { (*self.factory).create_plugin }.ok_or("plugin can't be created")?;
+        let plugin = unsafe { create(self.factory, &*host, self.id.as_ptr()) };
+        if plugin.is_null() {
+            return Err(format!("{}: plugin couldn't be created", self.name).into());
+        }
+        // from here on dropping it destroys the instance
+        let mut instance = Plugin {
+            enabled: true,
+            plugin,
+            blocks: Blocks::new(channels, 0, 0),
+            input_pointers: Vec::new(),
+            output_pointers: Vec::new(),
+            events: Vec::with_capacity(64),
+            steady_time: 0,
+            activated: false,
+            processing: false,
+            _host: host,
+            _library: self.clone(),
+        };
+        let functions = unsafe { *plugin };
+        if !functions.init.is_some_and(|init| unsafe { init(plugin) }) {
+            return Err(format!("{}: plugin failed to initialize", self.name).into());
+        }
+        let inputs = unsafe { main_port(plugin, true) };
+        let outputs = unsafe { main_port(plugin, false) };
+        if outputs == 0 {
+            return Err(format!("{}: not an effect, it has no audio output", self.name).into());
+        }
+        instance.blocks = Blocks::new(channels, inputs, outputs);
+        // the blocks never move, so the pointers the plugin gets stay valid
+        let blocks = &mut instance.blocks;
+        instance.input_pointers = blocks.inputs.iter_mut().map(|c| c.as_mut_ptr()).collect();
+        instance.output_pointers = blocks.outputs.iter_mut().map(|c| c.as_mut_ptr()).collect();
+        let parameters = unsafe { parameters(plugin) };
+        let activate = functions.activate.ok_or("plugin can't be activated")?;
+        if !unsafe { activate(plugin, sample_rate as f64, 1, BLOCK as u32) } {
+            return Err(format!("{}: plugin doesn't run at {sample_rate} Hz", self.name).into());
+        }
+        instance.activated = true;
+        Ok((instance, parameters))
+    }
+}
+
+impl Drop for PluginLibrary {
+    fn drop(&mut self) {
+        if let Some(deinit) = unsafe { (*self.entry).deinit } {
+            unsafe { deinit() };
+        }
+    }
+}
+
+// an activated instance, run a sample at a time like the filters
+pub struct Plugin {
+    enabled: bool,
+    plugin: *const clap_plugin,
+    // a block per channel of the main ports
+    blocks: Blocks,
+    input_pointers: Vec<*mut f32>,
+    output_pointers: Vec<*mut f32>,
+    // parameter changes for the next block
+    events: Vec<clap_event_param_value>,
+    // frames processed so far
+    steady_time: i64,
+    activated: bool,
+    processing: bool,
+    // the plugin may hold on to the host, and the code has to stay loaded while it lives
+    _host: Box<clap_host>,
+    _library: Arc<PluginLibrary>,
+}
+
+// created on the ui thread and processing on the audio thread, as CLAP intends
+unsafe impl Send for Plugin {}
+
+impl Plugin {
+    // bypassed, the samples pass through as they are
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // sent along with the next block
+    pub fn set_parameter(&mut self, id: u32, value: f64) {
+        self.events.push(clap_event_param_value {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_param_value>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value,
+        });
+    }
+
+    pub fn process(&mut self, channel: usize, value: f32) -> f32 {
+        if !self.enabled {
+            return value;
+        }
+        let (out, full) = self.blocks.exchange(channel, value);
+        if full {
+            self.run();
+        }
+        out
+    }
+
+    // the full block of input through the plugin
+    fn run(&mut self) {
+        let functions = unsafe { *self.plugin };
+        if !self.processing {
+            self.processing = functions
+                .start_processing
+                .is_some_and(|start| unsafe { start(self.plugin) });
+        }
+        let Some(process) = functions.process.filter(|_| self.processing) else {
+            self.blocks
+                .outputs
+                .iter_mut()
+                .for_each(|output| output.fill(0.0));
+            return;
+        };
+        let input = clap_audio_buffer {
+            data32: self.input_pointers.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.blocks.inputs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut output = clap_audio_buffer {
+            data32: self.output_pointers.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.blocks.outputs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let in_events = clap_input_events {
+            ctx: (&raw const self.events).cast_mut().cast(),
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let out_events = clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(events_drop),
+        };
+        let block = clap_process {
+            steady_time: self.steady_time,
+            frames_count: BLOCK as u32,
+            transport: ptr::null(),
+            audio_inputs: &input,
+            audio_outputs: &mut output,
+            audio_inputs_count: !self.blocks.inputs.is_empty() as u32,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+        if unsafe { process(self.plugin, &block) } == CLAP_PROCESS_ERROR {
+            self.blocks
+                .outputs
+                .iter_mut()
+                .for_each(|output| output.fill(0.0));
+        }
+        self.events.clear();
+        self.steady_time += BLOCK as i64;
+    }
+}
+
+// with the player's device, which is closed on the ui thread
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        let functions = unsafe { *self.plugin };
+        unsafe {
+            if let (true, Some(stop)) = (self.processing, functions.stop_processing) {
+                stop(self.plugin);
+            }
+            if let (true, Some(deactivate)) = (self.activated, functions.deactivate) {
+                deactivate(self.plugin);
+            }
+            if let Some(destroy) = functions.destroy {
+                destroy(self.plugin);
+            }
+        }
+    }
+}
+
+// channels of the main input or output port, or of the first one if none is marked main
+unsafe fn main_port(plugin: *const clap_plugin, is_input: bool) -> usize {
+    let ports: *const clap_plugin_audio_ports =
+        unsafe { extension(plugin, CLAP_EXT_AUDIO_PORTS) }.cast();
+    let Some(clap_plugin_audio_ports {
+        count: Some(count),
+        get: Some(get),
+    }) = (unsafe { ports.as_ref() })
+    else {
+        return 0;
+    };
+    let mut first = None;
+    for index in 0..unsafe { count(plugin, is_input) } {
+        let mut info: clap_audio_port_info = unsafe { mem::zeroed() };
+        if !unsafe { get(plugin, index, is_input, &mut info) } {
+            continue;
+        }
+        if info.flags & CLAP_AUDIO_PORT_IS_MAIN != 0 {
+            return info.channel_count as usize;
+        }
+        first.get_or_insert(info.channel_count as usize);
+    }
+    first.unwrap_or(0)
+}
+
+unsafe fn parameters(plugin: *const clap_plugin) -> Vec<Parameter> {
+    let params: *const clap_plugin_params = unsafe { extension(plugin, CLAP_EXT_PARAMS) }.cast();
+    let Some(clap_plugin_params {
+        count: Some(count),
+        get_info: Some(get_info),
+        get_value,
+        ..
+    }) = (unsafe { params.as_ref() })
+    else {
+        return Vec::new();
+    };
+    (0..unsafe { count(plugin) })
+        .filter_map(|index| {
+            let mut info: clap_param_info = unsafe { mem::zeroed() };
+            if !unsafe { get_info(plugin, index, &mut info) }
+                || info.flags & (CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY) != 0
+            {
+                return None;
+            }
+            let mut value = info.default_value;
+            if let Some(get_value) = get_value {
+                unsafe { get_value(plugin, info.id, &mut value) };
+            }
+            Some(Parameter {
+                id: info.id,
+                name: unsafe { text(info.name.as_ptr()) },
+                min: info.min_value,
+                max: info.max_value,
+                value,
+                stepped: info.flags & CLAP_PARAM_IS_STEPPED != 0,
+            })
+        })
+        .collect()
+}
+
+unsafe fn extension(plugin: *const clap_plugin, id: &CStr) -> *const c_void {
+    match unsafe { (*plugin).get_extension } {
+        Some(get_extension) => unsafe { get_extension(plugin, id.as_ptr()) },
+        None => ptr::null(),
+    }
+}
+
+unsafe fn text(text: *const c_char) -> String {
+    if text.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+// the host offers no extensions, plugins have to do without them
+unsafe extern "C" fn get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn ignore_request(_host: *const clap_host) {}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events: &Vec<clap_event_param_value> = unsafe { &*(*list).ctx.cast() };
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(
+    list: *const clap_input_events,
+    index: u32,
+) -> *const clap_event_header {
+    let events: &Vec<clap_event_param_value> = unsafe { &*(*list).ctx.cast() };
+    events
+        .get(index as usize)
+        .map_or(ptr::null(), |event| &event.header)
+}
+
+// what the plugin reports back, like its own parameter changes, isn't shown
+unsafe extern "C" fn events_drop(
+    _list: *const clap_output_events,
+    _event: *const clap_event_header,
+) -> bool {
+    true
+}
@@ -0,0 +1,155 @@
+use std::ops::ControlFlow;
+
+use crate::WavFile;
+use crate::dynamics::to_db;
+use crate::fft::{fft, hann};
+use crate::progress::{self, CancellationToken, Cancelled, Reporter};
+
+// one label per segment of this length
+pub const SEGMENT_SECONDS: f32 = 1.0;
+const FRAME_SECONDS: f32 = 0.02;
+const SILENCE_DB: f32 = -50.0;
+const FLUX_FFT_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Class {
+    Silence,
+    Speech,
+    Music,
+}
+
+// rule based speech/music discrimination after lu, zhang and jiang (2002): speech alternates
+// voiced and unvoiced sounds with short pauses in between, which shows up as many low energy
+// frames, bursts of high zero-crossing rate and a spectrum that keeps changing, while music
+// tends to be steady on all three. Only one segment is downmixed at a time, so a file that
+// went over the memory budget isn't copied back into memory
+pub fn classify(wav: &WavFile) -> Vec<Class> {
+    let cancel = CancellationToken::new();
+    classify_with_progress(wav, &mut |_| ControlFlow::Continue(()), &cancel).unwrap_or_default()
+}
+
+// like `classify`, reporting its progress in frames and stopping early once cancelled
+pub fn classify_with_progress(
+    wav: &WavFile,
+    progress: progress::Callback,
+    cancel: &CancellationToken,
+) -> Result<Vec<Class>, Cancelled> {
+    let mut reporter = Reporter::new(progress, cancel, wav.frames());
+    let sample_rate = wav.header.sample_rate as f32;
+    let frame = ((sample_rate * FRAME_SECONDS) as usize).max(1);
+    let segment = ((sample_rate * SEGMENT_SECONDS) as usize).max(frame);
+    let window = hann(FLUX_FFT_SIZE);
+
+    wav.mono_blocks(segment)
+        .enumerate()
+        .map(|(i, samples)| {
+            reporter.update(i * segment)?;
+            let energies: Vec<f32> = samples
+                .chunks(frame)
+                .map(|f| f.iter().map(|s| s * s).sum::<f32>() / f.len() as f32)
+                .collect();
+            let mean_energy = energies.iter().sum::<f32>() / energies.len() as f32;
+            if to_db(mean_energy.sqrt()) < SILENCE_DB {
+                return Ok(Class::Silence);
+            }
+
+            // low short-time energy ratio: share of frames below half the average energy
+            let low_energy = energies.iter().filter(|&&e| e < 0.5 * mean_energy).count();
+            let lster = low_energy as f32 / energies.len() as f32;
+
+            // high zero-crossing rate ratio: share of frames crossing zero 1.5x more often
+            // than average
+            let zcrs: Vec<f32> = samples.chunks(frame).map(zero_crossing_rate).collect();
+            let mean_zcr = zcrs.iter().sum::<f32>() / zcrs.len() as f32;
+            let high_zcr = zcrs.iter().filter(|&&z| z > 1.5 * mean_zcr).count();
+            let hzcrr = high_zcr as f32 / zcrs.len() as f32;
+
+            let flux = spectral_flux(&samples, &window);
+
+            let votes = [lster > 0.15, hzcrr > 0.1, flux > 0.5];
+            if votes.iter().filter(|&&v| v).count() >= 2 {
+                Ok(Class::Speech)
+            } else {
+                Ok(Class::Music)
+            }
+        })
+        .collect()
+}
+
+fn zero_crossing_rate(samples: &[f32]) -> f32 {
+    let crossings = samples
+        .windows(2)
+        .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+        .count();
+    crossings as f32 / samples.len() as f32
+}
+
+// average change of the normalized magnitude spectrum between consecutive blocks
+fn spectral_flux(samples: &[f32], window: &[f32]) -> f32 {
+    let mut previous: Option<Vec<f32>> = None;
+    let mut total = 0.0;
+    let mut count = 0;
+    for block in samples.chunks_exact(FLUX_FFT_SIZE) {
+        let mut re: Vec<f32> = block.iter().zip(window).map(|(s, w)| s * w).collect();
+        let mut im = vec![0.0; FLUX_FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        let mut magnitudes: Vec<f32> = (0..FLUX_FFT_SIZE / 2)
+            .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+            .collect();
+        let sum = magnitudes.iter().sum::<f32>().max(1e-10);
+        magnitudes.iter_mut().for_each(|m| *m /= sum);
+
+        if let Some(previous) = &previous {
+            total += magnitudes
+                .iter()
+                .zip(previous)
+                .map(|(a, b)| (a - b).abs())
+                .sum::<f32>();
+            count += 1;
+        }
+        previous = Some(magnitudes);
+    }
+    if count == 0 {
+        0.0
+    } else {
+        total / count as f32
+    }
+}
+
+#[cfg(all(test, feature = "spill"))]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn a_file_over_the_memory_budget_classifies_like_one_in_memory() {
+        // a minute of stereo at 8 kHz, almost 2 MB of samples against a budget of a tenth
+        let spec = wave::WavSpec {
+            sample_rate: 8000,
+            channels: 2,
+            bits_per_sample: 16,
+        };
+        let mut writer = wave::WavWriter::new(Cursor::new(Vec::new()), spec).unwrap();
+        let samples: Vec<i16> = (0..8000 * 60 * 2)
+            .map(|i| {
+                if i / 80000 % 2 == 0 {
+                    0
+                } else {
+                    (i % 200 * 100) as i16
+                }
+            })
+            .collect();
+        writer.write_samples(&samples).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let spilled = WavFile::from_reader_with_budget(&bytes[..], 200_000).unwrap();
+        let in_memory = WavFile::from_reader_with_budget(&bytes[..], u64::MAX).unwrap();
+        assert!(!spilled.data.in_memory());
+        assert!(in_memory.data.in_memory());
+        let classes = classify(&spilled);
+        assert_eq!(classes.len(), 60);
+        assert!(classes == classify(&in_memory));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::i18n;
+
+// minimal `--option value` / `--switch` command line parser shared by the subcommands
+pub struct Args {
+    pub positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    // `switches` lists the options that don't take a value, every other `--option` consumes
+    // the next argument (or the part after `=`)
+    pub fn parse(args: &[String], switches: &[&str]) -> Result<Self, String> {
+        let mut parsed = Self {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.clone());
+                continue;
+            };
+
+            if let Some((name, value)) = option.split_once('=') {
+                parsed.options.push((name.into(), Some(value.into())));
+            } else if switches.contains(&option) {
+                parsed.options.push((option.into(), None));
+            } else {
+                let value = args.next().ok_or_else(|| {
+                    i18n::fill("cli.needs-value", &[("option", option.to_string())])
+                })?;
+                parsed.options.push((option.into(), Some(value.clone())));
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    pub fn input(&self, usage: &str) -> Result<&str, String> {
+        self.positional
+            .first()
+            .map(String::as_str)
+            .ok_or_else(|| i18n::fill("cli.usage", &[("usage", usage.to_string())]))
+    }
+}
+
+// `usage` followed by what --strip keeps, for the subcommands that take it
+pub fn strip_usage(usage: &str) -> String {
+    format!("{usage}\n  {}", i18n::text("cli.strip-help"))
+}
@@ -0,0 +1,96 @@
+use crate::selection::Stats;
+use crate::timecode;
+
+// what the copy shortcuts put on the clipboard, `{name}` placeholders are filled in and
+// anything else is copied as written
+#[derive(Clone)]
+pub struct Templates {
+    // {file} {time} {frame}
+    pub timestamp: String,
+    // {file} {start} {end} {start_frame} {end_frame} {duration}
+    pub selection: String,
+    // everything the selection has, plus {peak} {rms} {loudness}
+    pub stats: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            timestamp: "{file} @ {time}".to_string(),
+            selection: "{file} {start}-{end}".to_string(),
+            stats: "{file} {start}-{end} ({duration})  peak {peak} dBFS  rms {rms} dBFS  \
+                    {loudness} LUFS"
+                .to_string(),
+        }
+    }
+}
+
+impl Templates {
+    pub fn timestamp(&self, file: &str, frame: usize, sample_rate: u32) -> String {
+        fill(
+            &self.timestamp,
+            &[
+                ("file", file.to_string()),
+                ("time", timecode::format(frame as f64 / sample_rate as f64)),
+                ("frame", frame.to_string()),
+            ],
+        )
+    }
+
+    pub fn selection(&self, file: &str, stats: &Stats, sample_rate: u32) -> String {
+        fill(&self.selection, &selection_fields(file, stats, sample_rate))
+    }
+
+    pub fn stats(&self, file: &str, stats: &Stats, sample_rate: u32) -> String {
+        let mut fields = selection_fields(file, stats, sample_rate);
+        fields.extend([
+            ("peak", format!("{:.1}", stats.peak_db)),
+            ("rms", format!("{:.1}", stats.rms_db)),
+            (
+                "loudness",
+                stats
+                    .loudness
+                    .map_or("-inf".to_string(), |lufs| format!("{lufs:.1}")),
+            ),
+        ]);
+        fill(&self.stats, &fields)
+    }
+}
+
+fn selection_fields(file: &str, stats: &Stats, sample_rate: u32) -> Vec<(&'static str, String)> {
+    let time = |frame: usize| timecode::format(frame as f64 / sample_rate as f64);
+    vec![
+        ("file", file.to_string()),
+        ("start", time(stats.frames.start)),
+        ("end", time(stats.frames.end)),
+        ("start_frame", stats.frames.start.to_string()),
+        ("end_frame", stats.frames.end.to_string()),
+        ("duration", timecode::format(stats.seconds)),
+    ]
+}
+
+// `template` with every `{name}` in `fields` replaced by its value
+fn fill(template: &str, fields: &[(&str, String)]) -> String {
+    fields
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_known_placeholders_and_keeps_the_rest() {
+        let templates = Templates {
+            timestamp: "{file} at {time} ({frame}) {unknown}".to_string(),
+            ..Templates::default()
+        };
+        assert_eq!(
+            templates.timestamp("take1.wav", 66150, 44100),
+            "take1.wav at 00:01.500 (66150) {unknown}"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Grayscale,
+    Audacity,
+}
+
+// evenly spaced stops sampled from the matplotlib maps, linearly interpolated in between
+const VIRIDIS: [(u8, u8, u8); 9] = [
+    (68, 1, 84),
+    (71, 45, 123),
+    (59, 82, 139),
+    (44, 114, 142),
+    (33, 145, 140),
+    (40, 174, 128),
+    (94, 201, 98),
+    (173, 220, 48),
+    (253, 231, 37),
+];
+
+const MAGMA: [(u8, u8, u8); 9] = [
+    (0, 0, 4),
+    (28, 16, 68),
+    (79, 18, 123),
+    (129, 37, 129),
+    (181, 54, 122),
+    (229, 80, 100),
+    (251, 135, 97),
+    (254, 194, 135),
+    (252, 253, 191),
+];
+
+const GRAYSCALE: [(u8, u8, u8); 2] = [(0, 0, 0), (255, 255, 255)];
+
+// the gradient older audacity versions used: gray background through blue, violet and red
+const AUDACITY: [(u8, u8, u8); 5] = [
+    (191, 191, 191),
+    (76, 153, 255),
+    (229, 25, 229),
+    (255, 0, 0),
+    (255, 255, 255),
+];
+
+impl Colormap {
+    pub const ALL: [Colormap; 4] = [
+        Colormap::Viridis,
+        Colormap::Magma,
+        Colormap::Grayscale,
+        Colormap::Audacity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Grayscale => "grayscale",
+            Colormap::Audacity => "audacity",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|map| map.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&map| map == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // `t` is the position on the map from 0.0 (quietest) to 1.0 (loudest)
+    pub fn color(self, t: f32) -> (u8, u8, u8) {
+        let stops: &[(u8, u8, u8)] = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Grayscale => &GRAYSCALE,
+            Colormap::Audacity => &AUDACITY,
+        };
+
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+        let (a, b) = (stops[index], stops[index + 1]);
+        let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * fraction) as u8;
+        (lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+use std::{env, fs, path::PathBuf};
+
+use crate::clipboard::Templates;
+use crate::i18n;
+
+// behaviour settings, read from a `key = value` file in the same format as the theme:
+//
+//   # desktop notifications when a file starts playing and when playback finishes
+//   notifications = on
+//   # start with seeks and loop points moved to the nearest zero crossing
+//   zero_crossing_snap = on
+//   # how much later than sdl thinks the sound comes out, as measured with `wave sync-test`
+//   output_delay_ms = 40
+//   # what ctrl+C, ctrl+shift+C and ctrl+alt+C copy, see `clipboard::Templates` for the
+//   # placeholders
+//   clipboard_timestamp = {file} @ {time}
+//   clipboard_selection = {file} {start}-{end}
+//   clipboard_stats = {start}-{end} peak {peak} loudness {loudness} LUFS
+//   # megabytes of samples a file may take up in memory, the rest goes to a temporary file.
+//   # Half of the physical memory unless set
+//   memory_budget_mb = 4096
+#[derive(Default)]
+pub struct Config {
+    // off unless asked for
+    pub notifications: bool,
+    pub zero_crossing_snap: bool,
+    pub output_delay: Duration,
+    pub clipboard: Templates,
+    // in bytes, None for the library's default
+    pub memory_budget: Option<u64>,
+}
+
+impl Config {
+    // $WAVE_CONFIG if set, otherwise $XDG_CONFIG_HOME/wave/config or ~/.config/wave/config
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("WAVE_CONFIG") {
+            return Some(path.into());
+        }
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("wave").join("config"))
+    }
+
+    // a missing config file just means the defaults, malformed lines are reported and skipped
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        let Some(contents) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return config;
+        };
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Err(e) = config.set(line) {
+                eprintln!(
+                    "{}",
+                    i18n::fill(
+                        "config.bad-line",
+                        &[("line", (number + 1).to_string()), ("error", e.to_string())]
+                    )
+                );
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key = value`, got `{line}`"))?;
+        let (key, value) = (key.trim(), value.trim());
+        let switch = || match value {
+            "on" | "true" | "yes" => Ok(true),
+            "off" | "false" | "no" => Ok(false),
+            _ => Err(format!("expected on or off, got `{value}`")),
+        };
+
+        match key {
+            "notifications" => self.notifications = switch()?,
+            "zero_crossing_snap" => self.zero_crossing_snap = switch()?,
+            "output_delay_ms" => {
+                let ms = value
+                    .parse()
+                    .map_err(|_| format!("expected milliseconds, got `{value}`"))?;
+                self.output_delay = Duration::from_millis(ms);
+            }
+            "memory_budget_mb" => {
+                let mb: u64 = value
+                    .parse()
+                    .map_err(|_| format!("expected megabytes, got `{value}`"))?;
+                self.memory_budget = Some(mb * 1024 * 1024);
+            }
+            "clipboard_timestamp" => self.clipboard.timestamp = value.to_string(),
+            "clipboard_selection" => self.clipboard.selection = value.to_string(),
+            "clipboard_stats" => self.clipboard.stats = value.to_string(),
+            _ => return Err(format!("unknown key `{key}`")),
+        }
+        Ok(())
+    }
+}
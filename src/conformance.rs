@@ -0,0 +1,602 @@
+// conformance suite: a corpus of wav files generated from a fixed signal, each decoded and
+// hashed against the value it decoded to when the case was added. A changed hash means the
+// decoder reads some existing file differently, which should only happen on purpose.
+//
+// only what the decoder supports is covered: 16, 24 and 32-bit integer, 32-bit float, A-law
+// and µ-law pcm, plain or in an extensible fmt chunk, any channel count and sample rate, RIFF
+// or RF64, and whatever chunks come before, between or after the fmt chunk and the data.
+// Other bit depths and formats are added here as the decoder learns them. Set WAVE_CORPUS to
+// a directory to also write the corpus out for checking with other tools.
+//
+// checks of a single module live in that module's tests, which build their files with the
+// helpers here; what stays is the corpus and the checks across formats
+use std::{env, fs};
+
+use crate::riff::{self, Chunk};
+use crate::{WavFile, id3, info_list};
+
+// what every corpus file decoded to when it was added, None for files that must be rejected
+const GOLDEN: &[(&str, Option<u64>)] = &[
+    ("mono-8000", Some(0x3c19_d832_6ff9_2c2e)),
+    ("stereo-44100", Some(0xaa38_654c_288c_887f)),
+    ("stereo-48000", Some(0x2895_b797_eefc_fb40)),
+    ("5.1-48000", Some(0x5494_974e_8fca_1233)),
+    ("mono-96000", Some(0x59e6_1e06_8fa4_7f49)),
+    ("silence", Some(0x9f49_22e4_d11c_b5fe)),
+    ("full-scale", Some(0xf64b_a3d5_d02d_e033)),
+    ("empty-data", Some(0x367d_4d63_6b07_32fe)),
+    ("trailing-list-info", Some(0x08ef_2900_0362_fed1)),
+    ("trailing-id3", Some(0xb83e_8d10_7bbb_2c34)),
+    ("list-info-over-id3", Some(0x68a3_d819_4312_2c0c)),
+    ("trailing-ixml-padded", Some(0xc77b_199a_e2d4_30df)),
+    ("odd-unknown-chunk-then-list", Some(0x298d_26c3_ba5f_ff69)),
+    ("odd-data-size", Some(0xaefc_4310_f989_18a8)),
+    ("truncated-trailing-chunk", Some(0xda18_6f67_e9fa_5306)),
+    ("truncated-data", None),
+    ("junk-before-fmt", Some(0x5cae_9ee3_3ca7_5dbb)),
+    ("list-and-fact-before-data", Some(0xfb75_5448_f60f_2ed4)),
+    ("odd-chunk-before-data", Some(0xaefc_4310_f989_18a8)),
+    ("fmt-with-extension", Some(0xaefc_4310_f989_18a8)),
+    ("data-before-fmt", None),
+    ("no-data", None),
+    ("24-bit-stereo-48000", Some(0x7855_69bf_86fb_73fe)),
+    ("24-bit-full-scale", Some(0x3088_5e00_aee6_6b4f)),
+    ("24-bit-odd-data-size", Some(0xa38d_d3ec_8fb1_56f4)),
+    ("8-bit", None),
+    ("no-channels", None),
+    ("sample-rate-0", None),
+    ("float-stereo-44100", Some(0xed1e_c79d_26e5_6aca)),
+    ("float-over-full-scale", Some(0x668c_111b_8546_56f2)),
+    ("float-64-bit", None),
+    ("extensible-stereo", Some(0x712b_7f55_27f1_fb15)),
+    ("extensible-5.1-24-in-32", Some(0x0d35_fca1_110b_8e25)),
+    ("extensible-float", Some(0x0bb2_9886_ea7d_3350)),
+    ("extensible-unknown-subformat", None),
+    ("a-law-8000", Some(0x9834_8997_16fb_3785)),
+    ("mu-law-8000", Some(0xe5c7_0833_749f_858d)),
+    ("mu-law-16-bit", None),
+    ("rf64-stereo-48000", Some(0x2895_b797_eefc_fb40)),
+    ("bw64-stereo-48000", Some(0x2895_b797_eefc_fb40)),
+];
+
+struct Case {
+    name: &'static str,
+    bytes: Vec<u8>,
+}
+
+fn corpus() -> Vec<Case> {
+    let tags = info_list::update(&[], &[(*b"INAM", "Corpus"), (*b"IART", "wave")]);
+    let id3_tag = id3::update(&[], &[(*b"TIT2", "From id3"), (*b"TALB", "Corpus")]);
+    let ixml = b"<BWFXML><PROJECT>corpus</PROJECT></BWFXML>\0\0\0\0".to_vec();
+
+    let mut cases = vec![
+        case("mono-8000", wav(1, 8000, &signal(1, 8000, 800), &[])),
+        case("stereo-44100", wav(2, 44100, &signal(2, 44100, 4410), &[])),
+        case("stereo-48000", wav(2, 48000, &signal(2, 48000, 4800), &[])),
+        case("5.1-48000", wav(6, 48000, &signal(6, 48000, 480), &[])),
+        case("mono-96000", wav(1, 96000, &signal(1, 96000, 9600), &[])),
+        case("silence", wav(2, 44100, &[0; 2000], &[])),
+        case(
+            "full-scale",
+            wav(1, 8000, &[i16::MAX, i16::MIN, i16::MAX, -1, 0, 1], &[]),
+        ),
+        case("empty-data", wav(2, 44100, &[], &[])),
+        case(
+            "trailing-list-info",
+            wav(
+                2,
+                44100,
+                &signal(2, 44100, 100),
+                &[(*b"LIST", tags.clone())],
+            ),
+        ),
+        case(
+            "trailing-id3",
+            wav(
+                2,
+                44100,
+                &signal(2, 44100, 100),
+                &[(*b"id3 ", id3_tag.clone())],
+            ),
+        ),
+        case(
+            "list-info-over-id3",
+            wav(
+                2,
+                44100,
+                &signal(2, 44100, 100),
+                &[(*b"id3 ", id3_tag), (*b"LIST", tags)],
+            ),
+        ),
+        case(
+            "trailing-ixml-padded",
+            wav(1, 48000, &signal(1, 48000, 100), &[(*b"iXML", ixml)]),
+        ),
+        case(
+            "odd-unknown-chunk-then-list",
+            wav(
+                1,
+                8000,
+                &signal(1, 8000, 100),
+                &[
+                    (*b"junk", vec![1, 2, 3]),
+                    (
+                        *b"LIST",
+                        info_list::update(&[], &[(*b"INAM", "After padding")]),
+                    ),
+                ],
+            ),
+        ),
+    ];
+
+    // a stray byte after the samples makes the data chunk odd sized and padded, the half
+    // sample is dropped
+    let mut bytes = wav(1, 8000, &signal(1, 8000, 100), &[]);
+    set_data_size(&mut bytes, 201);
+    bytes.extend([0x7f, 0]);
+    fix_riff_size(&mut bytes);
+    cases.push(case("odd-data-size", bytes));
+
+    // a trailing chunk claiming more than is left is read as far as it goes
+    let mut bytes = wav(
+        1,
+        8000,
+        &signal(1, 8000, 100),
+        &[(*b"LIST", info_list::update(&[], &[(*b"INAM", "Cut")]))],
+    );
+    let len = bytes.len();
+    bytes.truncate(len - 2);
+    cases.push(case("truncated-trailing-chunk", bytes));
+
+    // audio cut short of what the data chunk claims can't be decoded
+    let mut bytes = wav(2, 44100, &signal(2, 44100, 100), &[]);
+    let len = bytes.len();
+    bytes.truncate(len - 10);
+    cases.push(case("truncated-data", bytes));
+
+    // chunks ahead of the audio, as written by editors and broadcast recorders
+    let bytes = wav(2, 44100, &signal(2, 44100, 100), &[]);
+    cases.push(case(
+        "junk-before-fmt",
+        insert_chunk(&bytes, 0, *b"JUNK", vec![0; 28]),
+    ));
+    let bytes = insert_chunk(
+        &bytes,
+        1,
+        *b"LIST",
+        info_list::update(&[], &[(*b"INAM", "Before data")]),
+    );
+    cases.push(case(
+        "list-and-fact-before-data",
+        insert_chunk(&bytes, 1, *b"fact", 100u32.to_le_bytes().to_vec()),
+    ));
+    let bytes = wav(1, 8000, &signal(1, 8000, 100), &[]);
+    cases.push(case(
+        "odd-chunk-before-data",
+        insert_chunk(&bytes, 1, *b"junk", vec![1, 2, 3]),
+    ));
+
+    // an 18-byte fmt chunk with an empty extension, as some encoders write for plain pcm
+    let mut chunks = riff::read_chunks(&wav(1, 8000, &signal(1, 8000, 100), &[])).unwrap();
+    chunks[0].data.extend(0u16.to_le_bytes());
+    cases.push(case("fmt-with-extension", riff::write_chunks(&chunks)));
+
+    // nothing says how to read data that comes before fmt, or a file without any
+    let mut chunks = riff::read_chunks(&wav(1, 8000, &signal(1, 8000, 100), &[])).unwrap();
+    chunks.swap(0, 1);
+    cases.push(case("data-before-fmt", riff::write_chunks(&chunks)));
+    chunks.remove(0);
+    cases.push(case("no-data", riff::write_chunks(&chunks)));
+
+    // 24-bit samples come out dithered down to 16 bits
+    cases.push(case(
+        "24-bit-stereo-48000",
+        wav24(2, 48000, &signal(2, 48000, 4800)),
+    ));
+    cases.push(case(
+        "24-bit-full-scale",
+        wav24(1, 8000, &[i16::MAX, i16::MIN, i16::MAX, -1, 0, 1]),
+    ));
+    let mut bytes = wav24(1, 8000, &signal(1, 8000, 100));
+    set_data_size(&mut bytes, 301);
+    bytes.extend([0x7f, 0]);
+    fix_riff_size(&mut bytes);
+    cases.push(case("24-bit-odd-data-size", bytes));
+    cases.push(case("8-bit", pcm(1, 8000, 8, vec![0x80; 100], &[])));
+    // a format that can't be played at all
+    cases.push(case("no-channels", pcm(0, 8000, 16, vec![0; 200], &[])));
+    cases.push(case("sample-rate-0", pcm(1, 0, 16, vec![0; 200], &[])));
+
+    // floats are dithered down to 16 bits like 24-bit samples, and clipped past full scale
+    let samples: Vec<f32> = signal(2, 44100, 4410)
+        .iter()
+        .map(|&s| s as f32 / 32768.0)
+        .collect();
+    cases.push(case("float-stereo-44100", wav_float(2, 44100, &samples)));
+    cases.push(case(
+        "float-over-full-scale",
+        wav_float(1, 8000, &[1.5, -1.5, 1.0, -1.0, 0.0, f32::NAN]),
+    ));
+    cases.push(case(
+        "float-64-bit",
+        pcm_format(3, 1, 8000, 64, vec![0; 80], &[]),
+    ));
+
+    // WAVE_FORMAT_EXTENSIBLE, the real format is in the subformat GUID
+    let samples = signal(2, 48000, 480);
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    cases.push(case(
+        "extensible-stereo",
+        extensible(2, 48000, 16, 16, 0x3, 1, data),
+    ));
+    let data = signal(6, 48000, 480)
+        .iter()
+        .flat_map(|&s| (((s as i32) << 16) | 0x5a00).to_le_bytes())
+        .collect();
+    cases.push(case(
+        "extensible-5.1-24-in-32",
+        extensible(6, 48000, 32, 24, 0x3f, 1, data),
+    ));
+    let data = samples
+        .iter()
+        .flat_map(|&s| (s as f32 / 32768.0).to_le_bytes())
+        .collect();
+    cases.push(case(
+        "extensible-float",
+        extensible(2, 48000, 32, 32, 0x3, 3, data),
+    ));
+    let mut bytes = extensible(1, 8000, 16, 16, 0x4, 1, vec![0; 20]);
+    // the fmt chunk starts at 12, its GUID ends it
+    bytes[20 + 24 + 2] ^= 0xff;
+    cases.push(case("extensible-unknown-subformat", bytes));
+
+    // telephony recordings, every code once and then some speech-like noise
+    let codes: Vec<u8> = (0..=255)
+        .chain(signal(1, 8000, 400).iter().map(|&s| s as u8))
+        .collect();
+    cases.push(case(
+        "a-law-8000",
+        pcm_format(6, 1, 8000, 8, codes.clone(), &[]),
+    ));
+    cases.push(case("mu-law-8000", pcm_format(7, 1, 8000, 8, codes, &[])));
+    cases.push(case(
+        "mu-law-16-bit",
+        pcm_format(7, 1, 8000, 16, vec![0; 20], &[]),
+    ));
+
+    // RF64 keeps its sizes in a ds64 chunk, decoding the same as the plain file
+    let samples = signal(2, 48000, 4800);
+    cases.push(case(
+        "rf64-stereo-48000",
+        rf64(&wav(2, 48000, &samples, &[])),
+    ));
+    let mut bytes = rf64(&wav(2, 48000, &samples, &[]));
+    bytes[0..4].copy_from_slice(b"BW64");
+    cases.push(case("bw64-stereo-48000", bytes));
+    cases
+}
+
+fn case(name: &'static str, bytes: Vec<u8>) -> Case {
+    Case { name, bytes }
+}
+
+// a triangle per channel plus a little deterministic noise, so every sample differs and
+// channels can't be swapped without the hash noticing. Integer only, float sines could round
+// differently from one libm to the next
+pub(crate) fn signal(channels: u16, sample_rate: u32, frames: usize) -> Vec<i16> {
+    let mut noise: u32 = 0x1234_5678;
+    let mut samples = Vec::with_capacity(frames * channels as usize);
+    for frame in 0..frames {
+        for channel in 0..channels {
+            noise = noise.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let period = (sample_rate / (220 * (channel as u32 + 1))).max(2) as i64;
+            let phase = frame as i64 % period;
+            let triangle = (4 * phase - 2 * period).abs() - period;
+            let value = triangle * 24000 / period + (noise >> 26) as i64 - 32;
+            samples.push(value as i16);
+        }
+    }
+    samples
+}
+
+pub(crate) fn wav(
+    channels: u16,
+    sample_rate: u32,
+    samples: &[i16],
+    trailing: &[([u8; 4], Vec<u8>)],
+) -> Vec<u8> {
+    let data = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    pcm(channels, sample_rate, 16, data, trailing)
+}
+
+// 24-bit samples stretched from `samples`, with the low byte filled in
+pub(crate) fn wav24(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data = samples
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &s)| {
+            let value = (s as i32) << 8 | ((i as i32 * 37) & 0xff);
+            let [low, mid, high, _] = value.to_le_bytes();
+            [low, mid, high]
+        })
+        .collect();
+    pcm(channels, sample_rate, 24, data, &[])
+}
+
+fn pcm(
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    data: Vec<u8>,
+    trailing: &[([u8; 4], Vec<u8>)],
+) -> Vec<u8> {
+    pcm_format(1, channels, sample_rate, bits_per_sample, data, trailing)
+}
+
+pub(crate) fn extensible(
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    valid_bits: u16,
+    channel_mask: u32,
+    sub_format: u16,
+    data: Vec<u8>,
+) -> Vec<u8> {
+    let mut chunks = riff::read_chunks(&pcm_format(
+        0xfffe,
+        channels,
+        sample_rate,
+        bits_per_sample,
+        data,
+        &[],
+    ))
+    .unwrap();
+    let fmt = &mut chunks[0].data;
+    fmt.extend(22u16.to_le_bytes());
+    fmt.extend(valid_bits.to_le_bytes());
+    fmt.extend(channel_mask.to_le_bytes());
+    fmt.extend(sub_format.to_le_bytes());
+    fmt.extend([
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+    ]);
+    riff::write_chunks(&chunks)
+}
+
+// 32-bit float samples
+fn wav_float(channels: u16, sample_rate: u32, samples: &[f32]) -> Vec<u8> {
+    let data = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    pcm_format(3, channels, sample_rate, 32, data, &[])
+}
+
+fn pcm_format(
+    audio_format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    data: Vec<u8>,
+    trailing: &[([u8; 4], Vec<u8>)],
+) -> Vec<u8> {
+    let block_align = channels * bits_per_sample / 8;
+    let mut fmt = Vec::new();
+    fmt.extend(audio_format.to_le_bytes());
+    fmt.extend(channels.to_le_bytes());
+    fmt.extend(sample_rate.to_le_bytes());
+    fmt.extend((sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend(block_align.to_le_bytes());
+    fmt.extend(bits_per_sample.to_le_bytes());
+
+    let mut chunks = vec![
+        Chunk {
+            id: *b"fmt ",
+            data: fmt,
+        },
+        Chunk { id: *b"data", data },
+    ];
+    chunks.extend(trailing.iter().map(|(id, data)| Chunk {
+        id: *id,
+        data: data.clone(),
+    }));
+    riff::write_chunks(&chunks)
+}
+
+// the data size sits right after the 16-byte fmt chunk
+fn set_data_size(bytes: &mut [u8], size: u32) {
+    bytes[40..44].copy_from_slice(&size.to_le_bytes());
+}
+
+// `bytes` with a chunk inserted before the one at `index`
+pub(crate) fn insert_chunk(bytes: &[u8], index: usize, id: [u8; 4], data: Vec<u8>) -> Vec<u8> {
+    let mut chunks = riff::read_chunks(bytes).unwrap();
+    chunks.insert(index, Chunk { id, data });
+    riff::write_chunks(&chunks)
+}
+
+// `bytes` as an RF64 file: the riff and data sizes set to all ones and the real ones in a ds64
+// chunk up front
+fn rf64(bytes: &[u8]) -> Vec<u8> {
+    let chunks = riff::read_chunks(bytes).unwrap();
+    let data_size = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"data")
+        .map_or(0, |chunk| chunk.data.len() as u64);
+    let mut ds64 = (bytes.len() as u64 + 36 - 8).to_le_bytes().to_vec();
+    ds64.extend(data_size.to_le_bytes());
+    ds64.extend([0; 12]);
+    let mut out = riff::write_chunks(&[Chunk {
+        id: *b"ds64",
+        data: ds64,
+    }]);
+    out.truncate(12 + 36);
+    out[0..4].copy_from_slice(b"RF64");
+    out[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    for chunk in &chunks {
+        out.extend(chunk.id);
+        let size = if &chunk.id == b"data" {
+            u32::MAX
+        } else {
+            chunk.data.len() as u32
+        };
+        out.extend(size.to_le_bytes());
+        out.extend(&chunk.data);
+        if chunk.data.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+    out
+}
+
+pub(crate) fn fix_riff_size(bytes: &mut [u8]) {
+    let size = bytes.len() as u32 - 8;
+    bytes[4..8].copy_from_slice(&size.to_le_bytes());
+}
+
+// fnv-1a over everything the decoder hands the rest of the program
+fn decode_hash(wav: &WavFile) -> u64 {
+    let mut hash = Fnv::default();
+    hash.write(&wav.header.audio_format.to_le_bytes());
+    hash.write(&wav.header.num_channels.to_le_bytes());
+    hash.write(&wav.header.sample_rate.to_le_bytes());
+    hash.write(&wav.header.bits_per_sample.to_le_bytes());
+    for sample in &wav.data {
+        hash.write(&sample.to_le_bytes());
+    }
+    for (label, value) in wav.metadata.fields() {
+        hash.write(label.as_bytes());
+        hash.write(value.as_bytes());
+    }
+    for xml in [&wav.metadata.ixml, &wav.metadata.axml]
+        .into_iter()
+        .flatten()
+    {
+        hash.write(xml.as_bytes());
+    }
+    hash.0
+}
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<WavFile, Box<dyn std::error::Error + 'static>> {
+    WavFile::from_reader(bytes)
+}
+
+#[test]
+fn corpus_decodes_to_golden_hashes() {
+    let corpus = corpus();
+    if let Ok(dir) = env::var("WAVE_CORPUS") {
+        fs::create_dir_all(&dir).unwrap();
+        for case in &corpus {
+            fs::write(format!("{dir}/{}.wav", case.name), &case.bytes).unwrap();
+        }
+    }
+
+    let mut failures = Vec::new();
+    for case in &corpus {
+        let Some(&(_, golden)) = GOLDEN.iter().find(|(name, _)| *name == case.name) else {
+            failures.push(format!("{}: no golden hash", case.name));
+            continue;
+        };
+        let decoded = decode(&case.bytes).map(|wav| decode_hash(&wav));
+        match (decoded, golden) {
+            (Ok(hash), Some(expected)) if hash == expected => {}
+            (Err(_), None) => {}
+            (Ok(hash), _) => failures.push(format!("{}: decoded to {hash:#018x}", case.name)),
+            (Err(e), Some(_)) => failures.push(format!("{}: failed with {e}", case.name)),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn corpus_round_trips_its_samples() {
+    let samples = signal(2, 44100, 1000);
+    let wav = decode(&wav(2, 44100, &samples, &[])).unwrap();
+    assert_eq!(wav.header.num_channels, 2);
+    assert_eq!(wav.header.sample_rate, 44100);
+    assert_eq!(&*wav.data, &samples[..]);
+}
+
+#[test]
+fn trailing_chunks_are_read() {
+    let corpus = corpus();
+    let find = |name| {
+        let case = corpus.iter().find(|case| case.name == name).unwrap();
+        decode(&case.bytes).unwrap().metadata
+    };
+    assert_eq!(find("trailing-list-info").title.as_deref(), Some("Corpus"));
+    assert_eq!(find("trailing-id3").title.as_deref(), Some("From id3"));
+    assert_eq!(find("list-info-over-id3").title.as_deref(), Some("Corpus"));
+    assert_eq!(
+        find("trailing-ixml-padded").ixml.as_deref(),
+        Some("<BWFXML><PROJECT>corpus</PROJECT></BWFXML>")
+    );
+    assert_eq!(
+        find("odd-unknown-chunk-then-list").title.as_deref(),
+        Some("After padding")
+    );
+}
+
+// a big-endian 16-bit AIFF file, or AIFF-C with `compression` and its name
+pub(crate) fn aiff(
+    channels: u16,
+    samples: &[i16],
+    compression: Option<&[u8; 4]>,
+    chunks: &[u8],
+) -> Vec<u8> {
+    let mut comm = Vec::new();
+    comm.extend(channels.to_be_bytes());
+    comm.extend((samples.len() as u32 / channels as u32).to_be_bytes());
+    comm.extend(16u16.to_be_bytes());
+    // 44100 as an 80-bit extended float
+    comm.extend([0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+    let mut data: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+    if let Some(compression) = compression {
+        comm.extend(compression);
+        comm.extend([4, b'n', b'o', b'n', b'e', 0]);
+        if compression == b"sowt" {
+            data = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        }
+    }
+    let mut body = Vec::from(if compression.is_some() {
+        *b"AIFC"
+    } else {
+        *b"AIFF"
+    });
+    body.extend(b"COMM");
+    body.extend((comm.len() as u32).to_be_bytes());
+    body.extend(comm);
+    body.extend(chunks);
+    body.extend(b"SSND");
+    body.extend((data.len() as u32 + 8).to_be_bytes());
+    body.extend([0; 8]);
+    body.extend(data);
+    let mut bytes = Vec::from(*b"FORM");
+    bytes.extend((body.len() as u32).to_be_bytes());
+    bytes.extend(body);
+    bytes
+}
+
+#[test]
+fn aiff_and_aifc_decode_like_wav() {
+    let samples = signal(2, 44100, 500);
+    let from_wav = decode(&wav(2, 44100, &samples, &[])).unwrap();
+    for compression in [None, Some(b"NONE"), Some(b"sowt")] {
+        let wav = decode(&aiff(2, &samples, compression, &[])).unwrap();
+        assert_eq!(wav.header.sample_rate, 44100);
+        assert_eq!(wav.header.num_channels, 2);
+        assert_eq!(wav.data, from_wav.data);
+    }
+}
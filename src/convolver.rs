@@ -0,0 +1,231 @@
+use crate::WavFile;
+use crate::fft::fft;
+
+// frames per partition, and so the latency the convolution adds, 5 ms at 48 kHz
+const BLOCK: usize = 256;
+// longer responses are cut off, every second of them costs about 750 multiplies per sample
+// per channel
+pub const MAX_SECONDS: f64 = 10.0;
+
+// uniformly partitioned overlap-save convolution: the response is cut into blocks whose
+// spectra are kept, every block of input is transformed once and multiplied with all of them
+// against the spectra of the blocks before it. Works a sample at a time like the filters, the
+// output lagging the input by a block
+pub struct Convolver {
+    pub enabled: bool,
+    // the spectra of the response's blocks, per channel of the response
+    partitions: Vec<Vec<Spectrum>>,
+    channels: Vec<ChannelState>,
+}
+
+#[derive(Clone)]
+struct Spectrum {
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl Spectrum {
+    fn zero() -> Self {
+        Self {
+            re: vec![0.0; BLOCK * 2],
+            im: vec![0.0; BLOCK * 2],
+        }
+    }
+}
+
+struct ChannelState {
+    // channel of the response this one is convolved with
+    response: usize,
+    // the previous block of input followed by the one being filled
+    input: Vec<f32>,
+    filled: usize,
+    // spectra of the latest input blocks, as many as the response has partitions, `newest`
+    // being the last one written
+    history: Vec<Spectrum>,
+    newest: usize,
+    sum: Spectrum,
+    output: Vec<f32>,
+}
+
+impl Convolver {
+    // the response in `ir` for playing a file with `channels` at `sample_rate`. A mono
+    // response goes on every channel, otherwise they pair up in order. The response is
+    // resampled if it was captured at another rate, and scaled so noise comes out about as
+    // loud as it went in
+    pub fn new(ir: &WavFile, sample_rate: u32, channels: u16) -> Self {
+        let ir_channels = ir.header.num_channels.max(1) as usize;
+        let ir_rate = ir.header.sample_rate.max(1);
+        let ratio = ir_rate as f64 / sample_rate.max(1) as f64;
+        let frames = ir.data.len() / ir_channels;
+        let length =
+            ((frames as f64 / ratio) as usize).min((MAX_SECONDS * sample_rate as f64) as usize);
+        let responses: Vec<Vec<f32>> = (0..ir_channels)
+            .map(|channel| {
+                let at = |frame: usize| {
+                    ir.data
+                        .get(frame * ir_channels + channel)
+                        .map_or(0.0, |&s| s as f32 / 32768.0)
+                };
+                (0..length)
+                    .map(|i| {
+                        let position = i as f64 * ratio;
+                        let index = position.floor() as usize;
+                        let t = (position - index as f64) as f32;
+                        at(index) + (at(index + 1) - at(index)) * t
+                    })
+                    .collect()
+            })
+            .collect();
+        let energy = responses
+            .iter()
+            .map(|response| response.iter().map(|s| s * s).sum::<f32>())
+            .fold(0.0f32, f32::max);
+        let scale = if energy > 0.0 {
+            energy.sqrt().recip()
+        } else {
+            0.0
+        };
+
+        let count = length.div_ceil(BLOCK).max(1);
+        let partitions: Vec<Vec<Spectrum>> = responses
+            .iter()
+            .map(|response| {
+                response
+                    .chunks(BLOCK)
+                    .chain(std::iter::repeat_n(
+                        &[][..],
+                        count - response.len().div_ceil(BLOCK),
+                    ))
+                    .map(|block| {
+                        let mut spectrum = Spectrum::zero();
+                        for (to, &from) in spectrum.re.iter_mut().zip(block) {
+                            *to = from * scale;
+                        }
+                        fft(&mut spectrum.re, &mut spectrum.im);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+        let channels = (0..channels.max(1) as usize)
+            .map(|channel| ChannelState {
+                response: channel % ir_channels,
+                input: vec![0.0; BLOCK * 2],
+                filled: 0,
+                history: vec![Spectrum::zero(); count],
+                newest: 0,
+                sum: Spectrum::zero(),
+                output: vec![0.0; BLOCK],
+            })
+            .collect();
+        Self {
+            enabled: true,
+            partitions,
+            channels,
+        }
+    }
+
+    pub fn process(&mut self, channel: usize, value: f32) -> f32 {
+        if !self.enabled {
+            return value;
+        }
+        let state = &mut self.channels[channel];
+        let out = state.output[state.filled];
+        state.input[BLOCK + state.filled] = value;
+        state.filled += 1;
+        if state.filled == BLOCK {
+            state.convolve(&self.partitions[state.response]);
+        }
+        out
+    }
+}
+
+impl ChannelState {
+    // the next block of output from a full block of input
+    fn convolve(&mut self, partitions: &[Spectrum]) {
+        let count = self.history.len();
+        self.newest = (self.newest + 1) % count;
+        let newest = &mut self.history[self.newest];
+        newest.re.copy_from_slice(&self.input);
+        newest.im.fill(0.0);
+        fft(&mut newest.re, &mut newest.im);
+
+        // both signals are real, so only the bins up to nyquist are multiplied and the ones
+        // above are their mirror image
+        self.sum.re.fill(0.0);
+        self.sum.im.fill(0.0);
+        for (age, partition) in partitions.iter().enumerate() {
+            let input = &self.history[(self.newest + count - age) % count];
+            for i in 0..=BLOCK {
+                let (a, b) = (input.re[i], input.im[i]);
+                let (c, d) = (partition.re[i], partition.im[i]);
+                self.sum.re[i] += a * c - b * d;
+                self.sum.im[i] += a * d + b * c;
+            }
+        }
+        // the inverse transform as the forward one of the conjugate, only the real part is
+        // needed. The first half is the wrapped around part overlap-save throws away
+        for i in 1..BLOCK {
+            self.sum.re[BLOCK * 2 - i] = self.sum.re[i];
+            self.sum.im[BLOCK * 2 - i] = self.sum.im[i];
+        }
+        for im in &mut self.sum.im[..=BLOCK] {
+            *im = -*im;
+        }
+        fft(&mut self.sum.re, &mut self.sum.im);
+        let scale = 1.0 / (BLOCK * 2) as f32;
+        for (out, &sum) in self.output.iter_mut().zip(&self.sum.re[BLOCK..]) {
+            *out = sum * scale;
+        }
+        self.input.copy_within(BLOCK.., 0);
+        self.filled = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wave::Header;
+
+    fn wav(channels: u16, data: Vec<i16>) -> WavFile {
+        WavFile {
+            header: Header {
+                num_channels: channels,
+                sample_rate: 48000,
+                ..Header::default()
+            },
+            data: data.into(),
+            ..WavFile::default()
+        }
+    }
+
+    #[test]
+    fn matches_direct_convolution_a_block_late() {
+        // a response spanning a few partitions, and input as long again
+        let response: Vec<i16> = (0..BLOCK * 3 + 17)
+            .map(|i| ((i * 7919 % 2000) as i16 - 1000) * (i % 3) as i16)
+            .collect();
+        let mut convolver = Convolver::new(&wav(1, response.clone()), 48000, 1);
+        let scale = response
+            .iter()
+            .map(|&s| (s as f32 / 32768.0).powi(2))
+            .sum::<f32>()
+            .sqrt()
+            .recip()
+            / 32768.0;
+        let input: Vec<f32> = (0..BLOCK * 8)
+            .map(|i| ((i * 31) % 17) as f32 - 8.0)
+            .collect();
+        let output: Vec<f32> = input.iter().map(|&x| convolver.process(0, x)).collect();
+        for n in BLOCK..input.len() {
+            let expected: f32 = (0..response.len().min(n - BLOCK + 1))
+                .map(|k| input[n - BLOCK - k] * response[k] as f32 * scale)
+                .sum();
+            assert!(
+                (output[n] - expected).abs() < 1e-3,
+                "{n}: {} {expected}",
+                output[n]
+            );
+        }
+    }
+}
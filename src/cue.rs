@@ -0,0 +1,99 @@
+// https://www.recordingblogs.com/wiki/cue-chunk-of-a-wave-file and the `labl` subchunks of a
+// LIST chunk of type `adtl` that name the points
+
+/// A marker an editor placed in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub id: u32,
+    /// Frame offset into the samples.
+    pub frame: usize,
+    pub label: Option<String>,
+}
+
+// the points of a `cue ` chunk body in file order, a count larger than the chunk is cut short
+pub fn parse(body: &[u8]) -> Vec<Cue> {
+    // dwName, dwPosition, fccChunk, dwChunkStart, dwBlockStart, dwSampleOffset. The sample
+    // offset is the one that counts from the start of the data chunk, the position is in play
+    // order which only differs for wavl files
+    body.get(4..)
+        .unwrap_or_default()
+        .chunks_exact(24)
+        .map(|point| Cue {
+            id: le_u32(&point[0..4]),
+            frame: le_u32(&point[20..24]) as usize,
+            label: None,
+        })
+        .collect()
+}
+
+// (cue id, text) of every `labl` subchunk of a LIST body, empty unless it is an adtl list
+pub fn labels(list: &[u8]) -> Vec<(u32, String)> {
+    let mut labels = Vec::new();
+    if !list.starts_with(b"adtl") {
+        return labels;
+    }
+    let mut offset = 4;
+    while offset + 8 <= list.len() {
+        let id = &list[offset..offset + 4];
+        let size = le_u32(&list[offset + 4..offset + 8]) as usize;
+        let start = offset + 8;
+        let end = (start + size).min(list.len());
+        if id == b"labl" && end >= start + 4 {
+            let text = String::from_utf8_lossy(&list[start + 4..end]);
+            let text = text.trim_end_matches('\0').trim();
+            if !text.is_empty() {
+                labels.push((le_u32(&list[start..start + 4]), text.to_string()));
+            }
+        }
+        offset = start + size + size % 2;
+    }
+    labels
+}
+
+// names the cues, the chunks can come in either order so this runs once both are read. Sorted
+// by position afterwards since editors write them in the order they were added
+pub fn label(cues: &mut [Cue], labels: &[(u32, String)]) {
+    for cue in cues.iter_mut() {
+        if let Some((_, text)) = labels.iter().find(|(id, _)| *id == cue.id) {
+            cue.label = Some(text.clone());
+        }
+    }
+    cues.sort_by_key(|cue| cue.frame);
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{decode, insert_chunk, signal, wav};
+
+    #[test]
+    fn cue_points_are_read_with_their_labels_in_play_order() {
+        // two points written out of order before the data, named by an adtl list after it
+        let mut cues = 2u32.to_le_bytes().to_vec();
+        for (id, frame) in [(7u32, 300u32), (3, 100)] {
+            cues.extend(id.to_le_bytes());
+            cues.extend(frame.to_le_bytes());
+            cues.extend(b"data");
+            cues.extend([0; 8]);
+            cues.extend(frame.to_le_bytes());
+        }
+        let mut labels = b"adtl".to_vec();
+        for (id, text) in [(3u32, &b"Verse\0"[..]), (7, b"Chorus\0")] {
+            labels.extend(b"labl");
+            labels.extend((4 + text.len() as u32).to_le_bytes());
+            labels.extend(id.to_le_bytes());
+            labels.extend(text);
+        }
+        let bytes = wav(1, 8000, &signal(1, 8000, 400), &[(*b"LIST", labels)]);
+        let wav = decode(&insert_chunk(&bytes, 1, *b"cue ", cues)).unwrap();
+        let found: Vec<_> = wav
+            .cues()
+            .iter()
+            .map(|cue| (cue.id, cue.frame, cue.label.as_deref()))
+            .collect();
+        assert_eq!(found, [(3, 100, Some("Verse")), (7, 300, Some("Chorus"))]);
+    }
+}
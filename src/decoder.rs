@@ -0,0 +1,184 @@
+use std::error;
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::ops::Range;
+
+use crate::AudioSource;
+
+// reads windows of a wav, AIFF, FLAC, MP3 or Ogg file, for paths that only need part of a file
+// and shouldn't have to hold all of it. Opening only reads the header, the samples before a
+// window are stepped over without decoding in a wav file and decoded and dropped in the others
+pub struct Decoder {
+    path: String,
+    // the file the source reads from, which shares its position with it
+    file: File,
+    source: Box<dyn AudioSource>,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub frames: u64,
+    // the frame the source reads next, it only goes forwards
+    position: u64,
+}
+
+impl Decoder {
+    pub fn open(path: &str) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+        let source = open(&file, path)?;
+        let header = source.header();
+        let channels = header.num_channels.max(1);
+        let sample_rate = header.sample_rate;
+        let frame_bytes = header.bits_per_sample.div_ceil(8).max(1) as u64 * channels as u64;
+        let frames = source.data_size() / frame_bytes;
+        let mut decoder = Self {
+            path: path.to_string(),
+            file,
+            source,
+            channels,
+            sample_rate,
+            frames,
+            position: 0,
+        };
+        // MP3 and Ogg don't say how long they are, they're counted through once
+        if decoder.frames == 0 {
+            decoder.frames = decoder.source.skip_samples(u64::MAX)? / channels as u64;
+            decoder.rewind()?;
+        }
+        Ok(decoder)
+    }
+
+    // the interleaved samples of `frames`, clipped to the end of the file
+    pub fn read_range(
+        &mut self,
+        frames: Range<u64>,
+    ) -> Result<Vec<i16>, Box<dyn error::Error + 'static>> {
+        let end = frames.end.min(self.frames);
+        let start = frames.start.min(end);
+        if start < self.position {
+            self.rewind()?;
+        }
+        let channels = self.channels as u64;
+        let skipped = self
+            .source
+            .skip_samples((start - self.position) * channels)?;
+        self.position += skipped / channels;
+
+        let mut samples = vec![0; ((end - start) * channels) as usize];
+        let mut read = 0;
+        while read < samples.len() {
+            match self.source.read_samples(&mut samples[read..])? {
+                0 => break,
+                count => read += count,
+            }
+        }
+        samples.truncate(read - read % channels as usize);
+        self.position += samples.len() as u64 / channels;
+        Ok(samples)
+    }
+
+    // back to the first sample, by seeking the file to its start and reading the header again
+    fn rewind(&mut self) -> Result<(), Box<dyn error::Error + 'static>> {
+        (&self.file)
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| format!("{}: {e}", self.path))?;
+        self.source = open(&self.file, &self.path)?;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+// a source reading from where `file` is, through a handle sharing its position
+fn open(file: &File, path: &str) -> Result<Box<dyn AudioSource>, Box<dyn error::Error + 'static>> {
+    let file = file.try_clone().map_err(|e| format!("{path}: {e}"))?;
+    crate::open_source(file).map_err(|e| format!("{path}: {e}").into())
+}
+
+// the frames around a position that mostly moves forwards, like the playhead the waveform
+// view follows. Reads a second ahead at a time and carries on from where the last read
+// ended, so the decoder only goes back to the start when the position jumps backwards
+pub struct Window {
+    decoder: Decoder,
+    // the first frame of `samples`
+    start: u64,
+    samples: Vec<i16>,
+}
+
+impl Window {
+    pub fn new(decoder: Decoder) -> Self {
+        Self {
+            decoder,
+            start: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    // the interleaved samples of `frames`, clipped to the end of the file
+    pub fn read(&mut self, frames: Range<u64>) -> Result<&[i16], Box<dyn error::Error + 'static>> {
+        let channels = self.decoder.channels as u64;
+        let end = self.start + self.samples.len() as u64 / channels;
+        if frames.start < self.start || frames.start > end {
+            self.start = frames.start;
+            self.samples.clear();
+        } else if frames.end > end {
+            // what's behind the window isn't needed again
+            let behind = (frames.start - self.start) * channels;
+            self.samples.drain(..behind as usize);
+            self.start = frames.start;
+        }
+        let end = self.start + self.samples.len() as u64 / channels;
+        if frames.end > end && end < self.decoder.frames {
+            let ahead = frames.end.max(end + self.decoder.sample_rate.max(1) as u64);
+            let samples = self.decoder.read_range(end..ahead)?;
+            self.samples.extend(samples);
+        }
+        let from = ((frames.start - self.start) * channels) as usize;
+        let to = ((frames.end - self.start) * channels) as usize;
+        Ok(&self.samples[from.min(self.samples.len())..to.min(self.samples.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::conformance::{decode, signal, wav, wav24};
+
+    #[test]
+    fn ranges_of_a_24_bit_file_read_like_the_whole_file() {
+        let bytes = wav24(2, 48000, &signal(2, 48000, 1000));
+        let path = env::temp_dir().join(format!("wave-{}-range-24.wav", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let whole = decode(&bytes).unwrap();
+        let mut decoder = Decoder::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(decoder.frames, 1000);
+        // the dither runs differently once samples are skipped
+        let close = |range: &[i16], whole: &[i16]| {
+            range.len() == whole.len()
+                && range
+                    .iter()
+                    .zip(whole)
+                    .all(|(&a, &b)| (a as i32 - b as i32).abs() <= 2)
+        };
+        let later = decoder.read_range(600..650).unwrap();
+        assert!(close(&later, &whole.data[1200..1300]));
+        // going back seeks to the start of the file again
+        let earlier = decoder.read_range(10..20).unwrap();
+        assert!(close(&earlier, &whole.data[20..40]));
+        assert_eq!(decoder.read_range(990..2000).unwrap().len(), 20);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_window_moving_forwards_and_back_reads_like_the_whole_file() {
+        let samples = signal(2, 8000, 20000);
+        let path = env::temp_dir().join(format!("wave-{}-window.wav", std::process::id()));
+        fs::write(&path, wav(2, 8000, &samples, &[])).unwrap();
+        let mut window = Window::new(Decoder::open(path.to_str().unwrap()).unwrap());
+        for start in [0, 100, 7000, 9500, 300, 19990] {
+            let read = window.read(start..start + 2048).unwrap();
+            let end = (start as usize + 2048).min(20000);
+            assert_eq!(read, &samples[start as usize * 2..end * 2]);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+//! Ways a wav file deviates from the spec that didn't stop it from being read. The reader
+//! notes them as it goes instead of giving up, so `wave validate` and the player can say
+//! exactly what's off about a file.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Diagnostic {
+    /// A chunk that isn't part of any spec wave knows of, skipped.
+    UnknownChunk { id: String, size: u32 },
+    /// `byte_rate` isn't `sample_rate * block_align`.
+    ByteRate { stated: u32, expected: u32 },
+    /// `block_align` isn't the channel count times the bytes of a sample.
+    BlockAlign { stated: u16, expected: u16 },
+    /// The extensible format's valid bits are more than the samples hold.
+    ValidBits { valid: u16, bits: u16 },
+    /// A chunk claims more bytes than are left in the file, it's read as far as it goes.
+    TruncatedChunk {
+        id: String,
+        size: u32,
+        available: usize,
+    },
+    /// Something after the last chunk that isn't a chunk.
+    TrailingBytes,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownChunk { id, size } => {
+                write!(f, "unknown `{id}` chunk of {size} bytes, skipped")
+            }
+            Self::ByteRate { stated, expected } => write!(
+                f,
+                "byte rate is {stated}, sample rate times block align is {expected}"
+            ),
+            Self::BlockAlign { stated, expected } => write!(
+                f,
+                "block align is {stated}, channels times bytes per sample is {expected}"
+            ),
+            Self::ValidBits { valid, bits } => {
+                write!(f, "{valid} valid bits in {bits}-bit samples")
+            }
+            Self::TruncatedChunk {
+                id,
+                size,
+                available,
+            } => write!(
+                f,
+                "`{id}` chunk claims {size} bytes but the file ends after {available}"
+            ),
+            Self::TrailingBytes => write!(f, "bytes after the last chunk that aren't a chunk"),
+        }
+    }
+}
+
+// the chunks wave reads, then ones it doesn't that other software writes as a matter of
+// course: padding, the sample count of compressed formats, peak envelopes, sampler and loop
+// information and broadcast extensions
+const KNOWN_CHUNKS: &[&str] = &[
+    "fmt ", "data", "ds64", "LIST", "id3 ", "ID3 ", "cue ", "smpl", "iXML", "axml", "bext", "fact",
+    "JUNK", "junk", "PAD ", "FLLR", "PEAK", "inst", "acid", "plst", "minf", "elm1", "regn", "umid",
+    "DISP", "_PMX", "cart", "levl", "chna", "dbmd", "LGWV", "ResU",
+];
+
+/// Whether `id` is a chunk some spec describes, read by wave or not.
+pub fn known_chunk(id: &str) -> bool {
+    KNOWN_CHUNKS.contains(&id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::{decode, fix_riff_size, signal, wav};
+
+    #[test]
+    fn deviations_from_the_spec_are_collected_and_the_file_still_reads() {
+        let samples = signal(2, 8000, 100);
+        let mut bytes = wav(2, 8000, &samples, &[(*b"wxyz", vec![1, 2, 3, 4])]);
+        // the byte rate of a mono file
+        bytes[28..32].copy_from_slice(&16000u32.to_le_bytes());
+        bytes.extend([0; 8]);
+        fix_riff_size(&mut bytes);
+        let wav = decode(&bytes).unwrap();
+        assert_eq!(&*wav.data, &samples[..]);
+        assert_eq!(
+            wav.diagnostics(),
+            [
+                Diagnostic::ByteRate {
+                    stated: 16000,
+                    expected: 32000
+                },
+                Diagnostic::UnknownChunk {
+                    id: "wxyz".to_string(),
+                    size: 4
+                },
+                Diagnostic::TrailingBytes,
+            ]
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::WavFile;
+use crate::dynamics::to_db;
+use crate::fft::{fft, hann};
+use crate::progress::{self, CancellationToken, Cancelled, Reporter};
+
+pub const BLOCK: usize = 2048;
+// a block that differs by this much on average is drawn at full heat
+pub const MAX_DIFFERENCE_DB: f32 = 24.0;
+// bins quieter than this in both files don't count, codecs are free to mangle inaudible noise
+const FLOOR_DB: f32 = -90.0;
+
+// average absolute difference in dB between the magnitude spectra of `a` and `b` for every
+// BLOCK frames of `a`, with `b` delayed by `offset` frames. Reports its progress in frames
+pub fn spectral_difference(
+    a: &WavFile,
+    b: &WavFile,
+    offset: isize,
+    progress: progress::Callback,
+    cancel: &CancellationToken,
+) -> Result<Vec<f32>, Cancelled> {
+    let window = hann(BLOCK);
+    let frames = a.data.len() / a.header.num_channels.max(1) as usize;
+    let mut reporter = Reporter::new(progress, cancel, frames / BLOCK * BLOCK);
+
+    (0..frames / BLOCK)
+        .map(|block| {
+            reporter.update(block * BLOCK)?;
+            let start = (block * BLOCK) as isize;
+            let spectrum_a = spectrum(a, start, &window);
+            let spectrum_b = spectrum(b, start - offset, &window);
+            let total: f32 = spectrum_a
+                .iter()
+                .zip(&spectrum_b)
+                .map(|(&x, &y)| {
+                    if x < FLOOR_DB && y < FLOOR_DB {
+                        0.0
+                    } else {
+                        (x.max(FLOOR_DB) - y.max(FLOOR_DB)).abs()
+                    }
+                })
+                .sum();
+            Ok(total / spectrum_a.len() as f32)
+        })
+        .collect()
+}
+
+// mono magnitude spectrum in dB of BLOCK frames starting at `start`, out of range frames are silent
+fn spectrum(wav: &WavFile, start: isize, window: &[f32]) -> Vec<f32> {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let mut re = vec![0.0f32; BLOCK];
+    let mut im = vec![0.0f32; BLOCK];
+    for (i, value) in re.iter_mut().enumerate() {
+        let Ok(frame) = usize::try_from(start + i as isize) else {
+            continue;
+        };
+        if let Some(samples) = wav.data.get(frame * channels..(frame + 1) * channels) {
+            let sum: f32 = samples.iter().map(|&s| s as f32).sum();
+            *value = sum / channels as f32 / 32768.0 * window[i];
+        }
+    }
+    fft(&mut re, &mut im);
+
+    let scale = 4.0 / BLOCK as f32;
+    (0..BLOCK / 2)
+        .map(|bin| to_db((re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale))
+        .collect()
+}
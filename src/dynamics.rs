@@ -0,0 +1,73 @@
+use crate::WavFile;
+use crate::progress::{self, CancellationToken, Cancelled, Reporter};
+
+// the histograms cover -72..0 dBFS in 1 dB buckets, anything quieter lands in the first one
+pub const FLOOR_DB: f32 = -72.0;
+pub const BUCKETS: usize = 72;
+// short-term rms window, long enough to smooth single waveform cycles of low notes
+const RMS_WINDOW_SECONDS: f32 = 0.05;
+
+pub struct Dynamics {
+    // how many samples peak at each level
+    pub sample_histogram: [u64; BUCKETS],
+    // how many short-term rms windows sit at each level
+    pub rms_histogram: [u64; BUCKETS],
+    pub peak_db: f32,
+    pub rms_db: f32,
+    // peak to rms ratio of the whole file, heavily limited masters sit well below 10 dB
+    pub crest_db: f32,
+}
+
+impl Dynamics {
+    // reports its progress in samples, both passes together
+    pub fn analyze(
+        wav: &WavFile,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Cancelled> {
+        let mut dynamics = Self {
+            sample_histogram: [0; BUCKETS],
+            rms_histogram: [0; BUCKETS],
+            peak_db: FLOOR_DB,
+            rms_db: FLOOR_DB,
+            crest_db: 0.0,
+        };
+
+        let mut reporter = Reporter::new(progress, cancel, wav.data.len() * 2);
+        let mut peak = 0.0f32;
+        let mut total_square = 0.0f64;
+        for (i, &sample) in wav.data.iter().enumerate() {
+            reporter.update(i)?;
+            let level = sample as f32 / 32768.0;
+            peak = peak.max(level.abs());
+            total_square += (level * level) as f64;
+            dynamics.sample_histogram[bucket(to_db(level.abs()))] += 1;
+        }
+
+        let window = ((wav.header.sample_rate as f32 * RMS_WINDOW_SECONDS) as usize
+            * wav.header.num_channels.max(1) as usize)
+            .max(1);
+        for (i, chunk) in wav.data.chunks(window).enumerate() {
+            reporter.update(wav.data.len() + i * window)?;
+            let square: f32 = chunk.iter().map(|&s| (s as f32 / 32768.0).powi(2)).sum();
+            dynamics.rms_histogram[bucket(to_db((square / chunk.len() as f32).sqrt()))] += 1;
+        }
+
+        if !wav.data.is_empty() {
+            let rms = (total_square / wav.data.len() as f64).sqrt() as f32;
+            dynamics.peak_db = to_db(peak);
+            dynamics.rms_db = to_db(rms);
+            dynamics.crest_db = dynamics.peak_db - dynamics.rms_db;
+        }
+        reporter.update(wav.data.len() * 2)?;
+        Ok(dynamics)
+    }
+}
+
+pub fn to_db(level: f32) -> f32 {
+    20.0 * level.max(1e-10).log10()
+}
+
+fn bucket(db: f32) -> usize {
+    ((db - FLOOR_DB).max(0.0) as usize).min(BUCKETS - 1)
+}
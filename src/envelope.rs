@@ -0,0 +1,52 @@
+use std::error;
+
+use crate::decoder::Decoder;
+
+// classic peak follower on the mono mix: the envelope jumps towards louder input with the
+// `attack` time constant and decays with the `release` one (both in seconds), then gets
+// sampled `rate` times a second. The file is read a second at a time, long recordings
+// aren't decoded whole
+pub fn follow(
+    decoder: &mut Decoder,
+    rate: f32,
+    attack: f32,
+    release: f32,
+) -> Result<Vec<f32>, Box<dyn error::Error + 'static>> {
+    let channels = decoder.channels as usize;
+    let sample_rate = decoder.sample_rate as f32;
+    let coefficient = |seconds: f32| {
+        if seconds <= 0.0 {
+            0.0
+        } else {
+            (-1.0 / (seconds * sample_rate)).exp()
+        }
+    };
+    let (attack, release) = (coefficient(attack), coefficient(release));
+    let step = (sample_rate / rate).max(1.0);
+
+    let mut envelope = Vec::new();
+    let mut level = 0.0f32;
+    let mut next_output = 0.0f32;
+    let block = decoder.sample_rate.max(1) as u64;
+    let mut frame = 0;
+    for start in (0..decoder.frames).step_by(block as usize) {
+        for samples in decoder
+            .read_range(start..start + block)?
+            .chunks_exact(channels)
+        {
+            let input = samples
+                .iter()
+                .map(|&s| (s as f32 / 32768.0).abs())
+                .fold(0.0f32, f32::max);
+            let coefficient = if input > level { attack } else { release };
+            level = input + coefficient * (level - input);
+
+            if frame as f32 >= next_output {
+                envelope.push(level);
+                next_output += step;
+            }
+            frame += 1;
+        }
+    }
+    Ok(envelope)
+}
@@ -0,0 +1,373 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{error, fs};
+
+use crate::cli::Args;
+use crate::decoder::Decoder;
+use crate::{envelope, hum, i18n, timecode};
+
+const EXPORT_ARRAY_USAGE: &str =
+    "wave export-array <in.wav> [--lang rust|c|asset] [--name NAME] [--output FILE] [--hum]";
+const VALUES_PER_LINE: usize = 12;
+
+// `wave export-array`: bakes the pcm data and its spec into a source file, --hum notches out
+// mains hum on the way like the player's hum filter
+pub fn export_array(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["hum"])?;
+    let input = args.input(EXPORT_ARRAY_USAGE)?;
+    // read through the same path as the ranges of `wave export-samples`
+    let mut decoder = Decoder::open(input)?;
+    let mut samples = decoder.read_range(0..decoder.frames)?;
+    if args.flag("hum") {
+        samples = hum::remove(&samples, decoder.channels, decoder.sample_rate);
+    }
+    let region = Region {
+        samples: &samples,
+        channels: decoder.channels as usize,
+        sample_rate: decoder.sample_rate,
+        start: 0,
+    };
+
+    let stem = Path::new(input)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("sound");
+    let name = match args.value("name") {
+        Some(name) => name.to_string(),
+        None => identifier(stem),
+    };
+    let lang = args.value("lang").unwrap_or("rust");
+
+    let (source, extension) = match lang {
+        "rust" => (rust_array(&region, &name, input), "rs"),
+        "c" => (c_array(&region, &name, input), "h"),
+        // the samples go to a raw s16le file next to the source, for sounds too large to
+        // compile comfortably as an array literal
+        "asset" => {
+            let output = args.value("output").map(Path::new);
+            let asset_path = output.unwrap_or(Path::new(stem)).with_extension("pcm");
+            let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+            fs::write(&asset_path, bytes)?;
+            let asset_name = asset_path.file_name().unwrap().to_string_lossy();
+            (rust_asset(&region, &name, input, &asset_name), "rs")
+        }
+        _ => return Err(i18n::fill("export.unknown-lang", &[("lang", lang.into())]).into()),
+    };
+
+    let output = match args.value("output") {
+        Some(output) => output.to_string(),
+        None => format!("{stem}.{extension}"),
+    };
+    fs::write(&output, source)?;
+    println!(
+        "{}",
+        i18n::fill(
+            "export.wrote-samples",
+            &[
+                ("count", samples.len().to_string()),
+                ("path", output.to_string())
+            ]
+        )
+    );
+    Ok(())
+}
+
+// upper snake case version of a file name, usable as a constant in both languages
+fn identifier(stem: &str) -> String {
+    let mut name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+fn rust_spec(region: &Region, name: &str, input: &str) -> String {
+    format!(
+        "// generated by `wave export-array` from {input}\n\
+         pub const {name}_SAMPLE_RATE: u32 = {};\n\
+         pub const {name}_CHANNELS: u16 = {};\n\
+         pub const {name}_BITS_PER_SAMPLE: u16 = 16;\n",
+        region.sample_rate, region.channels
+    )
+}
+
+fn rust_array(region: &Region, name: &str, input: &str) -> String {
+    let mut source = rust_spec(region, name, input);
+    writeln!(source, "\n// interleaved samples").unwrap();
+    writeln!(
+        source,
+        "pub static {name}: [i16; {}] = [",
+        region.samples.len()
+    )
+    .unwrap();
+    write_values(&mut source, region.samples);
+    source.push_str("];\n");
+    source
+}
+
+fn rust_asset(region: &Region, name: &str, input: &str, asset: &str) -> String {
+    let mut source = rust_spec(region, name, input);
+    writeln!(
+        source,
+        "\n// interleaved little-endian i16 samples\n\
+         pub static {name}: &[u8; {}] = include_bytes!(\"{asset}\");",
+        region.samples.len() * 2
+    )
+    .unwrap();
+    source
+}
+
+fn c_array(region: &Region, name: &str, input: &str) -> String {
+    let mut source = format!(
+        "// generated by `wave export-array` from {input}\n\
+         #pragma once\n\
+         #include <stdint.h>\n\n\
+         #define {name}_SAMPLE_RATE {}\n\
+         #define {name}_CHANNELS {}\n\
+         #define {name}_BITS_PER_SAMPLE 16\n\
+         #define {name}_LENGTH {}\n\n\
+         // interleaved samples\n\
+         static const int16_t {name}[{name}_LENGTH] = {{\n",
+        region.sample_rate,
+        region.channels,
+        region.samples.len()
+    );
+    write_values(&mut source, region.samples);
+    source.push_str("};\n");
+    source
+}
+
+fn write_values(source: &mut String, samples: &[i16]) {
+    for line in samples.chunks(VALUES_PER_LINE) {
+        source.push_str("   ");
+        for value in line {
+            write!(source, " {value},").unwrap();
+        }
+        source.push('\n');
+    }
+}
+
+const EXPORT_SAMPLES_USAGE: &str = "wave export-samples <in.wav> [--format csv|npy] \
+     [--channel N] [--range START..END] [--output FILE] [--hum]";
+
+// `wave export-samples`: raw sample values for spreadsheets and numpy, --hum notches out mains
+// hum like `wave export-array --hum`
+pub fn export_samples(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["hum"])?;
+    let input = args.input(EXPORT_SAMPLES_USAGE)?;
+    // only the exported range of a wav file is decoded, long recordings can be cut from
+    // without decoding them whole
+    let mut decoder = Decoder::open(input)?;
+    let channels = decoder.channels as usize;
+    let frames = decoder.frames as usize;
+
+    let range = match args.value("range") {
+        Some(range) => timecode::parse_range(range, decoder.sample_rate, frames)?,
+        None => 0..frames,
+    };
+    let selected: Vec<usize> = match args.value("channel") {
+        Some(channel) => {
+            let channel: usize = channel
+                .parse()
+                .map_err(|_| i18n::fill("export.not-a-channel", &[("channel", channel.into())]))?;
+            if channel >= channels {
+                return Err(i18n::fill(
+                    "export.channel-count",
+                    &[("path", input.into()), ("channels", channels.to_string())],
+                )
+                .into());
+            }
+            vec![channel]
+        }
+        None => (0..channels).collect(),
+    };
+
+    let format = args.value("format").unwrap_or("csv");
+    let mut samples = decoder.read_range(range.start as u64..range.end as u64)?;
+    if args.flag("hum") {
+        samples = hum::remove(&samples, decoder.channels, decoder.sample_rate);
+    }
+    let region = Region {
+        samples: &samples,
+        channels,
+        sample_rate: decoder.sample_rate,
+        start: range.start,
+    };
+    let bytes = match format {
+        "csv" => samples_csv(&region, &selected).into_bytes(),
+        "npy" => samples_npy(&region, &selected),
+        _ => {
+            return Err(i18n::fill(
+                "export.unknown-samples-format",
+                &[("format", format.into())],
+            )
+            .into());
+        }
+    };
+
+    let output = match args.value("output") {
+        Some(output) => output.to_string(),
+        None => {
+            let stem = Path::new(input).file_stem().unwrap_or_default();
+            format!("{}.{format}", stem.to_string_lossy())
+        }
+    };
+    fs::write(&output, bytes)?;
+    println!(
+        "{}",
+        i18n::fill(
+            "export.wrote-frames",
+            &[
+                ("count", range.len().to_string()),
+                ("path", output.to_string())
+            ]
+        )
+    );
+    Ok(())
+}
+
+// interleaved samples read for export, starting at frame `start` of the file
+struct Region<'a> {
+    samples: &'a [i16],
+    channels: usize,
+    sample_rate: u32,
+    start: usize,
+}
+
+impl Region<'_> {
+    fn frames(&self) -> impl Iterator<Item = (usize, &[i16])> {
+        (self.start..).zip(self.samples.chunks_exact(self.channels))
+    }
+}
+
+// one row per frame: frame index, time in seconds and the selected channels
+fn samples_csv(region: &Region, selected: &[usize]) -> String {
+    let mut csv = String::from("frame,time");
+    for channel in selected {
+        write!(csv, ",ch{channel}").unwrap();
+    }
+    csv.push('\n');
+
+    for (frame, samples) in region.frames() {
+        let time = frame as f64 / region.sample_rate as f64;
+        write!(csv, "{frame},{time:.6}").unwrap();
+        for &channel in selected {
+            write!(csv, ",{}", samples[channel]).unwrap();
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+// numpy's .npy v1.0 format, a (frames, channels) int16 array
+fn samples_npy(region: &Region, selected: &[usize]) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '<i2', 'fortran_order': False, 'shape': ({}, {}), }}",
+        region.samples.len() / region.channels,
+        selected.len()
+    );
+    // magic, version and length take 10 bytes, the whole header has to be 64 byte aligned
+    // and end in a newline
+    let padding = 64 - (10 + header.len() + 1) % 64;
+    header.extend(std::iter::repeat_n(' ', padding % 64));
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    for (_, samples) in region.frames() {
+        for &channel in selected {
+            bytes.extend(samples[channel].to_le_bytes());
+        }
+    }
+    bytes
+}
+
+const EXPORT_ENVELOPE_USAGE: &str = "wave envelope <in.wav> [--rate HZ] [--attack MS] \
+     [--release MS] [--format json|csv] [--output FILE]";
+
+// `wave envelope`: the amplitude envelope at a fixed rate, for animation or sidechain curves
+pub fn export_envelope(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    let input = args.input(EXPORT_ENVELOPE_USAGE)?;
+    let mut decoder = Decoder::open(input)?;
+
+    let number = |name: &str, default: f32| -> Result<f32, String> {
+        match args.value(name) {
+            Some(value) => value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .ok_or_else(|| {
+                    i18n::fill(
+                        "export.positive-number",
+                        &[("option", name.into()), ("value", value.into())],
+                    )
+                }),
+            None => Ok(default),
+        }
+    };
+    let rate = number("rate", 100.0)?;
+    if rate <= 0.0 {
+        return Err(i18n::text("export.rate-above-zero").into());
+    }
+    let attack = number("attack", 5.0)? / 1000.0;
+    let release = number("release", 100.0)? / 1000.0;
+    let values = envelope::follow(&mut decoder, rate, attack, release)?;
+
+    let format = args.value("format").unwrap_or("json");
+    let contents = match format {
+        "json" => {
+            let values: Vec<String> = values.iter().map(|v| format!("{v:.6}")).collect();
+            format!(
+                "{{\"rate\": {rate}, \"attack_ms\": {}, \"release_ms\": {}, \"envelope\": [{}]}}\n",
+                attack * 1000.0,
+                release * 1000.0,
+                values.join(", ")
+            )
+        }
+        "csv" => {
+            let mut csv = String::from("time,envelope\n");
+            for (i, value) in values.iter().enumerate() {
+                writeln!(csv, "{:.6},{value:.6}", i as f32 / rate).unwrap();
+            }
+            csv
+        }
+        _ => {
+            return Err(i18n::fill(
+                "export.unknown-envelope-format",
+                &[("format", format.into())],
+            )
+            .into());
+        }
+    };
+
+    let output = match args.value("output") {
+        Some(output) => output.to_string(),
+        None => {
+            let stem = Path::new(input).file_stem().unwrap_or_default();
+            format!("{}.envelope.{format}", stem.to_string_lossy())
+        }
+    };
+    fs::write(&output, contents)?;
+    println!(
+        "{}",
+        i18n::fill(
+            "export.wrote-envelope",
+            &[
+                ("count", values.len().to_string()),
+                ("path", output.to_string())
+            ]
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::f32::consts::TAU;
+
+// second order iir section using the coefficient formulas from robert bristow-johnson's
+// "audio eq cookbook", processed in transposed direct form II
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn from_coefficients(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            ..Self::default()
+        }
+    }
+
+    pub fn notch(sample_rate: u32, frequency: f32, q: f32) -> Self {
+        let (sin, cos) = (TAU * frequency / sample_rate as f32).sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::from_coefficients(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn highpass(sample_rate: u32, frequency: f32, q: f32) -> Self {
+        let (sin, cos) = (TAU * frequency / sample_rate as f32).sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::from_coefficients(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn lowpass(sample_rate: u32, frequency: f32, q: f32) -> Self {
+        let (sin, cos) = (TAU * frequency / sample_rate as f32).sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::from_coefficients(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn high_shelf(sample_rate: u32, frequency: f32, gain_db: f32, q: f32) -> Self {
+        let (sin, cos) = (TAU * frequency / sample_rate as f32).sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f32.powf(gain_db / 40.0);
+        let root = 2.0 * a.sqrt() * alpha;
+        Self::from_coefficients(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + root),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - root),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + root,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+// the same cascade of biquads run independently on every channel, bypassed while disabled
+pub struct FilterBank {
+    pub enabled: bool,
+    channels: Vec<Vec<Biquad>>,
+}
+
+impl FilterBank {
+    pub fn new(stages: Vec<Biquad>, channels: u16) -> Self {
+        Self {
+            enabled: false,
+            channels: vec![stages; channels.max(1) as usize],
+        }
+    }
+
+    pub fn process(&mut self, channel: usize, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+        self.channels[channel]
+            .iter_mut()
+            .fold(sample, |x, stage| stage.process(x))
+    }
+}
+
+pub const RUMBLE_CUTOFF: f32 = 30.0;
+pub const AIR_CUTOFF: f32 = 16000.0;
+
+// q values of a 4th order butterworth split into two biquads
+const BUTTERWORTH_Q: [f32; 2] = [0.541_196_1, 1.306_563];
+
+// removes subsonic rumble (handling noise, wind, hvac) below RUMBLE_CUTOFF at 24 dB/octave
+pub fn rumble(sample_rate: u32, channels: u16) -> FilterBank {
+    let stages = BUTTERWORTH_Q
+        .iter()
+        .map(|&q| Biquad::highpass(sample_rate, RUMBLE_CUTOFF, q))
+        .collect();
+    FilterBank::new(stages, channels)
+}
+
+// attenuates the air band above AIR_CUTOFF at 24 dB/octave, a no-op when nyquist is below it
+pub fn air(sample_rate: u32, channels: u16) -> FilterBank {
+    let stages = if AIR_CUTOFF < sample_rate as f32 / 2.0 {
+        BUTTERWORTH_Q
+            .iter()
+            .map(|&q| Biquad::lowpass(sample_rate, AIR_CUTOFF, q))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    FilterBank::new(stages, channels)
+}
@@ -0,0 +1,230 @@
+// FLAC through the claxon decoder, only built with the `flac` feature. Every block is turned
+// into 16-bit samples the way wider wav samples are, so the rest of wave never knows the
+// difference
+
+use std::io::{self, Read};
+
+use crate::metadata::Metadata;
+use crate::reader::Dither;
+use crate::{AudioSource, Header};
+
+/// Reads a FLAC stream a block at a time like [`crate::WavReader`] reads a wav file. The
+/// header is filled in from the STREAMINFO block, the sizes as if the samples were PCM.
+pub struct FlacReader<R: Read> {
+    reader: claxon::FlacReader<R>,
+    header: Header,
+    data_size: u64,
+    remaining: u64,
+    // what's left of the last decoded block, already 16-bit and interleaved
+    pending: Vec<i16>,
+    read: usize,
+    buffer: Vec<i32>,
+    dither: Dither,
+    metadata: Metadata,
+}
+
+impl<R: Read> FlacReader<R> {
+    /// Reads the metadata blocks up to the first audio frame.
+    pub fn new(reader: R) -> Result<Self, Box<dyn std::error::Error + 'static>> {
+        let reader = claxon::FlacReader::new(reader)?;
+        let info = reader.streaminfo();
+        let channels = info.channels as u16;
+        // 12 and 20-bit files are rare, they're counted in whole bytes like wav stores them
+        let width = info.bits_per_sample.div_ceil(8) as u16;
+        let data_size = info.samples.unwrap_or(0) * (channels * width) as u64;
+        let header = Header {
+            chunk_id: "fLaC".into(),
+            format: "FLAC".into(),
+            audio_format: 1,
+            num_channels: channels,
+            sample_rate: info.sample_rate,
+            byte_rate: info.sample_rate * (channels * width) as u32,
+            block_align: channels * width,
+            bits_per_sample: info.bits_per_sample as u16,
+            subchunk2_size: data_size.min(u32::MAX as u64) as u32,
+            ..Header::default()
+        };
+        // vorbis comments, whose names are case insensitive
+        let tag = |name: &str| reader.get_tag(name).next().map(str::to_string);
+        let metadata = Metadata {
+            title: tag("TITLE"),
+            artist: tag("ARTIST"),
+            album: tag("ALBUM"),
+            comment: tag("COMMENT").or_else(|| tag("DESCRIPTION")),
+            date: tag("DATE"),
+            ..Metadata::default()
+        };
+        Ok(Self {
+            reader,
+            header,
+            data_size,
+            remaining: data_size,
+            pending: Vec::new(),
+            read: 0,
+            buffer: Vec::new(),
+            dither: Dither::default(),
+            metadata,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Size of the samples in bytes as PCM, 0 when STREAMINFO leaves the length unset.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Bytes of samples not decoded yet, as PCM.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Decodes the next samples into `out`, returning how many, 0 after the last block. 24
+    /// and 32-bit samples are dithered down to 16 bits.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        while self.read == self.pending.len() {
+            if !self.decode_block()? {
+                return Ok(0);
+            }
+        }
+        let count = out.len().min(self.pending.len() - self.read);
+        out[..count].copy_from_slice(&self.pending[self.read..self.read + count]);
+        self.read += count;
+        let width = self.header.bits_per_sample.div_ceil(8) as u64;
+        self.remaining = self.remaining.saturating_sub(count as u64 * width);
+        Ok(count)
+    }
+
+    // the next block into `pending`, false at the end of the stream
+    fn decode_block(&mut self) -> io::Result<bool> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let block = self
+            .reader
+            .blocks()
+            .read_next_or_eof(buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let Some(block) = block else {
+            return Ok(false);
+        };
+        let bits = self.header.bits_per_sample as i32;
+        self.pending.clear();
+        self.read = 0;
+        for frame in 0..block.duration() {
+            for channel in 0..block.channels() {
+                let value = block.sample(channel, frame);
+                self.pending.push(if bits > 16 {
+                    self.dither
+                        .quantize(value as f32 / (1 << (bits - 16)) as f32)
+                } else {
+                    (value << (16 - bits)) as i16
+                });
+            }
+        }
+        self.buffer = block.into_buffer();
+        Ok(true)
+    }
+
+    /// The vorbis comment tags. FLAC keeps them in front of the audio, so nothing is left
+    /// to read.
+    pub fn finish(self) -> io::Result<Metadata> {
+        Ok(self.metadata)
+    }
+}
+
+impl<R: Read> AudioSource for FlacReader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{decode, signal};
+
+    // 16-bit FLAC with every subframe stored verbatim, which is valid if pointless and needs no
+    // encoder
+    fn flac(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+        const BLOCK: usize = 4096;
+        let frames = samples.len() / channels as usize;
+        let mut bytes = Vec::from(*b"fLaC");
+        // the last metadata block, STREAMINFO, with the frame sizes and the md5 left unknown
+        bytes.extend([0x80, 0, 0, 34]);
+        bytes.extend((BLOCK as u16).to_be_bytes());
+        bytes.extend((BLOCK as u16).to_be_bytes());
+        bytes.extend([0; 6]);
+        let packed =
+            (sample_rate as u64) << 44 | ((channels - 1) as u64) << 41 | 15 << 36 | frames as u64;
+        bytes.extend(packed.to_be_bytes());
+        bytes.extend([0; 16]);
+        for (number, block) in samples.chunks(BLOCK * channels as usize).enumerate() {
+            let length = block.len() / channels as usize;
+            // a 16-bit block size after the header, the rate from STREAMINFO, independent
+            // channels of 16 bits, and the frame number as a one byte utf-8 style number
+            let mut frame = vec![0xff, 0xf8, 0x70, ((channels - 1) << 4) as u8 | 0b1000];
+            frame.push(number as u8);
+            frame.extend((length as u16 - 1).to_be_bytes());
+            frame.push(crc8(&frame));
+            for channel in 0..channels as usize {
+                frame.push(0b0000_0010);
+                for sample in block.iter().skip(channel).step_by(channels as usize) {
+                    frame.extend(sample.to_be_bytes());
+                }
+            }
+            frame.extend(crc16(&frame).to_be_bytes());
+            bytes.extend(frame);
+        }
+        bytes
+    }
+
+    fn crc8(bytes: &[u8]) -> u8 {
+        bytes.iter().fold(0, |crc, &byte| {
+            (0..8).fold(crc ^ byte, |crc, _| {
+                if crc & 0x80 != 0 {
+                    crc << 1 ^ 0x07
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    fn crc16(bytes: &[u8]) -> u16 {
+        bytes.iter().fold(0, |crc, &byte| {
+            (0..8).fold(crc ^ (byte as u16) << 8, |crc, _| {
+                if crc & 0x8000 != 0 {
+                    crc << 1 ^ 0x8005
+                } else {
+                    crc << 1
+                }
+            })
+        })
+    }
+
+    #[test]
+    fn flac_decodes_to_the_samples_it_was_encoded_from() {
+        let samples = signal(2, 44100, 10000);
+        let wav = decode(&flac(2, 44100, &samples)).unwrap();
+        assert_eq!(&*wav.header.format, "FLAC");
+        assert_eq!(wav.header.sample_rate, 44100);
+        assert_eq!(wav.data_size, samples.len() as u64 * 2);
+        assert_eq!(&*wav.data, &samples[..]);
+    }
+}
@@ -0,0 +1,325 @@
+use sdl2::rect::Rect;
+
+use crate::render::Renderer;
+
+pub const GLYPH_WIDTH: i32 = 5;
+pub const GLYPH_HEIGHT: i32 = 7;
+
+// 5x7 bitmap font for the printable ASCII range (0x20 - 0x7e), one byte per row with the
+// leftmost pixel in bit 4, so the on-screen readouts don't need SDL2_ttf or a font file
+const GLYPHS: [[u8; 7]; 95] = [
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // space
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+    ], // !
+    [
+        0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // "
+    [
+        0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+    ], // #
+    [
+        0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+    ], // $
+    [
+        0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+    ], // %
+    [
+        0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+    ], // &
+    [
+        0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // '
+    [
+        0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+    ], // (
+    [
+        0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+    ], // )
+    [
+        0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+    ], // *
+    [
+        0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+    ], // +
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+    ], // ,
+    [
+        0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+    ], // -
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+    ], // .
+    [
+        0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+    ], // /
+    [
+        0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+    ], // 0
+    [
+        0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // 1
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+    ], // 2
+    [
+        0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+    ], // 3
+    [
+        0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+    ], // 4
+    [
+        0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+    ], // 5
+    [
+        0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+    ], // 6
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+    ], // 7
+    [
+        0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+    ], // 8
+    [
+        0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+    ], // 9
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+    ], // :
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+    ], // ;
+    [
+        0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+    ], // <
+    [
+        0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+    ], // =
+    [
+        0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+    ], // >
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+    ], // ?
+    [
+        0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+    ], // @
+    [
+        0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ], // A
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+    ], // B
+    [
+        0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // C
+    [
+        0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+    ], // D
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+    ], // E
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // F
+    [
+        0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+    ], // G
+    [
+        0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ], // H
+    [
+        0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // I
+    [
+        0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // J
+    [
+        0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+    ], // K
+    [
+        0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+    ], // L
+    [
+        0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+    ], // M
+    [
+        0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+    ], // N
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // O
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+    ], // P
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+    ], // Q
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+    ], // R
+    [
+        0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+    ], // S
+    [
+        0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // T
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // U
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // V
+    [
+        0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+    ], // W
+    [
+        0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+    ], // X
+    [
+        0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // Y
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+    ], // Z
+    [
+        0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+    ], // [
+    [
+        0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+    ], // \
+    [
+        0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+    ], // ]
+    [
+        0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // ^
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+    ], // _
+    [
+        0b01000, 0b00100, 0b00010, 0b00000, 0b00000, 0b00000, 0b00000,
+    ], // `
+    [
+        0b00000, 0b00000, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111,
+    ], // a
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b11110,
+    ], // b
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b10000, 0b10001, 0b01110,
+    ], // c
+    [
+        0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111,
+    ], // d
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110,
+    ], // e
+    [
+        0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000,
+    ], // f
+    [
+        0b00000, 0b01111, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // g
+    [
+        0b10000, 0b10000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // h
+    [
+        0b00100, 0b00000, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // i
+    [
+        0b00010, 0b00000, 0b00110, 0b00010, 0b00010, 0b10010, 0b01100,
+    ], // j
+    [
+        0b10000, 0b10000, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010,
+    ], // k
+    [
+        0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ], // l
+    [
+        0b00000, 0b00000, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001,
+    ], // m
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10001, 0b10001, 0b10001,
+    ], // n
+    [
+        0b00000, 0b00000, 0b01110, 0b10001, 0b10001, 0b10001, 0b01110,
+    ], // o
+    [
+        0b00000, 0b00000, 0b11110, 0b10001, 0b11110, 0b10000, 0b10000,
+    ], // p
+    [
+        0b00000, 0b00000, 0b01101, 0b10011, 0b01111, 0b00001, 0b00001,
+    ], // q
+    [
+        0b00000, 0b00000, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000,
+    ], // r
+    [
+        0b00000, 0b00000, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110,
+    ], // s
+    [
+        0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110,
+    ], // t
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b10011, 0b01101,
+    ], // u
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ], // v
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b10101, 0b10101, 0b01010,
+    ], // w
+    [
+        0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001,
+    ], // x
+    [
+        0b00000, 0b00000, 0b10001, 0b10001, 0b01111, 0b00001, 0b01110,
+    ], // y
+    [
+        0b00000, 0b00000, 0b11111, 0b00010, 0b00100, 0b01000, 0b11111,
+    ], // z
+    [
+        0b00010, 0b00100, 0b00100, 0b01000, 0b00100, 0b00100, 0b00010,
+    ], // {
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ], // |
+    [
+        0b01000, 0b00100, 0b00100, 0b00010, 0b00100, 0b00100, 0b01000,
+    ], // }
+    [
+        0b00000, 0b00000, 0b01000, 0b10101, 0b00010, 0b00000, 0b00000,
+    ], // ~
+];
+
+// draws `text` with the current draw color, `scale` multiplies the size of every font pixel
+pub fn draw_text(canvas: &mut impl Renderer, x: i32, y: i32, scale: i32, text: &str) {
+    let mut rects = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let glyph = match c {
+            ' '..='~' => &GLYPHS[c as usize - 0x20],
+            _ => &GLYPHS['?' as usize - 0x20],
+        };
+        let origin_x = x + i as i32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    rects.push(Rect::new(
+                        origin_x + col * scale,
+                        y + row as i32 * scale,
+                        scale as u32,
+                        scale as u32,
+                    ));
+                }
+            }
+        }
+    }
+    canvas.fill_rects(&rects);
+}
+
+pub fn text_width(text: &str, scale: i32) -> i32 {
+    (text.chars().count() as i32 * (GLYPH_WIDTH + 1) - 1).max(0) * scale
+}
@@ -0,0 +1,55 @@
+// the companded 8-bit telephony formats of ITU-T G.711, expanded back to linear 16-bit. Both
+// store a sign, a 3-bit segment and a 4-bit step within it, with every bit inverted (µ-law)
+// or every other bit inverted (A-law) so silence doesn't come out as a run of zero bytes
+
+pub fn ulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let segment = (byte >> 4) & 0x7;
+    let step = (byte & 0xf) as i16;
+    let magnitude = (((step << 3) + 0x84) << segment) - 0x84;
+    if byte & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+pub fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let segment = (byte >> 4) & 0x7;
+    let step = (byte & 0xf) as i16;
+    let magnitude = match segment {
+        0 => (step << 4) + 8,
+        _ => ((step << 4) + 0x108) << (segment - 1),
+    };
+    // unlike µ-law, a set sign bit means positive
+    if byte & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_the_ends_and_the_middle_of_the_range() {
+        assert_eq!(ulaw_to_linear(0xff), 0);
+        assert_eq!(ulaw_to_linear(0x7f), 0);
+        assert_eq!(ulaw_to_linear(0x80), 32124);
+        assert_eq!(ulaw_to_linear(0x00), -32124);
+        assert_eq!(alaw_to_linear(0xd5), 8);
+        assert_eq!(alaw_to_linear(0x55), -8);
+        assert_eq!(alaw_to_linear(0xaa), 32256);
+        assert_eq!(alaw_to_linear(0x2a), -32256);
+    }
+
+    #[test]
+    fn every_code_is_monotonic_within_its_sign() {
+        // codes 0x80..=0xff count down from the loudest positive value to zero in µ-law
+        let positive: Vec<i16> = (0x80..=0xffu8).map(ulaw_to_linear).collect();
+        assert!(positive.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+}
@@ -0,0 +1,644 @@
+// the main window drawn with OpenGL shaders, with the gpu feature. Shapes are collected into
+// batches of vertices and drawn at the full pixel resolution of HiDPI screens, multisampled so
+// lines come out antialiased. Images are textures stretched with linear filtering, so the
+// spectrogram's bins blend into each other instead of showing as blocks. A trail is kept in a
+// framebuffer that's faded every frame and shown with a glow around whatever is bright in it,
+// like the phosphor of an analog scope
+
+use std::error;
+
+use glow::HasContext;
+use sdl2::VideoSubsystem;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::video::{GLContext, GLProfile, SwapInterval, Window};
+
+use crate::render::{Image, Layer, Renderer};
+
+// how much of the blurred trail is added on top of it
+const GLOW: f32 = 0.8;
+
+// positions are in pixels of the viewport with y running down, like sdl's
+const SHAPE_VERTEX: &str = "#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+uniform vec2 size;
+out vec4 shade;
+void main() {
+    vec2 clip = position / size * 2.0 - 1.0;
+    gl_Position = vec4(clip.x, -clip.y, 0.0, 1.0);
+    shade = color;
+}
+";
+
+const SHAPE_FRAGMENT: &str = "#version 330 core
+in vec4 shade;
+out vec4 color;
+void main() {
+    color = shade;
+}
+";
+
+const IMAGE_VERTEX: &str = "#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 corner;
+uniform vec2 size;
+out vec2 uv;
+void main() {
+    vec2 clip = position / size * 2.0 - 1.0;
+    gl_Position = vec4(clip.x, -clip.y, 0.0, 1.0);
+    uv = corner;
+}
+";
+
+const IMAGE_FRAGMENT: &str = "#version 330 core
+uniform sampler2D image;
+in vec2 uv;
+out vec4 color;
+void main() {
+    color = vec4(texture(image, uv).rgb, 1.0);
+}
+";
+
+// the trail plus a gaussian blur of it, so bright traces bleed into the dark around them
+const GLOW_FRAGMENT: &str = "#version 330 core
+uniform sampler2D image;
+uniform float glow;
+in vec2 uv;
+out vec4 color;
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(image, 0));
+    vec3 halo = vec3(0.0);
+    float total = 0.0;
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            float weight = exp(-float(x * x + y * y) / 8.0);
+            halo += weight * texture(image, uv + vec2(x, y) * texel).rgb;
+            total += weight;
+        }
+    }
+    color = vec4(texture(image, uv).rgb + halo / total * glow, 1.0);
+}
+";
+
+// floats per vertex: x and y, then the color
+const SHAPE_FLOATS: usize = 6;
+// x and y, then where in the texture
+const IMAGE_FLOATS: usize = 4;
+
+struct Trail {
+    framebuffer: glow::Framebuffer,
+    texture: glow::Texture,
+    // in pixels of the screen, not the window
+    width: u32,
+    height: u32,
+}
+
+pub struct Gpu {
+    gl: glow::Context,
+    window: Window,
+    // every gl call needs it current
+    context: GLContext,
+    shapes: glow::Program,
+    images: glow::Program,
+    glow: glow::Program,
+    shape_array: glow::VertexArray,
+    shape_buffer: glow::Buffer,
+    image_array: glow::VertexArray,
+    image_buffer: glow::Buffer,
+    // the shapes waiting to be drawn, all lines or all triangles
+    vertices: Vec<f32>,
+    mode: u32,
+    color: Color,
+    viewport: Rect,
+    // each with its size and whether it was drawn this frame, the rest are deleted when it's
+    // presented
+    textures: Vec<(glow::Texture, u32, u32, bool)>,
+    trail: Option<Trail>,
+    trail_drawn: bool,
+    // while a trail is drawn its framebuffer is where everything goes
+    in_trail: bool,
+}
+
+impl Gpu {
+    pub fn new(
+        video: &VideoSubsystem,
+        title: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let attributes = video.gl_attr();
+        attributes.set_context_profile(GLProfile::Core);
+        attributes.set_context_version(3, 3);
+        attributes.set_multisample_buffers(1);
+        attributes.set_multisample_samples(4);
+        let window = video
+            .window(title, width, height)
+            .position_centered()
+            .opengl()
+            .allow_highdpi()
+            .build()?;
+        let context = window.gl_create_context()?;
+        window.gl_make_current(&context)?;
+        // the frame loop paces itself
+        video.gl_set_swap_interval(SwapInterval::Immediate)?;
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| video.gl_get_proc_address(name) as *const _)
+        };
+
+        let shapes = program(&gl, SHAPE_VERTEX, SHAPE_FRAGMENT)?;
+        let images = program(&gl, IMAGE_VERTEX, IMAGE_FRAGMENT)?;
+        let glow = program(&gl, IMAGE_VERTEX, GLOW_FRAGMENT)?;
+        let (shape_array, shape_buffer) = vertex_array(&gl, &[2, 4])?;
+        let (image_array, image_buffer) = vertex_array(&gl, &[2, 2])?;
+        unsafe {
+            gl.enable(glow::MULTISAMPLE);
+            gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+        }
+        let (width, height) = window.size();
+        Ok(Self {
+            gl,
+            window,
+            context,
+            shapes,
+            images,
+            glow,
+            shape_array,
+            shape_buffer,
+            image_array,
+            image_buffer,
+            vertices: Vec::new(),
+            mode: glow::LINES,
+            color: Color::RGB(255, 255, 255),
+            viewport: Rect::new(0, 0, width, height),
+            textures: Vec::new(),
+            trail: None,
+            trail_drawn: false,
+            in_trail: false,
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    // starts a frame. The meter window's sdl renderer makes its own context current when it
+    // draws, so this one is made current again first
+    pub fn clear(&mut self, color: Color) {
+        self.window.gl_make_current(&self.context).ok();
+        self.flush();
+        let (width, height) = self.window.drawable_size();
+        unsafe {
+            self.gl.disable(glow::SCISSOR_TEST);
+            self.gl.viewport(0, 0, width as i32, height as i32);
+            let (r, g, b, a) = color.rgba();
+            self.gl.clear_color(
+                r as f32 / 255.0,
+                g as f32 / 255.0,
+                b as f32 / 255.0,
+                a as f32 / 255.0,
+            );
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+        self.set_viewport(None);
+    }
+
+    pub fn present(&mut self) {
+        self.flush();
+        self.window.gl_swap_window();
+        let gl = &self.gl;
+        self.textures.retain_mut(|(texture, _, _, drawn)| {
+            let kept = std::mem::take(drawn);
+            if !kept {
+                unsafe { gl.delete_texture(*texture) };
+            }
+            kept
+        });
+        if !std::mem::take(&mut self.trail_drawn)
+            && let Some(trail) = self.trail.take()
+        {
+            self.delete_trail(trail);
+        }
+    }
+
+    // screen pixels per window pixel, 2 on most HiDPI screens
+    fn scale(&self) -> f32 {
+        let (width, _) = self.window.size();
+        self.window.drawable_size().0 as f32 / width.max(1) as f32
+    }
+
+    // the gl viewport and scissor for the current viewport, which gl counts from the bottom
+    fn apply_viewport(&self) {
+        unsafe {
+            if let (true, Some(trail)) = (self.in_trail, &self.trail) {
+                self.gl.disable(glow::SCISSOR_TEST);
+                self.gl
+                    .viewport(0, 0, trail.width as i32, trail.height as i32);
+                return;
+            }
+            let scale = self.scale();
+            let (_, window_height) = self.window.size();
+            let area = self.viewport;
+            let bottom = window_height as i32 - area.bottom();
+            let (x, y) = (
+                (area.x() as f32 * scale) as i32,
+                (bottom as f32 * scale) as i32,
+            );
+            let (width, height) = (
+                (area.width() as f32 * scale) as i32,
+                (area.height() as f32 * scale) as i32,
+            );
+            self.gl.viewport(x, y, width, height);
+            self.gl.scissor(x, y, width, height);
+            self.gl.enable(glow::SCISSOR_TEST);
+        }
+    }
+
+    fn push(&mut self, mode: u32, points: &[(f32, f32)]) {
+        if mode != self.mode {
+            self.flush();
+            self.mode = mode;
+        }
+        let (r, g, b, a) = self.color.rgba();
+        for &(x, y) in points {
+            self.vertices.extend_from_slice(&[
+                x,
+                y,
+                r as f32 / 255.0,
+                g as f32 / 255.0,
+                b as f32 / 255.0,
+                a as f32 / 255.0,
+            ]);
+        }
+    }
+
+    // draws the shapes collected so far
+    fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let (width, height) = self.viewport.size();
+        unsafe {
+            self.gl.use_program(Some(self.shapes));
+            let size = self.gl.get_uniform_location(self.shapes, "size");
+            self.gl
+                .uniform_2_f32(size.as_ref(), width as f32, height as f32);
+            self.gl.bind_vertex_array(Some(self.shape_array));
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(self.shape_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                &bytes(&self.vertices),
+                glow::STREAM_DRAW,
+            );
+            self.gl
+                .draw_arrays(self.mode, 0, (self.vertices.len() / SHAPE_FLOATS) as i32);
+        }
+        self.vertices.clear();
+    }
+
+    // `texture` stretched over `to` with `program`, upside down for the ones gl rendered into
+    fn quad(&self, program: glow::Program, texture: glow::Texture, to: Rect, flip: bool) {
+        let (left, top) = (to.x() as f32, to.y() as f32);
+        let (right, bottom) = (to.right() as f32, to.bottom() as f32);
+        let (v_top, v_bottom) = if flip { (1.0, 0.0) } else { (0.0, 1.0) };
+        #[rustfmt::skip]
+        let vertices = [
+            left, top, 0.0, v_top,
+            right, top, 1.0, v_top,
+            right, bottom, 1.0, v_bottom,
+            left, top, 0.0, v_top,
+            right, bottom, 1.0, v_bottom,
+            left, bottom, 0.0, v_bottom,
+        ];
+        let (width, height) = self.viewport.size();
+        unsafe {
+            self.gl.use_program(Some(program));
+            let size = self.gl.get_uniform_location(program, "size");
+            self.gl
+                .uniform_2_f32(size.as_ref(), width as f32, height as f32);
+            let glow = self.gl.get_uniform_location(program, "glow");
+            self.gl.uniform_1_f32(glow.as_ref(), GLOW);
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            self.gl.bind_vertex_array(Some(self.image_array));
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(self.image_buffer));
+            self.gl
+                .buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes(&vertices), glow::STREAM_DRAW);
+            self.gl
+                .draw_arrays(glow::TRIANGLES, 0, (vertices.len() / IMAGE_FLOATS) as i32);
+        }
+    }
+
+    // a texture `width` by `height`, filled with `pixels` when there are some
+    fn texture(
+        &self,
+        width: u32,
+        height: u32,
+        format: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<glow::Texture, String> {
+        unsafe {
+            let texture = self.gl.create_texture()?;
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for (parameter, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ] {
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            self.gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                format as i32,
+                width as i32,
+                height as i32,
+                0,
+                format,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(pixels),
+            );
+            Ok(texture)
+        }
+    }
+
+    // a framebuffer the size of the viewport, cleared to black
+    fn new_trail(&self) -> Result<Trail, String> {
+        let scale = self.scale();
+        let (width, height) = self.viewport.size();
+        let (width, height) = (
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+        );
+        let texture = self.texture(width, height, glow::RGBA, None)?;
+        unsafe {
+            let framebuffer = self.gl.create_framebuffer()?;
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            self.gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            let complete =
+                self.gl.check_framebuffer_status(glow::FRAMEBUFFER) == glow::FRAMEBUFFER_COMPLETE;
+            if complete {
+                self.gl.disable(glow::SCISSOR_TEST);
+                self.gl.viewport(0, 0, width as i32, height as i32);
+                self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                self.gl.clear(glow::COLOR_BUFFER_BIT);
+            }
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.apply_viewport();
+            let trail = Trail {
+                framebuffer,
+                texture,
+                width,
+                height,
+            };
+            if !complete {
+                self.delete_trail(trail);
+                return Err("the trail's framebuffer isn't supported".to_string());
+            }
+            Ok(trail)
+        }
+    }
+
+    fn delete_trail(&self, trail: Trail) {
+        unsafe {
+            self.gl.delete_framebuffer(trail.framebuffer);
+            self.gl.delete_texture(trail.texture);
+        }
+    }
+}
+
+impl Renderer for Gpu {
+    fn size(&self) -> (u32, u32) {
+        self.viewport.size()
+    }
+
+    fn set_viewport(&mut self, area: impl Into<Option<Rect>>) {
+        self.flush();
+        let (width, height) = self.window.size();
+        self.viewport = area
+            .into()
+            .unwrap_or_else(|| Rect::new(0, 0, width, height));
+        self.apply_viewport();
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn set_blend(&mut self, blend: bool) {
+        self.flush();
+        unsafe {
+            if blend {
+                self.gl.enable(glow::BLEND);
+            } else {
+                self.gl.disable(glow::BLEND);
+            }
+        }
+    }
+
+    // through the middle of the pixels at both ends, like sdl draws them
+    fn line(&mut self, (x1, y1): (i32, i32), (x2, y2): (i32, i32)) {
+        self.push(
+            glow::LINES,
+            &[
+                (x1 as f32 + 0.5, y1 as f32 + 0.5),
+                (x2 as f32 + 0.5, y2 as f32 + 0.5),
+            ],
+        );
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        let (left, top) = (rect.x(), rect.y());
+        let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+        for (from, to) in [
+            ((left, top), (right, top)),
+            ((right, top), (right, bottom)),
+            ((right, bottom), (left, bottom)),
+            ((left, bottom), (left, top)),
+        ] {
+            self.line(from, to);
+        }
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect]) {
+        for rect in rects {
+            let (left, top) = (rect.x() as f32, rect.y() as f32);
+            let (right, bottom) = (rect.right() as f32, rect.bottom() as f32);
+            self.push(
+                glow::TRIANGLES,
+                &[
+                    (left, top),
+                    (right, top),
+                    (right, bottom),
+                    (left, top),
+                    (right, bottom),
+                    (left, bottom),
+                ],
+            );
+        }
+    }
+
+    fn image(&mut self, image: &Image, to: Option<Rect>) {
+        self.flush();
+        let fits = self.textures.iter().position(|&(_, width, height, drawn)| {
+            !drawn && (width, height) == (image.width, image.height)
+        });
+        let texture = match fits {
+            Some(index) => {
+                let texture = self.textures[index].0;
+                unsafe {
+                    self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                    self.gl.tex_sub_image_2d(
+                        glow::TEXTURE_2D,
+                        0,
+                        0,
+                        0,
+                        image.width as i32,
+                        image.height as i32,
+                        glow::RGB,
+                        glow::UNSIGNED_BYTE,
+                        glow::PixelUnpackData::Slice(Some(&image.pixels)),
+                    );
+                }
+                self.textures[index].3 = true;
+                texture
+            }
+            None => {
+                let Ok(texture) =
+                    self.texture(image.width, image.height, glow::RGB, Some(&image.pixels))
+                else {
+                    return;
+                };
+                self.textures
+                    .push((texture, image.width, image.height, true));
+                texture
+            }
+        };
+        let (width, height) = self.viewport.size();
+        let to = to.unwrap_or_else(|| Rect::new(0, 0, width, height));
+        self.quad(self.images, texture, to, false);
+    }
+
+    fn trail(&mut self, fade: u8, layer: &impl Layer) {
+        self.flush();
+        let scale = self.scale();
+        let (width, height) = self.viewport.size();
+        let fits = self.trail.as_ref().is_some_and(|trail| {
+            (trail.width, trail.height)
+                == (
+                    ((width as f32 * scale) as u32).max(1),
+                    ((height as f32 * scale) as u32).max(1),
+                )
+        });
+        if !fits {
+            if let Some(trail) = self.trail.take() {
+                self.delete_trail(trail);
+            }
+            match self.new_trail() {
+                Ok(trail) => self.trail = Some(trail),
+                Err(_) => {
+                    layer.draw(self);
+                    return;
+                }
+            }
+        }
+        self.trail_drawn = true;
+        let Some(trail) = &self.trail else {
+            return;
+        };
+        let (framebuffer, texture) = (trail.framebuffer, trail.texture);
+        let color = self.color;
+
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer))
+        };
+        self.in_trail = true;
+        self.apply_viewport();
+        self.set_blend(true);
+        self.set_color(Color::RGBA(0, 0, 0, fade));
+        self.fill_rect(Rect::new(0, 0, width, height));
+        self.set_blend(false);
+        layer.draw(self);
+        self.flush();
+        unsafe { self.gl.bind_framebuffer(glow::FRAMEBUFFER, None) };
+        self.in_trail = false;
+        self.apply_viewport();
+
+        self.quad(self.glow, texture, Rect::new(0, 0, width, height), true);
+        self.color = color;
+    }
+}
+
+fn program(gl: &glow::Context, vertex: &str, fragment: &str) -> Result<glow::Program, String> {
+    unsafe {
+        let program = gl.create_program()?;
+        let mut shaders = Vec::new();
+        for (kind, source) in [
+            (glow::VERTEX_SHADER, vertex),
+            (glow::FRAGMENT_SHADER, fragment),
+        ] {
+            let shader = gl.create_shader(kind)?;
+            gl.shader_source(shader, source);
+            gl.compile_shader(shader);
+            if !gl.get_shader_compile_status(shader) {
+                return Err(gl.get_shader_info_log(shader));
+            }
+            gl.attach_shader(program, shader);
+            shaders.push(shader);
+        }
+        gl.link_program(program);
+        for shader in shaders {
+            gl.detach_shader(program, shader);
+            gl.delete_shader(shader);
+        }
+        if !gl.get_program_link_status(program) {
+            return Err(gl.get_program_info_log(program));
+        }
+        Ok(program)
+    }
+}
+
+// a vertex array over a buffer of floats, `sizes` of them for each attribute in turn
+fn vertex_array(
+    gl: &glow::Context,
+    sizes: &[i32],
+) -> Result<(glow::VertexArray, glow::Buffer), String> {
+    let stride = sizes.iter().sum::<i32>() * 4;
+    unsafe {
+        let array = gl.create_vertex_array()?;
+        let buffer = gl.create_buffer()?;
+        gl.bind_vertex_array(Some(array));
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+        let mut offset = 0;
+        for (index, &size) in sizes.iter().enumerate() {
+            gl.vertex_attrib_pointer_f32(index as u32, size, glow::FLOAT, false, stride, offset);
+            gl.enable_vertex_attrib_array(index as u32);
+            offset += size * 4;
+        }
+        Ok((array, buffer))
+    }
+}
+
+fn bytes(floats: &[f32]) -> Vec<u8> {
+    floats
+        .iter()
+        .flat_map(|float| float.to_ne_bytes())
+        .collect()
+}
@@ -0,0 +1,116 @@
+use std::f32::consts::TAU;
+
+use crate::WavFile;
+use crate::filter::{Biquad, FilterBank};
+
+// how many multiples of the mains frequency get their own notch, including the fundamental
+const HARMONICS: usize = 8;
+// narrow enough to leave neighbouring musical content alone
+const NOTCH_Q: f32 = 30.0;
+// only the start of the file is inspected when guessing the mains frequency
+const DETECT_SECONDS: usize = 30;
+
+// notch filter bank centered on mains hum and its harmonics
+pub struct HumFilter {
+    pub mains: f32,
+    pub filter: FilterBank,
+}
+
+impl HumFilter {
+    pub fn new(sample_rate: u32, channels: u16, mains: f32) -> Self {
+        let notches = (1..=HARMONICS)
+            .map(|harmonic| mains * harmonic as f32)
+            .take_while(|&frequency| frequency < sample_rate as f32 / 2.0)
+            .map(|frequency| Biquad::notch(sample_rate, frequency, NOTCH_Q))
+            .collect();
+        Self {
+            mains,
+            filter: FilterBank::new(notches, channels),
+        }
+    }
+}
+
+// compares the energy at the first few harmonics of 50 Hz against those of 60 Hz and returns
+// whichever mains frequency is louder in the file
+pub fn detect_mains(wav: &WavFile) -> f32 {
+    mains_of(&wav.data, wav.header.num_channels, wav.header.sample_rate)
+}
+
+fn mains_of(samples: &[i16], channels: u16, sample_rate: u32) -> f32 {
+    let channels = channels.max(1) as usize;
+    let frames = (samples.len() / channels).min(DETECT_SECONDS * sample_rate as usize);
+    let mono: Vec<f32> = (0..frames)
+        .map(|frame| {
+            let samples = &samples[frame * channels..(frame + 1) * channels];
+            samples.iter().map(|&s| s as f32).sum::<f32>() / channels as f32
+        })
+        .collect();
+
+    let energy = |mains: f32| -> f32 {
+        (1..=3)
+            .map(|harmonic| goertzel(&mono, sample_rate, mains * harmonic as f32))
+            .sum()
+    };
+    if energy(60.0) > energy(50.0) {
+        60.0
+    } else {
+        50.0
+    }
+}
+
+// interleaved `samples` through the hum filter, for the export paths to write what the player
+// plays with it on. The mains frequency is guessed from the samples themselves
+pub fn remove(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<i16> {
+    let mut hum = HumFilter::new(
+        sample_rate,
+        channels,
+        mains_of(samples, channels, sample_rate),
+    );
+    hum.filter.enabled = true;
+    let channels = channels.max(1) as usize;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let value = hum.filter.process(i % channels, sample as f32);
+            value.round().clamp(-32768.0, 32767.0) as i16
+        })
+        .collect()
+}
+
+// power of a single frequency over the whole block
+fn goertzel(samples: &[f32], sample_rate: u32, frequency: f32) -> f32 {
+    // accumulated in f64, tens of seconds of audio is too long a recursion for f32 to stay accurate
+    let coefficient = 2.0 * (TAU as f64 * frequency as f64 / sample_rate as f64).cos();
+    let (mut s1, mut s2) = (0.0f64, 0.0f64);
+    for &x in samples {
+        let s0 = x as f64 + coefficient * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    (s1 * s1 + s2 * s2 - coefficient * s1 * s2) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f32, sample_rate: u32, frames: usize) -> Vec<i16> {
+        (0..frames)
+            .map(|i| ((TAU * frequency * i as f32 / sample_rate as f32).sin() * 8000.0) as i16)
+            .collect()
+    }
+
+    fn rms(samples: &[i16]) -> f32 {
+        (samples.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn removing_hum_notches_mains_and_leaves_the_rest() {
+        // past the first second, once the notches have settled
+        let hum = remove(&sine(60.0, 8000, 16000), 1, 8000);
+        assert!(rms(&hum[8000..]) < 100.0);
+        let tone = remove(&sine(1000.0, 8000, 16000), 1, 8000);
+        assert!(rms(&tone[8000..]) > 5000.0);
+    }
+}
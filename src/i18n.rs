@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+
+// the text wave shows on screen and prints to the terminal, looked up by key in the locale
+// files under locales/ that are built into the binary. The language comes from $WAVE_LANG,
+// otherwise the usual locale variables. Anything a locale doesn't translate is shown in English, and a key English
+// doesn't have either is shown as is so a typo is easy to spot
+
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.txt")),
+    ("de", include_str!("../locales/de.txt")),
+];
+
+struct Strings {
+    chosen: HashMap<&'static str, &'static str>,
+    english: HashMap<&'static str, &'static str>,
+}
+
+// `key = text` lines, blank lines and ones starting with # are skipped
+fn parse(text: &'static str) -> HashMap<&'static str, &'static str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, text)| (key.trim(), text.trim()))
+        .collect()
+}
+
+fn locale(language: &str) -> Option<&'static str> {
+    LOCALES
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map(|(_, text)| *text)
+}
+
+// the first locale variable that's set, cut down to the language: de_AT.UTF-8@euro is de.
+// C and POSIX are English
+fn language() -> String {
+    ["WAVE_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            let language = value.split(['_', '.', '@']).next().unwrap_or_default();
+            match language {
+                "C" | "POSIX" => "en".to_string(),
+                language => language.to_lowercase(),
+            }
+        })
+        .unwrap_or_else(|| "en".to_string())
+}
+
+fn strings() -> &'static Strings {
+    static STRINGS: OnceLock<Strings> = OnceLock::new();
+    STRINGS.get_or_init(|| Strings {
+        chosen: locale(&language()).map(parse).unwrap_or_default(),
+        english: parse(LOCALES[0].1),
+    })
+}
+
+pub fn text(key: &str) -> &str {
+    let strings = strings();
+    strings
+        .chosen
+        .get(key)
+        .or_else(|| strings.english.get(key))
+        .copied()
+        .unwrap_or(key)
+}
+
+// the text for `key` with every `{name}` in `fields` replaced by its value
+pub fn fill(key: &str, fields: &[(&str, String)]) -> String {
+    fields
+        .iter()
+        .fold(text(key).to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the `{name}`s in a text, in order
+    fn placeholders(text: &str) -> Vec<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}'))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn every_locale_translates_keys_english_has_with_the_same_placeholders() {
+        let english = parse(LOCALES[0].1);
+        for (name, text) in &LOCALES[1..] {
+            for (key, translated) in parse(text) {
+                let original = english
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{name}: `{key}` isn't in English"));
+                let mut expected = placeholders(original);
+                let mut found = placeholders(translated);
+                expected.sort();
+                found.sort();
+                assert_eq!(found, expected, "{name}: `{key}`");
+                assert!(translated.is_ascii(), "{name}: `{key}` isn't ASCII");
+            }
+        }
+    }
+
+    #[test]
+    fn every_key_english_has_is_translated() {
+        let english = parse(LOCALES[0].1);
+        for (name, text) in &LOCALES[1..] {
+            let translated = parse(text);
+            let mut missing: Vec<_> = english
+                .keys()
+                .filter(|key| !translated.contains_key(*key))
+                .collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{name}: no text for {missing:?}");
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::metadata::{Metadata, Picture};
+
+// https://id3.org/id3v2.3.0 and https://id3.org/id3v2.4.0-structure
+
+// reads the text, comment and picture frames of an ID3v2.3/2.4 tag as embedded in a wav
+// `id3 ` chunk, anything malformed is skipped rather than failing the whole file
+pub fn parse(tag: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    let Some((_, frames)) = frames(tag) else {
+        return metadata;
+    };
+    for frame in frames {
+        let body = &frame.body[..];
+        match &frame.id {
+            b"TIT2" => metadata.title = text_frame(body),
+            b"TPE1" => metadata.artist = text_frame(body),
+            b"TALB" => metadata.album = text_frame(body),
+            b"TDRC" | b"TYER" => metadata.date = text_frame(body),
+            b"COMM" => metadata.comment = comment_frame(body),
+            b"APIC" => metadata.picture = picture_frame(body),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+// `tag` (or a new ID3v2.4 tag when it is empty or unreadable) with the text frames in
+// `changes` replaced, COMM is written with an empty description, an empty value removes the
+// frame and every other frame such as the cover art is carried over untouched
+pub fn update(tag: &[u8], changes: &[([u8; 4], &str)]) -> Vec<u8> {
+    let (version, mut frames) = frames(tag).unwrap_or((4, Vec::new()));
+    for &(id, value) in changes {
+        frames.retain(|frame| frame.id != id);
+        if value.is_empty() {
+            continue;
+        }
+        // 2.3 has no utf-8, so it gets utf-16 with a byte order mark
+        let mut body = if version == 4 { vec![3] } else { vec![1] };
+        if &id == b"COMM" {
+            body.extend(b"eng");
+            body.extend(encode(version, ""));
+        }
+        body.extend(encode(version, value));
+        frames.push(Frame {
+            id,
+            flags: [0, 0],
+            body,
+        });
+    }
+    write(version, &frames)
+}
+
+// `tag` with the TXXX user text frames in `changes` replaced, matched by their description
+// such as REPLAYGAIN_TRACK_GAIN, an empty value removes the frame
+pub fn update_user_text(tag: &[u8], changes: &[(&str, &str)]) -> Vec<u8> {
+    let (version, mut frames) = frames(tag).unwrap_or((4, Vec::new()));
+    for &(description, value) in changes {
+        frames.retain(|frame| {
+            user_text_frame(frame).is_none_or(|(name, _)| !name.eq_ignore_ascii_case(description))
+        });
+        if value.is_empty() {
+            continue;
+        }
+        let mut body = if version == 4 { vec![3] } else { vec![1] };
+        body.extend(encode(version, description));
+        body.extend(encode(version, value));
+        frames.push(Frame {
+            id: *b"TXXX",
+            flags: [0, 0],
+            body,
+        });
+    }
+    write(version, &frames)
+}
+
+// `tag` without the TXXX user text frames whose description starts with `prefix`, such as
+// REPLAYGAIN_, in any case
+pub fn remove_user_text(tag: &[u8], prefix: &str) -> Vec<u8> {
+    let Some((version, mut frames)) = frames(tag) else {
+        return tag.to_vec();
+    };
+    frames.retain(|frame| {
+        user_text_frame(frame).is_none_or(|(name, _)| {
+            !name
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        })
+    });
+    write(version, &frames)
+}
+
+// the value of the TXXX frame with `description`, if the tag has one
+pub fn user_text(tag: &[u8], description: &str) -> Option<String> {
+    let (_, frames) = frames(tag)?;
+    frames
+        .iter()
+        .filter_map(user_text_frame)
+        .find(|(name, _)| name.eq_ignore_ascii_case(description))
+        .map(|(_, value)| value)
+}
+
+fn write(version: u8, frames: &[Frame]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for frame in frames {
+        body.extend(frame.id);
+        let size = frame.body.len() as u32;
+        match version {
+            4 => body.extend(to_syncsafe(size)),
+            _ => body.extend(size.to_be_bytes()),
+        }
+        body.extend(frame.flags);
+        body.extend(&frame.body);
+    }
+    let mut tag = Vec::from(*b"ID3");
+    tag.extend([version, 0, 0]);
+    tag.extend(to_syncsafe(body.len() as u32));
+    tag.extend(body);
+    tag
+}
+
+struct Frame {
+    id: [u8; 4],
+    flags: [u8; 2],
+    body: Vec<u8>,
+}
+
+// the major version and raw frames of a tag
+fn frames(tag: &[u8]) -> Option<(u8, Vec<Frame>)> {
+    if tag.len() < 10 || &tag[0..3] != b"ID3" {
+        return None;
+    }
+    let version = tag[3];
+    let flags = tag[5];
+    if !(3..=4).contains(&version) {
+        return None;
+    }
+
+    let size = syncsafe(&tag[6..10]) as usize;
+    let mut tag = tag[10..(10 + size).min(tag.len())].to_vec();
+    // 2.3 unsynchronises the whole tag, 2.4 marks it per frame
+    if version == 3 && flags & 0x80 != 0 {
+        tag = resynchronise(&tag);
+    }
+    let end = tag.len();
+    let mut offset = 0;
+    // skip the extended header, its size field is syncsafe in 2.4 and excludes itself in 2.3
+    if flags & 0x40 != 0 && offset + 4 <= end {
+        offset += match version {
+            4 => syncsafe(&tag[offset..offset + 4]) as usize,
+            _ => be_u32(&tag[offset..offset + 4]) as usize + 4,
+        };
+    }
+
+    let mut frames = Vec::new();
+    while offset + 10 <= end {
+        let id = &tag[offset..offset + 4];
+        // padding after the last frame
+        if id[0] == 0 {
+            break;
+        }
+        let frame_size = match version {
+            4 => syncsafe(&tag[offset + 4..offset + 8]),
+            _ => be_u32(&tag[offset + 4..offset + 8]),
+        } as usize;
+        let body_start = offset + 10;
+        let body_end = body_start + frame_size;
+        if body_end > end {
+            break;
+        }
+        frames.push(Frame {
+            id: [id[0], id[1], id[2], id[3]],
+            flags: [tag[offset + 8], tag[offset + 9]],
+            body: tag[body_start..body_end].to_vec(),
+        });
+        offset = body_end;
+    }
+    Some((version, frames))
+}
+
+// undoes unsynchronisation, which inserts a zero after every 0xff
+fn resynchronise(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == 0 && i > 0 && bytes[i - 1] == 0xff {
+            continue;
+        }
+        out.push(b);
+    }
+    out
+}
+
+// a nul terminated string in the encoding `update` writes for `version`
+fn encode(version: u8, text: &str) -> Vec<u8> {
+    if version == 4 {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        bytes
+    } else {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes.extend([0, 0]);
+        bytes
+    }
+}
+
+fn to_syncsafe(n: u32) -> [u8; 4] {
+    [
+        (n >> 21) as u8 & 0x7f,
+        (n >> 14) as u8 & 0x7f,
+        (n >> 7) as u8 & 0x7f,
+        n as u8 & 0x7f,
+    ]
+}
+
+// 28 bit integer stored in the low 7 bits of four bytes
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, &b| (n << 7) | (b & 0x7f) as u32)
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn text_frame(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let text = decode(encoding, text);
+    // 2.4 separates multiple values with nul, only the first one is shown
+    let text = text.split('\0').next().unwrap_or_default().trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// encoding, nul terminated description, then the value
+fn user_text_frame(frame: &Frame) -> Option<(String, String)> {
+    if &frame.id != b"TXXX" {
+        return None;
+    }
+    let (&encoding, rest) = frame.body.split_first()?;
+    let (description, value) = split_terminated(encoding, rest);
+    let value = decode(encoding, value);
+    Some((
+        decode(encoding, description),
+        value.trim_end_matches('\0').to_string(),
+    ))
+}
+
+// encoding, 3 byte language, nul terminated short description, then the comment itself
+fn comment_frame(body: &[u8]) -> Option<String> {
+    let (&encoding, rest) = body.split_first()?;
+    let rest = rest.get(3..)?;
+    let (_, text) = split_terminated(encoding, rest);
+    let text = decode(encoding, text);
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// encoding, latin-1 mime type, picture type, description, then the image bytes
+fn picture_frame(body: &[u8]) -> Option<Picture> {
+    let (&encoding, rest) = body.split_first()?;
+    let mime_end = rest.iter().position(|&b| b == 0)?;
+    let mime = String::from_utf8_lossy(&rest[..mime_end]).into_owned();
+    let rest = rest.get(mime_end + 2..)?;
+    let (_, data) = split_terminated(encoding, rest);
+    (!data.is_empty()).then(|| Picture {
+        mime,
+        data: data.to_vec(),
+    })
+}
+
+// splits off a string terminated according to `encoding`, utf-16 strings end with a double nul
+fn split_terminated(encoding: u8, bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        1 | 2 => (0..bytes.len().saturating_sub(1))
+            .step_by(2)
+            .find(|&i| bytes[i] == 0 && bytes[i + 1] == 0)
+            .map(|i| (i, i + 2)),
+        _ => bytes.iter().position(|&b| b == 0).map(|i| (i, i + 1)),
+    };
+    match end {
+        Some((text_end, rest_start)) => (&bytes[..text_end], &bytes[rest_start..]),
+        None => (bytes, &[]),
+    }
+}
+
+fn decode(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        // iso-8859-1 maps byte for byte onto the first 256 code points
+        0 => bytes.iter().map(|&b| b as char).collect(),
+        1 | 2 => {
+            let (big_endian, bytes) = match bytes {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, bytes),
+            };
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| {
+                    if big_endian {
+                        u16::from_be_bytes([pair[0], pair[1]])
+                    } else {
+                        u16::from_le_bytes([pair[0], pair[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removing_user_text_keeps_other_frames() {
+        let tag = update(&[], &[(*b"TIT2", "Song")]);
+        let tag = update_user_text(
+            &tag,
+            &[
+                ("REPLAYGAIN_TRACK_GAIN", "-3.00 dB"),
+                ("replaygain_album_peak", "0.5"),
+                ("MOOD", "calm"),
+            ],
+        );
+        let tag = remove_user_text(&tag, "REPLAYGAIN_");
+        assert_eq!(user_text(&tag, "REPLAYGAIN_TRACK_GAIN"), None);
+        assert_eq!(user_text(&tag, "REPLAYGAIN_ALBUM_PEAK"), None);
+        assert_eq!(user_text(&tag, "MOOD").as_deref(), Some("calm"));
+        assert_eq!(parse(&tag).title.as_deref(), Some("Song"));
+    }
+
+    #[test]
+    fn user_text_frames_replace_by_description_and_keep_the_rest() {
+        let tag = update(&[], &[(*b"TIT2", "Title")]);
+        let tag = update_user_text(&tag, &[("REPLAYGAIN_TRACK_GAIN", "-3.00 dB")]);
+        let tag = update_user_text(&tag, &[("replaygain_track_gain", "+1.25 dB")]);
+        assert_eq!(
+            user_text(&tag, "REPLAYGAIN_TRACK_GAIN").as_deref(),
+            Some("+1.25 dB")
+        );
+        assert_eq!(parse(&tag).title.as_deref(), Some("Title"));
+    }
+}
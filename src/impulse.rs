@@ -0,0 +1,293 @@
+use std::ops::Range;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::fft::fft;
+use crate::render::Renderer;
+use crate::{WavFile, font, i18n};
+
+// impulse responses of rooms, halls and plates are all well under this
+const MAX_SECONDS: f64 = 10.0;
+// the peak has to be in the first part of the file, after at most a little pre-delay
+const MAX_PEAK_FRACTION: f64 = 0.25;
+// and the energy after it has to die away by this much, anything else is a short sound
+const MIN_DECAY_DB: f32 = 30.0;
+// points of the decay curves per second
+const DECAY_RATE: usize = 1000;
+// the longest stretch of the response transformed for the frequency response
+const MAX_FFT: usize = 1 << 17;
+const FLOOR_DB: f32 = -90.0;
+// frames read at once while going over the response
+const READ_BLOCK: usize = 1 << 16;
+
+// what a convolution reverb user wants to know about an impulse response
+pub struct Impulse {
+    pub sample_rate: u32,
+    // seconds from the start of the file to the direct sound
+    pub peak_seconds: f64,
+    // Schroeder's backward integrated energy from the peak on, in dB under the total, and the
+    // level of the response itself, both DECAY_RATE points a second
+    pub decay: Vec<f32>,
+    pub envelope: Vec<f32>,
+    // reverberation time from the part of the decay between -5 and -35 dB (T30), or -25 dB
+    // (T20) when the response doesn't reach -35
+    pub rt60: Option<(f32, &'static str)>,
+    // early decay time, from the first 10 dB
+    pub edt: Option<f32>,
+    // magnitude of every bin up to nyquist in dB under the loudest one
+    pub response: Vec<f32>,
+}
+
+// the response of `wav` if it looks like an impulse response: short, with a peak near the
+// start followed by a decay
+pub fn detect(wav: &WavFile) -> Option<Impulse> {
+    let rate = wav.header.sample_rate;
+    let frames = wav.frames();
+    if rate == 0 || frames > (MAX_SECONDS * rate as f64) as usize {
+        return None;
+    }
+    let impulse = analyze(frames, rate, |range| wav.mono_range(range))?;
+    let decayed = impulse.decay.last().is_some_and(|&db| db <= -MIN_DECAY_DB);
+    let early = impulse.peak_seconds <= frames as f64 / rate as f64 * MAX_PEAK_FRACTION;
+    (decayed && early).then_some(impulse)
+}
+
+// the response in `frames` mono frames, `read` gives the ones in a range. It's read a block at
+// a time, only the part going into the frequency response is held at once. None for silence
+pub fn analyze(
+    frames: usize,
+    sample_rate: u32,
+    read: impl Fn(Range<usize>) -> Vec<f32>,
+) -> Option<Impulse> {
+    let step = (sample_rate as usize / DECAY_RATE).max(1);
+    // a whole number of decay points to a block, so none of them straddles two
+    let block = step * (READ_BLOCK / step).max(1);
+    let read = &read;
+    let blocks = move |from: usize| {
+        (from..frames)
+            .step_by(block)
+            .map(move |start| (start, read(start..(start + block).min(frames))))
+    };
+
+    let mut loudest: Option<(usize, f32)> = None;
+    for (start, samples) in blocks(0) {
+        for (i, sample) in samples.iter().enumerate() {
+            if loudest.is_none_or(|(_, level)| sample.abs() >= level) {
+                loudest = Some((start + i, sample.abs()));
+            }
+        }
+    }
+    let (peak, peak_level) = loudest?;
+    if peak_level == 0.0 {
+        return None;
+    }
+    // Schroeder's integral is the energy left after each point, the total from the peak on
+    // less what came before it
+    let energy = |samples: &[f32]| samples.iter().map(|&s| s as f64 * s as f64).sum::<f64>();
+    let total: f64 = blocks(peak).map(|(_, samples)| energy(&samples)).sum();
+    let mut spent = 0.0;
+    let mut decay = Vec::new();
+    let mut envelope = Vec::new();
+    for (_, samples) in blocks(peak) {
+        for chunk in samples.chunks(step) {
+            let remaining = (total - spent).max(0.0);
+            decay.push(((10.0 * (remaining / total).log10()) as f32).max(FLOOR_DB));
+            let loudest = chunk.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+            envelope.push((20.0 * (loudest / peak_level).log10()).max(FLOOR_DB));
+            spent += energy(chunk);
+        }
+    }
+
+    let seconds_per_point = step as f32 / sample_rate as f32;
+    let rt60 = decay_time(&decay, -5.0, -35.0, seconds_per_point)
+        .map(|time| (time, "T30"))
+        .or_else(|| decay_time(&decay, -5.0, -25.0, seconds_per_point).map(|time| (time, "T20")));
+    let edt = decay_time(&decay, 0.0, -10.0, seconds_per_point);
+
+    let size = frames.next_power_of_two().min(MAX_FFT);
+    let mut re = vec![0.0; size];
+    let mut im = vec![0.0; size];
+    let count = frames.min(size);
+    re[..count].copy_from_slice(&read(0..count));
+    fft(&mut re, &mut im);
+    let magnitudes: Vec<f32> = (0..size / 2).map(|i| re[i].hypot(im[i])).collect();
+    let loudest = magnitudes
+        .iter()
+        .fold(0.0f32, |max, &m| max.max(m))
+        .max(f32::MIN_POSITIVE);
+    let response = magnitudes
+        .iter()
+        .map(|&m| (20.0 * (m / loudest).log10()).max(FLOOR_DB))
+        .collect();
+
+    Some(Impulse {
+        sample_rate,
+        peak_seconds: peak as f64 / sample_rate as f64,
+        decay,
+        envelope,
+        rt60,
+        edt,
+        response,
+    })
+}
+
+// the time to fall 60 dB at the rate the decay falls from `from` to `to`, a least squares
+// line through the points in between. None if it never gets down to `to`
+fn decay_time(decay: &[f32], from: f32, to: f32, seconds_per_point: f32) -> Option<f32> {
+    let start = decay.iter().position(|&db| db <= from)?;
+    let end = decay.iter().position(|&db| db <= to)?;
+    if end < start + 2 {
+        return None;
+    }
+    let points: Vec<(f32, f32)> = (start..=end)
+        .map(|i| (i as f32 * seconds_per_point, decay[i]))
+        .collect();
+    let count = points.len() as f32;
+    let mean_t = points.iter().map(|p| p.0).sum::<f32>() / count;
+    let mean_db = points.iter().map(|p| p.1).sum::<f32>() / count;
+    let covariance: f32 = points
+        .iter()
+        .map(|(t, db)| (t - mean_t) * (db - mean_db))
+        .sum();
+    let variance: f32 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+    let slope = covariance / variance;
+    (slope < 0.0).then(|| -60.0 / slope)
+}
+
+impl Impulse {
+    // one line each for the terminal
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let seconds = |time: Option<f32>| time.map_or("-".to_string(), |t| format!("{t:.2} s"));
+        let mut lines = vec![(
+            "rt60",
+            match self.rt60 {
+                Some((time, method)) => format!("{time:.2} s ({method})"),
+                None => format!("- ({})", i18n::text("impulse.short-decay")),
+            },
+        )];
+        lines.push(("edt", seconds(self.edt)));
+        lines.push(("direct", format!("{:.1} ms", self.peak_seconds * 1000.0)));
+        lines
+    }
+}
+
+// the decay curves over time on top and the frequency response below it, on a log scale
+// from 20 Hz
+pub fn draw(canvas: &mut impl Renderer, impulse: &Impulse) {
+    let (width, height) = canvas.size();
+    let (left, right) = (40, width as i32 - 20);
+    let plot_height = (height as i32 - 100) / 2;
+    let decay_area = Rect::new(left, 40, (right - left) as u32, plot_height as u32);
+    let response_area = Rect::new(
+        left,
+        60 + plot_height,
+        (right - left) as u32,
+        plot_height as u32,
+    );
+    let y = |area: Rect, db: f32| area.y() + (db / FLOOR_DB * area.height() as f32) as i32;
+
+    let text: Vec<String> = impulse
+        .summary()
+        .iter()
+        .map(|(label, value)| format!("{} {value}", i18n::text(&format!("impulse.{label}"))))
+        .collect();
+    canvas.set_color(Color::RGB(255, 255, 255));
+    font::draw_text(canvas, left, 12, 2, &text.join("   "));
+
+    for area in [decay_area, response_area] {
+        canvas.set_color(Color::RGB(60, 60, 60));
+        for db in (0..=90).step_by(30) {
+            let y = y(area, -(db as f32));
+            canvas.line((area.left(), y), (area.right(), y));
+            font::draw_text(canvas, 4, y - 4, 1, &(-db).to_string());
+        }
+    }
+    let points = impulse.decay.len().max(2);
+    let x = |i: usize| left + (i * decay_area.width() as usize / (points - 1)) as i32;
+    for (curve, color) in [
+        (&impulse.envelope, Color::RGB(0, 110, 110)),
+        (&impulse.decay, Color::RGB(0, 255, 255)),
+    ] {
+        canvas.set_color(color);
+        for (i, pair) in curve.windows(2).enumerate() {
+            canvas.line(
+                (x(i), y(decay_area, pair[0])),
+                (x(i + 1), y(decay_area, pair[1])),
+            );
+        }
+    }
+    let seconds = impulse.decay.len() as f32 / DECAY_RATE as f32;
+    canvas.set_color(Color::RGB(255, 255, 255));
+    font::draw_text(
+        canvas,
+        decay_area.right() - 60,
+        decay_area.bottom() + 4,
+        1,
+        &format!("{seconds:.2} s"),
+    );
+
+    // the loudest bin under every column, the bins get dense towards the top
+    let nyquist = impulse.sample_rate as f32 / 2.0;
+    let bins = impulse.response.len();
+    let octaves = (nyquist / 20.0).log2();
+    let bin_at = |column: i32| {
+        let hz = 20.0 * 2f32.powf(column as f32 / response_area.width() as f32 * octaves);
+        ((hz / nyquist * bins as f32) as usize).min(bins - 1)
+    };
+    canvas.set_color(Color::RGB(255, 200, 0));
+    let mut previous = None;
+    for column in 0..response_area.width() as i32 {
+        let (from, to) = (bin_at(column), bin_at(column + 1).max(bin_at(column) + 1));
+        let db = impulse.response[from..to.min(bins)]
+            .iter()
+            .fold(FLOOR_DB, |max, &db| max.max(db));
+        let point = (left + column, y(response_area, db));
+        if let Some(previous) = previous {
+            canvas.line(previous, point);
+        }
+        previous = Some(point);
+    }
+    canvas.set_color(Color::RGB(255, 255, 255));
+    for hz in [100.0, 1000.0, 10000.0] {
+        if hz < nyquist {
+            let column = (hz / 20.0f32).log2() / octaves * response_area.width() as f32;
+            let label = if hz >= 1000.0 {
+                format!("{}k", hz / 1000.0)
+            } else {
+                format!("{hz}")
+            };
+            font::draw_text(
+                canvas,
+                left + column as i32,
+                response_area.bottom() + 4,
+                1,
+                &label,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rt60_of_exponentially_decaying_noise() {
+        // noise falling 60 dB in 0.8 s
+        let rate = 48000;
+        let mut noise: u32 = 7;
+        let samples: Vec<f32> = (0..rate * 2)
+            .map(|i| {
+                noise = noise.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let white = (noise >> 8) as f32 / (1 << 24) as f32 - 0.5;
+                let level = 10f32.powf(-3.0 * i as f32 / (0.8 * rate as f32));
+                if i == 0 { 1.0 } else { white * level }
+            })
+            .collect();
+        let impulse = analyze(samples.len(), rate as u32, |range| samples[range].to_vec()).unwrap();
+        let (rt60, method) = impulse.rt60.unwrap();
+        assert_eq!(method, "T30");
+        assert!((rt60 - 0.8).abs() < 0.08, "{rt60}");
+    }
+}
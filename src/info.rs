@@ -0,0 +1,292 @@
+use std::error;
+
+use crate::bext::Bext;
+use crate::cli::Args;
+use crate::{WavFile, i18n, impulse, ixml, json, timecode};
+
+const USAGE: &str = "wave info <in.wav> [--json]";
+
+// `wave info`: the format of a file and whatever tags it carries, and the reverb time of an
+// impulse response
+pub fn info(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["json"])?;
+    let input = args.input(USAGE)?;
+    let wav = WavFile::open(input)?;
+
+    if args.flag("json") {
+        println!("{}", info_json(&wav, input));
+        return Ok(());
+    }
+
+    let header = &wav.header;
+    let frames = wav.data.len() / header.num_channels.max(1) as usize;
+    println!("{:<12} {input}", i18n::text("info.file"));
+    let speakers = header.speakers();
+    if speakers.is_empty() {
+        println!(
+            "{:<12} {}",
+            i18n::text("info.channels"),
+            header.num_channels
+        );
+    } else {
+        println!(
+            "{:<12} {} ({})",
+            i18n::text("info.channels"),
+            header.num_channels,
+            speakers.join(" ")
+        );
+    }
+    println!(
+        "{:<12} {} Hz",
+        i18n::text("info.sample-rate"),
+        header.sample_rate
+    );
+    println!("{:<12} {}", i18n::text("info.bits"), header.bits_per_sample);
+    println!(
+        "{:<12} {} ({})",
+        i18n::text("info.duration"),
+        timecode::format(frames as f64 / header.sample_rate as f64),
+        i18n::fill("info.frames", &[("frames", frames.to_string())])
+    );
+
+    if let Some(impulse) = impulse::detect(&wav) {
+        println!("{}", i18n::text("info.impulse-response"));
+        for (label, value) in impulse.summary() {
+            println!("  {:<13} {value}", i18n::text(&format!("impulse.{label}")));
+        }
+    }
+
+    for (label, value) in wav.metadata.fields() {
+        println!("{:<12} {value}", i18n::text(&format!("metadata.{label}")));
+    }
+    if let Some(picture) = &wav.metadata.picture {
+        println!(
+            "{:<12} {} ({})",
+            i18n::text("info.cover-art"),
+            picture.mime,
+            i18n::fill("info.bytes", &[("bytes", picture.data.len().to_string())])
+        );
+    }
+    if let Some(xml) = &wav.metadata.ixml {
+        for (label, value) in ixml::fields(xml) {
+            println!("{:<12} {value}", i18n::text(&format!("ixml.{label}")));
+        }
+        let tracks = ixml::track_names(xml);
+        if !tracks.is_empty() {
+            println!("{:<12} {}", i18n::text("info.tracks"), tracks.join(", "));
+        }
+    }
+    if wav.metadata.axml.is_some() {
+        println!("{:<12} {}", "axml", i18n::text("info.axml-present"));
+    }
+    if let Some(bext) = &wav.metadata.bext {
+        print_bext(bext, header.sample_rate);
+    }
+    Ok(())
+}
+
+fn print_bext(bext: &Bext, sample_rate: u32) {
+    println!(
+        "{:<12} {}",
+        "bext",
+        i18n::fill(
+            "info.bext-version",
+            &[("version", bext.version.to_string())]
+        )
+    );
+    for (label, value) in [
+        ("info.description", &bext.description),
+        ("info.originator", &bext.originator),
+        ("info.reference", &bext.originator_reference),
+    ] {
+        if !value.is_empty() {
+            println!("  {:<13} {value}", i18n::text(label));
+        }
+    }
+    println!(
+        "  {:<13} {} {}",
+        i18n::text("info.originated"),
+        bext.origination_date,
+        bext.origination_time
+    );
+    println!(
+        "  {:<13} {} ({})",
+        i18n::text("info.time-reference"),
+        timecode::format(bext.time_reference as f64 / sample_rate.max(1) as f64),
+        i18n::fill(
+            "info.since-midnight",
+            &[("frames", bext.time_reference.to_string())]
+        )
+    );
+    if let Some(loudness) = &bext.loudness {
+        for (label, value, unit) in [
+            ("info.loudness", loudness.integrated, "LUFS"),
+            ("info.loudness-range", loudness.range, "LU"),
+            ("info.true-peak", loudness.max_true_peak, "dBTP"),
+            ("info.momentary", loudness.max_momentary, "LUFS"),
+            ("info.short-term", loudness.max_short_term, "LUFS"),
+        ] {
+            if let Some(value) = value {
+                println!("  {:<13} {value:.2} {unit}", i18n::text(label));
+            }
+        }
+    }
+    for (i, line) in bext.coding_history.iter().enumerate() {
+        let label = if i == 0 {
+            i18n::text("info.history")
+        } else {
+            ""
+        };
+        println!("  {label:<13} {line}");
+    }
+}
+
+// the same information as a single json object, for scripts
+fn info_json(wav: &WavFile, input: &str) -> String {
+    let header = &wav.header;
+    let frames = wav.data.len() / header.num_channels.max(1) as usize;
+    let mut fields = vec![
+        ("file".to_string(), json::string(input)),
+        ("channels".to_string(), header.num_channels.to_string()),
+        ("sample_rate".to_string(), header.sample_rate.to_string()),
+        (
+            "bits_per_sample".to_string(),
+            header.bits_per_sample.to_string(),
+        ),
+        ("channel_mask".to_string(), header.channel_mask.to_string()),
+        (
+            "speakers".to_string(),
+            format!(
+                "[{}]",
+                header
+                    .speakers()
+                    .iter()
+                    .map(|speaker| json::string(speaker))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ),
+        ("frames".to_string(), frames.to_string()),
+        (
+            "duration".to_string(),
+            format!("{:.6}", frames as f64 / header.sample_rate as f64),
+        ),
+    ];
+
+    let tags = wav
+        .metadata
+        .fields()
+        .into_iter()
+        .map(|(label, value)| (label.to_string(), json::string(value)))
+        .collect();
+    fields.push(("tags".to_string(), json::object(tags)));
+
+    let cover_art = match &wav.metadata.picture {
+        Some(picture) => json::object(vec![
+            ("mime".to_string(), json::string(&picture.mime)),
+            ("bytes".to_string(), picture.data.len().to_string()),
+        ]),
+        None => "null".to_string(),
+    };
+    fields.push(("cover_art".to_string(), cover_art));
+
+    let ixml = match &wav.metadata.ixml {
+        Some(xml) => {
+            let mut ixml: Vec<(String, String)> = ixml::fields(xml)
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), json::string(&value)))
+                .collect();
+            let tracks: Vec<String> = ixml::track_names(xml)
+                .iter()
+                .map(|name| json::string(name))
+                .collect();
+            ixml.push(("tracks".to_string(), format!("[{}]", tracks.join(", "))));
+            ixml.push(("xml".to_string(), json::string(xml)));
+            json::object(ixml)
+        }
+        None => "null".to_string(),
+    };
+    fields.push(("ixml".to_string(), ixml));
+    let axml = wav
+        .metadata
+        .axml
+        .as_deref()
+        .map_or("null".to_string(), json::string);
+    fields.push(("axml".to_string(), axml));
+    let bext = match &wav.metadata.bext {
+        Some(bext) => bext_json(bext),
+        None => "null".to_string(),
+    };
+    fields.push(("bext".to_string(), bext));
+    let impulse = match impulse::detect(wav) {
+        Some(impulse) => {
+            let number = |value: Option<f32>| value.map_or("null".to_string(), |v| v.to_string());
+            json::object(vec![
+                (
+                    "rt60".to_string(),
+                    number(impulse.rt60.map(|(time, _)| time)),
+                ),
+                (
+                    "rt60_method".to_string(),
+                    impulse
+                        .rt60
+                        .map_or("null".to_string(), |(_, method)| json::string(method)),
+                ),
+                ("edt".to_string(), number(impulse.edt)),
+                ("direct".to_string(), format!("{:.6}", impulse.peak_seconds)),
+            ])
+        }
+        None => "null".to_string(),
+    };
+    fields.push(("impulse".to_string(), impulse));
+
+    json::object(fields)
+}
+
+fn bext_json(bext: &Bext) -> String {
+    let number = |value: Option<f32>| value.map_or("null".to_string(), |value| value.to_string());
+    let loudness = match &bext.loudness {
+        Some(loudness) => json::object(vec![
+            ("integrated".to_string(), number(loudness.integrated)),
+            ("range".to_string(), number(loudness.range)),
+            ("max_true_peak".to_string(), number(loudness.max_true_peak)),
+            ("max_momentary".to_string(), number(loudness.max_momentary)),
+            (
+                "max_short_term".to_string(),
+                number(loudness.max_short_term),
+            ),
+        ]),
+        None => "null".to_string(),
+    };
+    let history: Vec<String> = bext
+        .coding_history
+        .iter()
+        .map(|line| json::string(line))
+        .collect();
+    json::object(vec![
+        ("description".to_string(), json::string(&bext.description)),
+        ("originator".to_string(), json::string(&bext.originator)),
+        (
+            "originator_reference".to_string(),
+            json::string(&bext.originator_reference),
+        ),
+        (
+            "origination_date".to_string(),
+            json::string(&bext.origination_date),
+        ),
+        (
+            "origination_time".to_string(),
+            json::string(&bext.origination_time),
+        ),
+        (
+            "time_reference".to_string(),
+            bext.time_reference.to_string(),
+        ),
+        ("version".to_string(), bext.version.to_string()),
+        ("loudness".to_string(), loudness),
+        (
+            "coding_history".to_string(),
+            format!("[{}]", history.join(", ")),
+        ),
+    ])
+}
@@ -0,0 +1,86 @@
+use crate::metadata::Metadata;
+
+// https://www.recordingblogs.com/wiki/list-chunk-of-a-wave-file, the `LIST` chunk of type
+// `INFO` holding nul terminated text subchunks
+
+// the tags of a LIST chunk body, empty unless it is an INFO list
+pub fn parse(list: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (id, value) in subchunks(list) {
+        let text = String::from_utf8_lossy(&value);
+        let text = text.trim_end_matches('\0').trim();
+        if text.is_empty() {
+            continue;
+        }
+        let text = Some(text.to_string());
+        match &id {
+            b"INAM" => metadata.title = text,
+            b"IART" => metadata.artist = text,
+            b"IPRD" => metadata.album = text,
+            b"ICRD" => metadata.date = text,
+            b"ICMT" => metadata.comment = text,
+            _ => {}
+        }
+    }
+    metadata
+}
+
+// a new INFO list body from `list` (another LIST body, or empty) with the given subchunks
+// replaced, an empty value removes the subchunk and everything else is kept as it was
+pub fn update(list: &[u8], changes: &[([u8; 4], &str)]) -> Vec<u8> {
+    let mut subchunks = subchunks(list);
+    for &(id, value) in changes {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        match subchunks.iter().position(|(existing, _)| *existing == id) {
+            Some(i) if value.is_empty() => {
+                subchunks.remove(i);
+            }
+            Some(i) => subchunks[i].1 = bytes,
+            None if value.is_empty() => {}
+            None => subchunks.push((id, bytes)),
+        }
+    }
+
+    let mut body = Vec::from(*b"INFO");
+    for (id, value) in subchunks {
+        body.extend(id);
+        body.extend((value.len() as u32).to_le_bytes());
+        body.extend(&value);
+        if value.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    body
+}
+
+pub fn is_info(list: &[u8]) -> bool {
+    list.starts_with(b"INFO")
+}
+
+fn subchunks(list: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+    let mut subchunks = Vec::new();
+    if !is_info(list) {
+        return subchunks;
+    }
+    let mut offset = 4;
+    while offset + 8 <= list.len() {
+        let id = [
+            list[offset],
+            list[offset + 1],
+            list[offset + 2],
+            list[offset + 3],
+        ];
+        let size = u32::from_le_bytes([
+            list[offset + 4],
+            list[offset + 5],
+            list[offset + 6],
+            list[offset + 7],
+        ]) as usize;
+        let start = offset + 8;
+        let end = (start + size).min(list.len());
+        subchunks.push((id, list[start..end].to_vec()));
+        offset = start + size + size % 2;
+    }
+    subchunks
+}
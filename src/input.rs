@@ -0,0 +1,579 @@
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod, TextInputUtil};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::render::Renderer;
+use crate::{font, i18n};
+
+// modifier bits of a chord, left and right keys count the same and the lock keys not at all
+const SHIFT: u8 = 1;
+const CTRL: u8 = 2;
+const ALT: u8 = 4;
+
+// everything the keyboard can ask the player to do
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    NextView,
+    NextSecondaryView,
+    ToggleSplit,
+    NextColormap,
+    // by the given number of dB
+    MoveDbFloor(f32),
+    MoveDbCeiling(f32),
+    PlayPause,
+    ToggleHarmonics,
+    ToggleGuideTone,
+    NextTonePreset,
+    NextToneLevel,
+    ToggleStats,
+    ToggleMini,
+    // J, K and L as in video editors
+    ShuttleReverse,
+    ShuttleStop,
+    ShuttleForward,
+    // through `SPEEDS` in main, faster or slower by the given number of steps
+    StepSpeed(isize),
+    // by the given number of seconds
+    Seek(f64),
+    // by the given number of dB
+    ChangeVolume(f32),
+    ToggleMuted,
+    NextTrack,
+    PreviousTrack,
+    Keep,
+    Reject,
+    ToggleMeters,
+    TogglePhosphor,
+    ToggleHumFilter,
+    ToggleRumbleFilter,
+    ToggleAirFilter,
+    ToggleConvolution,
+    // between binaural and a flat downmix, with --binaural
+    ToggleHeadModel,
+    // the --plugin effect, and its parameters one at a time
+    TogglePlugin,
+    SelectParameter(isize),
+    // by the given number of steps, see `plugin::Parameter::step`
+    AdjustParameter(f64),
+    FindChapters,
+    // the session a crashed run autosaved, see `recovery`
+    RestoreSession,
+    // by the given number of frames
+    NudgeCompare(isize),
+    SwitchCompare,
+    SaveQueue,
+    GoTo,
+    ToggleZeroSnap,
+    // while paused, by single frames or by spectrogram hops
+    StepFrames(isize),
+    StepHops(isize),
+    CopyTimestamp,
+    CopySelection,
+    CopyStats,
+    OpenPalette,
+    AddNote,
+    RemoveNote,
+    // to the cue point from the file's cue chunk after or before the playhead
+    NextCue,
+    PreviousCue,
+    ToggleSampleLoop,
+    // playback going round the whole file instead of stopping at its end
+    ToggleLoopFile,
+    // the A-B loop: its start, its end, which starts looping, and clearing it
+    MarkLoopStart,
+    MarkLoopEnd,
+    ClearLoop,
+    // of a take group's track, counted from 0
+    ToggleMute(usize),
+    TrackGain,
+    AlignTake,
+    // blind test listening and answers, see `abx`
+    HearA,
+    HearB,
+    HearX,
+    VoteXIsA,
+    VoteXIsB,
+    // the text typed into a prompt, once it's confirmed with return
+    Submit(Prompt, String),
+}
+
+// what a line of text is being typed for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prompt {
+    SaveQueue,
+    // a position in any form `timecode::parse` takes
+    GoTo,
+    // picks any bound action by name, what's typed narrows down the list
+    Palette,
+    // the text of a note pinned to the given frame
+    Note(usize),
+    // `track dB` for a take group
+    TrackGain,
+}
+
+impl Prompt {
+    fn label(self) -> &'static str {
+        match self {
+            Self::SaveQueue => i18n::text("prompt.save-queue"),
+            Self::GoTo => i18n::text("prompt.go-to"),
+            Self::Palette => i18n::text("prompt.palette"),
+            Self::Note(_) => i18n::text("prompt.note"),
+            Self::TrackGain => i18n::text("prompt.track-gain"),
+        }
+    }
+}
+
+impl Action {
+    // what the command palette lists it as
+    fn describe(&self) -> String {
+        match self {
+            Self::Quit => i18n::text("action.quit").to_string(),
+            Self::NextView => i18n::text("action.next-view").to_string(),
+            Self::NextSecondaryView => i18n::text("action.next-secondary-view").to_string(),
+            Self::ToggleSplit => i18n::text("action.toggle-split").to_string(),
+            Self::NextColormap => i18n::text("action.next-colormap").to_string(),
+            Self::MoveDbFloor(db) => {
+                i18n::fill("action.move-db-floor", &[("db", format!("{db:+}"))])
+            }
+            Self::MoveDbCeiling(db) => {
+                i18n::fill("action.move-db-ceiling", &[("db", format!("{db:+}"))])
+            }
+            Self::PlayPause => i18n::text("action.play-pause").to_string(),
+            Self::ToggleHarmonics => i18n::text("action.toggle-harmonics").to_string(),
+            Self::ToggleGuideTone => i18n::text("action.toggle-guide-tone").to_string(),
+            Self::NextTonePreset => i18n::text("action.next-tone-preset").to_string(),
+            Self::NextToneLevel => i18n::text("action.next-tone-level").to_string(),
+            Self::ToggleStats => i18n::text("action.toggle-stats").to_string(),
+            Self::ToggleMini => i18n::text("action.toggle-mini").to_string(),
+            Self::ShuttleReverse => i18n::text("action.shuttle-reverse").to_string(),
+            Self::ShuttleStop => i18n::text("action.shuttle-stop").to_string(),
+            Self::ShuttleForward => i18n::text("action.shuttle-forward").to_string(),
+            Self::StepSpeed(steps) if *steps > 0 => i18n::text("action.faster").to_string(),
+            Self::StepSpeed(_) => i18n::text("action.slower").to_string(),
+            Self::Seek(seconds) => {
+                i18n::fill("action.seek", &[("seconds", format!("{seconds:+}"))])
+            }
+            Self::ChangeVolume(db) => {
+                i18n::fill("action.change-volume", &[("db", format!("{db:+}"))])
+            }
+            Self::ToggleMuted => i18n::text("action.toggle-muted").to_string(),
+            Self::NextTrack => i18n::text("action.next-track").to_string(),
+            Self::PreviousTrack => i18n::text("action.previous-track").to_string(),
+            Self::Keep => i18n::text("action.keep").to_string(),
+            Self::Reject => i18n::text("action.reject").to_string(),
+            Self::ToggleMeters => i18n::text("action.toggle-meters").to_string(),
+            Self::TogglePhosphor => i18n::text("action.toggle-phosphor").to_string(),
+            Self::ToggleHumFilter => i18n::text("action.toggle-hum-filter").to_string(),
+            Self::ToggleRumbleFilter => i18n::text("action.toggle-rumble-filter").to_string(),
+            Self::ToggleAirFilter => i18n::text("action.toggle-air-filter").to_string(),
+            Self::ToggleConvolution => i18n::text("action.toggle-convolution").to_string(),
+            Self::ToggleHeadModel => i18n::text("action.toggle-head-model").to_string(),
+            Self::TogglePlugin => i18n::text("action.toggle-plugin").to_string(),
+            Self::SelectParameter(step) => {
+                i18n::fill("action.select-parameter", &[("step", format!("{step:+}"))])
+            }
+            Self::AdjustParameter(steps) => i18n::fill(
+                "action.adjust-parameter",
+                &[("steps", format!("{steps:+}"))],
+            ),
+            Self::FindChapters => i18n::text("action.find-chapters").to_string(),
+            Self::RestoreSession => i18n::text("action.restore-session").to_string(),
+            Self::NudgeCompare(frames) => {
+                i18n::fill("action.nudge-compare", &[("frames", format!("{frames:+}"))])
+            }
+            Self::SwitchCompare => i18n::text("action.switch-compare").to_string(),
+            Self::SaveQueue => i18n::text("action.save-queue").to_string(),
+            Self::GoTo => i18n::text("action.go-to").to_string(),
+            Self::ToggleZeroSnap => i18n::text("action.toggle-zero-snap").to_string(),
+            Self::StepFrames(frames) => {
+                i18n::fill("action.step-frames", &[("frames", format!("{frames:+}"))])
+            }
+            Self::StepHops(hops) => {
+                i18n::fill("action.step-hops", &[("hops", format!("{hops:+}"))])
+            }
+            Self::CopyTimestamp => i18n::text("action.copy-timestamp").to_string(),
+            Self::CopySelection => i18n::text("action.copy-selection").to_string(),
+            Self::CopyStats => i18n::text("action.copy-stats").to_string(),
+            Self::OpenPalette => i18n::text("action.open-palette").to_string(),
+            Self::AddNote => i18n::text("action.add-note").to_string(),
+            Self::RemoveNote => i18n::text("action.remove-note").to_string(),
+            Self::NextCue => i18n::text("action.next-cue").to_string(),
+            Self::PreviousCue => i18n::text("action.previous-cue").to_string(),
+            Self::ToggleSampleLoop => i18n::text("action.toggle-sample-loop").to_string(),
+            Self::ToggleLoopFile => i18n::text("action.toggle-loop-file").to_string(),
+            Self::MarkLoopStart => i18n::text("action.mark-loop-start").to_string(),
+            Self::MarkLoopEnd => i18n::text("action.mark-loop-end").to_string(),
+            Self::ClearLoop => i18n::text("action.clear-loop").to_string(),
+            Self::ToggleMute(track) => {
+                i18n::fill("action.toggle-mute", &[("track", (track + 1).to_string())])
+            }
+            Self::TrackGain => i18n::text("action.track-gain").to_string(),
+            Self::AlignTake => i18n::text("action.align-take").to_string(),
+            Self::HearA => i18n::text("action.hear-a").to_string(),
+            Self::HearB => i18n::text("action.hear-b").to_string(),
+            Self::HearX => i18n::text("action.hear-x").to_string(),
+            Self::VoteXIsA => i18n::text("action.vote-x-is-a").to_string(),
+            Self::VoteXIsB => i18n::text("action.vote-x-is-b").to_string(),
+            Self::Submit(prompt, _) => prompt.label().to_string(),
+        }
+    }
+}
+
+struct Binding {
+    key: Keycode,
+    modifiers: u8,
+    action: Action,
+    // held down, the action happens again at the keyboard's repeat rate. Only for the ones
+    // that step something, holding space shouldn't flicker between playing and paused
+    repeats: bool,
+}
+
+const fn bind(key: Keycode, modifiers: u8, action: Action) -> Binding {
+    Binding {
+        key,
+        modifiers,
+        action,
+        repeats: false,
+    }
+}
+
+const fn repeat(key: Keycode, modifiers: u8, action: Action) -> Binding {
+    Binding {
+        key,
+        modifiers,
+        action,
+        repeats: true,
+    }
+}
+
+const BINDINGS: &[Binding] = &[
+    bind(Keycode::Escape, 0, Action::Quit),
+    bind(Keycode::Tab, 0, Action::NextView),
+    bind(Keycode::Tab, SHIFT, Action::NextSecondaryView),
+    bind(Keycode::V, 0, Action::ToggleSplit),
+    bind(Keycode::C, 0, Action::NextColormap),
+    repeat(Keycode::Up, 0, Action::MoveDbFloor(5.0)),
+    repeat(Keycode::Down, 0, Action::MoveDbFloor(-5.0)),
+    repeat(Keycode::Up, SHIFT, Action::MoveDbCeiling(5.0)),
+    repeat(Keycode::Down, SHIFT, Action::MoveDbCeiling(-5.0)),
+    bind(Keycode::Space, 0, Action::PlayPause),
+    bind(Keycode::H, 0, Action::ToggleHarmonics),
+    bind(Keycode::T, 0, Action::ToggleGuideTone),
+    bind(Keycode::T, SHIFT, Action::NextTonePreset),
+    bind(Keycode::T, CTRL, Action::NextToneLevel),
+    bind(Keycode::F3, 0, Action::ToggleStats),
+    bind(Keycode::M, CTRL, Action::ToggleMini),
+    bind(Keycode::N, 0, Action::NextTrack),
+    bind(Keycode::N, SHIFT, Action::PreviousTrack),
+    bind(Keycode::J, 0, Action::ShuttleReverse),
+    bind(Keycode::K, 0, Action::ShuttleStop),
+    bind(Keycode::L, 0, Action::ShuttleForward),
+    bind(Keycode::L, SHIFT, Action::ToggleLoopFile),
+    bind(Keycode::A, ALT, Action::MarkLoopStart),
+    bind(Keycode::B, ALT, Action::MarkLoopEnd),
+    bind(Keycode::C, ALT, Action::ClearLoop),
+    repeat(Keycode::Left, 0, Action::Seek(-5.0)),
+    repeat(Keycode::Right, 0, Action::Seek(5.0)),
+    repeat(Keycode::Left, SHIFT, Action::Seek(-30.0)),
+    repeat(Keycode::Right, SHIFT, Action::Seek(30.0)),
+    // = and shift+= for keyboards where + is shifted
+    repeat(Keycode::Equals, 0, Action::ChangeVolume(1.0)),
+    repeat(Keycode::Equals, SHIFT, Action::ChangeVolume(1.0)),
+    repeat(Keycode::Minus, 0, Action::ChangeVolume(-1.0)),
+    repeat(Keycode::KpPlus, 0, Action::ChangeVolume(1.0)),
+    repeat(Keycode::KpMinus, 0, Action::ChangeVolume(-1.0)),
+    bind(Keycode::M, 0, Action::ToggleMuted),
+    bind(Keycode::Y, 0, Action::Keep),
+    bind(Keycode::R, 0, Action::Reject),
+    bind(Keycode::F9, 0, Action::ToggleMeters),
+    bind(Keycode::F8, 0, Action::TogglePhosphor),
+    bind(Keycode::F5, 0, Action::ToggleHumFilter),
+    bind(Keycode::F6, 0, Action::ToggleRumbleFilter),
+    bind(Keycode::F7, 0, Action::ToggleAirFilter),
+    bind(Keycode::F4, 0, Action::ToggleConvolution),
+    bind(Keycode::B, 0, Action::ToggleHeadModel),
+    bind(Keycode::F2, 0, Action::TogglePlugin),
+    repeat(Keycode::Up, ALT | SHIFT, Action::SelectParameter(-1)),
+    repeat(Keycode::Down, ALT | SHIFT, Action::SelectParameter(1)),
+    repeat(Keycode::Up, ALT, Action::AdjustParameter(1.0)),
+    repeat(Keycode::Down, ALT, Action::AdjustParameter(-1.0)),
+    bind(Keycode::S, 0, Action::FindChapters),
+    bind(Keycode::S, CTRL, Action::SaveQueue),
+    bind(Keycode::R, CTRL, Action::RestoreSession),
+    repeat(Keycode::Left, ALT, Action::NudgeCompare(-1)),
+    repeat(Keycode::Right, ALT, Action::NudgeCompare(1)),
+    repeat(Keycode::Left, ALT | SHIFT, Action::NudgeCompare(-100)),
+    repeat(Keycode::Right, ALT | SHIFT, Action::NudgeCompare(100)),
+    bind(Keycode::X, 0, Action::SwitchCompare),
+    bind(Keycode::G, 0, Action::GoTo),
+    bind(Keycode::Z, 0, Action::ToggleZeroSnap),
+    repeat(Keycode::Period, 0, Action::StepFrames(1)),
+    repeat(Keycode::Comma, 0, Action::StepFrames(-1)),
+    repeat(Keycode::Period, SHIFT, Action::StepHops(1)),
+    repeat(Keycode::Comma, SHIFT, Action::StepHops(-1)),
+    bind(Keycode::C, CTRL, Action::CopyTimestamp),
+    bind(Keycode::C, CTRL | SHIFT, Action::CopySelection),
+    bind(Keycode::C, CTRL | ALT, Action::CopyStats),
+    bind(Keycode::P, CTRL, Action::OpenPalette),
+    bind(Keycode::A, 0, Action::AddNote),
+    bind(Keycode::A, SHIFT, Action::RemoveNote),
+    repeat(Keycode::RightBracket, 0, Action::NextCue),
+    repeat(Keycode::LeftBracket, 0, Action::PreviousCue),
+    bind(Keycode::RightBracket, SHIFT, Action::StepSpeed(1)),
+    bind(Keycode::LeftBracket, SHIFT, Action::StepSpeed(-1)),
+    bind(Keycode::O, 0, Action::ToggleSampleLoop),
+    bind(Keycode::Num1, CTRL, Action::ToggleMute(0)),
+    bind(Keycode::Num2, CTRL, Action::ToggleMute(1)),
+    bind(Keycode::Num3, CTRL, Action::ToggleMute(2)),
+    bind(Keycode::Num4, CTRL, Action::ToggleMute(3)),
+    bind(Keycode::Num5, CTRL, Action::ToggleMute(4)),
+    bind(Keycode::Num6, CTRL, Action::ToggleMute(5)),
+    bind(Keycode::Num7, CTRL, Action::ToggleMute(6)),
+    bind(Keycode::Num8, CTRL, Action::ToggleMute(7)),
+    bind(Keycode::Num9, CTRL, Action::ToggleMute(8)),
+    bind(Keycode::G, CTRL, Action::TrackGain),
+    bind(Keycode::A, CTRL, Action::AlignTake),
+    bind(Keycode::Num1, 0, Action::HearA),
+    bind(Keycode::Num2, 0, Action::HearB),
+    bind(Keycode::Num3, 0, Action::HearX),
+    bind(Keycode::Num1, SHIFT, Action::VoteXIsA),
+    bind(Keycode::Num2, SHIFT, Action::VoteXIsB),
+];
+
+// how a binding's chord is written in the palette
+fn chord(binding: &Binding) -> String {
+    let mut chord = String::new();
+    for (bit, name) in [(CTRL, "ctrl+"), (ALT, "alt+"), (SHIFT, "shift+")] {
+        if binding.modifiers & bit != 0 {
+            chord.push_str(name);
+        }
+    }
+    chord.push_str(&binding.key.name());
+    chord
+}
+
+// how well `query` matches `text`: all of its characters have to appear in order, ignoring
+// case, and the fewer characters skipped between them the better. None if they don't appear
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut position = 0;
+    let mut skipped = 0;
+    let mut started = false;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = text[position..].iter().position(|&c| c == wanted)?;
+        // where the first match starts doesn't count, gaps after it do
+        if started {
+            skipped += found;
+        }
+        started = true;
+        position += found + 1;
+    }
+    Some(skipped)
+}
+
+// the bindings matching `query` in the palette, best match first and in table order otherwise
+fn palette_matches(query: &str) -> Vec<&'static Binding> {
+    let mut matches: Vec<(usize, &Binding)> = BINDINGS
+        .iter()
+        .filter(|binding| binding.action != Action::OpenPalette)
+        .filter_map(|binding| Some((fuzzy_score(query, &binding.action.describe())?, binding)))
+        .collect();
+    matches.sort_by_key(|&(score, _)| score);
+    matches.into_iter().map(|(_, binding)| binding).collect()
+}
+
+// palette entries shown at once
+const PALETTE_ROWS: usize = 12;
+
+// a line of text being typed
+struct Entry {
+    prompt: Prompt,
+    text: String,
+    // the highlighted row of the palette
+    selected: usize,
+}
+
+// turns key presses into actions. A key with modifiers held only matches a binding for
+// exactly those modifiers, and while a prompt is open the keyboard types into it instead
+pub struct Input {
+    text_input: TextInputUtil,
+    entry: Option<Entry>,
+}
+
+impl Input {
+    pub fn new(text_input: TextInputUtil) -> Self {
+        // sdl starts out sending text events for every key, they're only wanted in a prompt
+        text_input.stop();
+        Self {
+            text_input,
+            entry: None,
+        }
+    }
+
+    // opens a prompt with `text` already typed in
+    pub fn prompt(&mut self, prompt: Prompt, text: &str) {
+        self.text_input.start();
+        self.entry = Some(Entry {
+            prompt,
+            text: text.to_string(),
+            selected: 0,
+        });
+    }
+
+    // the action for `event` if it's a key press bound to one or confirms a prompt. Mouse and
+    // window events are left to the caller
+    pub fn handle(&mut self, event: &Event) -> Option<Action> {
+        if self.entry.is_some() {
+            return self.type_into_prompt(event);
+        }
+        let Event::KeyDown {
+            keycode: Some(key),
+            keymod,
+            repeat,
+            ..
+        } = *event
+        else {
+            return None;
+        };
+        let modifiers = modifiers(keymod);
+        BINDINGS
+            .iter()
+            .find(|binding| binding.key == key && binding.modifiers == modifiers)
+            .filter(|binding| binding.repeats || !repeat)
+            .map(|binding| binding.action.clone())
+    }
+
+    fn type_into_prompt(&mut self, event: &Event) -> Option<Action> {
+        let entry = self.entry.as_mut()?;
+        match event {
+            Event::TextInput { text, .. } => {
+                entry.text.push_str(text);
+                entry.selected = 0;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                ..
+            } => {
+                entry.text.pop();
+                entry.selected = 0;
+            }
+            Event::KeyDown {
+                keycode: Some(key @ (Keycode::Up | Keycode::Down)),
+                ..
+            } if entry.prompt == Prompt::Palette => {
+                let count = palette_matches(&entry.text).len().min(PALETTE_ROWS);
+                entry.selected = match *key {
+                    Keycode::Up => entry.selected.saturating_sub(1),
+                    _ => (entry.selected + 1).min(count.saturating_sub(1)),
+                };
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Return | Keycode::KpEnter),
+                ..
+            } => {
+                let entry = self.close()?;
+                if entry.prompt == Prompt::Palette {
+                    let binding = palette_matches(&entry.text)
+                        .into_iter()
+                        .nth(entry.selected)?;
+                    return Some(binding.action.clone());
+                }
+                return Some(Action::Submit(entry.prompt, entry.text));
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => {
+                self.close();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn close(&mut self) -> Option<Entry> {
+        self.text_input.stop();
+        self.entry.take()
+    }
+}
+
+fn modifiers(keymod: Mod) -> u8 {
+    let mut modifiers = 0;
+    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+        modifiers |= SHIFT;
+    }
+    if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+        modifiers |= CTRL;
+    }
+    if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+        modifiers |= ALT;
+    }
+    modifiers
+}
+
+// the open prompt along the bottom of the viewport, if there is one
+pub fn draw(canvas: &mut impl Renderer, input: &Input) {
+    let Some(entry) = &input.entry else {
+        return;
+    };
+    let (width, height) = canvas.size();
+    let line = format!("{}: {}_", entry.prompt.label(), entry.text);
+    let top = height as i32 - 28;
+    canvas.set_color(Color::RGB(32, 32, 32));
+    canvas.fill_rect(Rect::new(0, top, width, 28));
+    canvas.set_color(Color::RGB(255, 255, 255));
+    font::draw_text(canvas, 8, top + 6, 2, &line);
+
+    if entry.prompt != Prompt::Palette {
+        return;
+    }
+    // the matches stacked up above the prompt, the best one right above it
+    let matches = palette_matches(&entry.text);
+    for (row, binding) in matches.iter().take(PALETTE_ROWS).enumerate() {
+        let y = top - 24 * (row as i32 + 1);
+        canvas.set_color(if row == entry.selected {
+            Color::RGB(64, 64, 96)
+        } else {
+            Color::RGB(24, 24, 24)
+        });
+        canvas.fill_rect(Rect::new(0, y, width, 24));
+        canvas.set_color(Color::RGB(255, 255, 255));
+        font::draw_text(canvas, 8, y + 5, 2, &binding.action.describe());
+        let chord = chord(binding);
+        canvas.set_color(Color::RGB(160, 160, 160));
+        let x = width as i32 - 8 - font::text_width(&chord, 2);
+        font::draw_text(canvas, x, y + 5, 2, &chord);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_chord_is_bound_once() {
+        for (i, binding) in BINDINGS.iter().enumerate() {
+            let duplicate = BINDINGS[i + 1..]
+                .iter()
+                .find(|other| other.key == binding.key && other.modifiers == binding.modifiers);
+            assert!(
+                duplicate.is_none(),
+                "{:?} and {:?} share a chord",
+                binding.action,
+                duplicate.unwrap().action
+            );
+        }
+    }
+
+    #[test]
+    fn fuzzy_matches_in_order_and_prefers_tight_ones() {
+        assert_eq!(fuzzy_score("", "quit"), Some(0));
+        assert_eq!(fuzzy_score("tgl", "toggle split view"), Some(2));
+        assert_eq!(fuzzy_score("lgt", "toggle split view"), None);
+        assert!(fuzzy_score("split", "toggle split view") < fuzzy_score("sv", "toggle split view"));
+        let best = palette_matches("hum")[0];
+        assert_eq!(best.action, Action::ToggleHumFilter);
+    }
+}
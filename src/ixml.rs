@@ -0,0 +1,55 @@
+// http://www.gallery.co.uk/ixml/ the production metadata location recorders write into an
+// `iXML` chunk, only the elements dailies tooling usually cares about are picked out
+
+// (key, element) pairs, the elements are unique within a document so nesting is ignored
+const FIELDS: [(&str, &str); 9] = [
+    ("project", "PROJECT"),
+    ("scene", "SCENE"),
+    ("take", "TAKE"),
+    ("tape", "TAPE"),
+    ("circled", "CIRCLED"),
+    ("note", "NOTE"),
+    ("file_uid", "FILE_UID"),
+    ("timecode_rate", "TIMECODE_RATE"),
+    ("timecode_flag", "TIMECODE_FLAG"),
+];
+
+// the production fields present in `xml`, in a fixed order
+pub fn fields(xml: &str) -> Vec<(&'static str, String)> {
+    FIELDS
+        .iter()
+        .filter_map(|&(key, element)| {
+            let text = elements(xml, element).next()?;
+            (!text.is_empty()).then_some((key, text))
+        })
+        .collect()
+}
+
+// names of the recorded tracks from TRACK_LIST, in channel order
+pub fn track_names(xml: &str) -> Vec<String> {
+    elements(xml, "TRACK")
+        .map(|track| elements(&track, "NAME").next().unwrap_or_default())
+        .collect()
+}
+
+// the text of every `<name>...</name>` element, unescaped and trimmed
+fn elements<'a>(xml: &'a str, name: &str) -> impl Iterator<Item = String> + 'a {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let text = unescape(rest[start..end].trim());
+        rest = &rest[end + close.len()..];
+        Some(text)
+    })
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
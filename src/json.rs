@@ -0,0 +1,255 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// just enough json for the files wave writes and reads back itself, without pulling in a
+// serialization crate
+
+// a parsed document. Objects keep their keys in file order so rewriting one doesn't shuffle it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::String(text) => write!(f, "{}", string(text)),
+            Self::Array(items) => {
+                let items: Vec<String> = items.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Self::Object(fields) => write!(
+                f,
+                "{}",
+                object(
+                    fields
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.to_string()))
+                        .collect()
+                )
+            ),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected `{c}` after the document")),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            if eat(chars, '}') {
+                return Ok(Value::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                if chars.next() != Some('"') {
+                    return Err("expected a key".to_string());
+                }
+                let key = parse_string(chars)?;
+                if !eat(chars, ':') {
+                    return Err(format!("expected `:` after \"{key}\""));
+                }
+                fields.push((key, parse_value(chars)?));
+                if eat(chars, '}') {
+                    return Ok(Value::Object(fields));
+                }
+                if !eat(chars, ',') {
+                    return Err("expected `,` or `}`".to_string());
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            if eat(chars, ']') {
+                return Ok(Value::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars)?);
+                if eat(chars, ']') {
+                    return Ok(Value::Array(items));
+                }
+                if !eat(chars, ',') {
+                    return Err("expected `,` or `]`".to_string());
+                }
+            }
+        }
+        Some('"') => {
+            chars.next();
+            parse_string(chars).map(Value::String)
+        }
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            number
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("bad number `{number}`"))
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_alphabetic() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            match word.as_str() {
+                "null" => Ok(Value::Null),
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(format!("unexpected `{word}`")),
+            }
+        }
+        None => Err("unexpected end of the document".to_string()),
+    }
+}
+
+// the rest of a string after its opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut text = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(text),
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                'b' => text.push('\u{8}'),
+                'f' => text.push('\u{c}'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16)
+                        .map_err(|_| format!("bad escape \\u{hex}"))?;
+                    // surrogate pairs aren't written by anything here, they come out replaced
+                    text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                c => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+// consumes `c` if it's next after any whitespace
+fn eat(chars: &mut Peekable<Chars>, c: char) -> bool {
+    skip_whitespace(chars);
+    chars.next_if_eq(&c).is_some()
+}
+
+// `fields` hold values that are already json
+pub fn object(fields: Vec<(String, String)>) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}: {value}", string(key)))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+pub fn string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let text = r#"{"name": "a \"quoted\"\nline", "frames": [0, 1.5, -2e3], "on": true, "none": null, "empty": {}}"#;
+        let value = parse(text).unwrap();
+        assert_eq!(
+            value.get("name").and_then(Value::as_str),
+            Some("a \"quoted\"\nline")
+        );
+        assert_eq!(
+            value.get("frames").and_then(Value::as_array).unwrap()[2],
+            Value::Number(-2000.0)
+        );
+        assert_eq!(parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_broken_documents() {
+        assert!(parse(r#"{"a": 1"#).is_err());
+        assert!(parse(r#"{"a" 1}"#).is_err());
+        assert!(parse("[1, 2] 3").is_err());
+        assert!(parse(r#""open"#).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use sdl2::rect::Rect;
+
+use crate::View;
+
+// pixels either side of the divider that still grab it
+const GRAB_DISTANCE: i32 = 4;
+// smallest share of the height either pane can be dragged down to
+const MIN_SHARE: f32 = 0.1;
+
+// which views share the main area. Normally one view fills it, split it shows a second one
+// below with a divider that can be dragged to resize both
+pub struct Layout {
+    // the top view, or the only one
+    pub primary: View,
+    // the bottom view while split
+    pub secondary: Option<View>,
+    // share of the height the top view gets
+    divider: f32,
+    dragging: bool,
+}
+
+impl Layout {
+    pub fn new(view: View) -> Self {
+        Self {
+            primary: view,
+            secondary: None,
+            divider: 0.5,
+            dragging: false,
+        }
+    }
+
+    pub fn toggle_split(&mut self) {
+        self.secondary = match self.secondary {
+            Some(_) => None,
+            None => Some(self.primary.next(false)),
+        };
+    }
+
+    // every view on screen with the part of `area` it is drawn in, top first
+    pub fn panes(&self, area: Rect) -> Vec<(View, Rect)> {
+        match (self.secondary, self.divider_y(area)) {
+            (Some(secondary), Some(y)) => vec![
+                (
+                    self.primary,
+                    Rect::new(area.x(), area.y(), area.width(), (y - area.y()) as u32),
+                ),
+                (
+                    secondary,
+                    Rect::new(area.x(), y, area.width(), (area.bottom() - y) as u32),
+                ),
+            ],
+            _ => vec![(self.primary, area)],
+        }
+    }
+
+    pub fn shows(&self, view: View) -> bool {
+        self.primary == view || self.secondary == Some(view)
+    }
+
+    // the view under a point of `area`
+    pub fn view_at(&self, area: Rect, point: (i32, i32)) -> Option<View> {
+        self.panes(area)
+            .into_iter()
+            .find(|(_, pane)| pane.contains_point(point))
+            .map(|(view, _)| view)
+    }
+
+    pub fn divider_y(&self, area: Rect) -> Option<i32> {
+        self.secondary?;
+        Some(area.y() + (area.height() as f32 * self.divider) as i32)
+    }
+
+    // starts dragging the divider if `y` is on it
+    pub fn grab(&mut self, area: Rect, y: i32) -> bool {
+        self.dragging = self
+            .divider_y(area)
+            .is_some_and(|divider| (y - divider).abs() <= GRAB_DISTANCE);
+        self.dragging
+    }
+
+    pub fn drag(&mut self, area: Rect, y: i32) {
+        if self.dragging {
+            let share = (y - area.y()) as f32 / area.height().max(1) as f32;
+            self.divider = share.clamp(MIN_SHARE, 1.0 - MIN_SHARE);
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+}
@@ -0,0 +1,418 @@
+//! Reading and writing wav files (and reading AIFF, FLAC, MP3, Ogg Vorbis and Opus ones, or headerless samples), the parts of `wave` that don't need SDL2.
+//!
+//! ```no_run
+//! let wav = wave::WavFile::open("take.wav")?;
+//! println!("{} channels at {} Hz", wav.header().num_channels, wav.header().sample_rate);
+//! let loudest = wav.samples().iter().map(|s| s.unsigned_abs()).max();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod aiff;
+pub mod bext;
+#[cfg(test)]
+mod conformance;
+pub mod cue;
+pub mod decoder;
+pub mod diagnostic;
+#[cfg(feature = "flac")]
+pub mod flac;
+mod g711;
+pub mod id3;
+pub mod info_list;
+pub mod metadata;
+#[cfg(feature = "mp3")]
+pub mod mp3;
+#[cfg(feature = "ogg")]
+pub mod ogg;
+pub mod progress;
+pub mod raw;
+pub mod reader;
+pub mod riff;
+pub mod samples;
+pub mod smpl;
+pub mod writer;
+
+use std::io::{self, Read};
+use std::ops::{ControlFlow, Range};
+use std::{error, fs};
+
+pub use aiff::AiffReader;
+use cue::Cue;
+use diagnostic::Diagnostic;
+use metadata::Metadata;
+use progress::{CancellationToken, Reporter};
+use raw::{RawReader, RawSpec};
+pub use reader::WavReader;
+use samples::{Samples, Spill};
+use smpl::SampleLoop;
+pub use writer::{WavSpec, WavWriter};
+
+// samples decoded per read while loading a whole file
+const BLOCK_SAMPLES: usize = 1 << 16;
+
+// http://soundfile.sapp.org/doc/WaveFormat/
+
+/// The header of a PCM wav file, field by field. The offsets are where each field sits in the
+/// canonical 44 byte layout, other files can have chunks before, between or after these.
+#[derive(Clone, Default, Debug)]
+pub struct Header {
+    // The "RIFF" chunk descriptor
+    // The format of concern here is "WAVE", which requires two sub-chunks: "fmt " and "data"
+    pub chunk_id: Box<str>, // 0 - 4
+    pub chunk_size: u32,    // 4 - 8
+    pub format: Box<str>,   // 8 - 12
+
+    // The "fmt " sub-chunk
+    // describes the format of the sound information in the data sub-chunk
+    pub subchunk1_id: Box<str>, // 12 - 16
+    pub subchunk1_size: u32,    // 16 - 20
+    pub audio_format: u16,      // 20 - 22
+    pub num_channels: u16,      // 22 - 24
+    pub sample_rate: u32,       // 24 - 28
+    pub byte_rate: u32,         // 28 - 32
+    pub block_align: u16,       // 32 - 34
+    pub bits_per_sample: u16,   // 34 - 36
+
+    // The WAVE_FORMAT_EXTENSIBLE extension of the "fmt " sub-chunk, all zero without one
+    pub valid_bits_per_sample: u16,
+    // which speaker every channel is for, one bit each in the order of SPEAKERS
+    pub channel_mask: u32,
+    // the format code the subformat GUID stands for
+    pub sub_format: u16,
+
+    // The "data" sub chunk, an RF64 file sets the size to 0xffffffff and keeps the real one in
+    // its ds64 chunk, see WavFile::data_size
+    pub subchunk2_id: Box<str>, // 36 - 40
+    pub subchunk2_size: u32,    // 40 - 44
+}
+
+/// The audio_format of files with a WAVE_FORMAT_EXTENSIBLE fmt chunk, whose real format is
+/// in [`Header::sub_format`].
+pub const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Speaker positions of the channel mask bits, lowest bit first.
+pub const SPEAKERS: [&str; 18] = [
+    "FL", "FR", "FC", "LFE", "BL", "BR", "FLC", "FRC", "BC", "SL", "SR", "TC", "TFL", "TFC", "TFR",
+    "TBL", "TBC", "TBR",
+];
+
+impl Header {
+    /// The format the samples are actually in, looking through an extensible header.
+    pub fn sample_format(&self) -> u16 {
+        if self.audio_format == FORMAT_EXTENSIBLE {
+            self.sub_format
+        } else {
+            self.audio_format
+        }
+    }
+
+    /// The speaker of every channel according to the channel mask, in channel order. Empty
+    /// without a mask, channels past the ones it names are left out.
+    pub fn speakers(&self) -> Vec<&'static str> {
+        SPEAKERS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| self.channel_mask & 1 << bit != 0)
+            .map(|(_, &speaker)| speaker)
+            .take(self.num_channels as usize)
+            .collect()
+    }
+}
+
+/// A file read a block at a time, whichever container it's in. [`WavFile`] reads through
+/// one of these after looking at the first bytes of the file.
+pub trait AudioSource {
+    /// The format of the samples, as the fields of a wav header.
+    fn header(&self) -> &Header;
+    /// Size of the sample data in bytes.
+    fn data_size(&self) -> u64;
+    /// Bytes of the sample data not read yet.
+    fn remaining(&self) -> u64;
+    /// Decodes the next samples into `out` as 16-bit, returning how many, 0 at the end.
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize>;
+    /// Steps over the next `count` samples as if they were read, returning how many there
+    /// were. Decodes them unless the format can skip its bytes instead.
+    fn skip_samples(&mut self, count: u64) -> io::Result<u64> {
+        let mut block = vec![0; BLOCK_SAMPLES.min(count as usize)];
+        let mut skipped = 0;
+        while skipped < count {
+            let wanted = (count - skipped).min(block.len() as u64) as usize;
+            let read = self.read_samples(&mut block[..wanted])?;
+            if read == 0 {
+                break;
+            }
+            skipped += read as u64;
+        }
+        Ok(skipped)
+    }
+    /// Skips the rest of the samples and reads the tags after them.
+    fn finish(self: Box<Self>) -> io::Result<Metadata>;
+}
+
+/// Opens `reader` as a wav file or, if it starts like one, an AIFF, AIFF-C or (with the
+/// `flac`, `mp3` and `ogg` features) FLAC, MP3 or Ogg Vorbis or Opus file.
+pub fn open_source<'a>(
+    mut reader: impl Read + 'a,
+) -> Result<Box<dyn AudioSource + 'a>, Box<dyn error::Error + 'static>> {
+    let mut start = Vec::with_capacity(12);
+    reader.by_ref().take(12).read_to_end(&mut start)?;
+    let magic: [u8; 4] = start
+        .get(..4)
+        .and_then(|m| m.try_into().ok())
+        .unwrap_or_default();
+    let reader = io::Cursor::new(start).chain(reader);
+    match &magic {
+        b"FORM" => Ok(Box::new(AiffReader::new(reader)?)),
+        #[cfg(feature = "flac")]
+        b"fLaC" => Ok(Box::new(flac::FlacReader::new(reader)?)),
+        #[cfg(not(feature = "flac"))]
+        b"fLaC" => Err("reading FLAC files needs wave built with the flac feature".into()),
+        #[cfg(feature = "ogg")]
+        b"OggS" => Ok(Box::new(ogg::OggReader::new(reader)?)),
+        #[cfg(not(feature = "ogg"))]
+        b"OggS" => Err("reading Ogg files needs wave built with the ogg feature".into()),
+        // an ID3v2 tag, or straight away the sync bits of an MPEG audio frame
+        #[cfg(feature = "mp3")]
+        [b'I', b'D', b'3', _] | [0xff, 0xe0..=0xff, ..] => {
+            Ok(Box::new(mp3::Mp3Reader::new(reader)?))
+        }
+        #[cfg(not(feature = "mp3"))]
+        [b'I', b'D', b'3', _] | [0xff, 0xe0..=0xff, ..] => {
+            Err("reading MP3 files needs wave built with the mp3 feature".into())
+        }
+        _ => Ok(Box::new(WavReader::new(reader)?)),
+    }
+}
+
+/// A decoded PCM wav, AIFF, FLAC, MP3 or Ogg file. Everything is turned into 16-bit samples: wider ones and float are
+/// dithered down, A-law and µ-law expanded.
+#[derive(Default)]
+pub struct WavFile {
+    pub header: Header,
+    // size of the data chunk in bytes, subchunk2_size unless it's an RF64 file
+    pub data_size: u64,
+    /// Interleaved samples, one per channel for every frame.
+    pub data: Samples,
+    /// Tags and cue points from the chunks around the data, if any.
+    pub metadata: Metadata,
+}
+
+impl WavFile {
+    /// Reads and decodes the file at `path`.
+    pub fn open(path: &str) -> Result<Self, Box<dyn error::Error + 'static>> {
+        Self::open_with_progress(
+            path,
+            &mut |_| ControlFlow::Continue(()),
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Like [`WavFile::open`], reporting the decoding progress in bytes. Stops early with
+    /// [`progress::Cancelled`] if the callback says so or `cancel` is triggered from another
+    /// thread.
+    pub fn open_with_progress(
+        path: &str,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let file = fs::File::open(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::read(file, progress, cancel)
+    }
+
+    /// Reads the file at `path` as headerless samples laid out as `spec` says, reporting the
+    /// progress like [`WavFile::open_with_progress`].
+    pub fn open_raw(
+        path: &str,
+        spec: RawSpec,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let file = fs::File::open(path).map_err(|e| format!("{path}: {e}"))?;
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        Self::from_source(
+            Box::new(RawReader::new(file, spec, size)?),
+            progress,
+            cancel,
+        )
+    }
+
+    /// Decodes a whole wav, AIFF, FLAC, MP3 or Ogg file from `reader`, see [`open_source`] to go through one
+    /// a block at a time instead.
+    pub fn from_reader(reader: impl Read) -> Result<Self, Box<dyn error::Error + 'static>> {
+        Self::read(
+            reader,
+            &mut |_| ControlFlow::Continue(()),
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Like [`WavFile::from_reader`], keeping at most `budget` bytes of samples in memory
+    /// instead of the [`samples::memory_budget`] every other decode shares.
+    pub fn from_reader_with_budget(
+        reader: impl Read,
+        budget: u64,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        Self::decode(
+            open_source(reader)?,
+            budget,
+            &mut |_| ControlFlow::Continue(()),
+            &CancellationToken::new(),
+        )
+    }
+
+    /// Writes the samples to `path` as a plain 16-bit wav file at the same rate and channel
+    /// count, followed by every other chunk the file was read with (tags, markers, `bext`,
+    /// `iXML` and ones wave doesn't know) byte for byte. Clear `metadata.chunks` or pass it
+    /// through [`riff::strip`] first to leave them out. See [`WavWriter`] for other bit
+    /// depths.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn error::Error + 'static>> {
+        let spec = WavSpec {
+            sample_rate: self.header.sample_rate,
+            channels: self.header.num_channels,
+            bits_per_sample: 16,
+        };
+        let mut writer = WavWriter::create(path, spec)?;
+        writer.write_samples(&self.data)?;
+        for chunk in &self.metadata.chunks {
+            writer.write_chunk(chunk.id, &chunk.data)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The cue points of the file, in the order they play, see [`WavFile::metadata`].
+    pub fn cues(&self) -> &[Cue] {
+        &self.metadata.cues
+    }
+
+    /// The sampler loops of the file's `smpl` chunk, see [`WavFile::metadata`].
+    pub fn loops(&self) -> &[SampleLoop] {
+        &self.metadata.loops
+    }
+
+    /// What's off about the file's chunks, see [`WavFile::metadata`].
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.metadata.diagnostics
+    }
+
+    /// The interleaved samples, see [`WavFile::data`].
+    pub fn samples(&self) -> &[i16] {
+        &self.data
+    }
+
+    /// Every frame averaged down to a single channel, scaled to -1.0..1.0. This copies the
+    /// whole file, long ones are better read with [`WavFile::mono_blocks`].
+    pub fn mono(&self) -> Vec<f32> {
+        self.mono_range(0..self.frames())
+    }
+
+    /// How many frames of samples there are, a partial last frame doesn't count.
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.header.num_channels.max(1) as usize
+    }
+
+    /// The frames in `range` averaged down to a single channel like [`WavFile::mono`], the
+    /// part of it past the end is left out.
+    pub fn mono_range(&self, range: Range<usize>) -> Vec<f32> {
+        let channels = self.header.num_channels.max(1) as usize;
+        let end = range.end.min(self.frames());
+        let start = range.start.min(end);
+        self.data[start * channels..end * channels]
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().map(|&s| s as f32).sum::<f32>() / channels as f32 / 32768.0)
+            .collect()
+    }
+
+    /// [`WavFile::mono`] `frames` frames at a time, the last block is shorter. Only one block
+    /// is in memory at once, however long the file.
+    pub fn mono_blocks(&self, frames: usize) -> impl Iterator<Item = Vec<f32>> + '_ {
+        let frames = frames.max(1);
+        (0..self.frames())
+            .step_by(frames)
+            .map(move |start| self.mono_range(start..start + frames))
+    }
+
+    // decodes the samples a block at a time as they're read, reporting progress in bytes
+    fn read(
+        reader: impl Read,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        Self::from_source(open_source(reader)?, progress, cancel)
+    }
+
+    fn from_source(
+        reader: Box<dyn AudioSource + '_>,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        Self::decode(reader, samples::memory_budget(), progress, cancel)
+    }
+
+    // samples beyond `budget` bytes go to a spill file instead of memory, the ones decoded
+    // so far included
+    fn decode(
+        mut reader: Box<dyn AudioSource + '_>,
+        budget: u64,
+        progress: progress::Callback,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let data_size = reader.data_size();
+        // the size is only a claim until the samples are actually there, so a broken header
+        // can't reserve gigabytes up front, nor more than the budget
+        let capacity = (data_size / 2)
+            .min(BLOCK_SAMPLES as u64 * 64)
+            .min(budget / 2);
+        let mut data = Vec::with_capacity(capacity as usize);
+        let mut spill: Option<Spill> = None;
+        let mut block = vec![0; BLOCK_SAMPLES];
+        let mut reporter = Reporter::new(progress, cancel, data_size as usize);
+        loop {
+            let read = reader.read_samples(&mut block)?;
+            if read == 0 {
+                break;
+            }
+            match &mut spill {
+                Some(spill) => spill.write(&block[..read])?,
+                None if (data.len() + read) as u64 * 2 > budget => {
+                    let mut file = Spill::create()?;
+                    file.write(&data)?;
+                    file.write(&block[..read])?;
+                    data = Vec::new();
+                    spill = Some(file);
+                }
+                None => data.extend_from_slice(&block[..read]),
+            }
+            reporter.update((data_size - reader.remaining()) as usize)?;
+        }
+        reporter.update(data_size as usize)?;
+
+        Ok(Self {
+            header: reader.header().clone(),
+            data_size,
+            data: match spill {
+                Some(spill) => spill.finish()?,
+                None => data.into(),
+            },
+            metadata: reader.finish()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{decode, extensible};
+
+    #[test]
+    fn extensible_header_names_the_speakers() {
+        let data = vec![0; 6 * 2 * 10];
+        let wav = decode(&extensible(6, 48000, 16, 16, 0x60f, 1, data)).unwrap();
+        assert_eq!(wav.header.sample_format(), 1);
+        assert_eq!(wav.header.valid_bits_per_sample, 16);
+        assert_eq!(wav.header.speakers(), ["FL", "FR", "FC", "LFE", "SL", "SR"]);
+    }
+}
@@ -0,0 +1,349 @@
+use std::error;
+use std::ops::Range;
+
+use sdl2::audio::AudioSpecDesired;
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::player::{PlaybackEvent, Player};
+use crate::render::Renderer;
+use crate::samples::Samples;
+use crate::{AUDIO_BUFFER_FRAMES, WavFile, font, i18n, timecode, zero};
+
+const USAGE: &str = "wave looptest <file.wav> --start X --end Y";
+// heard on either side of the seam every time around
+const LEAD_SECONDS: f64 = 1.0;
+// frames drawn on either side of the seam
+const VIEW_FRAMES: usize = 160;
+// frames on either side the usual change from one sample to the next is measured over
+const CONTEXT_FRAMES: usize = 256;
+// a join is a click when it breaks the waveform this many times more than the samples around
+// it do, and by more than about -60 dBFS
+const CLICK_RATIO: f32 = 4.0;
+const CLICK_FLOOR: f32 = 32.0;
+// frames around the seam compared for a jump in level, 10 ms at 48 kHz
+const LEVEL_FRAMES: usize = 480;
+
+// how one channel carries on from the last frame of the loop into its first
+pub struct Join {
+    // distance of the first frame from where the end was heading, as a straight line through
+    // the last two
+    pub error: f32,
+    // the same distance averaged over the frames around the seam
+    pub typical: f32,
+    // level after the seam against before it
+    pub level_step_db: f32,
+}
+
+impl Join {
+    pub fn ratio(&self) -> f32 {
+        self.error / self.typical.max(1.0)
+    }
+
+    pub fn clicks(&self) -> bool {
+        self.error > CLICK_FLOOR && self.ratio() > CLICK_RATIO
+    }
+}
+
+// the join of every channel when `frames` plays over and over. The loop has to be at least
+// three frames long
+pub fn analyze(samples: &[i16], channels: usize, frames: Range<usize>) -> Vec<Join> {
+    (0..channels)
+        .map(|channel| {
+            let x = |frame: usize| samples[frame * channels + channel] as f32;
+            // the second difference: how far a sample is from the line through the two before
+            let bend = |a: usize, b: usize, c: usize| (x(c) - 2.0 * x(b) + x(a)).abs();
+            let (start, end) = (frames.start, frames.end);
+            let error = bend(end - 2, end - 1, start);
+
+            let before = (end - start).min(CONTEXT_FRAMES);
+            let after = (end - start).min(CONTEXT_FRAMES);
+            let bends: Vec<f32> = (end - before + 2..end)
+                .map(|frame| bend(frame - 2, frame - 1, frame))
+                .chain((start + 2..start + after).map(|frame| bend(frame - 2, frame - 1, frame)))
+                .collect();
+            let typical = bends.iter().sum::<f32>() / bends.len().max(1) as f32;
+
+            let level = |range: Range<usize>| {
+                let count = range.len().max(1) as f32;
+                (range.map(|frame| x(frame).powi(2)).sum::<f32>() / count).sqrt()
+            };
+            let length = (end - start).min(LEVEL_FRAMES);
+            let level_step_db = 20.0
+                * (level(start..start + length).max(1.0) / level(end - length..end).max(1.0))
+                    .log10();
+            Join {
+                error,
+                typical,
+                level_step_db,
+            }
+        })
+        .collect()
+}
+
+// `wave looptest`: plays the end of a loop running into its start over and over, next to a
+// close-up of the samples either side of the join with the channels that click marked red.
+// The arrow keys move the end a frame at a time (shift the start, ctrl a hundred frames), Z
+// moves both to zero crossings. The range it ends on is printed for --loop-range
+pub fn looptest(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    let path = args.input(USAGE)?;
+    let wav = WavFile::open(path)?;
+    let channels = wav.header.num_channels.max(1) as usize;
+    let total = wav.data.len() / channels;
+    let rate = wav.header.sample_rate;
+    let position = |name: &str| -> Result<usize, String> {
+        let value = args
+            .value(name)
+            .ok_or_else(|| i18n::fill("cli.usage", &[("usage", USAGE.to_string())]))?;
+        timecode::parse(value, rate)
+    };
+    let mut range = position("start")?..position("end")?.min(total);
+    if range.end < range.start + 3 {
+        return Err(format!("{}..{} is too short a loop", range.start, range.end).into());
+    }
+    print_joins(&wav, &range);
+
+    let (mut player, callback) = Player::new(&wav, None);
+    player.set_output_delay(Config::load().output_delay);
+    let events = player.subscribe();
+    let mut lead = load_preview(&mut player, &wav, &range);
+    player.play();
+
+    let sdl_context = sdl2::init()?;
+    let desired_spec = AudioSpecDesired {
+        freq: Some(rate as i32),
+        channels: Some(channels as u8),
+        samples: Some(AUDIO_BUFFER_FRAMES),
+    };
+    let device = sdl_context
+        .audio()?
+        .open_playback(None, &desired_spec, |_spec| callback)?;
+    device.resume();
+    let window = sdl_context
+        .video()?
+        .window(&format!("wave looptest  {path}"), 800, 400)
+        .position_centered()
+        .resizable()
+        .build()?;
+    let mut canvas = window.into_canvas().present_vsync().build()?;
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let mut handed_samples = 0;
+    loop {
+        for event in event_pump.poll_iter() {
+            let moved = match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => {
+                    print_joins(&wav, &range);
+                    println!("--loop-range {}..{}", range.start, range.end);
+                    return Ok(());
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Left | Keycode::Right)),
+                    keymod,
+                    ..
+                } => {
+                    let step = if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        100
+                    } else {
+                        1
+                    };
+                    let step = if keycode == Keycode::Left {
+                        -step
+                    } else {
+                        step
+                    };
+                    let edge = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        &mut range.start
+                    } else {
+                        &mut range.end
+                    };
+                    *edge = edge.saturating_add_signed(step).min(total);
+                    true
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Z),
+                    ..
+                } => {
+                    range = zero::nearest(&wav, range.start)..zero::nearest(&wav, range.end);
+                    true
+                }
+                _ => false,
+            };
+            if moved {
+                // an edge can't be moved past the other one
+                range.end = range.end.max(range.start + 3).min(total);
+                range.start = range.start.min(range.end - 3);
+                lead = load_preview(&mut player, &wav, &range);
+            }
+        }
+        player.pump();
+        for event in events.try_iter() {
+            if let PlaybackEvent::PositionChanged(position) = event {
+                handed_samples = position;
+            }
+        }
+
+        canvas.set_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let joins = analyze(&wav.data, channels, range.clone());
+        draw_seam(&mut canvas, &wav, &range, &joins);
+        draw_status(&mut canvas, &range, rate, &joins);
+        // where in the lead up or run out of the seam the playback is
+        let (width, height) = canvas.size();
+        let heard = player.audible_position(handed_samples) / channels;
+        let x = (heard * width as usize / (lead * 2).max(1)) as i32;
+        canvas.set_color(Color::RGB(255, 255, 255));
+        canvas.fill_rect(Rect::new(x, height as i32 - 6, 3, 6)).ok();
+        canvas.present();
+    }
+}
+
+// has the player go round the lead up to the end of the loop and the run out of its start,
+// returning how many frames each is
+fn load_preview(player: &mut Player, wav: &WavFile, range: &Range<usize>) -> usize {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let lead = ((LEAD_SECONDS * wav.header.sample_rate as f64) as usize).min(range.len());
+    let before = &wav.data[(range.end - lead) * channels..range.end * channels];
+    let after = &wav.data[range.start * channels..(range.start + lead) * channels];
+    let preview: Samples = [before, after].concat().into();
+    let length = preview.len();
+    player.set_data(preview);
+    player.set_loop(Some(0..length));
+    player.seek(0);
+    lead
+}
+
+fn print_joins(wav: &WavFile, range: &Range<usize>) {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let rate = wav.header.sample_rate as f64;
+    println!(
+        "{} {}..{} ({} .. {})",
+        i18n::text("looptest.loop"),
+        range.start,
+        range.end,
+        timecode::format(range.start as f64 / rate),
+        timecode::format(range.end as f64 / rate)
+    );
+    for (channel, join) in analyze(&wav.data, channels, range.clone())
+        .iter()
+        .enumerate()
+    {
+        println!(
+            "  {}: {}",
+            i18n::fill(
+                "looptest.channel",
+                &[("channel", (channel + 1).to_string())]
+            ),
+            describe(join)
+        );
+    }
+}
+
+fn describe(join: &Join) -> String {
+    let text = i18n::fill(
+        "looptest.join",
+        &[
+            ("jump", format!("{:.0}", join.error)),
+            ("ratio", format!("{:.1}", join.ratio())),
+            ("level", format!("{:+.1}", join.level_step_db)),
+        ],
+    );
+    if join.clicks() {
+        format!("{text}  {}", i18n::text("looptest.click"))
+    } else {
+        text
+    }
+}
+
+// the last frames of the loop on the left running into its first frames on the right, one
+// lane per channel with the seam down the middle
+fn draw_seam(canvas: &mut impl Renderer, wav: &WavFile, range: &Range<usize>, joins: &[Join]) {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let (width, height) = canvas.size();
+    let top = 80;
+    let lane_height = (height as i32 - top - 10) / channels as i32;
+    let shown = VIEW_FRAMES.min(range.len());
+    let frames: Vec<usize> = (range.end - shown..range.end)
+        .chain(range.start..range.start + shown)
+        .collect();
+    let center = width as i32 / 2;
+    let x = |i: usize| center + (i as i32 - shown as i32) * center / shown as i32;
+    for (channel, join) in joins.iter().enumerate() {
+        let middle = top + lane_height * channel as i32 + lane_height / 2;
+        let y = |frame: usize| {
+            let sample = wav.data[frame * channels + channel] as i32;
+            middle - sample * (lane_height / 2) / 32768
+        };
+        canvas.set_color(Color::RGB(60, 60, 60));
+        canvas.line((0, middle), (width as i32, middle));
+        canvas.set_color(if join.clicks() {
+            Color::RGB(255, 40, 40)
+        } else {
+            Color::RGB(40, 200, 40)
+        });
+        let lane_top = middle - lane_height / 2;
+        canvas.fill_rect(Rect::new(center - 1, lane_top, 3, lane_height as u32));
+        canvas.set_color(Color::RGB(0, 255, 255));
+        for (i, pair) in frames.windows(2).enumerate() {
+            canvas.line((x(i), y(pair[0])), (x(i + 1), y(pair[1])));
+        }
+    }
+}
+
+fn draw_status(canvas: &mut impl Renderer, range: &Range<usize>, rate: u32, joins: &[Join]) {
+    let rate = rate as f64;
+    let mut lines = vec![format!(
+        "loop {}..{}  ({} .. {})  left/right end, shift start, ctrl x100, z zero",
+        range.start,
+        range.end,
+        timecode::format(range.start as f64 / rate),
+        timecode::format(range.end as f64 / rate)
+    )];
+    lines.extend(
+        joins
+            .iter()
+            .enumerate()
+            .map(|(channel, join)| format!("{} {}", channel + 1, describe(join))),
+    );
+    for (i, (line, join)) in lines
+        .iter()
+        .zip([None].into_iter().chain(joins.iter().map(Some)))
+        .enumerate()
+    {
+        canvas.set_color(match join {
+            Some(join) if join.clicks() => Color::RGB(255, 80, 80),
+            _ => Color::RGB(255, 255, 255),
+        });
+        font::draw_text(canvas, 8, 8 + i as i32 * 18, 2, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frames: usize, period: f32) -> Vec<i16> {
+        (0..frames)
+            .map(|i| ((i as f32 / period * std::f32::consts::TAU).sin() * 20000.0) as i16)
+            .collect()
+    }
+
+    #[test]
+    fn a_whole_number_of_periods_joins_cleanly() {
+        let samples = sine(4800, 48.0);
+        let joins = analyze(&samples, 1, 0..4800);
+        assert!(!joins[0].clicks(), "{}", describe(&joins[0]));
+        // stopping a quarter period early jumps from the peak back to zero
+        let joins = analyze(&samples, 1, 0..4800 - 12);
+        assert!(joins[0].clicks(), "{}", describe(&joins[0]));
+    }
+}
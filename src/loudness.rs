@@ -0,0 +1,123 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::filter::Biquad;
+
+// gated integrated loudness after ITU-R BS.1770: K-weighted mean square over 400 ms blocks
+// overlapping by 75%, leaving out blocks below -70 LUFS and then those more than 10 LU under
+// the loudness of what's left. Every channel is weighted the same
+const BLOCK_SECONDS: f64 = 0.4;
+const BLOCK_STEPS: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+// loudness in LUFS of interleaved samples, None when everything is gated away. Anything
+// shorter than a block is measured as a single block
+pub fn integrated(samples: &[i16], channels: usize, sample_rate: u32) -> Option<f32> {
+    gated(&blocks(samples, channels, sample_rate))
+}
+
+// the mean square power of every block, before gating. The blocks of several files pooled
+// together and gated once give their loudness as an album
+pub fn blocks(samples: &[i16], channels: usize, sample_rate: u32) -> Vec<f64> {
+    let channels = channels.max(1);
+    // cookbook shelf and highpass fitted to the standard's 48 kHz K-weighting coefficients,
+    // so any rate can be weighted
+    let mut filters = vec![
+        [
+            Biquad::high_shelf(sample_rate, 1500.0, 4.0, FRAC_1_SQRT_2),
+            Biquad::highpass(sample_rate, 38.0, 0.5),
+        ];
+        channels
+    ];
+    let step = (sample_rate as f64 * BLOCK_SECONDS / BLOCK_STEPS as f64).max(1.0) as usize;
+    // summed squares of every step, a block is BLOCK_STEPS of them in a row
+    let mut steps = Vec::new();
+    for frames in samples.chunks(step * channels) {
+        let mut square = 0.0f64;
+        for frame in frames.chunks_exact(channels) {
+            for (sample, [shelf, highpass]) in frame.iter().zip(&mut filters) {
+                let weighted = highpass.process(shelf.process(*sample as f32 / 32768.0));
+                square += (weighted * weighted) as f64;
+            }
+        }
+        steps.push((square, frames.len() / channels));
+    }
+    // nothing to measure in an empty file, and windows of 0 would panic
+    if steps.is_empty() {
+        return Vec::new();
+    }
+
+    steps
+        .windows(BLOCK_STEPS.min(steps.len()))
+        .map(|window| {
+            let (square, frames) = window.iter().fold((0.0, 0), |(square, frames), step| {
+                (square + step.0, frames + step.1)
+            });
+            square / frames.max(1) as f64
+        })
+        .collect()
+}
+
+// loudness in LUFS of block powers from `blocks`, None when everything is gated away
+pub fn gated(blocks: &[f64]) -> Option<f32> {
+    let blocks: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&power| to_lufs(power) > ABSOLUTE_GATE)
+        .collect();
+    let gate = to_lufs(mean(&blocks)?) + RELATIVE_GATE;
+    let gated: Vec<f64> = blocks
+        .into_iter()
+        .filter(|&power| to_lufs(power) > gate)
+        .collect();
+    Some(to_lufs(mean(&gated)?) as f32)
+}
+
+fn mean(powers: &[f64]) -> Option<f64> {
+    (!powers.is_empty()).then(|| powers.iter().sum::<f64>() / powers.len() as f64)
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn full_scale_sine_at_1k_is_about_minus_3() {
+        // the reference point of the standard: a 0 dBFS 997 Hz sine in one channel reads
+        // -3.01 LUFS
+        let samples: Vec<i16> = (0..48000 * 2)
+            .map(|i| ((TAU * 997.0 * i as f32 / 48000.0).sin() * 32767.0) as i16)
+            .collect();
+        let loudness = integrated(&samples, 1, 48000).unwrap();
+        assert!((loudness + 3.01).abs() < 0.1, "{loudness}");
+    }
+
+    #[test]
+    fn album_of_a_loud_and_a_quiet_track_lies_between_them() {
+        let sine = |amplitude: f32| -> Vec<i16> {
+            (0..48000 * 2)
+                .map(|i| ((TAU * 997.0 * i as f32 / 48000.0).sin() * amplitude) as i16)
+                .collect()
+        };
+        let loud = blocks(&sine(32767.0), 1, 48000);
+        let quiet = blocks(&sine(8192.0), 1, 48000);
+        let album = gated(&[loud.clone(), quiet.clone()].concat()).unwrap();
+        assert!(album < gated(&loud).unwrap() && album > gated(&quiet).unwrap());
+    }
+
+    #[test]
+    fn no_samples_have_no_loudness() {
+        assert!(blocks(&[], 2, 48000).is_empty());
+        assert_eq!(integrated(&[], 2, 48000), None);
+    }
+
+    #[test]
+    fn silence_is_gated_away() {
+        assert_eq!(integrated(&[0; 96000], 2, 48000), None);
+    }
+}
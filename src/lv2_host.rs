@@ -0,0 +1,351 @@
+// hosting LV2 effects through lilv, https://drobilla.gitlab.io/lilv. lilv finds the installed
+// plugins and reads the ports from their Turtle descriptions, the plugin itself is a block
+// of memory per port it reads or writes whenever it runs. Audio input ports get the file's
+// channels, control input ports are the parameters and everything else needs to be optional
+
+use std::error;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::ptr;
+use std::sync::Arc;
+
+use crate::plugin::{BLOCK, Blocks, Parameter};
+
+type LilvWorld = c_void;
+type LilvPlugins = c_void;
+type LilvPlugin = c_void;
+type LilvPort = c_void;
+type LilvNode = c_void;
+
+// lilv's own, the instance functions working on it are inline in its header
+#[repr(C)]
+struct LilvInstance {
+    descriptor: *const Lv2Descriptor,
+    handle: *mut c_void,
+    pimpl: *mut c_void,
+}
+
+#[repr(C)]
+struct Lv2Descriptor {
+    uri: *const c_char,
+    instantiate: *const c_void,
+    connect_port: Option<unsafe extern "C" fn(handle: *mut c_void, port: u32, data: *mut c_void)>,
+    activate: Option<unsafe extern "C" fn(handle: *mut c_void)>,
+    run: Option<unsafe extern "C" fn(handle: *mut c_void, sample_count: u32)>,
+    deactivate: Option<unsafe extern "C" fn(handle: *mut c_void)>,
+    cleanup: Option<unsafe extern "C" fn(handle: *mut c_void)>,
+    extension_data: *const c_void,
+}
+
+// linked by build.rs
+unsafe extern "C" {
+    fn lilv_world_new() -> *mut LilvWorld;
+    fn lilv_world_load_all(world: *mut LilvWorld);
+    fn lilv_world_free(world: *mut LilvWorld);
+    fn lilv_world_get_all_plugins(world: *const LilvWorld) -> *const LilvPlugins;
+    fn lilv_new_uri(world: *mut LilvWorld, uri: *const c_char) -> *mut LilvNode;
+    fn lilv_node_free(node: *mut LilvNode);
+    fn lilv_node_as_string(node: *const LilvNode) -> *const c_char;
+    fn lilv_plugins_get_by_uri(
+        plugins: *const LilvPlugins,
+        uri: *const LilvNode,
+    ) -> *const LilvPlugin;
+    fn lilv_plugin_get_name(plugin: *const LilvPlugin) -> *mut LilvNode;
+    fn lilv_plugin_get_num_ports(plugin: *const LilvPlugin) -> u32;
+    fn lilv_plugin_get_port_by_index(plugin: *const LilvPlugin, index: u32) -> *const LilvPort;
+    fn lilv_plugin_get_port_ranges_float(
+        plugin: *const LilvPlugin,
+        min_values: *mut f32,
+        max_values: *mut f32,
+        def_values: *mut f32,
+    );
+    fn lilv_port_is_a(
+        plugin: *const LilvPlugin,
+        port: *const LilvPort,
+        port_class: *const LilvNode,
+    ) -> bool;
+    fn lilv_port_has_property(
+        plugin: *const LilvPlugin,
+        port: *const LilvPort,
+        property: *const LilvNode,
+    ) -> bool;
+    fn lilv_port_get_name(plugin: *const LilvPlugin, port: *const LilvPort) -> *mut LilvNode;
+    fn lilv_plugin_instantiate(
+        plugin: *const LilvPlugin,
+        sample_rate: f64,
+        features: *const *const c_void,
+    ) -> *mut LilvInstance;
+    fn lilv_instance_free(instance: *mut LilvInstance);
+}
+
+// a node lilv handed out or one made for a URI, freed when dropped
+struct Node(*mut LilvNode);
+
+impl Node {
+    unsafe fn uri(world: *mut LilvWorld, uri: &CStr) -> Self {
+        Self(unsafe { lilv_new_uri(world, uri.as_ptr()) })
+    }
+
+    fn text(&self) -> String {
+        if self.0.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(lilv_node_as_string(self.0)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { lilv_node_free(self.0) };
+        }
+    }
+}
+
+// what a port is connected to
+#[derive(Clone, Copy, PartialEq)]
+enum Port {
+    AudioInput,
+    AudioOutput,
+    // a parameter, or a meter the plugin writes and nobody reads
+    Control,
+    Unconnected,
+}
+
+// every installed plugin as lilv found them, with the one asked for picked out
+pub struct PluginLibrary {
+    world: *mut LilvWorld,
+    plugin: *const LilvPlugin,
+    name: String,
+}
+
+// lilv's world is only read once loaded
+unsafe impl Send for PluginLibrary {}
+unsafe impl Sync for PluginLibrary {}
+
+impl PluginLibrary {
+    // the installed plugin with `uri`, from wherever LV2_PATH or the default places have them
+    pub fn open(uri: &str) -> Result<Arc<Self>, Box<dyn error::Error + 'static>> {
+        let world = unsafe { lilv_world_new() };
+        if world.is_null() {
+            return Err("lilv couldn't start".into());
+        }
+        // from here on dropping it frees the world
+        let mut library = Self {
+            world,
+            plugin: ptr::null(),
+            name: uri.to_string(),
+        };
+        unsafe { lilv_world_load_all(world) };
+        let node = unsafe { Node::uri(world, &CString::new(uri)?) };
+        library.plugin =
+            unsafe { lilv_plugins_get_by_uri(lilv_world_get_all_plugins(world), node.0) };
+        if library.plugin.is_null() {
+            return Err(format!("{uri}: no such LV2 plugin installed").into());
+        }
+        library.name = Node(unsafe { lilv_plugin_get_name(library.plugin) }).text();
+        Ok(Arc::new(library))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // an instance activated for a file with `channels` at `sample_rate`, with the parameters
+    // it starts out with
+    pub fn instantiate(
+        self: &Arc<Self>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(Plugin, Vec<Parameter>), Box<dyn error::Error + 'static>> {
+        let plugin = self.plugin;
+        let class = |uri: &str| unsafe {
+            Node::uri(
+                self.world,
+                &CString::new(format!("http://lv2plug.in/ns/lv2core#{uri}")).unwrap(),
+            )
+        };
+        let (audio, control, input) =
+            (class("AudioPort"), class("ControlPort"), class("InputPort"));
+        let optional = class("connectionOptional");
+        let whole = [class("integer"), class("toggled"), class("enumeration")];
+
+        let count = unsafe { lilv_plugin_get_num_ports(plugin) } as usize;
+        let mut min = vec![f32::NAN; count];
+        let mut max = vec![f32::NAN; count];
+        let mut default = vec![f32::NAN; count];
+        unsafe {
+            lilv_plugin_get_port_ranges_float(
+                plugin,
+                min.as_mut_ptr(),
+                max.as_mut_ptr(),
+                default.as_mut_ptr(),
+            )
+        };
+        let mut ports = Vec::with_capacity(count);
+        let mut controls = vec![0.0; count];
+        let mut parameters = Vec::new();
+        for index in 0..count {
+            let port = unsafe { lilv_plugin_get_port_by_index(plugin, index as u32) };
+            let is = |node: &Node| unsafe { lilv_port_is_a(plugin, port, node.0) };
+            let has = |node: &Node| unsafe { lilv_port_has_property(plugin, port, node.0) };
+            ports.push(match (is(&audio), is(&control), is(&input)) {
+                (true, _, true) => Port::AudioInput,
+                (true, _, false) => Port::AudioOutput,
+                (_, true, false) => Port::Control,
+                (_, true, true) => {
+                    let min = if min[index].is_nan() { 0.0 } else { min[index] };
+                    let max = if max[index].is_nan() { 1.0 } else { max[index] };
+                    let value = if default[index].is_nan() {
+                        min
+                    } else {
+                        default[index]
+                    };
+                    controls[index] = value;
+                    parameters.push(Parameter {
+                        id: index as u32,
+                        name: Node(unsafe { lilv_port_get_name(plugin, port) }).text(),
+                        min: min as f64,
+                        max: max as f64,
+                        value: value as f64,
+                        stepped: whole.iter().any(has),
+                    });
+                    Port::Control
+                }
+                _ if has(&optional) => Port::Unconnected,
+                _ => {
+                    return Err(format!("{}: has ports wave can't connect", self.name).into());
+                }
+            });
+        }
+        let inputs = ports.iter().filter(|&&p| p == Port::AudioInput).count();
+        let outputs = ports.iter().filter(|&&p| p == Port::AudioOutput).count();
+        if outputs == 0 {
+            return Err(format!("{}: not an effect, it has no audio output", self.name).into());
+        }
+
+        // no host features are offered, plugins that need one refuse to be instantiated
+        let features = [ptr::null()];
+        let instance =
+            unsafe { lilv_plugin_instantiate(plugin, sample_rate as f64, features.as_ptr()) };
+        if instance.is_null() {
+            return Err(format!(
+                "{}: plugin couldn't be instantiated, it may need features wave doesn't offer",
+                self.name
+            )
+            .into());
+        }
+        // from here on dropping it frees the instance
+        let mut instance = Plugin {
+            enabled: true,
+            instance,
+            blocks: Blocks::new(channels, inputs, outputs),
+            controls,
+            activated: false,
+            _library: self.clone(),
+        };
+        let descriptor = unsafe { &*(*instance.instance).descriptor };
+        let (Some(connect), Some(_)) = (descriptor.connect_port, descriptor.run) else {
+            return Err(format!("{}: incomplete LV2 descriptor", self.name).into());
+        };
+        // the blocks and the controls never move, so the pointers the plugin gets stay valid
+        let handle = unsafe { (*instance.instance).handle };
+        let mut audio_inputs = instance.blocks.inputs.iter_mut();
+        let mut audio_outputs = instance.blocks.outputs.iter_mut();
+        for (index, port) in ports.iter().enumerate() {
+            let data: *mut f32 = match port {
+                Port::AudioInput => audio_inputs.next().unwrap().as_mut_ptr(),
+                Port::AudioOutput => audio_outputs.next().unwrap().as_mut_ptr(),
+                Port::Control => &mut instance.controls[index],
+                Port::Unconnected => ptr::null_mut(),
+            };
+            unsafe { connect(handle, index as u32, data.cast()) };
+        }
+        if let Some(activate) = descriptor.activate {
+            unsafe { activate(handle) };
+        }
+        instance.activated = true;
+        Ok((instance, parameters))
+    }
+}
+
+impl Drop for PluginLibrary {
+    fn drop(&mut self) {
+        unsafe { lilv_world_free(self.world) };
+    }
+}
+
+// an activated instance, run a sample at a time like the filters
+pub struct Plugin {
+    enabled: bool,
+    instance: *mut LilvInstance,
+    // a block per audio port
+    blocks: Blocks,
+    // a value per port, only the control ones are connected
+    controls: Vec<f32>,
+    activated: bool,
+    // the plugins are lilv's to load and unload
+    _library: Arc<PluginLibrary>,
+}
+
+// instantiated on the ui thread and run on the audio thread, as LV2 allows
+unsafe impl Send for Plugin {}
+
+impl Plugin {
+    // bypassed, the samples pass through as they are
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    // `id` is the index of the control port, which the plugin reads on its next run
+    pub fn set_parameter(&mut self, id: u32, value: f64) {
+        if let Some(control) = self.controls.get_mut(id as usize) {
+            *control = value as f32;
+        }
+    }
+
+    pub fn process(&mut self, channel: usize, value: f32) -> f32 {
+        if !self.enabled {
+            return value;
+        }
+        let (out, full) = self.blocks.exchange(channel, value);
+        if full {
+            let instance = unsafe { &*self.instance };
+            if let Some(run) = unsafe { (*instance.descriptor).run } {
+                unsafe { run(instance.handle, BLOCK as u32) };
+            }
+        }
+        out
+    }
+}
+
+// with the player's device, which is closed on the ui thread
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        let instance = unsafe { &*self.instance };
+        if let (true, Some(deactivate)) =
+            (self.activated, unsafe { (*instance.descriptor).deactivate })
+        {
+            unsafe { deactivate(instance.handle) };
+        }
+        unsafe { lilv_instance_free(self.instance) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lilv links and brings up a world, whatever plugins happen to be installed
+    #[test]
+    fn lilv_world_loads() {
+        unsafe {
+            let world = lilv_world_new();
+            assert!(!world.is_null());
+            lilv_world_load_all(world);
+            assert!(!lilv_world_get_all_plugins(world).is_null());
+            lilv_world_free(world);
+        }
+    }
+}
@@ -1,225 +1,2582 @@
-use std::sync::{Arc, Mutex};
-use std::{error, fs, str::Utf8Error};
+mod abx;
+mod accessible;
+mod align;
+mod audition;
+mod binaural;
+mod carve;
+#[cfg(feature = "clap")]
+mod clap_host;
+mod classify;
+mod cli;
+mod clipboard;
+mod colormap;
+mod config;
+mod convolver;
+mod difference;
+mod dynamics;
+mod envelope;
+mod export;
+mod fft;
+mod filter;
+mod font;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod hum;
+mod i18n;
+mod impulse;
+mod info;
+mod input;
+mod ixml;
+mod json;
+mod layout;
+mod looptest;
+mod loudness;
+#[cfg(feature = "lv2")]
+mod lv2_host;
+mod meter;
+mod midi;
+mod notes;
+mod notify;
+#[cfg(test)]
+mod offline;
+mod onset;
+mod overview;
+mod pitch;
+mod player;
+mod playlist;
+mod plugin;
+mod quality;
+mod recovery;
+mod render;
+mod repair;
+mod replaygain;
+mod report;
+mod selection;
+mod silence;
+mod spectrogram;
+mod subtitles;
+mod sync;
+mod tag;
+mod takes;
+mod theme;
+mod timecode;
+mod tone;
+mod transcribe;
+mod tray;
+mod validate;
+mod video;
+mod watch;
+mod zero;
 
-use sdl2::audio::{AudioCallback, AudioSpecDesired};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use std::sync::Arc;
+use std::{env, error};
+
+use abx::{Abx, Source};
+use audition::{Audition, Summary, Verdict};
+use classify::Class;
+use cli::Args;
+use config::Config;
+use dynamics::Dynamics;
+use input::{Action, Input, Prompt};
+use layout::Layout;
+use metadata::Metadata;
+use meter::Levels;
+use notes::Notes;
+use notify::Notification;
+use overview::Overview;
+use player::{PlaybackEvent, Player, Settings};
+use playlist::Sort;
+use plugin::{Parameter, PluginLibrary};
+use progress::{CancellationToken, Job, Progress};
+use quality::{FrameRate, RenderQuality};
+use recovery::{Autosave, Session};
+use render::{Image, Layer, Renderer, Screen};
+use sdl2::audio::AudioSpecDesired;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
-use std::time::Duration;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use selection::Selection;
+use spectrogram::Spectrogram;
+use std::time::Instant;
+use takes::TakeGroup;
+use theme::{Palette, Theme};
+use tray::{Tray, TrayAction};
+use wave::raw::{RawFormat, RawSpec};
+use wave::{Header, WavFile, bext, decoder, id3, info_list, metadata, progress, riff, samples};
+
+// "artist - title" from the tags, the file name when there is no title
+fn track_name(path: &str, wav: &WavFile) -> String {
+    match (&wav.metadata.artist, &wav.metadata.title) {
+        (Some(artist), Some(title)) => format!("{artist} - {title}"),
+        (None, Some(title)) => title.clone(),
+        _ => std::path::Path::new(path)
+            .file_name()
+            .map_or(path.to_string(), |name| name.to_string_lossy().into_owned()),
+    }
+}
+
+// interleaved samples shown by the waveform view, starting at the playhead
+const WAVEFORM_SAMPLES: usize = 4096;
+// range the volume keys move the gain in, in dB
+const MIN_VOLUME_DB: f32 = -60.0;
+const MAX_VOLUME_DB: f32 = 12.0;
+
+// the full-file overview strip along the bottom of the window
+const OVERVIEW_HEIGHT: u32 = 64;
+// spec deviations listed in the window, past that they're counted
+const MAX_DIAGNOSTICS: usize = 3;
+// size of the mini player window
+const MINI_WIDTH: u32 = 360;
+const MINI_HEIGHT: u32 = 56;
+// frames per audio callback asked for, the device may pick another size. Drawing makes up
+// for however long the buffer it got takes to play
+const AUDIO_BUFFER_FRAMES: u16 = 1024;
+// fastest the J and L keys shuttle at
+const MAX_SHUTTLE: f64 = 8.0;
+// what shift+[ and shift+] step through, shuttling in reverse steps backwards the same way
+const SPEEDS: [f64; 10] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 3.0, 4.0];
+// what ctrl+S offers to save the queue as
+const QUEUE_PLAYLIST: &str = "queue.m3u";
+
+// the spectral difference strip below the overview while a comparison file is loaded
+const DIFFERENCE_HEIGHT: u32 = 12;
+
+// marker lines for the chapters S finds and the cue points stored in the file
+const CHAPTER_COLOR: Color = Color::RGB(255, 255, 0);
+const CUE_COLOR: Color = Color::RGB(0, 220, 220);
+// how far the marks over clipped samples reach into the waveform view
+const CLIP_MARK_HEIGHT: u32 = 8;
+// the loop playback cycles through, shaded, and an A-B loop start waiting for its end
+const LOOP_COLOR: Color = Color::RGB(80, 220, 120);
+// where subtitles come on and go off
+const SUBTITLE_START_COLOR: Color = Color::RGB(255, 150, 60);
+const SUBTITLE_END_COLOR: Color = Color::RGB(130, 75, 30);
 
-// http://soundfile.sapp.org/doc/WaveFormat/
-
-#[derive(Default, Debug)]
-struct Header {
-    // The "RIFF" chunk descriptor
-    // The format of concern here is "WAVE", which requires two sub-chunks: "fmt " and "data"
-    chunk_id: Box<str>, // 0 - 4
-    chunk_size: u32,    // 4 - 8
-    format: Box<str>,   // 8 - 12
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Waveform,
+    Spectrogram,
+    Dynamics,
+    // decay and frequency response, only for files that look like impulse responses
+    Impulse,
+}
 
-    // The "fmt " sub-chunk
-    // describes the format of the sound information in the data sub-chunk
-    subchunk1_id: Box<str>, // 12 - 16
-    subchunk1_size: u32,    // 16 - 20
-    audio_format: u16,      // 20 - 22
-    num_channels: u16,      // 22 - 24
-    sample_rate: u32,       // 24 - 28
-    byte_rate: u32,         // 28 - 32
-    block_align: u16,       // 32 - 34
-    bits_per_sample: u16,   // 34 - 36
+impl View {
+    fn next(self, impulse: bool) -> Self {
+        match self {
+            View::Waveform => View::Spectrogram,
+            View::Spectrogram => View::Dynamics,
+            View::Dynamics if impulse => View::Impulse,
+            View::Dynamics | View::Impulse => View::Waveform,
+        }
+    }
+}
 
-    // The "data" sub chunk
-    subchunk2_id: Box<str>, // 36 - 40
-    subchunk2_size: u32,    // 40 - 44
+// a second file loaded with `--compare`, drawn over the waveform and optionally heard instead
+// of the main file
+struct Comparison {
+    path: String,
+    // shared with the thread working out the difference
+    wav: Arc<WavFile>,
+    // how many frames the comparison is delayed against the main file, nudged by hand
+    offset: isize,
+    audible: bool,
+    // per block spectral difference against the main file, the last one finished stays up
+    // while a nudge recomputes it
+    difference: Option<Vec<f32>>,
+    difference_job: Option<Job<Vec<f32>>>,
 }
 
-#[derive(Default)]
-struct WAVFile {
-    header: Header,
-    // copy of subchunk2_size
-    data_size: u32,
-    // pointer to data
-    data: Box<[i16]>,
+impl Comparison {
+    // starts over on the difference at the current offset, replacing the job for an older
+    // offset cancels it
+    fn recompute_difference(&mut self, wav: &Arc<WavFile>) {
+        let (wav, compare, offset) = (wav.clone(), self.wav.clone(), self.offset);
+        self.difference_job = Some(Job::spawn(move |progress, cancel| {
+            difference::spectral_difference(&wav, &compare, offset, progress, cancel)
+        }));
+    }
 }
 
-impl WAVFile {
-    fn new() -> Self {
-        Self::default()
+fn main() -> Result<(), Box<dyn error::Error + 'static>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("export-array") => export::export_array(&args[1..]),
+        Some("export-samples") => export::export_samples(&args[1..]),
+        Some("envelope") => export::export_envelope(&args[1..]),
+        Some("transcribe") => transcribe::transcribe(&args[1..]),
+        Some("info") => info::info(&args[1..]),
+        Some("tag") => tag::tag(&args[1..]),
+        Some("repair") => repair::repair(&args[1..]),
+        Some("validate") => validate::validate(&args[1..]),
+        Some("carve") => carve::carve(&args[1..]),
+        Some("report") => report::report(&args[1..]),
+        Some("watch") => watch::watch(&args[1..]),
+        Some("loudness") => replaygain::loudness(&args[1..]),
+        Some("play") => play(&args[1..]),
+        Some("sync-test") => sync::sync_test(&args[1..]),
+        Some("looptest") => looptest::looptest(&args[1..]),
+        _ => play(&args),
     }
+}
 
-    fn parse(&mut self, data: &mut Vec<u8>) -> Result<(), Box<dyn error::Error + 'static>> {
-        self.header.chunk_id = bytes_to_boxed_str(data).unwrap();
-        self.header.chunk_size = little_to_big_u32(data);
-        self.header.format = bytes_to_boxed_str(data).unwrap();
-        self.header.subchunk1_id = bytes_to_boxed_str(data).unwrap();
-        self.header.subchunk1_size = little_to_big_u32(data);
-        self.header.audio_format = little_to_big_u16(data);
-        self.header.num_channels = little_to_big_u16(data);
-        self.header.sample_rate = little_to_big_u32(data);
-        self.header.byte_rate = little_to_big_u32(data);
-        self.header.block_align = little_to_big_u16(data);
-        self.header.bits_per_sample = little_to_big_u16(data);
-        self.header.subchunk2_id = bytes_to_boxed_str(data).unwrap();
+// the interactive player, with a queue of files from the arguments
+// `wave [play] [file.wav|clip.mp4|dir|glob|playlist.m3u ...] [--sort name|mtime|random] [--compare other.wav
+//  [--abx [--trials N]]] [--takes] [--gain DB] [--loop] [--loop-range A..B] [--srt subtitles.srt]
+//  [--ir room.wav] [--plugin effect.clap|URI] [--binaural]
+//  [--raw --rate HZ --channels N [--format s16le]] [--audition SECONDS [--report audition.txt]]
+//  [--accessible]`
+fn play(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(
+        args,
+        &["abx", "takes", "binaural", "raw", "loop", "accessible"],
+    )?;
+    // before anything is decoded, for the memory budget
+    let config = Config::load();
+    if let Some(budget) = config.memory_budget {
+        samples::set_memory_budget(budget);
+    }
+    let sort = match args.value("sort") {
+        Some(name) => Sort::from_name(name)
+            .ok_or_else(|| i18n::fill("cli.unknown-sort", &[("name", name.to_string())]))?,
+        None => Sort::Name,
+    };
+    let playlist = if args.positional.is_empty() {
+        vec!["file_example_WAV_5MG.wav".to_string()]
+    } else {
+        playlist::expand(&args.positional, sort)?
+    };
+    // with --takes the tracks of a take play as one entry, named after its first file
+    let takes = if args.flag("takes") {
+        takes::group(&playlist)
+    } else {
+        playlist.iter().map(|file| vec![file.clone()]).collect()
+    };
+    let playlist: Vec<String> = takes.iter().map(|take| take[0].clone()).collect();
+    let mut audition = match args.value("audition") {
+        Some(seconds) => {
+            let seconds: f64 = seconds
+                .parse()
+                .map_err(|_| i18n::fill("player.audition-seconds", &[("value", seconds.into())]))?;
+            let report = args.value("report").unwrap_or(audition::DEFAULT_REPORT);
+            Some(Audition::new(seconds, report.into(), playlist.len()))
+        }
+        None => None,
+    };
+    // a blind test of the file against the --compare one, the trials carry on across tracks
+    let mut abx = match args.value("trials") {
+        _ if !args.flag("abx") => None,
+        _ if args.value("compare").is_none() => {
+            return Err(i18n::text("player.abx-needs-compare").into());
+        }
+        Some(trials) => Some(Abx::new(trials.parse().map_err(|_| {
+            i18n::fill("player.trials-number", &[("value", trials.into())])
+        })?)),
+        None => Some(Abx::new(abx::DEFAULT_TRIALS)),
+    };
+    // with --raw every file is taken as bare samples in the given layout
+    let raw = if args.flag("raw") {
+        let rate = args
+            .value("rate")
+            .ok_or(i18n::text("player.raw-needs-rate"))?;
+        let channels = args
+            .value("channels")
+            .ok_or(i18n::text("player.raw-needs-channels"))?;
+        let format = args.value("format").unwrap_or("s16le");
+        Some(RawSpec {
+            sample_rate: rate
+                .parse()
+                .map_err(|_| i18n::fill("player.rate-hz", &[("value", rate.into())]))?,
+            channels: channels
+                .parse()
+                .map_err(|_| i18n::fill("player.channels-number", &[("value", channels.into())]))?,
+            format: RawFormat::from_name(format).ok_or_else(|| {
+                i18n::fill("player.unknown-raw-format", &[("format", format.into())])
+            })?,
+        })
+    } else {
+        None
+    };
+    // heard through every file, F4 switches it off and on
+    let impulse_response = match args.value("ir") {
+        Some(path) => {
+            let ir = WavFile::open(path)?;
+            let seconds = ir.data.len() as f64
+                / ir.header.num_channels.max(1) as f64
+                / ir.header.sample_rate.max(1) as f64;
+            if seconds > convolver::MAX_SECONDS {
+                println!(
+                    "{path}: {}",
+                    i18n::fill(
+                        "player.ir-cut",
+                        &[
+                            ("used", convolver::MAX_SECONDS.to_string()),
+                            ("seconds", format!("{seconds:.1}")),
+                        ]
+                    )
+                );
+            }
+            Some((path, ir))
+        }
+        None => None,
+    };
+    // a CLAP or LV2 effect after the impulse response, F2 bypasses it
+    let plugin = args.value("plugin").map(PluginLibrary::open).transpose()?;
 
-        let data_size = little_to_big_u32(data);
-        self.data_size = data_size;
-        self.header.subchunk2_size = data_size;
+    // no window, commands typed in the terminal and answers read out by a screen reader
+    if args.flag("accessible") {
+        return accessible::run(&playlist, raw, &args, &config);
+    }
 
-        if data.len() < data_size as usize {
-            return Err("unexpected end of file".into());
+    let sdl_context = sdl2::init().unwrap();
+    let audio_subsystem = sdl_context.audio().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    // sdl's textures live as long as what made them, which outlives the window's canvas
+    let textures;
+    let mut canvas = match Screen::gpu(&video_subsystem, "wave", 800, 600) {
+        Some(screen) => screen,
+        None => {
+            let window = video_subsystem
+                .window("wave", 800, 600)
+                .position_centered()
+                .build()
+                .unwrap();
+            let canvas = window.into_canvas().build().unwrap();
+            textures = canvas.texture_creator();
+            Screen::sdl(canvas, &textures)
         }
+    };
+    // one column per horizontal pixel, kept while the width stays the same
+    let mut spectrogram_image: Option<Image> = None;
+    let mut layout = Layout::new(View::Waveform);
+    let mut theme = Theme::load();
+    let mut show_harmonics = false;
+    let mut mouse: Option<(i32, i32)> = None;
+    let mut quality = RenderQuality::default();
+    let mut frame_rate = FrameRate::default();
+    let mut show_stats = false;
+    // the waveform is drawn as a trail that fades a little every frame while the phosphor
+    // mode is on
+    let mut phosphor = false;
+    // the level meters, popped out into a window of their own that stays on top
+    let mut meter_canvas: Option<Canvas<Window>> = None;
+    // the compact borderless window for background listening, with the size the full window
+    // goes back to
+    let mut mini = false;
+    let mut full_size = canvas.window().size();
+    // with an icon in the tray closing the window only hides it, so sdl shouldn't quit with it
+    let mut tray = Tray::new();
+    if tray.is_shown() {
+        sdl2::hint::set("SDL_QUIT_ON_LAST_WINDOW_CLOSE", "0");
+    }
+    // seeks and loop points go to the nearest zero crossing
+    let mut snap_zero = config.zero_crossing_snap;
+    // every file starts over from the beginning at its end, shift+L switches it for the rest
+    // of the queue
+    let mut loop_file = args.flag("loop");
+    // the plugin parameter alt+up and alt+down change, and the values they were set to, which
+    // the next track's instance of the plugin starts out with
+    let mut plugin_parameter = 0;
+    let mut plugin_values: Vec<Parameter> = Vec::new();
+    // what the last run autosaved if it crashed, ctrl+R brings it back once its file is open
+    let mut leftover = recovery::leftover();
+    let mut autosave = Autosave::new();
+
+    canvas.clear(Color::RGB(0, 255, 255));
+    canvas.present();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut input = Input::new(video_subsystem.text_input());
+    let clipboard = video_subsystem.clipboard();
 
-        let raw = data.drain(..data_size as usize).collect::<Vec<u8>>();
-        // since the buffer we are reading is represented as Vec<u8> we had to convert the audio
-        // data to Vec<i16> by combining two elements of idx 0 u8 & 1 u8 to be a single i16
-        let mut pcm_data = Vec::with_capacity(raw.len() / 2);
-        for chunk in raw.chunks_exact(2) {
-            let sample_le = i16::from_le_bytes([chunk[0], chunk[1]]);
-            pcm_data.push(sample_le);
+    // everything below is per file, the window and the view settings carry over from one
+    // file to the next
+    let mut track = 0;
+    'playlist: loop {
+        let path = playlist[track].as_str();
+        canvas.window_mut().set_title(&format!("wave  {path}"))?;
+        // shared with the threads analyzing it in the background
+        let (wav, mut take_group) = match takes[track].as_slice() {
+            [_] if let Some(spec) = raw => {
+                let wav = WavFile::open_raw(
+                    path,
+                    spec,
+                    &mut progress::terminal_bar(i18n::text("player.loading")),
+                    &CancellationToken::new(),
+                )?;
+                (wav, None)
+            }
+            [_] if video::is_video(path) => (video::open(path)?, None),
+            [_] => {
+                let wav = WavFile::open_with_progress(
+                    path,
+                    &mut progress::terminal_bar(i18n::text("player.loading")),
+                    &CancellationToken::new(),
+                )?;
+                (wav, None)
+            }
+            files => {
+                let (group, wav) = TakeGroup::open(files)?;
+                println!(
+                    "{}",
+                    i18n::fill(
+                        "player.take",
+                        &[
+                            ("tracks", files.len().to_string()),
+                            ("files", files.join(", ")),
+                        ]
+                    )
+                );
+                (wav, Some(group))
+            }
+        };
+        let wav = Arc::new(wav);
+        // the waveform view reads the samples it shows from the file, unless wave turned it
+        // into samples some other way than decoding it
+        let mut waveform_window = match takes[track].as_slice() {
+            [_] if raw.is_none() && !video::is_video(path) => {
+                decoder::Decoder::open(path).ok().map(decoder::Window::new)
+            }
+            _ => None,
+        };
+        // the tags are in the window too, but a terminal keeps a record of what was played
+        if wav.metadata.title.is_some() {
+            println!(
+                "{}",
+                i18n::fill("player.playing", &[("name", track_name(path, &wav))])
+            );
+        }
+        if let Some(comment) = &wav.metadata.comment {
+            println!("  {comment}");
+        }
+        if !wav.data.in_memory() {
+            println!("  {}", i18n::text("player.spilled"));
         }
 
-        self.data = pcm_data.into_boxed_slice();
-        Ok(())
-    }
-}
+        let mut comparison = match args.value("compare") {
+            Some(path) => {
+                let compare = WavFile::open(path)?;
+                if compare.header.num_channels != wav.header.num_channels
+                    || compare.header.sample_rate != wav.header.sample_rate
+                {
+                    return Err(
+                        i18n::fill("player.compare-mismatch", &[("path", path.into())]).into(),
+                    );
+                }
+                Some(Comparison {
+                    path: path.to_string(),
+                    wav: Arc::new(compare),
+                    offset: 0,
+                    audible: false,
+                    difference: None,
+                    difference_job: None,
+                })
+            }
+            None => None,
+        };
+        if let Some(comparison) = &mut comparison {
+            comparison.recompute_difference(&wav);
+        }
 
-fn little_to_big_u32(data: &mut Vec<u8>) -> u32 {
-    let value = data[0] as u32
-        | ((data[1] as u32) << 8)
-        | ((data[2] as u32) << 16)
-        | ((data[3] as u32) << 24);
-    data.drain(0..4);
-    value
-}
+        let channels = wav.header.num_channels.max(1) as usize;
+        let (mut player, callback) =
+            Player::new(&wav, comparison.as_ref().map(|c| c.wav.data.clone()));
+        player.set_output_delay(config.output_delay);
+        if let Some((name, ir)) = &impulse_response {
+            player.set_impulse_response(name, ir);
+        }
+        if let Some(library) = &plugin {
+            let (instance, parameters) =
+                library.instantiate(wav.header.sample_rate, wav.header.num_channels)?;
+            player.set_plugin(library.name(), instance, parameters);
+            for value in &plugin_values {
+                let index = player
+                    .settings()
+                    .parameters
+                    .iter()
+                    .position(|p| p.id == value.id);
+                if let Some(index) = index {
+                    player.set_parameter(index, value.value);
+                }
+            }
+            player.select_parameter(plugin_parameter as isize);
+        }
+        // headphone monitoring of multichannel files, B compares it with a flat downmix
+        let binaural = args.flag("binaural");
+        if binaural {
+            player.set_binaural(&wav);
+        }
+        let playback_events = player.subscribe();
+        // the position the callback last reported, drawing uses what's audible instead
+        let mut handed_samples = 0;
+        // where the arrow keys last seeked to, until the audio thread confirms it. The buffers
+        // it handed over before taking the seek would pull the playhead back for a frame
+        let mut pending_seek: Option<usize> = None;
+        let mut ended = false;
+        let mut xruns = 0;
+        // the first Started is the file starting, later ones are resuming after a pause
+        let mut announced = false;
+        if let Some(gain) = args.value("gain") {
+            let db: f32 = gain
+                .parse()
+                .map_err(|_| i18n::fill("player.gain-db", &[("value", gain.into())]))?;
+            player.set_gain(10f32.powf(db / 20.0));
+        }
+        if let Some(range) = args.value("loop-range") {
+            let frames = wav.data.len() / channels;
+            let range = timecode::parse_range(range, wav.header.sample_rate, frames)?;
+            let range = if snap_zero {
+                zero::nearest(&wav, range.start)..zero::nearest(&wav, range.end)
+            } else {
+                range
+            };
+            player.set_loop(Some(range.start * channels..range.end * channels));
+            player.seek(range.start * channels);
+        }
+        // the first sustain loop of a sampler file plays like an instrument would hold it,
+        // unless --loop-range asked for another one. O turns it off and back on
+        let sample_loop = wav
+            .loops()
+            .first()
+            .map(|sample_loop| sample_loop.start * channels..sample_loop.end * channels)
+            .filter(|range| range.end <= wav.data.len());
+        if let Some(range) = &sample_loop
+            && args.value("loop-range").is_none()
+        {
+            player.set_loop(Some(range.clone()));
+        }
+        player.set_loop_file(loop_file);
+        player.play();
 
-fn little_to_big_u16(data: &mut Vec<u8>) -> u16 {
-    let value = data[0] as u16 | ((data[1] as u16) << 8);
-    data.drain(0..2);
-    value
-}
+        let desired_spec = AudioSpecDesired {
+            freq: Some(wav.header.sample_rate as i32),
+            channels: Some(if binaural {
+                2
+            } else {
+                wav.header.num_channels as u8
+            }),
+            samples: Some(AUDIO_BUFFER_FRAMES),
+        };
 
-fn bytes_to_boxed_str(data: &mut Vec<u8>) -> Result<Box<str>, Utf8Error> {
-    let bytes = data[0..4].to_vec();
-    let s = std::str::from_utf8(&bytes)?;
-    data.drain(0..4);
-    Ok(s.into())
-}
+        // use callback since we want to syncronize the samples position in the audio buffer
+        let device = audio_subsystem.open_playback(None, &desired_spec, |_spec| callback)?;
+        device.resume();
 
-struct AudioPlayer {
-    data: Arc<[i16]>,
-    position: usize,
-    shared_position: Arc<Mutex<usize>>,
-}
-
-impl AudioCallback for AudioPlayer {
-    type Channel = i16;
+        let mut spectrogram = Spectrogram::new(&wav);
+        // decoding the cover art needs SDL2_image, so it's only shown with the cover-art feature
+        #[cfg(feature = "cover-art")]
+        let cover_art = wav.metadata.picture.as_ref().and_then(|picture| {
+            use sdl2::image::ImageRWops;
+            let surface = sdl2::rwops::RWops::from_bytes(&picture.data)
+                .ok()?
+                .load()
+                .ok()?;
+            Image::from_surface(&surface)
+        });
+        #[cfg(not(feature = "cover-art"))]
+        let cover_art: Option<Image> = None;
+        // scanning the whole file is only worth it once the view is actually opened
+        let mut dynamics: Option<Dynamics> = None;
+        // runs while the dynamics view is open, leaving the view cancels it
+        let mut dynamics_job: Option<Job<Dynamics>> = None;
+        let mut overview: Option<Overview> = None;
+        // speech and music labels along the overview, worked out in the background so a long
+        // file opens straight away, they show up once they're ready
+        let mut classes: Vec<Class> = Vec::new();
+        let mut classes_job = Some({
+            let wav = wav.clone();
+            Job::spawn(move |progress, cancel| {
+                classify::classify_with_progress(&wav, progress, cancel)
+            })
+        });
+        // impulse responses open on their decay and frequency response, the next file that
+        // isn't one goes back to the waveform
+        let impulse = impulse::detect(&wav);
+        if impulse.is_some() {
+            layout.primary = View::Impulse;
+        } else {
+            if layout.primary == View::Impulse {
+                layout.primary = View::Waveform;
+            }
+            if layout.secondary == Some(View::Impulse) {
+                layout.secondary = Some(View::Waveform);
+            }
+        }
+        // frame positions of chapter markers
+        let mut markers: Vec<usize> = Vec::new();
+        // and of the cue points the file came with
+        let cues: Vec<usize> = wav.cues().iter().map(|cue| cue.frame).collect();
+        let mut notes = Notes::load(path, &wav);
+        let subtitles = subtitles::load(args.value("srt"), path, wav.header.sample_rate)?;
+        let subtitle_starts: Vec<usize> = subtitles.iter().map(|s| s.start).collect();
+        let subtitle_ends: Vec<usize> = subtitles.iter().map(|s| s.end).collect();
+        device.resume();
 
-    fn callback(&mut self, out: &mut [i16]) {
-        for sample in out.iter_mut() {
-            *sample = if self.position < self.data.len() {
-                self.data[self.position]
+        let summary = audition.as_ref().map(|_| Summary::new(path, &wav));
+        // the overview is being dragged along
+        let mut scrubbing = false;
+        // where the waveform view started when a click in it began seeking, dragging seeks
+        // relative to that rather than to the view as it follows the playhead
+        let mut waveform_drag: Option<usize> = None;
+        // where the last seek asked to go and where snapping to a zero crossing put it
+        let mut snapped: Option<(usize, usize)> = None;
+        // frames picked by right-dragging along the overview, and while it's being dragged
+        let mut selection: Option<Selection> = None;
+        let mut selecting = false;
+        let mut selection_stats: Option<selection::Stats> = None;
+        // the frame alt+A marked as the start of an A-B loop, alt+B then loops up to the
+        // playhead
+        let mut loop_start: Option<usize> = None;
+        // set by the N key or the end of a track, the playlist entry to go on with
+        let mut next_track = None;
+        if leftover
+            .as_ref()
+            .is_some_and(|session| session.file == path)
+        {
+            println!("{}", i18n::text("player.unclean-exit"));
+        }
+        loop {
+            let frame_start = Instant::now();
+            frame_rate.tick();
+            player.pump();
+            canvas.clear(Color::RGB(0, 0, 0));
+            if let Some(job) = &mut classes_job
+                && let Some(result) = job.try_take()
+            {
+                classes = result.unwrap_or_default();
+                classes_job = None;
+            }
+            for event in playback_events.try_iter() {
+                match event {
+                    PlaybackEvent::PositionChanged(position) => {
+                        if pending_seek.is_some_and(|target| target != position) {
+                            continue;
+                        }
+                        pending_seek = None;
+                        handed_samples = position;
+                        if position < wav.data.len() {
+                            ended = false;
+                        }
+                    }
+                    PlaybackEvent::Started if !announced => {
+                        announced = true;
+                        if config.notifications {
+                            Notification::new(i18n::text("player.now-playing"))
+                                .body(&track_name(path, &wav))
+                                .show();
+                        }
+                    }
+                    PlaybackEvent::EndOfStream => {
+                        ended = true;
+                        if track + 1 < playlist.len() {
+                            next_track = Some(track + 1);
+                        }
+                        if config.notifications {
+                            Notification::new(i18n::text("player.finished-playing"))
+                                .body(&track_name(path, &wav))
+                                .show();
+                        }
+                    }
+                    PlaybackEvent::Xrun => xruns += 1,
+                    PlaybackEvent::Started
+                    | PlaybackEvent::Paused
+                    | PlaybackEvent::DeviceChanged => {}
+                }
+            }
+            let played_samples = if scrubbing {
+                handed_samples
+            } else {
+                player.audible_position(handed_samples)
+            };
+            // auditioning moves on once the start of the file has been heard
+            if let Some(audition) = &audition
+                && played_samples >= audition.samples(&wav)
+                && next_track.is_none()
+            {
+                if track + 1 < playlist.len() {
+                    next_track = Some(track + 1);
+                } else if player.settings().playing {
+                    player.pause();
+                }
+            }
+            let (width, height) = canvas.size();
+            let difference_height = if comparison.is_some() {
+                DIFFERENCE_HEIGHT
+            } else {
+                0
+            };
+            // measured again whenever the selection changes, so the bar follows a dragged edge
+            match selection.map(|selection| selection.range()) {
+                Some(frames) if !frames.is_empty() => {
+                    if selection_stats.as_ref().is_none_or(|s| s.frames != frames) {
+                        selection_stats = Some(selection::Stats::measure(&wav, frames));
+                    }
+                }
+                _ => selection_stats = None,
+            }
+            let bar_height = if selection_stats.is_some() {
+                selection::BAR_HEIGHT
             } else {
                 0
             };
-            self.position += 1;
+            // only meaningful outside the mini player, which is shorter than the strips
+            let strips_top =
+                height.saturating_sub(bar_height + OVERVIEW_HEIGHT + difference_height);
+            let view_area = Rect::new(0, 0, width, strips_top);
+            let bar_area = Rect::new(0, strips_top as i32, width, selection::BAR_HEIGHT);
+            let overview_top = strips_top + bar_height;
+            let overview_area = Rect::new(0, overview_top as i32, width, OVERVIEW_HEIGHT);
+            let difference_area = Rect::new(
+                0,
+                (overview_top + OVERVIEW_HEIGHT) as i32,
+                width,
+                DIFFERENCE_HEIGHT,
+            );
+            if overview.as_ref().is_none_or(|o| o.width() != width) {
+                overview = Some(Overview::cached(path, &wav, width));
+            }
+            if mini {
+                let state = if ended {
+                    i18n::text("player.state-end")
+                } else if player.settings().playing {
+                    i18n::text("player.state-play")
+                } else {
+                    i18n::text("player.state-pause")
+                };
+                draw_mini_player(
+                    &mut canvas,
+                    state,
+                    &wav,
+                    &theme,
+                    &classes,
+                    overview.as_ref().unwrap(),
+                    played_samples,
+                );
+            } else {
+                for (pane_view, pane) in layout.panes(view_area) {
+                    canvas.set_viewport(pane);
+                    // the hover readouts only make sense while the cursor is over the pane
+                    let pane_mouse = mouse
+                        .filter(|&(x, y)| pane.contains_point((x, y)))
+                        .map(|(x, y)| (x - pane.x(), y - pane.y()));
+                    match pane_view {
+                        View::Waveform => {
+                            let shown =
+                                waveform_samples(&mut waveform_window, &wav, played_samples);
+                            match phosphor {
+                                true => {
+                                    draw_phosphor(&mut canvas, shown, quality.step(), theme.palette)
+                                }
+                                false => {
+                                    draw_waveform(&mut canvas, shown, quality.step(), theme.palette)
+                                }
+                            }
+                            if let Some(snapped) = snapped {
+                                draw_snap(&mut canvas, &wav, played_samples, snapped);
+                            }
+                            if let Some((x, y)) = pane_mouse {
+                                draw_waveform_readout(&mut canvas, &wav, played_samples, x, y);
+                            }
+                            if let Some(comparison) = &comparison {
+                                let channels = wav.header.num_channels as usize;
+                                draw_compare_overlay(
+                                    &mut canvas,
+                                    comparison,
+                                    channels,
+                                    played_samples,
+                                    quality.step(),
+                                );
+                            }
+                        }
+                        View::Spectrogram => {
+                            if spectrogram_image
+                                .as_ref()
+                                .is_none_or(|image| image.width != width)
+                            {
+                                spectrogram_image =
+                                    Some(Image::new(width, spectrogram::BINS as u32));
+                            }
+                            let image = spectrogram_image.as_mut().unwrap();
+                            draw_spectrogram(
+                                &mut canvas,
+                                image,
+                                &mut spectrogram,
+                                &theme,
+                                &wav,
+                                played_samples,
+                                quality.step(),
+                            );
+                            draw_filtered_bands(&mut canvas, player.settings(), &wav);
+                            if let Some((x, y)) = pane_mouse {
+                                if show_harmonics {
+                                    draw_harmonics(&mut canvas, &wav, x, y);
+                                }
+                                draw_spectrogram_readout(
+                                    &mut canvas,
+                                    &mut spectrogram,
+                                    &wav,
+                                    played_samples,
+                                    x,
+                                    y,
+                                );
+                            }
+                        }
+                        View::Dynamics => {
+                            if dynamics.is_none() {
+                                let job = dynamics_job.get_or_insert_with(|| {
+                                    let wav = wav.clone();
+                                    Job::spawn(move |progress, cancel| {
+                                        Dynamics::analyze(&wav, progress, cancel)
+                                    })
+                                });
+                                match job.try_take() {
+                                    Some(result) => {
+                                        dynamics = result.ok();
+                                        dynamics_job = None;
+                                    }
+                                    None => draw_progress_bar(
+                                        &mut canvas,
+                                        i18n::text("player.analyzing-levels"),
+                                        job.progress(),
+                                    ),
+                                }
+                            }
+                            if let Some(dynamics) = &dynamics {
+                                draw_dynamics(&mut canvas, dynamics);
+                            }
+                        }
+                        View::Impulse => {
+                            if let Some(impulse) = &impulse {
+                                impulse::draw(&mut canvas, impulse);
+                            }
+                        }
+                    }
+                    for (frames, color) in [
+                        (&markers, CHAPTER_COLOR),
+                        (&cues, CUE_COLOR),
+                        (&subtitle_starts, SUBTITLE_START_COLOR),
+                        (&subtitle_ends, SUBTITLE_END_COLOR),
+                    ] {
+                        draw_markers(&mut canvas, pane_view, frames, color, &wav, played_samples);
+                    }
+                    if let Some(range) = &player.settings().loop_range {
+                        let x = |sample: usize| {
+                            view_x(
+                                pane_view,
+                                sample / channels,
+                                pane.width(),
+                                &wav,
+                                played_samples,
+                            )
+                        };
+                        if let (Some(left), Some(right)) = (x(range.start), x(range.end)) {
+                            draw_loop_region(&mut canvas, left, right);
+                        }
+                    }
+                    if let Some(start) = loop_start {
+                        let start = [start];
+                        draw_markers(
+                            &mut canvas,
+                            pane_view,
+                            &start,
+                            LOOP_COLOR,
+                            &wav,
+                            played_samples,
+                        );
+                    }
+                }
+
+                canvas.set_viewport(view_area);
+                if let Some(y) = layout.divider_y(view_area) {
+                    canvas.set_color(Color::RGB(96, 96, 96));
+                    canvas.fill_rect(Rect::new(0, y - 1, width, 3));
+                }
+                draw_metadata(&mut canvas, &wav.metadata, cover_art.as_ref());
+                if let Some(subtitle) = subtitles::at(&subtitles, played_samples / channels) {
+                    subtitles::draw_caption(&mut canvas, subtitle);
+                }
+                draw_processing_status(
+                    &mut canvas,
+                    player.settings(),
+                    ended,
+                    xruns,
+                    &quality,
+                    phosphor,
+                    snap_zero.then(|| snapped.map_or(0, |(from, to)| to as isize - from as isize)),
+                );
+                if !player.settings().playing {
+                    draw_sample_values(&mut canvas, &wav, played_samples);
+                }
+                if let (Some(audition), Some(summary)) = (&audition, &summary) {
+                    audition::draw(
+                        &mut canvas,
+                        summary,
+                        track,
+                        playlist.len(),
+                        audition.verdict(track),
+                    );
+                }
+                if let Some(abx) = &abx {
+                    abx::draw(&mut canvas, abx);
+                }
+                if let Some(group) = &take_group {
+                    takes::draw(&mut canvas, group);
+                }
+                if show_stats {
+                    draw_stats(&mut canvas, &frame_rate, &player, xruns);
+                }
+
+                canvas.set_viewport(overview_area);
+                draw_overview(
+                    &mut canvas,
+                    overview.as_ref().unwrap(),
+                    &theme,
+                    &classes,
+                    &[
+                        (&markers, CHAPTER_COLOR),
+                        (&cues, CUE_COLOR),
+                        (&subtitle_starts, SUBTITLE_START_COLOR),
+                    ],
+                    &wav,
+                    played_samples,
+                );
+                notes::draw_flags(&mut canvas, &notes, wav.data.len() / channels);
+                if let Some(range) = &player.settings().loop_range {
+                    let x = |sample: usize| {
+                        (sample as f64 / wav.data.len().max(1) as f64 * width as f64) as f32
+                    };
+                    draw_loop_region(&mut canvas, x(range.start), x(range.end));
+                }
+                if let Some(stats) = &selection_stats {
+                    selection::draw_range(&mut canvas, &stats.frames, wav.data.len() / channels);
+                    canvas.set_viewport(bar_area);
+                    selection::draw_bar(&mut canvas, stats);
+                }
+                if let Some(comparison) = &mut comparison {
+                    if let Some(job) = &mut comparison.difference_job
+                        && let Some(result) = job.try_take()
+                    {
+                        if let Ok(difference) = result {
+                            comparison.difference = Some(difference);
+                        }
+                        comparison.difference_job = None;
+                    }
+                    if let Some(difference) = &comparison.difference {
+                        canvas.set_viewport(difference_area);
+                        draw_difference(&mut canvas, difference, &theme);
+                    }
+                }
+                if let Some((x, y)) = mouse
+                    && overview_area.contains_point((x, y))
+                    && let Some(note) = notes.at(x, width, wav.data.len() / channels)
+                {
+                    canvas.set_viewport(None);
+                    notes::draw_note(
+                        &mut canvas,
+                        note,
+                        wav.header.sample_rate,
+                        x,
+                        overview_top as i32,
+                    );
+                }
+            }
+            canvas.set_viewport(None);
+
+            tray.update(&track_name(path, &wav), player.settings().playing);
+            for action in tray.actions() {
+                match action {
+                    TrayAction::PlayPause if player.settings().playing => player.pause(),
+                    TrayAction::PlayPause => player.play(),
+                    TrayAction::NextTrack => {
+                        next_track = Some(track + 1).filter(|&next| next < playlist.len());
+                    }
+                    TrayAction::ShowWindow => {
+                        canvas.window_mut().show();
+                        canvas.window_mut().raise();
+                    }
+                    TrayAction::Quit => break 'playlist,
+                }
+            }
+            for event in event_pump.poll_iter() {
+                // keys work in either window, but the meter window's mouse and window events
+                // aren't meant for the views
+                let from_meter = meter_canvas
+                    .as_ref()
+                    .is_some_and(|meter| event.get_window_id() == Some(meter.window().id()));
+                if from_meter && (event.is_mouse() || event.is_window()) {
+                    if let Event::Window {
+                        win_event: WindowEvent::Close,
+                        ..
+                    } = event
+                    {
+                        meter_canvas = None;
+                    }
+                    continue;
+                }
+                if let Some(action) = input.handle(&event) {
+                    match action {
+                        Action::Quit => break 'playlist,
+                        Action::NextView | Action::NextSecondaryView => {
+                            // shift+tab switches the bottom view while split
+                            match &mut layout.secondary {
+                                Some(secondary) if action == Action::NextSecondaryView => {
+                                    *secondary = secondary.next(impulse.is_some())
+                                }
+                                _ => layout.primary = layout.primary.next(impulse.is_some()),
+                            }
+                            if !layout.shows(View::Dynamics) {
+                                dynamics_job = None;
+                            }
+                        }
+                        Action::ToggleSplit => {
+                            layout.toggle_split();
+                            if !layout.shows(View::Dynamics) {
+                                dynamics_job = None;
+                            }
+                        }
+                        Action::NextColormap if layout.shows(View::Spectrogram) => {
+                            theme.spectrogram_colormap = theme.spectrogram_colormap.next();
+                        }
+                        Action::MoveDbFloor(step) if layout.shows(View::Spectrogram) => {
+                            theme.spectrogram_db_floor += step;
+                            theme.clamp_db_range();
+                        }
+                        Action::MoveDbCeiling(step) if layout.shows(View::Spectrogram) => {
+                            theme.spectrogram_db_ceiling += step;
+                            theme.clamp_db_range();
+                        }
+                        Action::PlayPause => {
+                            if player.settings().playing {
+                                player.pause();
+                            } else {
+                                player.play();
+                            }
+                        }
+                        Action::ToggleHarmonics => show_harmonics = !show_harmonics,
+                        Action::ToggleGuideTone
+                        | Action::NextTonePreset
+                        | Action::NextToneLevel => {
+                            let mut guide_tone = player.settings().guide_tone;
+                            match action {
+                                Action::NextTonePreset => guide_tone.next_preset(),
+                                Action::NextToneLevel => guide_tone.next_level(),
+                                _ => guide_tone.enabled = !guide_tone.enabled,
+                            }
+                            player.set_guide_tone(guide_tone);
+                        }
+                        Action::ToggleStats => show_stats = !show_stats,
+                        Action::ToggleMini => {
+                            mini = !mini;
+                            let window = canvas.window_mut();
+                            if mini {
+                                full_size = window.size();
+                                window.set_bordered(false);
+                                window.set_size(MINI_WIDTH, MINI_HEIGHT)?;
+                            } else {
+                                window.set_bordered(true);
+                                window.set_size(full_size.0, full_size.1)?;
+                            }
+                        }
+                        Action::ShuttleForward | Action::ShuttleReverse => {
+                            // pressing again in the same direction doubles the speed
+                            let direction = if action == Action::ShuttleForward {
+                                1.0
+                            } else {
+                                -1.0
+                            };
+                            let settings = player.settings();
+                            let speed = if settings.playing && settings.speed * direction > 0.0 {
+                                (settings.speed * 2.0).clamp(-MAX_SHUTTLE, MAX_SHUTTLE)
+                            } else {
+                                direction
+                            };
+                            player.set_speed(speed);
+                            player.play();
+                        }
+                        Action::StepSpeed(steps) => {
+                            let speed = player.settings().speed;
+                            // from the nearest step, so a shuttle speed joins in too
+                            let nearest = (0..SPEEDS.len())
+                                .min_by(|&a, &b| {
+                                    (SPEEDS[a] - speed.abs())
+                                        .abs()
+                                        .total_cmp(&(SPEEDS[b] - speed.abs()).abs())
+                                })
+                                .unwrap();
+                            let index = nearest.saturating_add_signed(steps).min(SPEEDS.len() - 1);
+                            player.set_speed(SPEEDS[index].copysign(speed));
+                        }
+                        Action::ShuttleStop => {
+                            // space resumes at normal speed afterwards
+                            player.pause();
+                            player.set_speed(1.0);
+                        }
+                        Action::NextTrack => {
+                            next_track = Some(track + 1).filter(|&next| next < playlist.len());
+                        }
+                        Action::PreviousTrack => next_track = track.checked_sub(1),
+                        Action::Keep | Action::Reject if audition.is_some() => {
+                            let audition = audition.as_mut().unwrap();
+                            let verdict = if action == Action::Keep {
+                                Verdict::Keep
+                            } else {
+                                Verdict::Reject
+                            };
+                            audition.tag(track, verdict);
+                            if let Err(e) = audition.write_report(&playlist) {
+                                eprintln!("{}: {e}", audition.report().display());
+                            }
+                            if track + 1 < playlist.len() {
+                                next_track = Some(track + 1);
+                            }
+                        }
+                        Action::ToggleMeters => {
+                            meter_canvas = match meter_canvas {
+                                Some(_) => None,
+                                None => {
+                                    let channels = wav.header.num_channels.max(1) as u32;
+                                    let window = video_subsystem
+                                        .window(
+                                            i18n::text("player.meters-title"),
+                                            channels * meter::CHANNEL_WIDTH,
+                                            meter::HEIGHT,
+                                        )
+                                        .always_on_top()
+                                        .build()?;
+                                    Some(window.into_canvas().build()?)
+                                }
+                            };
+                        }
+                        Action::TogglePhosphor => {
+                            // turning it back on starts from a clean screen, the trail isn't
+                            // kept while it's not drawn
+                            phosphor = !phosphor;
+                        }
+                        Action::ToggleHumFilter => {
+                            player.set_hum_filter(!player.settings().hum_filter)
+                        }
+                        Action::ToggleRumbleFilter => {
+                            player.set_rumble_filter(!player.settings().rumble_filter)
+                        }
+                        Action::ToggleAirFilter => {
+                            player.set_air_filter(!player.settings().air_filter)
+                        }
+                        Action::ToggleConvolution => {
+                            player.set_convolution(!player.settings().convolution)
+                        }
+                        Action::TogglePlugin if player.settings().plugin.is_some() => {
+                            player.set_plugin_enabled(!player.settings().plugin_enabled)
+                        }
+                        Action::SelectParameter(step) => {
+                            player.select_parameter(player.settings().parameter as isize + step);
+                            plugin_parameter = player.settings().parameter;
+                        }
+                        Action::AdjustParameter(steps) => {
+                            let settings = player.settings();
+                            if let Some(parameter) = settings.parameters.get(settings.parameter) {
+                                let value = parameter.step(steps);
+                                player.set_parameter(settings.parameter, value);
+                                plugin_values = player.settings().parameters.clone();
+                            }
+                        }
+                        Action::ToggleHeadModel => {
+                            if let Some(enabled) = player.settings().binaural {
+                                player.set_head_model(!enabled)
+                            }
+                        }
+                        Action::SaveQueue => input.prompt(Prompt::SaveQueue, QUEUE_PLAYLIST),
+                        Action::Submit(Prompt::SaveQueue, file) => {
+                            // the queue as a playlist, relative paths resolve from the
+                            // current directory
+                            match playlist::write_m3u(std::path::Path::new(&file), &playlist) {
+                                Ok(()) => println!(
+                                    "{}",
+                                    i18n::fill(
+                                        "player.saved-queue",
+                                        &[
+                                            ("count", playlist.len().to_string()),
+                                            ("path", file.clone()),
+                                        ]
+                                    )
+                                ),
+                                Err(e) => eprintln!("{file}: {e}"),
+                            }
+                        }
+                        Action::GoTo => input.prompt(Prompt::GoTo, ""),
+                        Action::OpenPalette => input.prompt(Prompt::Palette, ""),
+                        Action::AddNote => {
+                            input.prompt(Prompt::Note(played_samples / channels), "")
+                        }
+                        Action::Submit(Prompt::Note(frame), text) => {
+                            let text = text.trim();
+                            if !text.is_empty()
+                                && let Err(e) = notes.add(frame, text, wav.header.sample_rate)
+                            {
+                                eprintln!("{e}");
+                            }
+                        }
+                        Action::RemoveNote => {
+                            let frame = played_samples / channels;
+                            if let Err(e) = notes.remove_nearest(frame, wav.header.sample_rate) {
+                                eprintln!("{e}");
+                            }
+                        }
+                        Action::Submit(Prompt::GoTo, text) => {
+                            match timecode::parse(&text, wav.header.sample_rate) {
+                                Ok(frame) => {
+                                    let frames = wav.data.len() / channels;
+                                    snapped =
+                                        seek_to(&mut player, &wav, frame.min(frames), snap_zero);
+                                }
+                                Err(e) => {
+                                    // back to the prompt to fix what was typed
+                                    eprintln!("{}: {e}", i18n::text("prompt.go-to"));
+                                    input.prompt(Prompt::GoTo, &text);
+                                }
+                            }
+                        }
+                        Action::CopyTimestamp | Action::CopySelection | Action::CopyStats => {
+                            let rate = wav.header.sample_rate;
+                            let templates = &config.clipboard;
+                            let text = match (&action, &selection_stats) {
+                                (Action::CopyTimestamp, _) => {
+                                    Some(templates.timestamp(path, played_samples / channels, rate))
+                                }
+                                (Action::CopySelection, Some(stats)) => {
+                                    Some(templates.selection(path, stats, rate))
+                                }
+                                (Action::CopyStats, Some(stats)) => {
+                                    Some(templates.stats(path, stats, rate))
+                                }
+                                _ => None,
+                            };
+                            match text {
+                                Some(text) => {
+                                    if let Err(e) = clipboard.set_clipboard_text(&text) {
+                                        eprintln!("{}: {e}", i18n::text("player.clipboard"));
+                                    }
+                                }
+                                None => eprintln!(
+                                    "{}: {}",
+                                    i18n::text("player.clipboard"),
+                                    i18n::text("player.nothing-selected")
+                                ),
+                            }
+                        }
+                        Action::ChangeVolume(db) => {
+                            let gain = dynamics::to_db(player.settings().gain) + db;
+                            player.set_gain(
+                                10f32.powf(gain.clamp(MIN_VOLUME_DB, MAX_VOLUME_DB) / 20.0),
+                            );
+                        }
+                        Action::ToggleMuted => player.set_muted(!player.settings().muted),
+                        Action::Seek(seconds) => {
+                            let last = (wav.data.len() / channels).saturating_sub(1);
+                            // on from the previous seek while it isn't heard yet, so holding
+                            // the key keeps going
+                            let from = pending_seek.unwrap_or(played_samples) / channels;
+                            let frames = (seconds * wav.header.sample_rate as f64).round();
+                            let frame = from.saturating_add_signed(frames as isize).min(last);
+                            snapped = seek_to(&mut player, &wav, frame, snap_zero);
+                            let target = snapped.map_or(frame, |(_, to)| to) * channels;
+                            pending_seek = Some(target);
+                            handed_samples = target;
+                        }
+                        Action::StepFrames(_) | Action::StepHops(_)
+                            if !player.settings().playing =>
+                        {
+                            let frames = match action {
+                                Action::StepHops(hops) => hops * spectrogram::HOP as isize,
+                                Action::StepFrames(frames) => frames,
+                                _ => 0,
+                            };
+                            // from where the last step landed, not where the previous one
+                            // was heard
+                            let last = (wav.data.len() / channels).saturating_sub(1);
+                            let frame = (handed_samples / channels)
+                                .saturating_add_signed(frames)
+                                .min(last);
+                            player.seek(frame * channels);
+                            handed_samples = frame * channels;
+                        }
+                        Action::ToggleZeroSnap => {
+                            snap_zero = !snap_zero;
+                            snapped = None;
+                        }
+                        Action::FindChapters => {
+                            // a marker at the start of everything that follows a stretch of
+                            // silence
+                            markers = silence::sound_segments(&wav)
+                                .into_iter()
+                                .map(|segment| segment.start)
+                                .collect();
+                            println!(
+                                "{}",
+                                i18n::fill(
+                                    "player.chapters",
+                                    &[("count", markers.len().to_string())]
+                                )
+                            );
+                            for (i, &frame) in markers.iter().enumerate() {
+                                let seconds = frame as f64 / wav.header.sample_rate as f64;
+                                println!("  {:>3}  {}", i + 1, timecode::format(seconds));
+                            }
+                        }
+                        Action::RestoreSession => {
+                            match leftover.take_if(|session| session.file == path) {
+                                Some(session) => {
+                                    let frames = wav.data.len() / channels;
+                                    let within = |range: std::ops::Range<usize>| {
+                                        range.start.min(frames)..range.end.min(frames)
+                                    };
+                                    markers = session.markers;
+                                    markers.retain(|&frame| frame < frames);
+                                    selection = session.selection.map(|range| {
+                                        let range = within(range);
+                                        let mut selection = Selection::new(range.start);
+                                        selection.drag(range.end);
+                                        selection
+                                    });
+                                    player.set_gain(session.gain);
+                                    player.set_loop(session.loop_range.map(|range| {
+                                        let range = within(range);
+                                        range.start * channels..range.end * channels
+                                    }));
+                                    let frame = session.frame.min(frames.saturating_sub(1));
+                                    snapped = seek_to(&mut player, &wav, frame, false);
+                                    pending_seek = Some(frame * channels);
+                                    handed_samples = frame * channels;
+                                    println!("{}", i18n::text("player.restored"));
+                                }
+                                None => eprintln!("{}", i18n::text("player.no-session")),
+                            }
+                        }
+                        Action::ToggleLoopFile => {
+                            loop_file = !loop_file;
+                            player.set_loop_file(loop_file);
+                        }
+                        Action::MarkLoopStart => {
+                            let frame = played_samples / channels;
+                            let frame = if snap_zero {
+                                zero::nearest(&wav, frame)
+                            } else {
+                                frame
+                            };
+                            // moves the start of a loop that's already going
+                            match player.settings().loop_range.clone() {
+                                Some(range) if frame * channels < range.end => {
+                                    player.set_loop(Some(frame * channels..range.end));
+                                }
+                                _ => loop_start = Some(frame),
+                            }
+                            println!(
+                                "{}",
+                                i18n::fill(
+                                    "player.loop-from",
+                                    &[(
+                                        "start",
+                                        timecode::format(
+                                            frame as f64 / wav.header.sample_rate as f64
+                                        )
+                                    )]
+                                )
+                            );
+                        }
+                        Action::MarkLoopEnd => {
+                            let frame = played_samples / channels;
+                            let frame = if snap_zero {
+                                zero::nearest(&wav, frame)
+                            } else {
+                                frame
+                            };
+                            let start = loop_start.or_else(|| {
+                                let range = player.settings().loop_range.as_ref()?;
+                                Some(range.start / channels)
+                            });
+                            match start {
+                                Some(start) if start < frame => {
+                                    player.set_loop(Some(start * channels..frame * channels));
+                                    loop_start = None;
+                                    let seconds = |frame: usize| {
+                                        timecode::format(
+                                            frame as f64 / wav.header.sample_rate as f64,
+                                        )
+                                    };
+                                    println!(
+                                        "{}",
+                                        i18n::fill(
+                                            "player.looping",
+                                            &[("start", seconds(start)), ("end", seconds(frame))]
+                                        )
+                                    );
+                                }
+                                Some(_) => eprintln!("{}", i18n::text("player.loop-backwards")),
+                                None => eprintln!("{}", i18n::text("player.no-loop-start")),
+                            }
+                        }
+                        Action::ClearLoop => {
+                            loop_start = None;
+                            player.set_loop(None);
+                        }
+                        Action::ToggleSampleLoop => match &sample_loop {
+                            Some(range) => {
+                                let looping = player.settings().loop_range.as_ref() == Some(range);
+                                player.set_loop((!looping).then(|| range.clone()));
+                            }
+                            None => eprintln!("{}", i18n::text("player.no-sample-loop")),
+                        },
+                        Action::ToggleMute(_) | Action::Submit(Prompt::TrackGain, _)
+                            if take_group.is_some() =>
+                        {
+                            let group = take_group.as_mut().unwrap();
+                            match action {
+                                Action::ToggleMute(track) => group.toggle_mute(track),
+                                Action::Submit(_, text) => {
+                                    match takes::parse_gain(&text, group.len()) {
+                                        Ok((track, db)) => group.set_gain(track, db),
+                                        Err(e) => {
+                                            eprintln!("{}: {e}", i18n::text("prompt.track-gain"));
+                                            input.prompt(Prompt::TrackGain, &text);
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                            player.set_data(group.mix());
+                        }
+                        Action::AlignTake if take_group.is_some() => {
+                            let group = take_group.as_mut().unwrap();
+                            group.align();
+                            println!("{}", i18n::text("player.take-aligned"));
+                            for line in group.lines() {
+                                println!("  {line}");
+                            }
+                            player.set_data(group.mix());
+                        }
+                        Action::TrackGain if take_group.is_some() => {
+                            input.prompt(Prompt::TrackGain, "")
+                        }
+                        Action::NextCue | Action::PreviousCue => {
+                            let frame = played_samples / channels;
+                            // going back skips the cue just passed while playing on, like a cd
+                            // player's previous button
+                            let grace = wav.header.sample_rate as usize / 2;
+                            let cue = match action {
+                                Action::NextCue => wav.cues().iter().find(|cue| cue.frame > frame),
+                                _ => wav
+                                    .cues()
+                                    .iter()
+                                    .rev()
+                                    .find(|cue| cue.frame + grace < frame),
+                            };
+                            match cue {
+                                Some(cue) => {
+                                    let seconds = cue.frame as f64 / wav.header.sample_rate as f64;
+                                    println!(
+                                        "{}  {}  {}",
+                                        i18n::fill("player.cue", &[("id", cue.id.to_string())]),
+                                        timecode::format(seconds),
+                                        cue.label.as_deref().unwrap_or("")
+                                    );
+                                    snapped = seek_to(&mut player, &wav, cue.frame, snap_zero);
+                                }
+                                None if wav.cues().is_empty() => {
+                                    eprintln!("{}", i18n::text("player.no-cues"))
+                                }
+                                None => {}
+                            }
+                        }
+                        Action::NudgeCompare(frames) if comparison.is_some() => {
+                            let comparison = comparison.as_mut().unwrap();
+                            comparison.offset += frames;
+                            comparison.recompute_difference(&wav);
+                            player.set_compare_offset(comparison.offset);
+                        }
+                        Action::HearA | Action::HearB | Action::HearX if abx.is_some() => {
+                            let abx = abx.as_mut().unwrap();
+                            abx.hearing = match action {
+                                Action::HearA => Source::A,
+                                Action::HearB => Source::B,
+                                _ => Source::X,
+                            };
+                            player.set_play_compare(abx.plays_b());
+                        }
+                        Action::VoteXIsA | Action::VoteXIsB if abx.is_some() => {
+                            let abx = abx.as_mut().unwrap();
+                            abx.vote(action == Action::VoteXIsB);
+                            player.set_play_compare(abx.plays_b());
+                            if abx.done() {
+                                println!("{}", abx.results());
+                            }
+                        }
+                        // switching by hand would give X away
+                        Action::SwitchCompare if comparison.is_some() && abx.is_none() => {
+                            let comparison = comparison.as_mut().unwrap();
+                            comparison.audible = !comparison.audible;
+                            player.set_play_compare(comparison.audible);
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                match event {
+                    Event::Quit { .. } => break 'playlist,
+                    Event::MouseMotion { x, y, .. } => {
+                        mouse = Some((x, y));
+                        layout.drag(view_area, y);
+                        if scrubbing {
+                            player.scrub(overview_frame(x, width, &wav));
+                        }
+                        if let Some(start) = waveform_drag {
+                            let frame = waveform_frame(x, width, &wav, start);
+                            snapped = seek_to(&mut player, &wav, frame, snap_zero);
+                            let target = snapped.map_or(frame, |(_, to)| to) * channels;
+                            pending_seek = Some(target);
+                            handed_samples = target;
+                        }
+                        if selecting && let Some(selection) = &mut selection {
+                            selection.drag(overview_frame(x, width, &wav));
+                        }
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Right,
+                        ..
+                    } if selecting => {
+                        selecting = false;
+                        // a click without dragging clears the selection
+                        if selection.is_some_and(|selection| selection.range().is_empty()) {
+                            selection = None;
+                        }
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Right,
+                        x,
+                        y,
+                        ..
+                    } if !mini && overview_area.contains_point((x, y)) => {
+                        // right-dragging selects, starting on an edge of the selection drags
+                        // that edge instead
+                        let frame = overview_frame(x, width, &wav);
+                        let frames = wav.data.len() / channels;
+                        let edge_x = |frame: usize| {
+                            (frame as f64 / frames.max(1) as f64 * width as f64) as i32
+                        };
+                        match &mut selection {
+                            Some(selection)
+                                if [selection.range().start, selection.range().end]
+                                    .into_iter()
+                                    .any(|edge| {
+                                        (edge_x(edge) - x).abs() <= selection::EDGE_GRAB
+                                    }) =>
+                            {
+                                selection.grab(frame)
+                            }
+                            _ => selection = Some(Selection::new(frame)),
+                        }
+                        selecting = true;
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        ..
+                    } => {
+                        layout.release();
+                        waveform_drag = None;
+                        if scrubbing {
+                            scrubbing = false;
+                            player.stop_scrub();
+                            if snap_zero {
+                                let frame = overview_frame(x, width, &wav);
+                                snapped = seek_to(&mut player, &wav, frame, true);
+                            }
+                        }
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } if !mini && overview_area.contains_point((x, y)) => {
+                        // dragging along the overview scrubs, letting go leaves the playhead
+                        // there
+                        scrubbing = true;
+                        player.scrub(overview_frame(x, width, &wav));
+                    }
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } => {
+                        // the divider takes the click before the view under it
+                        let on_divider = layout.grab(view_area, y);
+                        match layout.view_at(view_area, (x, y)) {
+                            _ if on_divider => {}
+                            Some(View::Spectrogram) => {
+                                let column = spectrogram_column(x, width, &wav, played_samples);
+                                if column >= 0 && (column as usize) < spectrogram.len() {
+                                    let frame = column as usize * spectrogram::HOP;
+                                    snapped = seek_to(&mut player, &wav, frame, snap_zero);
+                                }
+                            }
+                            // clicking or dragging in the waveform seeks to the sample under
+                            // the cursor
+                            Some(View::Waveform) => {
+                                let start = pending_seek.unwrap_or(played_samples);
+                                let frame = waveform_frame(x, width, &wav, start);
+                                snapped = seek_to(&mut player, &wav, frame, snap_zero);
+                                let target = snapped.map_or(frame, |(_, to)| to) * channels;
+                                pending_seek = Some(target);
+                                handed_samples = target;
+                                waveform_drag = Some(start);
+                            }
+                            _ => {}
+                        }
+                    }
+                    Event::AudioDeviceAdded { .. } | Event::AudioDeviceRemoved { .. } => {
+                        player.device_changed();
+                    }
+                    Event::Window {
+                        win_event: WindowEvent::Leave,
+                        ..
+                    } => mouse = None,
+                    // with the meter window open sdl only quits once both are closed
+                    Event::Window {
+                        win_event: WindowEvent::Close,
+                        ..
+                    } if tray.is_shown() => canvas.window_mut().hide(),
+                    Event::Window {
+                        win_event: WindowEvent::Close,
+                        ..
+                    } => break 'playlist,
+                    _ => {}
+                }
+            }
+
+            input::draw(&mut canvas, &input);
+            canvas.present();
+            if let Some(meter) = &mut meter_canvas {
+                meter.set_draw_color(Color::RGB(0, 0, 0));
+                meter.clear();
+                meter::draw(meter, &Levels::at(&wav, played_samples), theme.palette);
+                meter.present();
+            }
+            if quality.frame_took(frame_start.elapsed()) {
+                println!(
+                    "{}",
+                    i18n::fill(
+                        "player.render-quality",
+                        &[("step", quality.step().to_string())]
+                    )
+                );
+            }
+            if autosave.due() {
+                autosave.save(Session {
+                    file: path.to_string(),
+                    frame: played_samples / channels,
+                    gain: player.settings().gain,
+                    loop_range: player
+                        .settings()
+                        .loop_range
+                        .as_ref()
+                        .map(|range| range.start / channels..range.end / channels),
+                    markers: markers.clone(),
+                    selection: selection.map(|selection| selection.range()),
+                });
+            }
+            ::std::thread::sleep(quality::FRAME_BUDGET);
+            if let Some(next) = next_track {
+                track = next;
+                continue 'playlist;
+            }
         }
+    }
+    recovery::clear();
+    // quitting halfway still reports the trials answered
+    if let Some(abx) = &abx
+        && !abx.done()
+    {
+        println!("{}", abx.results());
+    }
+    Ok(())
+}
 
-        // Update shared position for rendering
-        *self.shared_position.lock().unwrap() = self.position;
+// the WAVEFORM_SAMPLES from `start` the waveform view shows, read through the window on
+// the file when there is one and from the decoded samples otherwise
+fn waveform_samples<'a>(
+    window: &'a mut Option<decoder::Window>,
+    wav: &'a WavFile,
+    start: usize,
+) -> &'a [i16] {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let frames = (start / channels) as u64..(start + WAVEFORM_SAMPLES).div_ceil(channels) as u64;
+    if let Some(window) = window
+        && let Ok(samples) = window.read(frames)
+    {
+        return samples.get(start % channels..).unwrap_or_default();
     }
+    let shown = wav.data.get(start..).unwrap_or_default();
+    &shown[..shown.len().min(WAVEFORM_SAMPLES)]
 }
 
-fn main() -> Result<(), Box<dyn error::Error + 'static>> {
-    let mut wav = WAVFile::new();
-    let mut data = fs::read("file_example_WAV_5MG.wav")?;
-    let _ = wav.parse(&mut data).unwrap();
+// `shown` are the samples waveform_samples picked for the view
+fn draw_waveform(canvas: &mut impl Renderer, shown: &[i16], step: usize, palette: Palette) {
+    canvas.set_color(palette.waveform());
+    draw_trace(canvas, shown, 0, step);
+    draw_clip_marks(canvas, shown, 0, palette);
+}
 
-    let sdl_context = sdl2::init().unwrap();
+// a notch into the view from the top or bottom edge above every full-scale sample, so clipping
+// stands out by where and how it's drawn and not only by its color
+fn draw_clip_marks(canvas: &mut impl Renderer, data: &[i16], start: usize, palette: Palette) {
+    let (width, height) = canvas.size();
+    let shown = data.get(start..).unwrap_or_default();
+    let shown = &shown[..shown.len().min(WAVEFORM_SAMPLES)];
+    let x = |i: usize| (i as f32 / WAVEFORM_SAMPLES as f32 * width as f32) as i32;
+    let mut marks = Vec::new();
+    for (i, &sample) in shown.iter().enumerate() {
+        let y = match sample {
+            i16::MAX => 0,
+            i16::MIN => height as i32 - CLIP_MARK_HEIGHT as i32,
+            _ => continue,
+        };
+        let mark = Rect::new(x(i) - 1, y, 3, CLIP_MARK_HEIGHT);
+        // a run of clipped samples is one mark per pixel, not one per sample
+        if marks.last() != Some(&mark) {
+            marks.push(mark);
+        }
+    }
+    canvas.set_color(palette.clip());
+    canvas.fill_rects(&marks);
+}
 
-    let shared_position = Arc::new(Mutex::new(0));
-    let player = AudioPlayer {
-        data: wav.data.clone().into(),
-        position: 0,
-        shared_position: shared_position.clone(),
+// seeks to `frame`, or to the zero crossing nearest it while snapping. Returns both frames
+// when snapping so the adjustment can be shown
+fn seek_to(player: &mut Player, wav: &WavFile, frame: usize, snap: bool) -> Option<(usize, usize)> {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let target = if snap {
+        zero::nearest(wav, frame)
+    } else {
+        frame
     };
-    let desired_spec = AudioSpecDesired {
-        freq: Some(wav.header.sample_rate as i32),
-        channels: Some(wav.header.num_channels as u8),
-        samples: Some(wav.header.bits_per_sample),
+    player.seek(target * channels);
+    snap.then_some((frame, target))
+}
+
+// the requested seek position in grey and the zero crossing it moved to in yellow, where the
+// waveform view shows them
+fn draw_snap(
+    canvas: &mut impl Renderer,
+    wav: &WavFile,
+    played_samples: usize,
+    (from, to): (usize, usize),
+) {
+    let (width, height) = canvas.size();
+    let channels = wav.header.num_channels.max(1) as usize;
+    for (frame, color) in [
+        (from, Color::RGB(128, 128, 128)),
+        (to, Color::RGB(255, 255, 0)),
+    ] {
+        let offset = (frame * channels) as isize - played_samples as isize;
+        if (0..WAVEFORM_SAMPLES as isize).contains(&offset) {
+            let x = (offset * width as isize / WAVEFORM_SAMPLES as isize) as i32;
+            canvas.set_color(color);
+            canvas.line((x, 0), (x, height as i32));
+        }
+    }
+}
+
+// share of the old traces' brightness taken away every frame in phosphor mode, out of 255.
+// At 60 fps a trace fades out over roughly a third of a second
+const PHOSPHOR_FADE: u8 = 48;
+
+// the waveform like an analog scope: the new trace is drawn over the previous ones, which
+// fade out gradually, so transients stay visible for a moment after they scrolled past
+fn draw_phosphor(canvas: &mut impl Renderer, shown: &[i16], step: usize, palette: Palette) {
+    let trace = Trace {
+        shown,
+        step,
+        palette,
     };
+    canvas.trail(PHOSPHOR_FADE, &trace);
+}
 
-    let audio_subsystem = sdl_context.audio().unwrap();
+// the waveform's arguments, for the renderer to draw into the phosphor trail
+struct Trace<'a> {
+    shown: &'a [i16],
+    step: usize,
+    palette: Palette,
+}
 
-    // use callback since we want to syncronize the samples position in the audio buffer
-    let device = audio_subsystem.open_playback(None, &desired_spec, |_spec| player)?;
-    device.resume();
+impl Layer for Trace<'_> {
+    fn draw(&self, canvas: &mut impl Renderer) {
+        draw_waveform(canvas, self.shown, self.step, self.palette);
+    }
+}
 
-    let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("wave", 800, 600)
-        .position_centered()
-        .build()
-        .unwrap();
+// draws WAVEFORM_SAMPLES of `data` starting at `start` with the current draw color, indices
+// outside of `data` are skipped so a shifted trace can start or end mid view. Only every
+// `step`th sample is connected when the render quality is reduced
+fn draw_trace(canvas: &mut impl Renderer, data: &[i16], start: isize, step: usize) {
+    let (width, height) = canvas.size();
+    let samples_to_display = WAVEFORM_SAMPLES;
 
-    let mut canvas = window.into_canvas().build().unwrap();
+    let first = start.max(0) as usize;
+    let end = (start + samples_to_display as isize).clamp(0, data.len() as isize) as usize;
 
-    canvas.set_draw_color(Color::RGB(0, 255, 255));
-    canvas.clear();
-    canvas.present();
-    let mut event_pump = sdl_context.event_pump().unwrap();
-    device.resume();
+    if first >= end {
+        return;
+    }
+
+    let chunk = &data[first..end];
+    let skipped = (first as isize - start) as usize;
+
+    let center_y = height as i32 / 2;
+
+    for i in (0..chunk.len().saturating_sub(1)).step_by(step) {
+        let j = (i + step).min(chunk.len() - 1);
+        let x1 = ((skipped + i) as f32 / samples_to_display as f32 * width as f32) as i32;
+        let x2 = ((skipped + j) as f32 / samples_to_display as f32 * width as f32) as i32;
+
+        let y1 = center_y - (chunk[i] as i32 * height as i32 / 2 / 32768);
+        let y2 = center_y - (chunk[j] as i32 * height as i32 / 2 / 32768);
+
+        canvas.line((x1, y1), (x2, y2));
+    }
+}
+
+// the comparison file drawn translucent over the main waveform, shifted by the nudged offset
+fn draw_compare_overlay(
+    canvas: &mut impl Renderer,
+    compare: &Comparison,
+    channels: usize,
+    played_samples: usize,
+    step: usize,
+) {
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(255, 0, 255, 140));
+    let start = played_samples as isize - compare.offset * channels as isize;
+    draw_trace(canvas, &compare.wav.data, start, step);
+    canvas.set_blend(false);
+
+    let (_, height) = canvas.size();
+    let offset_ms = compare.offset as f64 * 1000.0 / compare.wav.header.sample_rate as f64;
+    let mut text = i18n::fill(
+        "overlay.compare",
+        &[
+            ("path", compare.path.to_string()),
+            ("frames", format!("{:+}", compare.offset)),
+            ("ms", format!("{offset_ms:+.2}")),
+        ],
+    );
+    if compare.audible {
+        text.push_str(&format!("  [{}]", i18n::text("overlay.listening")));
+    }
+    canvas.set_color(Color::RGB(255, 0, 255));
+    font::draw_text(canvas, 8, height as i32 - 44, 2, &text);
+}
 
-    'running: loop {
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
-        canvas.clear();
-        let played_samples = *shared_position.lock().unwrap();
-        draw_waveform(&mut canvas, &wav, played_samples);
+// overlays the sample peak and short-term rms level distributions, each bar is 1 dB wide
+fn draw_dynamics(canvas: &mut impl Renderer, dynamics: &Dynamics) {
+    let (width, height) = canvas.size();
+    let (left, right, top, bottom) = (40, width as i32 - 20, 60, height as i32 - 60);
+    let bar_width = (right - left) / dynamics::BUCKETS as i32;
 
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
-                _ => {}
+    canvas.set_blend(true);
+    let histograms = [
+        (&dynamics.sample_histogram, Color::RGBA(0, 255, 0, 160)),
+        (&dynamics.rms_histogram, Color::RGBA(255, 128, 0, 160)),
+    ];
+    for (histogram, color) in histograms {
+        let max = histogram.iter().copied().max().unwrap_or(0).max(1);
+        canvas.set_color(color);
+        for (i, &count) in histogram.iter().enumerate() {
+            let bar_height = (count as f64 / max as f64 * (bottom - top) as f64) as i32;
+            if bar_height > 0 {
+                canvas.fill_rect(Rect::new(
+                    left + i as i32 * bar_width,
+                    bottom - bar_height,
+                    (bar_width - 1).max(1) as u32,
+                    bar_height as u32,
+                ));
             }
         }
+    }
+    canvas.set_blend(false);
 
-        canvas.present();
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+    canvas.set_color(Color::RGB(255, 255, 255));
+    canvas.line((left, bottom), (right, bottom));
+    for db in (0..=dynamics::BUCKETS as i32).step_by(12) {
+        let x = left + db * bar_width;
+        canvas.line((x, bottom), (x, bottom + 4));
+        let label = format!("{}", dynamics::FLOOR_DB as i32 + db);
+        font::draw_text(
+            canvas,
+            x - font::text_width(&label, 1) / 2,
+            bottom + 8,
+            1,
+            &label,
+        );
     }
-    Ok(())
+
+    let summary = i18n::fill(
+        "overlay.dynamics",
+        &[
+            ("peak", format!("{:.1}", dynamics.peak_db)),
+            ("rms", format!("{:.1}", dynamics.rms_db)),
+            ("crest", format!("{:.1}", dynamics.crest_db)),
+        ],
+    );
+    font::draw_text(canvas, left, 20, 2, &summary);
+    let peaks = i18n::text("overlay.sample-peaks");
+    canvas.set_color(Color::RGB(0, 255, 0));
+    font::draw_text(canvas, left, bottom + 24, 1, peaks);
+    canvas.set_color(Color::RGB(255, 128, 0));
+    let rms_left = left + (font::text_width(peaks, 1) + 16).max(100);
+    font::draw_text(
+        canvas,
+        rms_left,
+        bottom + 24,
+        1,
+        i18n::text("overlay.short-term-rms"),
+    );
+}
+
+// the mini player: transport state, time and title on one line over a thin overview strip
+fn draw_mini_player(
+    canvas: &mut impl Renderer,
+    state: &str,
+    wav: &WavFile,
+    theme: &Theme,
+    classes: &[Class],
+    overview: &Overview,
+    played_samples: usize,
+) {
+    canvas.set_viewport(None);
+    let (width, height) = canvas.size();
+    let channels = wav.header.num_channels.max(1) as usize;
+    let rate = wav.header.sample_rate.max(1) as f64;
+    let mut text = format!(
+        "{state}  {} / {}",
+        timecode::format((played_samples / channels) as f64 / rate),
+        timecode::format((wav.data.len() / channels) as f64 / rate)
+    );
+    if let Some(title) = &wav.metadata.title {
+        text.push_str("  ");
+        text.push_str(title);
+    }
+    canvas.set_color(Color::RGB(255, 255, 255));
+    font::draw_text(canvas, 8, 8, 2, &text);
+
+    canvas.set_viewport(Rect::new(0, 30, width, height.saturating_sub(34).max(1)));
+    draw_overview(canvas, overview, theme, classes, &[], wav, played_samples);
+}
+
+// a bar across the middle of the view for work still running in the background
+fn draw_progress_bar(canvas: &mut impl Renderer, label: &str, progress: Progress) {
+    let (width, height) = canvas.size();
+    let bar = Rect::new(40, height as i32 / 2, width.saturating_sub(80).max(1), 16);
+    canvas.set_color(Color::RGB(255, 255, 255));
+    canvas.rect(bar);
+    let filled = (bar.width() as f32 * progress.fraction()) as u32;
+    if filled > 0 {
+        canvas.fill_rect(Rect::new(bar.x(), bar.y(), filled, bar.height()));
+    }
+    let text = format!("{label}  {:.0}%", progress.fraction() * 100.0);
+    font::draw_text(canvas, bar.x(), bar.y() - 24, 2, &text);
 }
 
-fn draw_waveform(
-    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    wav: &WAVFile,
+// the whole file at a glance, every column colored by its rms level (quiet is dark, loud is
+// bright) and stretched to its min/max peaks, with the playhead on top
+fn draw_overview(
+    canvas: &mut impl Renderer,
+    overview: &Overview,
+    theme: &Theme,
+    classes: &[Class],
+    // each set of marker frames with its color
+    markers: &[(&[usize], Color)],
+    wav: &WavFile,
     played_samples: usize,
 ) {
-    let (width, height) = canvas.output_size().unwrap();
-    let samples_to_display = 4096;
+    let (width, height) = canvas.size();
+    let center_y = height as i32 / 2;
+    for (x, column) in overview.columns.iter().enumerate() {
+        let t = (column.rms_db - overview::RMS_FLOOR_DB) / -overview::RMS_FLOOR_DB;
+        let (r, g, b) = theme.overview_colormap.color(t);
+        canvas.set_color(Color::RGB(r, g, b));
+        let top = center_y - (column.max * center_y as f32) as i32;
+        let bottom = center_y - (column.min * center_y as f32) as i32;
+        canvas.line(
+            (x as i32, top.min(center_y - 1)),
+            (x as i32, bottom.max(center_y + 1)),
+        );
+    }
+
+    // speech/music/silence band along the top edge
+    for x in 0..width as usize {
+        let Some(&class) = classes.get(x * classes.len() / width as usize) else {
+            break;
+        };
+        canvas.set_color(match class {
+            Class::Silence => Color::RGB(40, 40, 40),
+            Class::Speech => Color::RGB(60, 140, 255),
+            Class::Music => Color::RGB(255, 150, 40),
+        });
+        canvas.line((x as i32, 1), (x as i32, 5));
+    }
+
+    let frames = wav.data.len() / wav.header.num_channels.max(1) as usize;
+    for &(markers, color) in markers {
+        canvas.set_color(color);
+        for &marker in markers {
+            let x = (marker as f64 / frames.max(1) as f64 * width as f64) as i32;
+            canvas.line((x, 0), (x, height as i32));
+        }
+    }
+
+    canvas.set_color(Color::RGB(255, 255, 255));
+    canvas.line((0, 0), (width as i32, 0));
+    if !wav.data.is_empty() {
+        let x = (played_samples as f64 / wav.data.len() as f64 * width as f64) as i32;
+        canvas.line((x, 0), (x, height as i32));
+    }
+}
+
+// where `frame` falls across the waveform or spectrogram view `width` pixels wide, possibly
+// outside of it. None for the views that don't run along time
+fn view_x(
+    view: View,
+    frame: usize,
+    width: u32,
+    wav: &WavFile,
+    played_samples: usize,
+) -> Option<f32> {
+    let channels = wav.header.num_channels.max(1) as usize;
+    match view {
+        View::Waveform => {
+            let offset = (frame * channels) as isize - played_samples as isize;
+            Some(offset as f32 / WAVEFORM_SAMPLES as f32 * width as f32)
+        }
+        View::Spectrogram => {
+            let column = (frame / spectrogram::HOP) as isize;
+            let center = (played_samples / channels / spectrogram::HOP) as isize;
+            Some((column - center + width as isize / 2) as f32)
+        }
+        View::Dynamics | View::Impulse => None,
+    }
+}
+
+// the loop shaded between `left` and `right`, with a line on each edge that's in sight
+fn draw_loop_region(canvas: &mut impl Renderer, left: f32, right: f32) {
+    let (width, height) = canvas.size();
+    let clamp = |x: f32| x.clamp(0.0, width as f32) as i32;
+    let (from, to) = (clamp(left), clamp(right));
+    if to > from {
+        canvas.set_blend(true);
+        canvas.set_color(Color::RGBA(LOOP_COLOR.r, LOOP_COLOR.g, LOOP_COLOR.b, 60));
+        canvas.fill_rect(Rect::new(from, 0, (to - from) as u32, height));
+        canvas.set_blend(false);
+    }
+    canvas.set_color(LOOP_COLOR);
+    for x in [left, right] {
+        if (0.0..width as f32).contains(&x) {
+            canvas.line((x as i32, 0), (x as i32, height as i32));
+        }
+    }
+}
 
-    let start = played_samples;
-    let end = (start + samples_to_display).min(wav.data.len());
+// vertical lines for the markers that fall inside the time range the current view shows
+fn draw_markers(
+    canvas: &mut impl Renderer,
+    view: View,
+    markers: &[usize],
+    color: Color,
+    wav: &WavFile,
+    played_samples: usize,
+) {
+    let (width, height) = canvas.size();
+    canvas.set_color(color);
+    for &marker in markers {
+        let Some(x) = view_x(view, marker, width, wav, played_samples) else {
+            return;
+        };
+        if (0.0..width as f32).contains(&x) {
+            canvas.line((x as i32, 0), (x as i32, height as i32));
+        }
+    }
+}
 
-    if start >= wav.data.len() {
+// heat strip of how much the comparison file differs from the main one, every column shows the
+// worst block underneath it so short but large changes don't get averaged away
+fn draw_difference(canvas: &mut impl Renderer, difference: &[f32], theme: &Theme) {
+    let (width, height) = canvas.size();
+    if difference.is_empty() {
         return;
     }
+    for x in 0..width as usize {
+        let first = x * difference.len() / width as usize;
+        let last = ((x + 1) * difference.len() / width as usize).max(first + 1);
+        let worst = difference[first..last.min(difference.len())]
+            .iter()
+            .fold(0.0f32, |a, &b| a.max(b));
+        let (r, g, b) = theme
+            .overview_colormap
+            .color(worst / difference::MAX_DIFFERENCE_DB);
+        canvas.set_color(Color::RGB(r, g, b));
+        canvas.line((x as i32, 0), (x as i32, height as i32));
+    }
+}
 
-    let chunk = &wav.data[start..end];
+const COVER_ART_SIZE: u32 = 96;
 
-    canvas.set_draw_color(Color::RGB(0, 255, 0));
+// title and artist in the top right corner, under the cover art when there is one
+fn draw_metadata(canvas: &mut impl Renderer, metadata: &Metadata, cover_art: Option<&Image>) {
+    let (width, _) = canvas.size();
+    let right = width as i32 - 8;
+    let mut y = 8;
+    if let Some(image) = cover_art {
+        let rect = Rect::new(
+            right - COVER_ART_SIZE as i32,
+            y,
+            COVER_ART_SIZE,
+            COVER_ART_SIZE,
+        );
+        canvas.image(image, Some(rect));
+        y += COVER_ART_SIZE as i32 + 8;
+    }
 
-    let center_y = height as i32 / 2;
+    let mut lines: Vec<String> = [&metadata.title, &metadata.artist]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if let (Some(picture), None) = (&metadata.picture, cover_art) {
+        lines.push(i18n::fill(
+            "overlay.cover-art",
+            &[
+                ("mime", picture.mime.clone()),
+                ("kb", (picture.data.len() / 1024).to_string()),
+            ],
+        ));
+    }
+    canvas.set_color(Color::RGB(255, 255, 255));
+    for line in lines {
+        font::draw_text(canvas, right - font::text_width(&line, 2), y, 2, &line);
+        y += 20;
+    }
+
+    // how the file deviates from the spec, the first few of them with `wave validate` for
+    // the rest
+    let diagnostics = &metadata.diagnostics;
+    let mut lines: Vec<String> = diagnostics
+        .iter()
+        .take(MAX_DIAGNOSTICS)
+        .map(ToString::to_string)
+        .collect();
+    if diagnostics.len() > MAX_DIAGNOSTICS {
+        lines.push(i18n::fill(
+            "overlay.more-diagnostics",
+            &[("count", (diagnostics.len() - MAX_DIAGNOSTICS).to_string())],
+        ));
+    }
+    canvas.set_color(Color::RGB(255, 170, 0));
+    for line in lines {
+        font::draw_text(canvas, right - font::text_width(&line, 1), y, 1, &line);
+        y += 10;
+    }
+}
+
+// the frame under the playhead and every channel's sample value there, in the bottom right
+// corner while paused
+fn draw_sample_values(canvas: &mut impl Renderer, wav: &WavFile, played_samples: usize) {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let frame = played_samples / channels;
+    let Some(values) = wav.data.get(frame * channels..(frame + 1) * channels) else {
+        return;
+    };
+    let mut lines = vec![i18n::fill("overlay.frame", &[("frame", frame.to_string())])];
+    lines.extend(values.iter().enumerate().map(|(channel, value)| {
+        i18n::fill(
+            "overlay.sample-value",
+            &[
+                ("channel", (channel + 1).to_string()),
+                ("value", format!("{value:+6}")),
+            ],
+        )
+    }));
 
-    for i in 0..chunk.len().saturating_sub(1) {
-        let x1 = (i as f32 / samples_to_display as f32 * width as f32) as i32;
-        let x2 = ((i + 1) as f32 / samples_to_display as f32 * width as f32) as i32;
+    let (width, height) = canvas.size();
+    canvas.set_color(Color::RGB(255, 255, 0));
+    for (i, line) in lines.iter().rev().enumerate() {
+        let x = width as i32 - 8 - font::text_width(line, 2);
+        font::draw_text(canvas, x, height as i32 - 22 - i as i32 * 20, 2, line);
+    }
+}
 
-        let y1 = center_y - (chunk[i] as i32 * height as i32 / 2 / 32768);
-        let y2 = center_y - (chunk[i + 1] as i32 * height as i32 / 2 / 32768);
+// the frame under the cursor in the waveform view: its index, time and every channel's value
+// as an integer and in dBFS, for checking exactly what a DSP stage wrote
+fn draw_waveform_readout(
+    canvas: &mut impl Renderer,
+    wav: &WavFile,
+    played_samples: usize,
+    x: i32,
+    y: i32,
+) {
+    let (width, height) = canvas.size();
+    let channels = wav.header.num_channels.max(1) as usize;
+    let frame = waveform_frame(x, width, wav, played_samples);
+    let Some(values) = wav.data.get(frame * channels..(frame + 1) * channels) else {
+        return;
+    };
+    let mut lines = vec![format!(
+        "frame {frame}  {}",
+        timecode::format(frame as f64 / wav.header.sample_rate as f64)
+    )];
+    lines.extend(values.iter().enumerate().map(|(channel, &value)| {
+        let level = if value == 0 {
+            "-inf".to_string()
+        } else {
+            format!(
+                "{:.1}",
+                dynamics::to_db(value.unsigned_abs() as f32 / 32768.0)
+            )
+        };
+        format!("ch{} {value:+6}  {level} dBFS", channel + 1)
+    }));
 
-        canvas.draw_line((x1, y1), (x2, y2)).ok();
+    canvas.set_color(Color::RGB(128, 128, 128));
+    canvas.line((x, 0), (x, height as i32));
+
+    // keep the tooltip inside the pane when hovering near the right or bottom edge
+    let box_width = lines
+        .iter()
+        .map(|line| font::text_width(line, 2))
+        .max()
+        .unwrap_or(0)
+        + 6;
+    let box_height = lines.len() as i32 * 20 + 4;
+    let left = (x + 12).min(width as i32 - box_width - 4);
+    let top = (y + 12).min(height as i32 - box_height - 4);
+    canvas.set_color(Color::RGB(0, 0, 0));
+    canvas.fill_rect(Rect::new(left, top, box_width as u32, box_height as u32));
+    canvas.set_color(Color::RGB(255, 255, 0));
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(canvas, left + 3, top + 3 + i as i32 * 20, 2, line);
     }
 }
+
+// playback state and whatever is currently altering the sound, in the bottom left corner
+fn draw_processing_status(
+    canvas: &mut impl Renderer,
+    settings: &Settings,
+    ended: bool,
+    xruns: usize,
+    quality: &RenderQuality,
+    phosphor: bool,
+    // frames the last seek was moved while snapping to zero crossings
+    zero_snap: Option<isize>,
+) {
+    let mut lines = Vec::new();
+    if ended {
+        lines.push(i18n::text("status.end-of-file").to_string());
+    } else if !settings.playing {
+        lines.push(i18n::text("status.paused").to_string());
+    }
+    if xruns > 0 {
+        lines.push(i18n::fill(
+            "status.dropouts",
+            &[("count", xruns.to_string())],
+        ));
+    }
+    if phosphor {
+        lines.push(i18n::text("status.phosphor").to_string());
+    }
+    if let Some(moved) = zero_snap {
+        lines.push(i18n::fill(
+            "status.zero-snap",
+            &[("frames", format!("{moved:+}"))],
+        ));
+    }
+    if quality.reduced() {
+        lines.push(i18n::fill(
+            "status.reduced-detail",
+            &[("step", quality.step().to_string())],
+        ));
+    }
+    if settings.speed != 1.0 {
+        lines.push(i18n::fill(
+            "status.speed",
+            &[("speed", settings.speed.to_string())],
+        ));
+    }
+    let gain = [("db", format!("{:+.1}", dynamics::to_db(settings.gain)))];
+    if settings.muted {
+        lines.push(i18n::fill("status.muted", &gain));
+    } else if settings.gain != 1.0 {
+        lines.push(i18n::fill("status.gain", &gain));
+    }
+    if settings.loop_file && settings.loop_range.is_none() {
+        lines.push(i18n::text("status.loop-file").to_string());
+    }
+    if let Some(range) = &settings.loop_range {
+        lines.push(i18n::fill(
+            "status.loop",
+            &[
+                ("start", range.start.to_string()),
+                ("end", range.end.to_string()),
+            ],
+        ));
+    }
+    let tone = settings.guide_tone;
+    if tone.enabled {
+        lines.push(i18n::fill(
+            "status.tone",
+            &[
+                ("frequency", format!("{:.0}", tone.frequency)),
+                ("db", format!("{:.0}", tone.level_db)),
+            ],
+        ));
+    }
+    if settings.hum_filter {
+        lines.push(i18n::fill(
+            "status.hum-filter",
+            &[("frequency", format!("{:.0}", settings.mains))],
+        ));
+    }
+    if settings.rumble_filter {
+        lines.push(i18n::fill(
+            "status.rumble-filter",
+            &[("frequency", format!("{:.0}", filter::RUMBLE_CUTOFF))],
+        ));
+    }
+    if settings.air_filter {
+        lines.push(i18n::fill(
+            "status.air-filter",
+            &[("frequency", format!("{:.0}", filter::AIR_CUTOFF))],
+        ));
+    }
+    if let Some(name) = &settings.impulse_response
+        && settings.convolution
+    {
+        lines.push(i18n::fill(
+            "status.impulse-response",
+            &[("name", name.clone())],
+        ));
+    }
+    if let Some(name) = &settings.plugin
+        && settings.plugin_enabled
+    {
+        lines.push(i18n::fill("status.plugin", &[("name", name.clone())]));
+        if let Some(parameter) = settings.parameters.get(settings.parameter) {
+            lines.push(format!(
+                "  {} {:.3} ({}/{})",
+                parameter.name,
+                parameter.value,
+                settings.parameter + 1,
+                settings.parameters.len()
+            ));
+        }
+    }
+    match settings.binaural {
+        Some(true) => lines.push(i18n::text("status.binaural").to_string()),
+        Some(false) => lines.push(i18n::text("status.downmix").to_string()),
+        None => {}
+    }
+
+    let (_, height) = canvas.size();
+    canvas.set_color(Color::RGB(255, 255, 0));
+    for (i, line) in lines.iter().rev().enumerate() {
+        font::draw_text(canvas, 8, height as i32 - 22 - i as i32 * 20, 2, line);
+    }
+}
+
+// the F3 debug overlay: how well the ui and the audio callback keep up
+fn draw_stats(canvas: &mut impl Renderer, frame_rate: &FrameRate, player: &Player, xruns: usize) {
+    let mut lines = vec![i18n::fill(
+        "stats.fps",
+        &[("fps", format!("{:.0}", frame_rate.fps()))],
+    )];
+    match player.callback_stats() {
+        Some(stats) => {
+            lines.push(i18n::fill(
+                "stats.callback",
+                &[
+                    (
+                        "took",
+                        format!("{:.2}", stats.duration.as_secs_f64() * 1000.0),
+                    ),
+                    (
+                        "buffer",
+                        format!("{:.2}", stats.buffer.as_secs_f64() * 1000.0),
+                    ),
+                    (
+                        "percent",
+                        format!(
+                            "{:.0}",
+                            stats.duration.as_secs_f64() / stats.buffer.as_secs_f64() * 100.0
+                        ),
+                    ),
+                ],
+            ));
+            lines.push(i18n::fill(
+                "stats.buffer-fill",
+                &[("percent", format!("{:.0}", stats.fill * 100.0))],
+            ));
+        }
+        None => lines.push(i18n::text("stats.no-buffer").to_string()),
+    }
+    lines.push(i18n::fill("stats.xruns", &[("count", xruns.to_string())]));
+
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(Rect::new(4, 4, 420, lines.len() as u32 * 20 + 8));
+    canvas.set_blend(false);
+    canvas.set_color(Color::RGB(255, 255, 255));
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(canvas, 8, 8 + i as i32 * 20, 2, line);
+    }
+}
+
+// the frame under pixel `x` of the overview strip
+fn overview_frame(x: i32, width: u32, wav: &WavFile) -> usize {
+    let frames = wav.data.len() / wav.header.num_channels.max(1) as usize;
+    let x = x.clamp(0, width as i32) as usize;
+    (x * frames / width.max(1) as usize).min(frames.saturating_sub(1))
+}
+
+// the frame under pixel `x` of the waveform view while it shows the samples from `start`,
+// kept within the file
+fn waveform_frame(x: i32, width: u32, wav: &WavFile, start: usize) -> usize {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let x = x.clamp(0, width as i32) as usize;
+    let sample = start + x * WAVEFORM_SAMPLES / width.max(1) as usize;
+    (sample / channels).min((wav.data.len() / channels).saturating_sub(1))
+}
+
+// index of the spectrogram column under pixel `x`, the view is centered on the playhead so
+// the result is negative or past the end near the edges of the file
+fn spectrogram_column(x: i32, width: u32, wav: &WavFile, played_samples: usize) -> isize {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let center = (played_samples / channels / spectrogram::HOP) as isize;
+    center + x as isize - width as isize / 2
+}
+
+fn draw_spectrogram(
+    canvas: &mut impl Renderer,
+    image: &mut Image,
+    spectrogram: &mut Spectrogram,
+    theme: &Theme,
+    wav: &WavFile,
+    played_samples: usize,
+    step: usize,
+) {
+    let (width, height) = canvas.size();
+    for x in 0..image.width as usize {
+        // at reduced quality one transformed column is stretched over `step` pixels
+        let x_column = x - x % step;
+        let column = spectrogram_column(x_column as i32, width, wav, played_samples);
+        let column = (column >= 0 && (column as usize) < spectrogram.len())
+            .then(|| spectrogram.column(wav, column as usize));
+        for row in 0..spectrogram::BINS {
+            // low frequencies at the bottom
+            let color = match column {
+                Some(bins) => theme.spectrogram_color(bins[spectrogram::BINS - 1 - row]),
+                None => (0, 0, 0),
+            };
+            image.set(x, row, color);
+        }
+    }
+    canvas.image(image, None);
+
+    canvas.set_color(Color::RGB(255, 255, 255));
+    let center_x = width as i32 / 2;
+    canvas.line((center_x, 0), (center_x, height as i32));
+}
+
+// shades the parts of the spectrum the rumble and air filters are currently cutting
+fn draw_filtered_bands(canvas: &mut impl Renderer, settings: &Settings, wav: &WavFile) {
+    let (width, height) = canvas.size();
+    let nyquist = wav.header.sample_rate as f32 / 2.0;
+    let frequency_y = |frequency: f32| height as i32 - (frequency / nyquist * height as f32) as i32;
+
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(255, 64, 64, 96));
+    if settings.rumble_filter {
+        // at least a few pixels, 30 Hz is less than one row on a linear axis
+        let top = frequency_y(filter::RUMBLE_CUTOFF).min(height as i32 - 4);
+        canvas.fill_rect(Rect::new(0, top, width, (height as i32 - top) as u32));
+    }
+    if settings.air_filter && filter::AIR_CUTOFF < nyquist {
+        let bottom = frequency_y(filter::AIR_CUTOFF);
+        canvas.fill_rect(Rect::new(0, 0, width, bottom as u32));
+    }
+    canvas.set_blend(false);
+}
+
+// marks the integer multiples of the frequency under the cursor, e.g. hovering 60 Hz mains hum
+// lights up 120, 180, 240 Hz and so on up to nyquist
+fn draw_harmonics(canvas: &mut impl Renderer, wav: &WavFile, x: i32, y: i32) {
+    let (width, height) = canvas.size();
+    if y < 0 || y >= height as i32 {
+        return;
+    }
+
+    let nyquist = wav.header.sample_rate as f32 / 2.0;
+    let fundamental = (height as i32 - 1 - y) as f32 / height as f32 * nyquist;
+    // below a few pixels apart the lines would just fill the whole view
+    if fundamental / nyquist * (height as f32) < 4.0 {
+        return;
+    }
+
+    canvas.set_color(Color::RGB(255, 0, 255));
+    let mut harmonic = 1;
+    while fundamental * harmonic as f32 <= nyquist {
+        let harmonic_y =
+            height as i32 - 1 - (fundamental * harmonic as f32 / nyquist * height as f32) as i32;
+        // dashed so the spectrogram underneath stays readable
+        for dash_x in (0..width as i32).step_by(8) {
+            canvas.line((dash_x, harmonic_y), (dash_x + 3, harmonic_y));
+        }
+        font::draw_text(canvas, x - 40, harmonic_y - 8, 1, &format!("{harmonic}x"));
+        harmonic += 1;
+    }
+}
+
+fn draw_spectrogram_readout(
+    canvas: &mut impl Renderer,
+    spectrogram: &mut Spectrogram,
+    wav: &WavFile,
+    played_samples: usize,
+    x: i32,
+    y: i32,
+) {
+    let (width, height) = canvas.size();
+    let column = spectrogram_column(x, width, wav, played_samples);
+    if column < 0 || column as usize >= spectrogram.len() || y < 0 || y >= height as i32 {
+        return;
+    }
+
+    let bin = (height as i32 - 1 - y) as usize * spectrogram::BINS / height as usize;
+    let db = spectrogram.column(wav, column as usize)[bin];
+    let frequency = spectrogram::bin_frequency(bin, wav.header.sample_rate);
+    let text = format!("{frequency:.1} Hz  {db:.1} dB");
+
+    // keep the readout inside the window when hovering near the right or bottom edge
+    let scale = 2;
+    let text_width = font::text_width(&text, scale);
+    let text_height = font::GLYPH_HEIGHT * scale;
+    let text_x = (x + 12).min(width as i32 - text_width - 4);
+    let text_y = (y + 12).min(height as i32 - text_height - 4);
+
+    canvas.set_color(Color::RGB(0, 0, 0));
+    canvas.fill_rect(Rect::new(
+        text_x - 3,
+        text_y - 3,
+        (text_width + 6) as u32,
+        (text_height + 6) as u32,
+    ));
+    canvas.set_color(Color::RGB(255, 255, 0));
+    font::draw_text(canvas, text_x, text_y, scale, &text);
+}
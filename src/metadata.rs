@@ -0,0 +1,71 @@
+use crate::bext::Bext;
+use crate::cue::Cue;
+use crate::diagnostic::Diagnostic;
+use crate::riff::Chunk;
+use crate::smpl::SampleLoop;
+
+// descriptive tags gathered from whichever metadata chunks a file carries
+#[derive(Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub comment: Option<String>,
+    pub date: Option<String>,
+    pub picture: Option<Picture>,
+    // raw xml of the iXML production metadata and the EBU axml chunk
+    pub ixml: Option<String>,
+    pub axml: Option<String>,
+    // the broadcast wave description, origin and coding history
+    pub bext: Option<Bext>,
+    // markers from the cue chunk, by position
+    pub cues: Vec<Cue>,
+    // sustain loops from the smpl chunk
+    pub loops: Vec<SampleLoop>,
+    // how the chunks deviate from the spec, only wav files are checked
+    pub diagnostics: Vec<Diagnostic>,
+    // every chunk of a wav file besides fmt, data, fact and ds64 as it was read, in file
+    // order, so saving the samples again carries them through
+    pub chunks: Vec<Chunk>,
+}
+
+// embedded cover art, still encoded
+pub struct Picture {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+impl Metadata {
+    // fills in whatever is still missing from `other`, the first chunk to set a field wins
+    pub fn merge(&mut self, other: Metadata) {
+        self.title = self.title.take().or(other.title);
+        self.artist = self.artist.take().or(other.artist);
+        self.album = self.album.take().or(other.album);
+        self.comment = self.comment.take().or(other.comment);
+        self.date = self.date.take().or(other.date);
+        self.picture = self.picture.take().or(other.picture);
+        self.ixml = self.ixml.take().or(other.ixml);
+        self.axml = self.axml.take().or(other.axml);
+        self.bext = self.bext.take().or(other.bext);
+        if self.cues.is_empty() {
+            self.cues = other.cues;
+        }
+        if self.loops.is_empty() {
+            self.loops = other.loops;
+        }
+    }
+
+    // (label, value) pairs of every text field that is set, in display order
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("date", &self.date),
+            ("comment", &self.comment),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_deref().map(|value| (label, value)))
+        .collect()
+    }
+}
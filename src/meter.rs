@@ -0,0 +1,117 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::WavFile;
+use crate::dynamics::to_db;
+use crate::font;
+use crate::render::Renderer;
+use crate::theme::Palette;
+
+// audio just before the playhead the levels are measured over, about what a vu meter
+// integrates over
+const WINDOW_SECONDS: f32 = 0.3;
+// bottom of the scale
+const FLOOR_DB: f32 = -60.0;
+// slim enough to park beside another application
+pub const CHANNEL_WIDTH: u32 = 28;
+pub const HEIGHT: u32 = 320;
+
+// peak and rms level of every channel in dBFS
+pub struct Levels {
+    pub peak_db: Vec<f32>,
+    pub rms_db: Vec<f32>,
+    // whether a sample reached full scale
+    pub clipped: Vec<bool>,
+}
+
+impl Levels {
+    pub fn at(wav: &WavFile, played_samples: usize) -> Self {
+        let channels = wav.header.num_channels.max(1) as usize;
+        let window = (wav.header.sample_rate as f32 * WINDOW_SECONDS) as usize * channels;
+        let end = played_samples.min(wav.data.len()) / channels * channels;
+        let samples = &wav.data[end.saturating_sub(window)..end];
+
+        let mut peak = vec![0.0f32; channels];
+        let mut square = vec![0.0f64; channels];
+        let mut clipped = vec![false; channels];
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                clipped[channel] |= sample == i16::MAX || sample == i16::MIN;
+                let value = sample as f32 / 32768.0;
+                peak[channel] = peak[channel].max(value.abs());
+                square[channel] += (value * value) as f64;
+            }
+        }
+        let frames = (samples.len() / channels).max(1) as f64;
+        Self {
+            peak_db: peak.into_iter().map(to_db).collect(),
+            rms_db: square
+                .into_iter()
+                .map(|square| to_db((square / frames).sqrt() as f32))
+                .collect(),
+            clipped,
+        }
+    }
+}
+
+// one bar per channel filling the viewport: the rms level solid, the peak as a line above it
+// and a clip indicator at the top
+pub fn draw(canvas: &mut impl Renderer, levels: &Levels, palette: Palette) {
+    let (width, height) = canvas.size();
+    let channels = levels.peak_db.len().max(1) as u32;
+    let bar_width = (width / channels).saturating_sub(6).max(1);
+    let indicator = Rect::new(0, 4, bar_width, font::GLYPH_HEIGHT as u32 + 4);
+    let top = indicator.bottom() + 4;
+    let bottom = height as i32 - 24;
+    let y = |db: f32| {
+        let t = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+        bottom - (t * (bottom - top) as f32) as i32
+    };
+
+    for (channel, (&peak_db, &rms_db)) in levels.peak_db.iter().zip(&levels.rms_db).enumerate() {
+        let x = (channel as u32 * width / channels) as i32 + 3;
+        canvas.set_color(Color::RGB(40, 40, 40));
+        canvas.fill_rect(Rect::new(x, top, bar_width, (bottom - top) as u32));
+
+        // clipping lights the box above the bar and spells it out, which reads the same in
+        // any palette
+        let indicator = Rect::new(x, indicator.y(), indicator.width(), indicator.height());
+        if levels.clipped[channel] {
+            canvas.set_color(palette.clip());
+            canvas.fill_rect(indicator);
+            canvas.set_color(Color::RGB(0, 0, 0));
+            let text_x = x + (bar_width as i32 - font::text_width("CLIP", 1)) / 2;
+            font::draw_text(canvas, text_x, indicator.y() + 2, 1, "CLIP");
+        } else {
+            canvas.set_color(Color::RGB(40, 40, 40));
+            canvas.rect(indicator);
+        }
+
+        // green up to -18, yellow up to -6 and red above in the standard palette, like most
+        // digital meters
+        let [low, middle, high] = palette.meter();
+        let color = match rms_db {
+            db if db > -6.0 => high,
+            db if db > -18.0 => middle,
+            _ => low,
+        };
+        canvas.set_color(color);
+        let rms_y = y(rms_db);
+        canvas.fill_rect(Rect::new(
+            x,
+            rms_y,
+            bar_width,
+            (bottom - rms_y).max(0) as u32,
+        ));
+        canvas.set_color(Color::RGB(255, 255, 255));
+        let peak_y = y(peak_db);
+        canvas.line((x, peak_y), (x + bar_width as i32 - 1, peak_y));
+
+        let label = if peak_db <= FLOOR_DB {
+            "-inf".to_string()
+        } else {
+            format!("{peak_db:.0}")
+        };
+        font::draw_text(canvas, x, bottom + 8, 1, &label);
+    }
+}
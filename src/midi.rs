@@ -0,0 +1,62 @@
+// a note with its start and end in seconds
+pub struct Note {
+    pub start: f64,
+    pub end: f64,
+    pub key: u8,
+    pub velocity: u8,
+}
+
+const TICKS_PER_QUARTER: u16 = 480;
+// 120 bpm, so a second is exactly two quarters
+const MICROSECONDS_PER_QUARTER: u32 = 500_000;
+const TICKS_PER_SECOND: f64 = TICKS_PER_QUARTER as f64 * 2.0;
+
+// a format 0 standard midi file with every note on channel 1
+pub fn write_smf(notes: &[Note]) -> Vec<u8> {
+    // (tick, order, bytes), note offs sort before note ons on the same tick so repeated
+    // notes don't cut each other short
+    let mut events: Vec<(u64, u8, [u8; 3])> = Vec::new();
+    for note in notes {
+        let start = (note.start * TICKS_PER_SECOND).round() as u64;
+        let end = ((note.end * TICKS_PER_SECOND).round() as u64).max(start + 1);
+        events.push((start, 1, [0x90, note.key, note.velocity.max(1)]));
+        events.push((end, 0, [0x80, note.key, 0]));
+    }
+    events.sort_by_key(|&(tick, order, _)| (tick, order));
+
+    let mut track = Vec::new();
+    // tempo meta event
+    track.extend([0x00, 0xff, 0x51, 0x03]);
+    track.extend(&MICROSECONDS_PER_QUARTER.to_be_bytes()[1..]);
+    let mut last_tick = 0;
+    for (tick, _, bytes) in events {
+        write_variable_length(&mut track, (tick - last_tick) as u32);
+        track.extend(bytes);
+        last_tick = tick;
+    }
+    // end of track
+    track.extend([0x00, 0xff, 0x2f, 0x00]);
+
+    let mut smf = Vec::new();
+    smf.extend(b"MThd");
+    smf.extend(6u32.to_be_bytes());
+    smf.extend(0u16.to_be_bytes()); // format 0
+    smf.extend(1u16.to_be_bytes()); // one track
+    smf.extend(TICKS_PER_QUARTER.to_be_bytes());
+    smf.extend(b"MTrk");
+    smf.extend((track.len() as u32).to_be_bytes());
+    smf.extend(track);
+    smf
+}
+
+// midi's variable length quantity: 7 bits per byte, most significant group first, with the
+// high bit set on every byte but the last
+fn write_variable_length(out: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
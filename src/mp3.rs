@@ -0,0 +1,143 @@
+// MPEG Layer III through the puremp3 decoder, only built with the `mp3` feature. The decoder
+// hands out float frames, which are dithered down to 16 bits like float wav samples
+
+use std::io::{self, BufReader, Cursor, Read};
+
+use crate::metadata::Metadata;
+use crate::reader::{Dither, truncated};
+use crate::{AudioSource, Header, id3};
+
+type Stream<R> = io::Chain<Cursor<Vec<u8>>, BufReader<R>>;
+
+/// Decodes an MP3 stream a frame at a time like [`crate::WavReader`] reads a wav file. The
+/// header is filled in from the first frame, as 16-bit PCM. An MP3 doesn't say how long it
+/// is, so the sizes stay 0.
+pub struct Mp3Reader<R: Read> {
+    decoder: puremp3::Mp3Decoder<Stream<R>>,
+    header: Header,
+    // what's left of the last decoded frame, already 16-bit and interleaved
+    pending: Vec<i16>,
+    read: usize,
+    dither: Dither,
+    metadata: Metadata,
+}
+
+impl<R: Read> Mp3Reader<R> {
+    /// Reads the ID3v2 tag in front of the audio, if there is one, and decodes the first
+    /// frame.
+    pub fn new(reader: R) -> Result<Self, Box<dyn std::error::Error + 'static>> {
+        let mut reader = BufReader::new(reader);
+        let mut start = Vec::with_capacity(10);
+        reader.by_ref().take(10).read_to_end(&mut start)?;
+        let mut metadata = Metadata::default();
+        if start.len() == 10 && start.starts_with(b"ID3") {
+            // a syncsafe size of what follows the 10 byte header, plus a footer if flagged
+            let size = start[6..10]
+                .iter()
+                .fold(0, |size, &b| size << 7 | (b & 0x7f) as usize);
+            let footer = if start[5] & 0x10 != 0 { 10 } else { 0 };
+            start.resize(10 + size + footer, 0);
+            reader.read_exact(&mut start[10..]).map_err(truncated)?;
+            metadata = id3::parse(&start);
+            start.clear();
+        }
+        let mut decoder = puremp3::Mp3Decoder::new(Cursor::new(start).chain(reader));
+        let frame = decoder.next_frame()?;
+        let channels = frame.header.channels.num_channels() as u16;
+        let sample_rate = frame.header.sample_rate.hz();
+        let header = Header {
+            chunk_id: "MPEG".into(),
+            format: "MP3".into(),
+            audio_format: 1,
+            num_channels: channels,
+            sample_rate,
+            byte_rate: sample_rate * channels as u32 * 2,
+            block_align: channels * 2,
+            bits_per_sample: 16,
+            ..Header::default()
+        };
+        let mut reader = Self {
+            decoder,
+            header,
+            pending: Vec::new(),
+            read: 0,
+            dither: Dither::default(),
+            metadata,
+        };
+        reader.convert(&frame);
+        Ok(reader)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Always 0, the length isn't known until the last frame is decoded.
+    pub fn data_size(&self) -> u64 {
+        0
+    }
+
+    pub fn remaining(&self) -> u64 {
+        0
+    }
+
+    /// Decodes the next samples into `out`, returning how many, 0 after the last frame.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        while self.read == self.pending.len() {
+            match self.decoder.next_frame() {
+                Ok(frame) => self.convert(&frame),
+                // the decoder reads until it finds a frame, so the end of the stream is the
+                // end of the file
+                Err(puremp3::Error::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(0);
+                }
+                Err(puremp3::Error::IoError(e)) => return Err(e),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            }
+        }
+        let count = out.len().min(self.pending.len() - self.read);
+        out[..count].copy_from_slice(&self.pending[self.read..self.read + count]);
+        self.read += count;
+        Ok(count)
+    }
+
+    // a decoded frame into `pending`. The decoder fills both channels of a mono stream
+    fn convert(&mut self, frame: &puremp3::Frame) {
+        let channels = self.header.num_channels as usize;
+        self.pending.clear();
+        self.read = 0;
+        for i in 0..frame.num_samples {
+            for channel in &frame.samples[..channels] {
+                self.pending
+                    .push(self.dither.quantize(channel[i] * 32768.0));
+            }
+        }
+    }
+
+    /// The ID3v2 tags. They come in front of the audio, so nothing is left to read.
+    pub fn finish(self) -> io::Result<Metadata> {
+        Ok(self.metadata)
+    }
+}
+
+impl<R: Read> AudioSource for Mp3Reader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
@@ -0,0 +1,189 @@
+use std::{error, fs};
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::json::{self, Value};
+use crate::render::Renderer;
+use crate::{WavFile, font, i18n, timecode};
+
+// free-text notes pinned to positions in a file, for reviewing it. They're kept next to it in
+// `<file>.notes.json`, grouped under a hash of the audio: a re-rendered file starts with no
+// notes while the ones on the earlier render stay in the file
+pub struct Note {
+    pub frame: usize,
+    pub text: String,
+}
+
+pub struct Notes {
+    sidecar: String,
+    hash: String,
+    pub notes: Vec<Note>,
+    // every hash in the sidecar, the notes of this one are written back over their entry.
+    // None if the sidecar couldn't be parsed, it's left alone then
+    document: Option<Vec<(String, Value)>>,
+}
+
+impl Notes {
+    pub fn load(path: &str, wav: &WavFile) -> Self {
+        let sidecar = format!("{path}.notes.json");
+        let hash = content_hash(wav);
+        let document = match fs::read_to_string(&sidecar) {
+            Err(_) => Some(Vec::new()),
+            Ok(text) => match json::parse(&text) {
+                Ok(Value::Object(fields)) => Some(fields),
+                Ok(_) => {
+                    eprintln!("{sidecar}: {}", i18n::text("notes.not-an-object"));
+                    None
+                }
+                Err(e) => {
+                    eprintln!("{sidecar}: {e}");
+                    None
+                }
+            },
+        };
+        let mut notes: Vec<Note> = document
+            .iter()
+            .flatten()
+            .find(|(key, _)| *key == hash)
+            .and_then(|(_, value)| value.as_array())
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|note| {
+                Some(Note {
+                    frame: note.get("frame")?.as_f64()? as usize,
+                    text: note.get("text")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+        notes.sort_by_key(|note| note.frame);
+        Self {
+            sidecar,
+            hash,
+            notes,
+            document,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        frame: usize,
+        text: &str,
+        sample_rate: u32,
+    ) -> Result<(), Box<dyn error::Error + 'static>> {
+        let at = self.notes.partition_point(|note| note.frame <= frame);
+        self.notes.insert(
+            at,
+            Note {
+                frame,
+                text: text.to_string(),
+            },
+        );
+        self.save(sample_rate)
+    }
+
+    // removes the note closest to `frame`, if there are any
+    pub fn remove_nearest(
+        &mut self,
+        frame: usize,
+        sample_rate: u32,
+    ) -> Result<(), Box<dyn error::Error + 'static>> {
+        let Some(nearest) =
+            (0..self.notes.len()).min_by_key(|&i| self.notes[i].frame.abs_diff(frame))
+        else {
+            return Ok(());
+        };
+        self.notes.remove(nearest);
+        self.save(sample_rate)
+    }
+
+    fn save(&mut self, sample_rate: u32) -> Result<(), Box<dyn error::Error + 'static>> {
+        let Some(document) = &mut self.document else {
+            return Err(format!("{}: not valid json, leaving it as it is", self.sidecar).into());
+        };
+        // the time is only there for people reading the file, the frame is what's read back
+        let notes = Value::Array(
+            self.notes
+                .iter()
+                .map(|note| {
+                    Value::Object(vec![
+                        ("frame".to_string(), Value::Number(note.frame as f64)),
+                        (
+                            "time".to_string(),
+                            Value::String(timecode::format(note.frame as f64 / sample_rate as f64)),
+                        ),
+                        ("text".to_string(), Value::String(note.text.clone())),
+                    ])
+                })
+                .collect(),
+        );
+        match document.iter_mut().find(|(key, _)| *key == self.hash) {
+            Some((_, entry)) => *entry = notes,
+            None => document.push((self.hash.clone(), notes)),
+        }
+        document.retain(|(_, notes)| notes.as_array().is_none_or(|notes| !notes.is_empty()));
+        if document.is_empty() {
+            fs::remove_file(&self.sidecar).ok();
+            return Ok(());
+        }
+        let text = Value::Object(document.clone()).to_string();
+        fs::write(&self.sidecar, text + "\n").map_err(|e| format!("{}: {e}", self.sidecar))?;
+        Ok(())
+    }
+
+    // the note whose flag is under `x` on an overview `width` pixels wide
+    pub fn at(&self, x: i32, width: u32, frames: usize) -> Option<&Note> {
+        self.notes
+            .iter()
+            .find(|note| (flag_x(note.frame, width, frames) - x).abs() <= FLAG_WIDTH)
+    }
+}
+
+// fnv-1a over the format and the samples, so the same audio in a new container keeps its notes
+fn content_hash(wav: &WavFile) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let header = [
+        wav.header.num_channels.to_le_bytes().as_slice(),
+        wav.header.sample_rate.to_le_bytes().as_slice(),
+    ]
+    .concat();
+    let samples = wav.data.iter().flat_map(|sample| sample.to_le_bytes());
+    for byte in header.into_iter().chain(samples) {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+const FLAG_WIDTH: i32 = 6;
+
+fn flag_x(frame: usize, width: u32, frames: usize) -> i32 {
+    (frame as f64 / frames.max(1) as f64 * width as f64) as i32
+}
+
+// a flag on the overview for every note
+pub fn draw_flags(canvas: &mut impl Renderer, notes: &Notes, frames: usize) {
+    let (width, height) = canvas.size();
+    canvas.set_color(Color::RGB(255, 120, 200));
+    for note in &notes.notes {
+        let x = flag_x(note.frame, width, frames);
+        canvas.line((x, 0), (x, height as i32));
+        canvas.fill_rect(Rect::new(x, 0, FLAG_WIDTH as u32, 5));
+    }
+}
+
+// the text of a hovered flag, right above the strip it's on
+pub fn draw_note(canvas: &mut impl Renderer, note: &Note, sample_rate: u32, x: i32, bottom: i32) {
+    let (width, _) = canvas.size();
+    let text = format!(
+        "{}  {}",
+        timecode::format(note.frame as f64 / sample_rate as f64),
+        note.text
+    );
+    let text_width = font::text_width(&text, 2);
+    let left = x.min(width as i32 - text_width - 10).max(0);
+    let top = bottom - 24;
+    canvas.set_color(Color::RGB(0, 0, 0));
+    canvas.fill_rect(Rect::new(left, top, text_width as u32 + 8, 22));
+    canvas.set_color(Color::RGB(255, 120, 200));
+    font::draw_text(canvas, left + 4, top + 4, 2, &text);
+}
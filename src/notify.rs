@@ -0,0 +1,49 @@
+use std::process::Command;
+use std::thread;
+
+// a desktop notification, shown through the tool every desktop already has for scripts so no
+// notification library has to be linked in: notify-send on linux and the bsds, osascript on
+// macos. Showing one never fails loudly, a desktop without a notification daemon just
+// doesn't get any
+pub struct Notification {
+    summary: String,
+    body: String,
+}
+
+impl Notification {
+    pub fn new(summary: &str) -> Self {
+        Self {
+            summary: summary.to_string(),
+            body: String::new(),
+        }
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    pub fn show(&self) {
+        let mut command = if cfg!(target_os = "macos") {
+            let mut command = Command::new("osascript");
+            command.arg("-e").arg(format!(
+                "display notification {} with title {}",
+                applescript_string(&self.body),
+                applescript_string(&self.summary)
+            ));
+            command
+        } else {
+            let mut command = Command::new("notify-send");
+            command.args(["--app-name=wave", &self.summary, &self.body]);
+            command
+        };
+        // reaped on a thread of its own so the caller never waits on the desktop
+        if let Ok(mut child) = command.spawn() {
+            thread::spawn(move || child.wait());
+        }
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,305 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use sdl2::audio::AudioCallback;
+
+use crate::WavFile;
+use crate::player::{AudioPlayer, Clock, Player};
+
+// a clock that only moves when advanced by hand
+#[derive(Clone, Default)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        self.0.fetch_add(by.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
+
+// stands in for the sdl audio device: nothing is played, every buffer the callback fills is
+// handed back and time only passes as buffers are rendered, so playback logic can be tested
+// deterministically
+pub struct OfflineDevice {
+    callback: AudioPlayer,
+    clock: ManualClock,
+    sample_rate: u32,
+    channels: usize,
+}
+
+impl OfflineDevice {
+    pub fn open(wav: &WavFile) -> (Player, Self) {
+        let clock = ManualClock::default();
+        let (player, callback) = Player::with_clock(wav, None, Arc::new(clock.clone()));
+        let device = Self {
+            callback,
+            clock,
+            sample_rate: wav.header.sample_rate,
+            channels: wav.header.num_channels.max(1) as usize,
+        };
+        (player, device)
+    }
+
+    // one buffer of `frames` frames, requested exactly when the previous one ran out
+    pub fn render(&mut self, frames: usize) -> Vec<i16> {
+        self.render_late(frames, Duration::ZERO)
+    }
+
+    // one buffer requested `delay` after the previous one ran out
+    pub fn render_late(&mut self, frames: usize, delay: Duration) -> Vec<i16> {
+        let buffer = Duration::from_secs_f64(frames as f64 / self.sample_rate as f64);
+        self.clock.advance(buffer + delay);
+        let mut out = vec![0; frames * self.channels];
+        self.callback.callback(&mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{Receiver, TryRecvError};
+
+    use super::*;
+    use crate::player::{PlaybackEvent, SUBSCRIBERS};
+    use wave::Header;
+
+    // a mono ramp 0, 1, 2, ... so every output sample says where it was read from
+    fn ramp(frames: usize) -> WavFile {
+        WavFile {
+            header: Header {
+                num_channels: 1,
+                sample_rate: 8000,
+                bits_per_sample: 16,
+                ..Header::default()
+            },
+            data: (0..frames as i16).collect(),
+            ..WavFile::default()
+        }
+    }
+
+    fn drain(events: &Receiver<PlaybackEvent>) -> Vec<PlaybackEvent> {
+        events.try_iter().collect()
+    }
+
+    fn started(frames: usize) -> (Player, OfflineDevice, Receiver<PlaybackEvent>) {
+        let (mut player, device) = OfflineDevice::open(&ramp(frames));
+        let events = player.subscribe();
+        player.play();
+        (player, device, events)
+    }
+
+    #[test]
+    fn plays_in_order_and_reports_position() {
+        let (_player, mut device, events) = started(100);
+        assert_eq!(device.render(4), [0, 1, 2, 3]);
+        assert_eq!(device.render(4), [4, 5, 6, 7]);
+        assert_eq!(
+            drain(&events),
+            [
+                PlaybackEvent::Started,
+                PlaybackEvent::PositionChanged(4),
+                PlaybackEvent::PositionChanged(8),
+            ]
+        );
+    }
+
+    #[test]
+    fn starts_paused() {
+        let (player, mut device) = OfflineDevice::open(&ramp(100));
+        let events = player.subscribe();
+        assert_eq!(device.render(4), [0; 4]);
+        assert_eq!(drain(&events), []);
+    }
+
+    #[test]
+    fn seek_applies_at_the_next_buffer() {
+        let (mut player, mut device, events) = started(100);
+        device.render(4);
+        player.seek(50);
+        assert_eq!(device.render(3), [50, 51, 52]);
+        assert!(drain(&events).ends_with(&[
+            PlaybackEvent::PositionChanged(50),
+            PlaybackEvent::PositionChanged(53),
+        ]));
+    }
+
+    #[test]
+    fn pause_outputs_silence_and_holds_position() {
+        let (mut player, mut device, events) = started(100);
+        device.render(4);
+        player.pause();
+        assert_eq!(device.render(4), [0; 4]);
+        player.play();
+        assert_eq!(device.render(2), [4, 5]);
+        assert_eq!(
+            drain(&events)[2..],
+            [
+                PlaybackEvent::Paused,
+                PlaybackEvent::Started,
+                PlaybackEvent::PositionChanged(6),
+            ]
+        );
+    }
+
+    #[test]
+    fn loop_wraps_to_its_start() {
+        let (mut player, mut device, _events) = started(100);
+        player.set_loop(Some(2..5));
+        player.seek(2);
+        assert_eq!(device.render(7), [2, 3, 4, 2, 3, 4, 2]);
+    }
+
+    #[test]
+    fn a_looping_file_crossfades_its_end_into_its_start() {
+        let (mut player, mut device, events) = started(100);
+        player.set_loop_file(true);
+        player.seek(95);
+        // 5 ms at 8 kHz fade 40 frames, the end is mostly the start by the time it wraps
+        let out = device.render(10);
+        assert_eq!(out[..5], [42, 42, 41, 41, 40]);
+        assert_eq!(out[5..], [40, 41, 42, 43, 44]);
+        assert!(!drain(&events).contains(&PlaybackEvent::EndOfStream));
+    }
+
+    #[test]
+    fn end_of_stream_is_reported_once_until_the_next_seek() {
+        let (mut player, mut device, events) = started(6);
+        assert_eq!(device.render(4), [0, 1, 2, 3]);
+        assert_eq!(device.render(4), [4, 5, 0, 0]);
+        device.render(4);
+        let ends = |events: &[PlaybackEvent]| {
+            events
+                .iter()
+                .filter(|&e| *e == PlaybackEvent::EndOfStream)
+                .count()
+        };
+        assert_eq!(ends(&drain(&events)), 1);
+
+        player.seek(0);
+        device.render(8);
+        assert_eq!(ends(&drain(&events)), 1);
+    }
+
+    #[test]
+    fn gain_scales_the_file() {
+        let (mut player, mut device, _events) = started(100);
+        player.seek(10);
+        player.set_gain(0.5);
+        // through the ramp from the old gain first
+        device.render(80);
+        assert_eq!(device.render(3), [45, 45, 46]);
+    }
+
+    #[test]
+    fn muting_fades_out_instead_of_cutting_off() {
+        let (mut player, mut device, _events) = started(1000);
+        player.seek(500);
+        player.set_muted(true);
+        let faded = device.render(80);
+        assert!(faded[0] > 490);
+        assert!(faded.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(faded[79], 0);
+        player.set_muted(false);
+        device.render(80);
+        assert_eq!(device.render(1), [660]);
+    }
+
+    #[test]
+    fn late_buffers_count_as_xruns() {
+        let (_player, mut device, events) = started(100_000);
+        device.render(80);
+        device.render(80);
+        device.render_late(80, Duration::from_millis(2));
+        assert!(!drain(&events).contains(&PlaybackEvent::Xrun));
+
+        device.render_late(80, Duration::from_millis(50));
+        assert!(drain(&events).contains(&PlaybackEvent::Xrun));
+    }
+
+    #[test]
+    fn scrubbing_plays_while_paused_and_leaves_the_playhead_behind() {
+        let (mut player, mut device) = OfflineDevice::open(&ramp(10_000));
+        // the first grain loops in place, past its fade in it reads the file as it is
+        player.scrub(1000);
+        let grain = device.render(400);
+        assert_eq!(grain[100..103], [1100, 1101, 1102]);
+
+        player.stop_scrub();
+        assert_eq!(device.render(2), [0, 0]);
+        player.play();
+        assert_eq!(device.render(2), [1000, 1001]);
+    }
+
+    #[test]
+    fn other_speeds_read_between_samples_and_run_backwards() {
+        let (mut player, mut device, _events) = started(100);
+        player.seek(10);
+        player.set_speed(0.5);
+        assert_eq!(device.render(4), [10, 10, 11, 11]);
+        player.set_speed(-2.0);
+        assert_eq!(device.render(3), [12, 10, 8]);
+    }
+
+    #[test]
+    fn backwards_a_loop_wraps_from_its_start_to_its_end() {
+        let (mut player, mut device, _events) = started(100);
+        player.set_loop(Some(2..5));
+        player.seek(4);
+        player.set_speed(-1.0);
+        assert_eq!(device.render(7), [4, 3, 2, 4, 3, 2, 4]);
+    }
+
+    #[test]
+    fn backwards_the_start_of_the_file_is_the_end_of_the_stream() {
+        let (mut player, mut device, events) = started(100);
+        player.seek(4);
+        player.set_speed(-2.0);
+        assert_eq!(device.render(4), [4, 2, 0, 0]);
+        assert!(drain(&events).contains(&PlaybackEvent::EndOfStream));
+        device.render(4);
+        assert!(!drain(&events).contains(&PlaybackEvent::EndOfStream));
+    }
+
+    #[test]
+    fn commands_past_what_the_ring_holds_arrive_in_order() {
+        let (mut player, mut device, _events) = started(1000);
+        for position in 0..300 {
+            player.seek(position);
+        }
+        device.render(1);
+        // the ones that didn't fit go with the next pump, without another command being sent
+        player.pump();
+        assert_eq!(device.render(2), [299, 300]);
+    }
+
+    #[test]
+    fn subscribers_past_what_the_audio_thread_holds_are_disconnected() {
+        let (player, mut device) = OfflineDevice::open(&ramp(100));
+        let events: Vec<_> = (0..=SUBSCRIBERS).map(|_| player.subscribe()).collect();
+        device.render(1);
+        player.pump();
+        assert_eq!(events[0].try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            events[SUBSCRIBERS].try_recv(),
+            Err(TryRecvError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn audible_position_trails_the_buffer_still_playing() {
+        let (player, mut device, _events) = started(100_000);
+        device.render(80);
+        // just handed over, none of it heard yet
+        assert_eq!(player.audible_position(80), 0);
+        device.clock.advance(Duration::from_millis(5));
+        assert_eq!(player.audible_position(80), 40);
+        device.clock.advance(Duration::from_millis(50));
+        assert_eq!(player.audible_position(80), 80);
+    }
+}
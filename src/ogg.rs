@@ -0,0 +1,392 @@
+// Ogg Vorbis through lewton and Ogg Opus through opus-decoder, only built with the `ogg`
+// feature. Both come as packets in Ogg pages, the first packet says which codec it is:
+// https://xiph.org/vorbis/doc/Vorbis_I_spec.html and https://www.rfc-editor.org/rfc/rfc7845
+
+use std::io::{self, BufReader, Read};
+
+use ::ogg::Packet;
+use ::ogg::reading::{BasePacketReader, PageParser};
+use lewton::audio::{PreviousWindowRight, read_audio_packet};
+use lewton::header::{IdentHeader, SetupHeader, read_header_ident, read_header_setup};
+use opus_decoder::OpusMultistreamDecoder;
+
+use crate::metadata::Metadata;
+use crate::reader::{read_all_or_nothing, truncated};
+use crate::{AudioSource, Header};
+
+// longest Opus packet, 120 ms at 48 kHz
+const MAX_OPUS_FRAMES: usize = 5760;
+
+/// Decodes the first Vorbis or Opus stream of an Ogg file a packet at a time like
+/// [`crate::WavReader`] reads a wav file. The header is filled in as 16-bit PCM, Opus always
+/// at 48 kHz. The length is only known at the last page, so the sizes stay 0.
+pub struct OggReader<R: Read> {
+    reader: BufReader<R>,
+    packets: BasePacketReader,
+    // of the stream being decoded, pages of any other (video, a skeleton) are left out
+    serial: u32,
+    codec: Codec,
+    header: Header,
+    // frames decoded so far, in the units the granule positions count
+    position: u64,
+    // frames at the start that are only there to prime the decoder
+    pre_skip: u64,
+    // what's left of the last decoded packet, interleaved
+    pending: Vec<i16>,
+    read: usize,
+    ended: bool,
+    metadata: Metadata,
+}
+
+enum Codec {
+    Vorbis(Box<Vorbis>),
+    Opus {
+        decoder: OpusMultistreamDecoder,
+        // the output gain of the OpusHead, linear
+        gain: f32,
+        buffer: Vec<i16>,
+    },
+}
+
+struct Vorbis {
+    ident: IdentHeader,
+    setup: SetupHeader,
+    window: PreviousWindowRight,
+}
+
+impl<R: Read> OggReader<R> {
+    /// Reads the header packets of the first stream.
+    pub fn new(reader: R) -> Result<Self, Box<dyn std::error::Error + 'static>> {
+        let mut reader = BufReader::new(reader);
+        let mut packets = BasePacketReader::new();
+        let first = next_packet(&mut reader, &mut packets, None)?.ok_or("empty Ogg file")?;
+        let serial = first.stream_serial();
+        let mut next = || -> Result<Packet, Box<dyn std::error::Error + 'static>> {
+            Ok(next_packet(&mut reader, &mut packets, Some(serial))?
+                .ok_or("Ogg stream ends in its headers")?)
+        };
+        let (codec, channels, sample_rate, pre_skip, metadata) =
+            if first.data.starts_with(b"\x01vorbis") {
+                let ident = read_header_ident(&first.data)?;
+                let metadata = comments(next()?.data.get(7..).unwrap_or_default());
+                let setup = read_header_setup(
+                    &next()?.data,
+                    ident.audio_channels,
+                    (ident.blocksize_0, ident.blocksize_1),
+                )?;
+                let (channels, rate) = (ident.audio_channels as u16, ident.audio_sample_rate);
+                let vorbis = Vorbis {
+                    ident,
+                    setup,
+                    window: PreviousWindowRight::new(),
+                };
+                (Codec::Vorbis(Box::new(vorbis)), channels, rate, 0, metadata)
+            } else if first.data.starts_with(b"OpusHead") && first.data.len() >= 19 {
+                let head = &first.data;
+                let channels = head[9] as usize;
+                if channels == 0 {
+                    return Err("Opus stream without channels".into());
+                }
+                let pre_skip = u16::from_le_bytes([head[10], head[11]]) as u64;
+                let gain_db = i16::from_le_bytes([head[16], head[17]]) as f32 / 256.0;
+                // family 0 is mono or stereo in a single stream, the others list the streams
+                // and which output channel each decoded channel goes to
+                let (streams, coupled, mapping) = match head[18] {
+                    0 => (1, channels / 2, (0..channels as u8).collect()),
+                    _ => {
+                        let mapping = head.get(21..21 + channels).ok_or("OpusHead too short")?;
+                        (head[19] as usize, head[20] as usize, mapping.to_vec())
+                    }
+                };
+                let decoder =
+                    OpusMultistreamDecoder::new(48000, channels, streams, coupled, &mapping)?;
+                let metadata = comments(next()?.data.get(8..).unwrap_or_default());
+                let codec = Codec::Opus {
+                    decoder,
+                    gain: 10f32.powf(gain_db / 20.0),
+                    buffer: vec![0; MAX_OPUS_FRAMES * channels],
+                };
+                (codec, channels as u16, 48000, pre_skip, metadata)
+            } else {
+                return Err("Ogg stream in a codec other than Vorbis or Opus".into());
+            };
+        let header = Header {
+            chunk_id: "OggS".into(),
+            format: match codec {
+                Codec::Vorbis(_) => "Vorbis".into(),
+                Codec::Opus { .. } => "Opus".into(),
+            },
+            audio_format: 1,
+            num_channels: channels,
+            sample_rate,
+            byte_rate: sample_rate * channels as u32 * 2,
+            block_align: channels * 2,
+            bits_per_sample: 16,
+            ..Header::default()
+        };
+        Ok(Self {
+            reader,
+            packets,
+            serial,
+            codec,
+            header,
+            position: 0,
+            pre_skip,
+            pending: Vec::new(),
+            read: 0,
+            ended: false,
+            metadata,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Always 0, the length is only known from the last page.
+    pub fn data_size(&self) -> u64 {
+        0
+    }
+
+    pub fn remaining(&self) -> u64 {
+        0
+    }
+
+    /// Decodes the next samples into `out`, returning how many, 0 after the last packet.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        while self.read == self.pending.len() {
+            if self.ended || !self.decode_packet()? {
+                return Ok(0);
+            }
+        }
+        let count = out.len().min(self.pending.len() - self.read);
+        out[..count].copy_from_slice(&self.pending[self.read..self.read + count]);
+        self.read += count;
+        Ok(count)
+    }
+
+    // the next packet into `pending`, false at the end of the stream
+    fn decode_packet(&mut self) -> io::Result<bool> {
+        let Some(packet) = next_packet(&mut self.reader, &mut self.packets, Some(self.serial))?
+        else {
+            return Ok(false);
+        };
+        let channels = self.header.num_channels as usize;
+        self.pending.clear();
+        self.read = 0;
+        match &mut self.codec {
+            Codec::Vorbis(vorbis) => {
+                let decoded = read_audio_packet(
+                    &vorbis.ident,
+                    &vorbis.setup,
+                    &packet.data,
+                    &mut vorbis.window,
+                )
+                .map_err(invalid)?;
+                let frames = decoded.first().map_or(0, Vec::len);
+                for i in 0..frames {
+                    self.pending
+                        .extend(decoded.iter().map(|channel| channel[i]));
+                }
+            }
+            Codec::Opus {
+                decoder,
+                gain,
+                buffer,
+            } => {
+                let frames = decoder
+                    .decode(&packet.data, buffer, false)
+                    .map_err(invalid)?;
+                self.pending.extend(
+                    buffer[..frames * channels]
+                        .iter()
+                        .map(|&s| (s as f32 * *gain).clamp(-32768.0, 32767.0) as i16),
+                );
+            }
+        }
+        // the granule position of the last page says where the stream really ends, the last
+        // packet is padded out to a whole frame
+        let start = self.position;
+        self.position += (self.pending.len() / channels) as u64;
+        if packet.last_in_stream() {
+            self.ended = true;
+            let end = packet.absgp_page().clamp(start, self.position);
+            self.pending.truncate((end - start) as usize * channels);
+            self.position = end;
+        }
+        let skip = self
+            .pre_skip
+            .saturating_sub(start)
+            .min(self.position - start);
+        self.pending.drain(..skip as usize * channels);
+        Ok(true)
+    }
+
+    /// The vorbis comment tags. Ogg keeps them in front of the audio, so nothing is left to
+    /// read.
+    pub fn finish(self) -> io::Result<Metadata> {
+        Ok(self.metadata)
+    }
+}
+
+impl<R: Read> AudioSource for OggReader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
+
+// the next packet of the stream `serial`, or of whichever stream comes first, reading pages
+// as they're needed. The pages of a file follow each other, so unlike ogg's own reader this
+// doesn't need to seek
+fn next_packet(
+    reader: &mut impl Read,
+    packets: &mut BasePacketReader,
+    serial: Option<u32>,
+) -> io::Result<Option<Packet>> {
+    loop {
+        while let Some(packet) = packets.read_packet() {
+            if serial.is_none_or(|serial| packet.stream_serial() == serial) {
+                return Ok(Some(packet));
+            }
+        }
+        let mut header = [0; 27];
+        if !read_all_or_nothing(reader, &mut header)? {
+            return Ok(None);
+        }
+        let (mut parser, segments) = PageParser::new(header).map_err(invalid)?;
+        let mut table = vec![0; segments];
+        reader.read_exact(&mut table).map_err(truncated)?;
+        let mut data = vec![0; parser.parse_segments(table)];
+        reader.read_exact(&mut data).map_err(truncated)?;
+        let page = parser.parse_packet_data(data).map_err(invalid)?;
+        packets.push_page(page).map_err(invalid)?;
+    }
+}
+
+// a vendor string and then `NAME=value` pairs, each after its length, the names case
+// insensitive
+fn comments(body: &[u8]) -> Metadata {
+    fn field<'a>(rest: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let length = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let text = rest.get(4..4 + length)?;
+        *rest = &rest[4 + length..];
+        Some(text)
+    }
+    let mut rest = body;
+    let mut fields = Vec::new();
+    if field(&mut rest).is_some() && rest.len() >= 4 {
+        let count = u32::from_le_bytes(rest[..4].try_into().unwrap());
+        rest = &rest[4..];
+        for _ in 0..count {
+            let Some(text) = field(&mut rest) else {
+                break;
+            };
+            if let Some((name, value)) = String::from_utf8_lossy(text).split_once('=') {
+                fields.push((name.to_ascii_uppercase(), value.to_string()));
+            }
+        }
+    }
+    let tag = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    };
+    Metadata {
+        title: tag("TITLE"),
+        artist: tag("ARTIST"),
+        album: tag("ALBUM"),
+        comment: tag("COMMENT").or_else(|| tag("DESCRIPTION")),
+        date: tag("DATE"),
+        ..Metadata::default()
+    }
+}
+
+fn invalid(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::decode;
+
+    // an Ogg page of one stream holding whole `packets`, `flags` 2 for the first page and 4 for
+    // the last
+    fn ogg_page(flags: u8, granule: u64, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+        let mut page = Vec::from(*b"OggS");
+        page.push(0);
+        page.push(flags);
+        page.extend(granule.to_le_bytes());
+        page.extend(0x5741_5645u32.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        // lacing values: 255s for the full segments of a packet, then what's left of it
+        let lacing: Vec<u8> = packets
+            .iter()
+            .flat_map(|packet| {
+                let full = packet.len() / 255;
+                std::iter::repeat_n(255, full).chain([(packet.len() - full * 255) as u8])
+            })
+            .collect();
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        for packet in packets {
+            page.extend(*packet);
+        }
+        let crc = page.iter().fold(0u32, |crc, &byte| {
+            (0..8).fold(crc ^ (byte as u32) << 24, |crc, _| {
+                if crc & 0x8000_0000 != 0 {
+                    crc << 1 ^ 0x04c1_1db7
+                } else {
+                    crc << 1
+                }
+            })
+        });
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn ogg_opus_trims_the_pre_skip_and_the_padded_end() {
+        let mut head = Vec::from(*b"OpusHead");
+        head.extend([1, 1]);
+        head.extend(312u16.to_le_bytes());
+        head.extend(48000u32.to_le_bytes());
+        head.extend([0, 0, 0]);
+        // the vendor, then a single comment
+        let mut tags = Vec::from(*b"OpusTags");
+        tags.extend(4u32.to_le_bytes());
+        tags.extend(b"wave");
+        tags.extend(1u32.to_le_bytes());
+        tags.extend(15u32.to_le_bytes());
+        tags.extend(b"TITLE=Room tone");
+        // a 20 ms CELT frame that was never sent, which decodes to silence
+        let frame = [0xf8];
+        let mut bytes = ogg_page(2, 0, 0, &[&head]);
+        bytes.extend(ogg_page(0, 0, 1, &[&tags]));
+        bytes.extend(ogg_page(4, 312 + 2000, 2, &[&frame, &frame, &frame]));
+
+        let wav = decode(&bytes).unwrap();
+        assert_eq!(&*wav.header.format, "Opus");
+        assert_eq!(wav.header.sample_rate, 48000);
+        assert_eq!(wav.header.num_channels, 1);
+        assert_eq!(wav.data.len(), 2000);
+        assert_eq!(wav.metadata.title.as_deref(), Some("Room tone"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::fft::{fft, hann};
+
+pub const FFT_SIZE: usize = 1024;
+pub const HOP: usize = 256;
+// an onset has to stand this far above the local average flux, plus the average flux of the
+// whole file so the quantization noise of near silent passages doesn't count
+const THRESHOLD: f32 = 1.5;
+// half width of the moving window the local average is taken over, in hops
+const AVERAGE_HOPS: usize = 8;
+// two onsets closer than this are the same note
+const MIN_GAP_SECONDS: f32 = 0.05;
+
+// sample positions of note onsets in the mono `blocks` read one after the other, found by
+// peak picking the positive spectral flux against a moving average. Only the samples of one
+// analysis window are kept between blocks
+pub fn detect(blocks: impl IntoIterator<Item = Vec<f32>>, sample_rate: u32) -> Vec<usize> {
+    let window = hann(FFT_SIZE);
+    let mut previous = vec![0.0f32; FFT_SIZE / 2];
+    let mut flux = Vec::new();
+    let mut pending: Vec<f32> = Vec::with_capacity(FFT_SIZE * 2);
+    for block in blocks {
+        pending.extend_from_slice(&block);
+        while pending.len() >= FFT_SIZE {
+            flux.push(hop_flux(&pending[..FFT_SIZE], &window, &mut previous));
+            pending.drain(..HOP);
+        }
+    }
+
+    let min_gap = (MIN_GAP_SECONDS * sample_rate as f32 / HOP as f32) as usize;
+    let global_average = flux.iter().sum::<f32>() / flux.len().max(1) as f32;
+    let mut onsets: Vec<usize> = Vec::new();
+    let mut last_hop: Option<usize> = None;
+    for i in 0..flux.len().saturating_sub(1) {
+        let lo = i.saturating_sub(AVERAGE_HOPS);
+        let hi = (i + AVERAGE_HOPS + 1).min(flux.len());
+        let average = flux[lo..hi].iter().sum::<f32>() / (hi - lo) as f32;
+        // a note right at the start of the file peaks in the very first hop
+        let before = if i == 0 { 0.0 } else { flux[i - 1] };
+        let is_peak = flux[i] > before && flux[i] >= flux[i + 1];
+        let spaced = last_hop.is_none_or(|last| i - last >= min_gap);
+        if is_peak && flux[i] > average * THRESHOLD + global_average && spaced {
+            // report the center of the analysis window rather than its start
+            onsets.push(i * HOP + FFT_SIZE / 2);
+            last_hop = Some(i);
+        }
+    }
+    onsets
+}
+
+// how much the log magnitude spectrum of `samples` rose over `previous`, which it replaces
+fn hop_flux(samples: &[f32], window: &[f32], previous: &mut [f32]) -> f32 {
+    let mut re: Vec<f32> = samples.iter().zip(window).map(|(s, w)| s * w).collect();
+    let mut im = vec![0.0; FFT_SIZE];
+    fft(&mut re, &mut im);
+
+    let mut total = 0.0;
+    for (bin, previous) in previous.iter_mut().enumerate() {
+        // log compression keeps loud sustained notes from drowning out quiet attacks
+        let magnitude = (1.0 + 100.0 * (re[bin] * re[bin] + im[bin] * im[bin]).sqrt()).ln();
+        total += (magnitude - *previous).max(0.0);
+        *previous = magnitude;
+    }
+    total
+}
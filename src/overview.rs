@@ -0,0 +1,207 @@
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+use rayon::prelude::*;
+
+use crate::WavFile;
+use crate::dynamics::to_db;
+
+// sidecar cache layout: magic, then the size and modification time of the file the columns
+// were computed from and the column count, then min, max and rms_db of every column
+const CACHE_MAGIC: &[u8; 8] = b"WAVPEAK1";
+const CACHE_HEADER: usize = 8 + 8 + 8 + 4 + 4;
+
+// quietest level that still gets a color of its own, anything below is drawn like silence
+pub const RMS_FLOOR_DB: f32 = -60.0;
+
+// the whole file squeezed into one column per pixel
+pub struct Overview {
+    pub columns: Vec<Column>,
+}
+
+pub struct Column {
+    pub min: f32,
+    pub max: f32,
+    // short-term level of everything under the column in dBFS
+    pub rms_db: f32,
+}
+
+impl Overview {
+    // columns are scanned in parallel, a multi-hour file is gigabytes of samples
+    pub fn new(wav: &WavFile, width: u32) -> Self {
+        let channels = wav.header.num_channels.max(1) as usize;
+        let frames = wav.data.len() / channels;
+        let width = width.max(1) as usize;
+
+        let columns = (0..width)
+            .into_par_iter()
+            .map(|x| {
+                let start = x * frames / width * channels;
+                let end = ((x + 1) * frames / width * channels).max(start + channels);
+                let samples = &wav.data[start.min(wav.data.len())..end.min(wav.data.len())];
+
+                let (min, max, square) = scan(samples);
+                let rms = (square as f64 / samples.len().max(1) as f64).sqrt() as f32 / 32768.0;
+                Column {
+                    min: min as f32 / 32768.0,
+                    max: max as f32 / 32768.0,
+                    rms_db: to_db(rms),
+                }
+            })
+            .collect();
+        Self { columns }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.columns.len() as u32
+    }
+
+    // like `new`, but reuses `{path}.peaks` when it was written for the same file contents and
+    // width, and leaves one behind otherwise so the next open of a long file skips the scan.
+    // The cache is best effort, a directory that can't be written to just means no cache
+    pub fn cached(path: &str, wav: &WavFile, width: u32) -> Self {
+        let cache_path = format!("{path}.peaks");
+        let key = cache_key(path, width);
+        if let Some(key) = &key
+            && let Some(overview) = fs::read(&cache_path)
+                .ok()
+                .and_then(|bytes| Self::from_cache(&bytes, key))
+        {
+            return overview;
+        }
+        let overview = Self::new(wav, width);
+        if let Some(key) = key {
+            fs::write(&cache_path, overview.to_cache(key)).ok();
+        }
+        overview
+    }
+
+    fn from_cache(bytes: &[u8], key: &[u8]) -> Option<Self> {
+        if bytes.len() < CACHE_HEADER || &bytes[..CACHE_HEADER] != key {
+            return None;
+        }
+        let columns: Vec<Column> = bytes[CACHE_HEADER..]
+            .chunks_exact(12)
+            .map(|column| {
+                let value =
+                    |i: usize| f32::from_le_bytes(column[i * 4..i * 4 + 4].try_into().unwrap());
+                Column {
+                    min: value(0),
+                    max: value(1),
+                    rms_db: value(2),
+                }
+            })
+            .collect();
+        let width = u32::from_le_bytes(key[CACHE_HEADER - 4..].try_into().unwrap());
+        (columns.len() == width as usize).then_some(Self { columns })
+    }
+
+    fn to_cache(&self, key: Vec<u8>) -> Vec<u8> {
+        let mut bytes = key;
+        for column in &self.columns {
+            bytes.extend(column.min.to_le_bytes());
+            bytes.extend(column.max.to_le_bytes());
+            bytes.extend(column.rms_db.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+// samples handled side by side, wide enough for the compiler to turn the loop into simd
+const LANES: usize = 16;
+
+// min and max (both including 0) and the sum of squares of `samples`, in integers so the
+// lanes vectorize and the sum is exact
+fn scan(samples: &[i16]) -> (i16, i16, u64) {
+    let (mut min, mut max, mut square) = ([0i16; LANES], [0i16; LANES], [0u64; LANES]);
+    let mut chunks = samples.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        for lane in 0..LANES {
+            let sample = chunk[lane];
+            min[lane] = min[lane].min(sample);
+            max[lane] = max[lane].max(sample);
+            square[lane] += (sample as i32 * sample as i32) as u64;
+        }
+    }
+    let (mut min, mut max, mut square) = (
+        *min.iter().min().unwrap(),
+        *max.iter().max().unwrap(),
+        square.iter().sum::<u64>(),
+    );
+    for &sample in chunks.remainder() {
+        min = min.min(sample);
+        max = max.max(sample);
+        square += (sample as i32 * sample as i32) as u64;
+    }
+    (min, max, square)
+}
+
+// the cache header a matching sidecar starts with, None if the file can't be looked at
+fn cache_key(path: &str, width: u32) -> Option<Vec<u8>> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let mut key = CACHE_MAGIC.to_vec();
+    key.extend(metadata.len().to_le_bytes());
+    key.extend(modified.as_secs().to_le_bytes());
+    key.extend(modified.subsec_nanos().to_le_bytes());
+    key.extend(width.to_le_bytes());
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use wave::Header;
+
+    fn wav(channels: u16, data: Vec<i16>) -> WavFile {
+        WavFile {
+            header: Header {
+                num_channels: channels,
+                sample_rate: 48000,
+                bits_per_sample: 16,
+                ..Header::default()
+            },
+            data: data.into(),
+            ..WavFile::default()
+        }
+    }
+
+    #[test]
+    fn scan_matches_a_plain_loop() {
+        let samples: Vec<i16> = (0..1000)
+            .map(|i| ((i * 7919) % 65536 - 32768) as i16)
+            .collect();
+        for len in [0, 1, 15, 16, 17, 1000] {
+            let samples = &samples[..len];
+            let min = samples.iter().copied().fold(0, i16::min);
+            let max = samples.iter().copied().fold(0, i16::max);
+            let square = samples.iter().map(|&s| (s as i64 * s as i64) as u64).sum();
+            assert_eq!(scan(samples), (min, max, square));
+        }
+    }
+
+    #[test]
+    fn columns_cover_their_share_of_the_file() {
+        let overview = Overview::new(&wav(1, vec![-16384, 0, 0, 16384]), 2);
+        assert_eq!(overview.columns[0].min, -0.5);
+        assert_eq!(overview.columns[0].max, 0.0);
+        assert_eq!(overview.columns[1].max, 0.5);
+    }
+
+    // three hours of 48 kHz mono, about a gigabyte of samples. Only meaningful in release
+    // builds: cargo test --release -- --ignored overview
+    #[test]
+    #[ignore]
+    fn three_hour_overview_in_under_a_second() {
+        let frames = 3 * 60 * 60 * 48000;
+        let data: Vec<i16> = (0..frames).map(|i| (i % 65536) as u16 as i16).collect();
+        let wav = wav(1, data);
+        let start = Instant::now();
+        let overview = Overview::new(&wav, 1920);
+        let elapsed = start.elapsed();
+        println!("{} columns in {elapsed:?}", overview.width());
+        assert!(elapsed.as_secs_f32() < 1.0);
+    }
+}
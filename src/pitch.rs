@@ -0,0 +1,58 @@
+// lowest and highest fundamental the detector searches for
+const MIN_FREQUENCY: f32 = 50.0;
+const MAX_FREQUENCY: f32 = 2000.0;
+// yin's absolute threshold on the cumulative mean normalized difference
+const THRESHOLD: f32 = 0.15;
+
+// fundamental frequency of a monophonic block using the yin algorithm (de cheveigné and
+// kawahara, 2002), None when the block isn't periodic enough to call it a pitch
+pub fn yin(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let min_lag = (sample_rate as f32 / MAX_FREQUENCY) as usize;
+    let max_lag = ((sample_rate as f32 / MIN_FREQUENCY) as usize).min(samples.len() / 2);
+    if max_lag <= min_lag + 2 {
+        return None;
+    }
+
+    let window = samples.len() - max_lag;
+    let difference: Vec<f32> = (0..=max_lag)
+        .map(|lag| {
+            (0..window)
+                .map(|i| (samples[i] - samples[i + lag]).powi(2))
+                .sum()
+        })
+        .collect();
+
+    let mut normalized = vec![1.0f32; max_lag + 1];
+    let mut running = 0.0;
+    for lag in 1..=max_lag {
+        running += difference[lag];
+        normalized[lag] = if running > 0.0 {
+            difference[lag] * lag as f32 / running
+        } else {
+            1.0
+        };
+    }
+
+    // the first dip under the threshold, followed down to its local minimum
+    let mut lag = (min_lag.max(2)..max_lag).find(|&lag| normalized[lag] < THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    // parabolic interpolation between neighbouring lags for sub-sample precision
+    let (a, b, c) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+    let denominator = a - 2.0 * b + c;
+    let shift = if denominator.abs() > f32::EPSILON {
+        (a - c) / (2.0 * denominator)
+    } else {
+        0.0
+    };
+    Some(sample_rate as f32 / (lag as f32 + shift))
+}
+
+// nearest midi note number, 69 being A4 at 440 Hz
+pub fn midi_note(frequency: f32) -> u8 {
+    (69.0 + 12.0 * (frequency / 440.0).log2())
+        .round()
+        .clamp(0.0, 127.0) as u8
+}
@@ -0,0 +1,994 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rtrb::{Consumer, Producer, PushError, RingBuffer};
+use sdl2::audio::AudioCallback;
+
+use crate::WavFile;
+use crate::binaural::Binaural;
+use crate::convolver::Convolver;
+use crate::filter::{self, FilterBank};
+use crate::hum::{self, HumFilter};
+use crate::plugin::{Parameter, Plugin};
+use crate::samples::Samples;
+use crate::tone::{GuideTone, ToneSettings};
+
+// everything the ui can ask of the audio thread. Commands carry the new value rather than a
+// toggle so the handle always knows what the callback is doing without asking it
+enum Command {
+    Play,
+    Pause,
+    Seek(usize),
+    SetGain(f32),
+    SetMuted(bool),
+    SetLoop(Option<Range<usize>>),
+    SetLoopFile(bool),
+    SetGuideTone(ToneSettings),
+    SetHumFilter(bool),
+    SetRumbleFilter(bool),
+    SetAirFilter(bool),
+    // boxed, the spectra of a long response are megabytes
+    SetConvolver(Box<Convolver>),
+    SetConvolution(bool),
+    SetBinaural(Box<Binaural>),
+    SetHeadModel(bool),
+    SetPlugin(Box<Plugin>),
+    SetPluginEnabled(bool),
+    // a parameter id and its new value
+    SetParameter(u32, f64),
+    SetCompareOffset(isize),
+    SetPlayCompare(bool),
+    // same length as what it replaces, playback carries on where it was
+    SetData(Samples),
+    SetSpeed(f64),
+    // frame to scrub around, None ends scrubbing there
+    Scrub(Option<usize>),
+    Subscribe(SyncSender<PlaybackEvent>),
+    DeviceChanged,
+}
+
+// what the audio thread replaced and hands back to be dropped on the ui thread, freeing a
+// megabyte impulse response or a plugin instance mid-buffer could make it late
+enum Garbage {
+    Convolver(Box<Convolver>),
+    Binaural(Box<Binaural>),
+    Plugin(Box<Plugin>),
+    Samples(Samples),
+    Subscriber(SyncSender<PlaybackEvent>),
+}
+
+// what subscribers hear about from the audio thread
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlaybackEvent {
+    Started,
+    Paused,
+    // interleaved sample index after every buffer handed to the device
+    PositionChanged(usize),
+    // playback ran past the last sample, sent once until the next seek
+    EndOfStream,
+    // an audio device was added or removed
+    DeviceChanged,
+    // the callback came late enough that the device must have run dry
+    Xrun,
+}
+
+// time source for spotting late callbacks, the offline test device swaps in one that only
+// moves when told to
+pub trait Clock: Send + Sync {
+    // time since some fixed starting point
+    fn now(&self) -> Duration;
+}
+
+struct SystemClock(Instant);
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+// what the audio thread last reported about its own timing, written every buffer and read by
+// the ui's debug overlay without either side waiting on the other
+#[derive(Default)]
+struct Timing {
+    // clock time the last buffer was handed over at
+    last_callback: AtomicU64,
+    // how long filling it took
+    callback_duration: AtomicU64,
+    buffer_frames: AtomicU64,
+}
+
+// a snapshot of the callback timing
+pub struct CallbackStats {
+    pub duration: Duration,
+    // how long a buffer lasts on the device
+    pub buffer: Duration,
+    // share of the last buffer still waiting to be played, estimated from when it was handed
+    // over, near 0 just before the next callback is due
+    pub fill: f32,
+}
+
+// events a subscriber can fall behind by before new ones are dropped, the audio thread never
+// waits on a slow reader
+const EVENT_QUEUE: usize = 1024;
+// commands in flight to the audio thread, and garbage on its way back. Both rings are
+// allocated up front, more commands than fit wait on the handle's side until the next send
+// or pump finds room for them
+const COMMAND_QUEUE: usize = 256;
+// subscribers the audio thread has room for without growing its list, the ones past it are
+// sent back and their receivers see the stream disconnected
+pub const SUBSCRIBERS: usize = 16;
+
+// the state last requested through the handle, for drawing
+#[derive(Clone)]
+pub struct Settings {
+    pub playing: bool,
+    // linear gain applied to the file, not to the guide tone
+    pub gain: f32,
+    // silent without losing the gain
+    pub muted: bool,
+    // interleaved sample range playback wraps around in
+    pub loop_range: Option<Range<usize>>,
+    // the end of the file wraps around to its start, when there's no loop_range
+    pub loop_file: bool,
+    pub guide_tone: ToneSettings,
+    pub hum_filter: bool,
+    // mains frequency the hum filter notches out, detected when the file is opened
+    pub mains: f32,
+    pub rumble_filter: bool,
+    pub air_filter: bool,
+    // name of the `--ir` impulse response playback is convolved with, and whether it's on
+    pub impulse_response: Option<String>,
+    pub convolution: bool,
+    // None while the device plays the file's own channels, otherwise whether the stereo
+    // it gets is binaural or a flat downmix
+    pub binaural: Option<bool>,
+    // name of the `--plugin` effect, whether it's on and its parameters as last set
+    pub plugin: Option<String>,
+    pub plugin_enabled: bool,
+    pub parameters: Vec<Parameter>,
+    // index of the parameter shown and adjusted
+    pub parameter: usize,
+    // frames the comparison file is delayed by
+    pub compare_offset: isize,
+    pub play_compare: bool,
+    // playback rate, negative plays backwards
+    pub speed: f64,
+}
+
+// control side of playback. Every method posts a command to a ring the audio callback drains
+// at the start of each buffer, so nothing here ever waits on the audio thread and the handle
+// can live on (or be sent to) any thread
+pub struct Player {
+    commands: Mutex<Commands>,
+    settings: Settings,
+    clock: Arc<dyn Clock>,
+    timing: Arc<Timing>,
+    sample_rate: u32,
+    channels: usize,
+    // time between the device taking a buffer and the sound coming out of the speakers, which
+    // sdl can't know about (bluetooth, external converters)
+    output_delay: Duration,
+}
+
+impl Player {
+    // the handle plus the callback to open the audio device with, `compare` is the
+    // `--compare` file heard instead of the main one while play_compare is set. Playback
+    // starts paused, so subscribers can be in place for the first Started
+    pub fn new(wav: &WavFile, compare: Option<Samples>) -> (Self, AudioPlayer) {
+        Self::with_clock(wav, compare, Arc::new(SystemClock(Instant::now())))
+    }
+
+    pub fn with_clock(
+        wav: &WavFile,
+        compare: Option<Samples>,
+        clock: Arc<dyn Clock>,
+    ) -> (Self, AudioPlayer) {
+        let (commands, receiver) = RingBuffer::new(COMMAND_QUEUE);
+        let (garbage, garbage_receiver) = RingBuffer::new(COMMAND_QUEUE);
+        let sample_rate = wav.header.sample_rate;
+        let channels = wav.header.num_channels;
+        let hum_filter = HumFilter::new(sample_rate, channels, hum::detect_mains(wav));
+        let settings = Settings {
+            playing: false,
+            gain: 1.0,
+            muted: false,
+            loop_range: None,
+            loop_file: false,
+            guide_tone: ToneSettings::default(),
+            hum_filter: false,
+            mains: hum_filter.mains,
+            rumble_filter: false,
+            air_filter: false,
+            impulse_response: None,
+            convolution: false,
+            binaural: None,
+            plugin: None,
+            plugin_enabled: false,
+            parameters: Vec::new(),
+            parameter: 0,
+            compare_offset: 0,
+            play_compare: false,
+            speed: 1.0,
+        };
+        let callback = AudioPlayer {
+            commands: receiver,
+            garbage,
+            subscribers: Vec::with_capacity(SUBSCRIBERS),
+            data: wav.data.clone(),
+            channels,
+            sample_rate,
+            position: 0,
+            playing: false,
+            ended: false,
+            clock: clock.clone(),
+            timing: Arc::default(),
+            last_callback: None,
+            gain: 1.0,
+            muted: false,
+            level: Level::new(sample_rate),
+            loop_range: None,
+            loop_file: false,
+            guide_tone: GuideTone::new(sample_rate),
+            hum_filter,
+            rumble_filter: filter::rumble(sample_rate, channels),
+            air_filter: filter::air(sample_rate, channels),
+            convolver: None,
+            binaural: None,
+            plugin: None,
+            file_frames: Vec::new(),
+            compare,
+            compare_offset: 0,
+            play_compare: false,
+            speed: 1.0,
+            fraction: 0.0,
+            scrub: None,
+        };
+        let player = Self {
+            commands: Mutex::new(Commands {
+                ring: commands,
+                waiting: VecDeque::new(),
+                garbage: garbage_receiver,
+            }),
+            settings,
+            clock,
+            timing: callback.timing.clone(),
+            sample_rate,
+            channels: channels.max(1) as usize,
+            output_delay: Duration::ZERO,
+        };
+        (player, callback)
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // None until the first buffer was played
+    pub fn callback_stats(&self) -> Option<CallbackStats> {
+        let frames = self.timing.buffer_frames.load(Ordering::Relaxed);
+        if frames == 0 {
+            return None;
+        }
+        let buffer = Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64);
+        let last = Duration::from_nanos(self.timing.last_callback.load(Ordering::Relaxed));
+        let since = self.clock.now().saturating_sub(last);
+        Some(CallbackStats {
+            duration: Duration::from_nanos(self.timing.callback_duration.load(Ordering::Relaxed)),
+            buffer,
+            fill: (1.0 - since.as_secs_f32() / buffer.as_secs_f32()).max(0.0),
+        })
+    }
+
+    // what is being heard right now. `position` from PositionChanged is where the callback
+    // stopped filling, a whole buffer ahead of the speakers when it's reported and catching up
+    // as the device plays it, so the part of the buffer still waiting is taken off again
+    pub fn audible_position(&self, position: usize) -> usize {
+        let Some(stats) = self.callback_stats() else {
+            return position;
+        };
+        let waiting = (stats.buffer.as_secs_f64() * stats.fill as f64
+            + self.output_delay.as_secs_f64())
+            * self.sample_rate as f64;
+        let frames = (waiting * self.settings.speed).round() as isize;
+        position.saturating_add_signed(-frames * self.channels as isize)
+    }
+
+    // added to the latency audible_position makes up for, see `wave sync-test`
+    pub fn set_output_delay(&mut self, delay: Duration) {
+        self.output_delay = delay;
+    }
+
+    // a new stream of playback events, starting with the next buffer
+    pub fn subscribe(&self) -> Receiver<PlaybackEvent> {
+        let (sender, receiver) = mpsc::sync_channel(EVENT_QUEUE);
+        self.send(Command::Subscribe(sender));
+        receiver
+    }
+
+    // sdl reports device hotplugging on the event loop, this passes it on to subscribers
+    pub fn device_changed(&self) {
+        self.send(Command::DeviceChanged);
+    }
+
+    pub fn play(&mut self) {
+        self.settings.playing = true;
+        self.send(Command::Play);
+    }
+
+    pub fn pause(&mut self) {
+        self.settings.playing = false;
+        self.send(Command::Pause);
+    }
+
+    // jumps to an interleaved sample index
+    pub fn seek(&mut self, position: usize) {
+        self.send(Command::Seek(position));
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.settings.gain = gain;
+        self.send(Command::SetGain(gain));
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.settings.muted = muted;
+        self.send(Command::SetMuted(muted));
+    }
+
+    pub fn set_loop(&mut self, range: Option<Range<usize>>) {
+        self.settings.loop_range = range.clone();
+        self.send(Command::SetLoop(range));
+    }
+
+    pub fn set_loop_file(&mut self, enabled: bool) {
+        self.settings.loop_file = enabled;
+        self.send(Command::SetLoopFile(enabled));
+    }
+
+    pub fn set_guide_tone(&mut self, tone: ToneSettings) {
+        self.settings.guide_tone = tone;
+        self.send(Command::SetGuideTone(tone));
+    }
+
+    pub fn set_hum_filter(&mut self, enabled: bool) {
+        self.settings.hum_filter = enabled;
+        self.send(Command::SetHumFilter(enabled));
+    }
+
+    pub fn set_rumble_filter(&mut self, enabled: bool) {
+        self.settings.rumble_filter = enabled;
+        self.send(Command::SetRumbleFilter(enabled));
+    }
+
+    pub fn set_air_filter(&mut self, enabled: bool) {
+        self.settings.air_filter = enabled;
+        self.send(Command::SetAirFilter(enabled));
+    }
+
+    // convolves playback with `ir` from now on, called `name` in the status
+    pub fn set_impulse_response(&mut self, name: &str, ir: &WavFile) {
+        let convolver = Convolver::new(ir, self.sample_rate, self.channels as u16);
+        self.settings.impulse_response = Some(name.to_string());
+        self.settings.convolution = true;
+        self.send(Command::SetConvolver(Box::new(convolver)));
+    }
+
+    // bypasses the impulse response and brings it back
+    pub fn set_convolution(&mut self, enabled: bool) {
+        self.settings.convolution = enabled;
+        self.send(Command::SetConvolution(enabled));
+    }
+
+    // renders every channel into stereo for headphones from where its speaker would be, the
+    // device has to be opened with two channels. Has to come before the first callback
+    pub fn set_binaural(&mut self, wav: &WavFile) {
+        self.settings.binaural = Some(true);
+        self.send(Command::SetBinaural(Box::new(Binaural::new(&wav.header))));
+    }
+
+    // switches a binaural device between the head model and a flat downmix
+    pub fn set_head_model(&mut self, enabled: bool) {
+        if self.settings.binaural.is_some() {
+            self.settings.binaural = Some(enabled);
+            self.send(Command::SetHeadModel(enabled));
+        }
+    }
+
+    // plays through `plugin` from now on, after the impulse response
+    pub fn set_plugin(&mut self, name: &str, plugin: Plugin, parameters: Vec<Parameter>) {
+        self.settings.plugin = Some(name.to_string());
+        self.settings.plugin_enabled = true;
+        self.settings.parameters = parameters;
+        self.send(Command::SetPlugin(Box::new(plugin)));
+    }
+
+    // bypasses the plugin and brings it back
+    pub fn set_plugin_enabled(&mut self, enabled: bool) {
+        self.settings.plugin_enabled = enabled;
+        self.send(Command::SetPluginEnabled(enabled));
+    }
+
+    // the parameter shown, wrapping around at either end
+    pub fn select_parameter(&mut self, index: isize) {
+        let count = self.settings.parameters.len().max(1);
+        self.settings.parameter = index.rem_euclid(count as isize) as usize;
+    }
+
+    // the plugin's parameter at `index` of `Settings::parameters`
+    pub fn set_parameter(&mut self, index: usize, value: f64) {
+        if let Some(parameter) = self.settings.parameters.get_mut(index) {
+            parameter.value = value;
+            let id = parameter.id;
+            self.send(Command::SetParameter(id, value));
+        }
+    }
+
+    pub fn set_compare_offset(&mut self, frames: isize) {
+        self.settings.compare_offset = frames;
+        self.send(Command::SetCompareOffset(frames));
+    }
+
+    // new samples for the file being played, such as a take group mixed again after a track
+    // was muted. They have to line up with the old ones
+    pub fn set_data(&mut self, data: Samples) {
+        self.send(Command::SetData(data));
+    }
+
+    pub fn set_play_compare(&mut self, enabled: bool) {
+        self.settings.play_compare = enabled;
+        self.send(Command::SetPlayCompare(enabled));
+    }
+
+    // playback rate, 2.0 plays twice as fast and -1.0 backwards. The pitch goes with it like
+    // a tape machine's
+    pub fn set_speed(&mut self, speed: f64) {
+        self.settings.speed = speed;
+        self.send(Command::SetSpeed(speed));
+    }
+
+    // plays short grains around a frame while the playhead is dragged, whether playing or
+    // not. Keep calling it with the frame under the mouse, the grains speed up and slow down
+    // to follow it
+    pub fn scrub(&mut self, frame: usize) {
+        self.send(Command::Scrub(Some(frame)));
+    }
+
+    // leaves the playhead where scrubbing stopped
+    pub fn stop_scrub(&mut self) {
+        self.send(Command::Scrub(None));
+    }
+
+    // passes on the commands still waiting for room in the ring and drops what the audio
+    // thread handed back. Call it every frame, so nothing waits on the next command to go
+    pub fn pump(&self) {
+        let mut commands = self.commands.lock().unwrap();
+        commands.collect_garbage();
+        commands.flush();
+    }
+
+    fn send(&self, command: Command) {
+        let mut commands = self.commands.lock().unwrap();
+        commands.collect_garbage();
+        commands.waiting.push_back(command);
+        commands.flush();
+    }
+}
+
+// the ui thread's ends of the rings, plus the commands that didn't fit yet
+struct Commands {
+    ring: Producer<Command>,
+    waiting: VecDeque<Command>,
+    garbage: Consumer<Garbage>,
+}
+
+impl Commands {
+    // in order, as many as there's room for
+    fn flush(&mut self) {
+        // once the device, and with it the callback, is gone nobody will read them
+        if self.ring.is_abandoned() {
+            self.waiting.clear();
+            return;
+        }
+        while let Some(command) = self.waiting.pop_front() {
+            if let Err(PushError::Full(command)) = self.ring.push(command) {
+                self.waiting.push_front(command);
+                break;
+            }
+        }
+    }
+
+    fn collect_garbage(&mut self) {
+        while let Ok(garbage) = self.garbage.pop() {
+            match garbage {
+                Garbage::Convolver(convolver) => drop(convolver),
+                Garbage::Binaural(binaural) => drop(binaural),
+                Garbage::Plugin(plugin) => drop(plugin),
+                Garbage::Samples(samples) => drop(samples),
+                Garbage::Subscriber(sender) => drop(sender),
+            }
+        }
+    }
+}
+
+// the audio thread side, owned by the sdl device
+pub struct AudioPlayer {
+    commands: Consumer<Command>,
+    garbage: Producer<Garbage>,
+    subscribers: Vec<SyncSender<PlaybackEvent>>,
+    data: Samples,
+    channels: u16,
+    sample_rate: u32,
+    position: usize,
+    playing: bool,
+    ended: bool,
+    clock: Arc<dyn Clock>,
+    timing: Arc<Timing>,
+    last_callback: Option<Duration>,
+    gain: f32,
+    muted: bool,
+    // what's actually applied, following the gain and mute
+    level: Level,
+    loop_range: Option<Range<usize>>,
+    loop_file: bool,
+    guide_tone: GuideTone,
+    hum_filter: HumFilter,
+    rumble_filter: FilterBank,
+    air_filter: FilterBank,
+    convolver: Option<Box<Convolver>>,
+    binaural: Option<Box<Binaural>>,
+    plugin: Option<Box<Plugin>>,
+    // the frames of the file the binaural output is rendered from
+    file_frames: Vec<i16>,
+    compare: Option<Samples>,
+    compare_offset: isize,
+    play_compare: bool,
+    speed: f64,
+    // how far past `position` playback is at other speeds, in frames
+    fraction: f64,
+    scrub: Option<Scrub>,
+}
+
+// time a change of gain is spread over, jumping straight to it clicks and a volume key held
+// down would sound like a zipper
+const GAIN_RAMP_SECONDS: f32 = 0.01;
+
+// a gain that moves to a new value in a straight line over GAIN_RAMP_SECONDS
+struct Level {
+    current: f32,
+    target: f32,
+    // change per frame
+    step: f32,
+    ramp_frames: f32,
+}
+
+impl Level {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            current: 1.0,
+            target: 1.0,
+            step: 0.0,
+            ramp_frames: (GAIN_RAMP_SECONDS * sample_rate as f32).max(1.0),
+        }
+    }
+
+    fn set(&mut self, target: f32) {
+        self.target = target;
+        self.step = (target - self.current) / self.ramp_frames;
+    }
+
+    // the gain for the next frame
+    fn next(&mut self) -> f32 {
+        if (self.target - self.current).abs() <= self.step.abs().max(f32::EPSILON) {
+            self.current = self.target;
+        } else {
+            self.current += self.step;
+        }
+        self.current
+    }
+}
+
+// time the end of the file is crossfaded into its start over while it loops, the jump between
+// two unrelated samples would click
+const LOOP_FADE_SECONDS: f64 = 0.005;
+
+// length of a scrub grain, short enough to follow the mouse and long enough to hear what's
+// there
+const GRAIN_SECONDS: f64 = 0.05;
+// frames faded in and out at the ends of a grain so the jumps between them don't click
+const GRAIN_FADE: f64 = 64.0;
+// fastest a grain plays back at, flinging the mouse across the whole file stays audible
+const MAX_SCRUB_SPEED: f64 = 4.0;
+
+// varispeed scrubbing: every grain starts where the previous one was aimed and plays at the
+// speed that reaches the current target by its end, a still mouse loops the same grain
+struct Scrub {
+    // frame under the mouse
+    target: usize,
+    // where the grains have got to on their way to the target
+    head: f64,
+    // frame the current grain started at and how fast it moves, in frames per frame
+    start: f64,
+    speed: f64,
+    // frames into the current grain
+    elapsed: f64,
+}
+
+impl AudioPlayer {
+    fn apply(&mut self, command: Command) {
+        match command {
+            Command::Play => {
+                self.playing = true;
+                self.emit(PlaybackEvent::Started);
+            }
+            Command::Pause => {
+                self.playing = false;
+                self.emit(PlaybackEvent::Paused);
+            }
+            Command::Seek(position) => {
+                self.position = position;
+                self.fraction = 0.0;
+                self.ended = false;
+                self.emit(PlaybackEvent::PositionChanged(position));
+            }
+            Command::SetGain(gain) => {
+                self.gain = gain;
+                self.level.set(if self.muted { 0.0 } else { gain });
+            }
+            Command::SetMuted(muted) => {
+                self.muted = muted;
+                self.level.set(if muted { 0.0 } else { self.gain });
+            }
+            Command::SetLoop(range) => self.loop_range = range,
+            Command::SetLoopFile(enabled) => self.loop_file = enabled,
+            Command::SetGuideTone(tone) => self.guide_tone.settings = tone,
+            Command::SetHumFilter(enabled) => self.hum_filter.filter.enabled = enabled,
+            Command::SetRumbleFilter(enabled) => self.rumble_filter.enabled = enabled,
+            Command::SetAirFilter(enabled) => self.air_filter.enabled = enabled,
+            Command::SetConvolver(convolver) => {
+                if let Some(old) = self.convolver.replace(convolver) {
+                    self.discard(Garbage::Convolver(old));
+                }
+            }
+            Command::SetConvolution(enabled) => {
+                if let Some(convolver) = &mut self.convolver {
+                    convolver.enabled = enabled;
+                }
+            }
+            Command::SetBinaural(binaural) => {
+                if let Some(old) = self.binaural.replace(binaural) {
+                    self.discard(Garbage::Binaural(old));
+                }
+            }
+            Command::SetHeadModel(enabled) => {
+                if let Some(binaural) = &mut self.binaural {
+                    binaural.enabled = enabled;
+                }
+            }
+            Command::SetPlugin(plugin) => {
+                if let Some(old) = self.plugin.replace(plugin) {
+                    self.discard(Garbage::Plugin(old));
+                }
+            }
+            Command::SetPluginEnabled(enabled) => {
+                if let Some(plugin) = &mut self.plugin {
+                    plugin.set_enabled(enabled);
+                }
+            }
+            Command::SetParameter(id, value) => {
+                if let Some(plugin) = &mut self.plugin {
+                    plugin.set_parameter(id, value);
+                }
+            }
+            Command::SetCompareOffset(frames) => self.compare_offset = frames,
+            Command::SetPlayCompare(enabled) => self.play_compare = enabled,
+            Command::SetData(data) => {
+                let old = std::mem::replace(&mut self.data, data);
+                self.discard(Garbage::Samples(old));
+            }
+            Command::SetSpeed(speed) => {
+                // turning round leaves the end that was reached behind
+                if (speed < 0.0) != (self.speed < 0.0) {
+                    self.ended = false;
+                }
+                self.speed = speed;
+                self.fraction = 0.0;
+            }
+            Command::Scrub(Some(target)) => match &mut self.scrub {
+                Some(scrub) => scrub.target = target,
+                None => {
+                    self.scrub = Some(Scrub {
+                        target,
+                        head: target as f64,
+                        start: target as f64,
+                        speed: 1.0,
+                        elapsed: 0.0,
+                    })
+                }
+            },
+            Command::Scrub(None) => {
+                if let Some(scrub) = self.scrub.take() {
+                    self.position = scrub.target * self.channels.max(1) as usize;
+                    self.fraction = 0.0;
+                    self.ended = false;
+                    self.emit(PlaybackEvent::PositionChanged(self.position));
+                }
+            }
+            Command::Subscribe(sender) if self.subscribers.len() < SUBSCRIBERS => {
+                self.subscribers.push(sender)
+            }
+            Command::Subscribe(sender) => self.discard(Garbage::Subscriber(sender)),
+            Command::DeviceChanged => self.emit(PlaybackEvent::DeviceChanged),
+        }
+    }
+
+    // back to the ui thread to be dropped there. The ring has room for every command that
+    // was in flight, so it only drops here if the handle is gone
+    fn discard(&mut self, garbage: Garbage) {
+        self.garbage.push(garbage).ok();
+    }
+
+    // hands `event` to every subscriber with room for it, and forgets the ones that hung up
+    fn emit(&mut self, event: PlaybackEvent) {
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    // the filters, impulse response, plugin, gain and guide tone every sample goes through on its way
+    // out
+    fn output(&mut self, channel: usize, value: f32, tone: f32) -> i16 {
+        let value = self.hum_filter.filter.process(channel, value);
+        let value = self.rumble_filter.process(channel, value);
+        let value = self.air_filter.process(channel, value);
+        let value = match &mut self.convolver {
+            Some(convolver) => convolver.process(channel, value),
+            None => value,
+        };
+        let value = match &mut self.plugin {
+            Some(plugin) => plugin.process(channel, value),
+            None => value,
+        };
+        // the level moves once per frame, every channel gets the same
+        if channel == 0 {
+            self.level.next();
+        }
+        (value * self.level.current + tone).clamp(-32768.0, 32767.0) as i16
+    }
+
+    // playback at any speed other than 1, reading between samples. Only the main file, the
+    // comparison is for listening at the original speed
+    fn fill_varispeed(&mut self, out: &mut [i16]) {
+        let channels = self.channels.max(1) as usize;
+        let frames = self.data.len() / channels;
+        let mut frame = (self.position / channels) as f64 + self.fraction;
+        for output in out.chunks_mut(channels) {
+            if let Some(range) = &self.loop_range {
+                let (start, end) = (
+                    (range.start / channels) as f64,
+                    (range.end / channels) as f64,
+                );
+                // backwards the loop goes round from its start to its end
+                if self.speed > 0.0 && frame >= end {
+                    frame = start;
+                } else if self.speed < 0.0 && frame < start {
+                    frame += end - start;
+                }
+            } else if self.wraps() {
+                frame = frame.rem_euclid(frames as f64);
+            }
+            let tone = self.guide_tone.next_sample() * 32767.0;
+            // backwards into the start or forwards past the end there's nothing left to play
+            let inside = frame >= 0.0 && frame < frames as f64;
+            let index = frame.max(0.0).floor() as usize;
+            let t = (frame - index as f64) as f32;
+            for (channel, sample) in output.iter_mut().enumerate() {
+                let value = if inside {
+                    let wraps = self.wraps();
+                    let at = |index: usize| {
+                        let index = if wraps { index % frames } else { index };
+                        self.data
+                            .get(index * channels + channel)
+                            .copied()
+                            .unwrap_or(0) as f32
+                    };
+                    at(index) + (at(index + 1) - at(index)) * t
+                } else {
+                    0.0
+                };
+                *sample = self.output(channel, value, tone);
+            }
+            frame += self.speed;
+        }
+        let frame = frame.clamp(0.0, frames as f64);
+        self.position = frame.floor() as usize * channels;
+        self.fraction = frame.fract();
+    }
+
+    // the grains while scrubbing, without the filters or the comparison file
+    fn fill_scrub(&mut self, out: &mut [i16]) {
+        let channels = self.channels.max(1) as usize;
+        let frames = self.data.len() / channels;
+        let grain = (GRAIN_SECONDS * self.sample_rate as f64).max(1.0);
+        let Some(scrub) = &mut self.scrub else {
+            return;
+        };
+        for frame in out.chunks_mut(channels) {
+            if scrub.elapsed >= grain {
+                let step = (scrub.target as f64 - scrub.head)
+                    .clamp(-MAX_SCRUB_SPEED * grain, MAX_SCRUB_SPEED * grain);
+                scrub.start = scrub.head;
+                // with nothing to catch up with the grain loops in place
+                scrub.speed = if step == 0.0 { 1.0 } else { step / grain };
+                scrub.head += step;
+                scrub.elapsed = 0.0;
+            }
+            let fade = (scrub.elapsed.min(grain - scrub.elapsed) / GRAIN_FADE).min(1.0);
+            let gain = self.level.next();
+            let position = scrub.start + scrub.speed * scrub.elapsed;
+            let index = position.floor();
+            let t = (position - index) as f32;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let at = |frame: f64| {
+                    if frame < 0.0 || frame as usize >= frames {
+                        return 0.0;
+                    }
+                    self.data[frame as usize * channels + channel] as f32
+                };
+                let value = at(index) + (at(index + 1.0) - at(index)) * t;
+                *sample = (value * gain * fade as f32).clamp(-32768.0, 32767.0) as i16;
+            }
+            scrub.elapsed += 1.0;
+        }
+        let position = scrub.target * channels;
+        self.emit(PlaybackEvent::PositionChanged(position));
+    }
+
+    // whether playback goes round the whole file, a loop range takes over from it
+    fn wraps(&self) -> bool {
+        self.loop_file && self.loop_range.is_none() && !self.data.is_empty()
+    }
+
+    // frames of the crossfade at the end of a looping file, at most half of it
+    fn loop_fade(&self) -> usize {
+        let frames = self.data.len() / self.channels.max(1) as usize;
+        ((LOOP_FADE_SECONDS * self.sample_rate as f64) as usize).min(frames / 2)
+    }
+
+    // the sample at `position` while the file loops: over the last frames the start of the
+    // file fades in as the end fades out, so the wrap lands on the same sound
+    fn looped_sample(&self, position: usize) -> i16 {
+        let channels = self.channels.max(1) as usize;
+        let fade = self.loop_fade() * channels;
+        let value = self.data.get(position).copied().unwrap_or(0);
+        let Some(into) = (position + fade).checked_sub(self.data.len()) else {
+            return value;
+        };
+        let t = (into / channels) as f32 / (fade / channels) as f32;
+        let start = self.data.get(into).copied().unwrap_or(0);
+        (value as f32 * (1.0 - t) + start as f32 * t) as i16
+    }
+
+    // a callback arriving well after the previous buffer should have run out means the device
+    // played silence in between. A clock that went backwards counts as no time passing
+    fn check_xrun(&mut self, frames: usize) {
+        let now = self.clock.now();
+        if let Some(last) = self.last_callback.replace(now) {
+            let buffer = Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64);
+            if now.saturating_sub(last) > buffer * 2 + XRUN_SLACK {
+                self.emit(PlaybackEvent::Xrun);
+            }
+        }
+    }
+}
+
+// scheduling jitter allowed on top of twice the buffer length before a late callback counts
+const XRUN_SLACK: Duration = Duration::from_millis(5);
+
+impl AudioCallback for AudioPlayer {
+    type Channel = i16;
+
+    fn callback(&mut self, out: &mut [i16]) {
+        while let Ok(command) = self.commands.pop() {
+            self.apply(command);
+        }
+        match self.binaural.take() {
+            Some(mut binaural) => {
+                let channels = self.channels.max(1) as usize;
+                let mut frames = std::mem::take(&mut self.file_frames);
+                frames.resize(out.len() / 2 * channels, 0);
+                self.fill(&mut frames);
+                binaural.render(&frames, out);
+                self.file_frames = frames;
+                self.binaural = Some(binaural);
+            }
+            None => self.fill(out),
+        }
+    }
+}
+
+impl AudioPlayer {
+    // a buffer of the file's channels
+    fn fill(&mut self, out: &mut [i16]) {
+        let channels = self.channels.max(1) as usize;
+        if self.scrub.is_some() {
+            self.last_callback = None;
+            self.fill_scrub(out);
+            return;
+        }
+        if !self.playing {
+            // nothing is due while paused, so the next buffer after resuming isn't late
+            self.last_callback = None;
+            out.fill(0);
+            return;
+        }
+        let started = self.clock.now();
+        self.check_xrun(out.len() / channels);
+
+        if self.speed != 1.0 {
+            self.fill_varispeed(out);
+        } else {
+            let mut tone = 0.0;
+            for (i, sample) in out.iter_mut().enumerate() {
+                if let Some(range) = &self.loop_range
+                    && self.position >= range.end
+                {
+                    self.position = range.start;
+                } else if self.wraps() && self.position >= self.data.len() {
+                    // the frames the crossfade already played the start of are skipped
+                    self.position = self.loop_fade() * channels;
+                    self.ended = false;
+                }
+                // the guide tone advances once per frame and lands on every channel
+                if i % channels == 0 {
+                    tone = self.guide_tone.next_sample() * 32767.0;
+                }
+                let value = match &self.compare {
+                    Some(compare) if self.play_compare => {
+                        let index =
+                            self.position as isize - self.compare_offset * channels as isize;
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|index| compare.get(index))
+                            .copied()
+                            .unwrap_or(0)
+                    }
+                    _ if self.wraps() => self.looped_sample(self.position),
+                    _ => self.data.get(self.position).copied().unwrap_or(0),
+                };
+                *sample = self.output(i % channels, value as f32, tone);
+                self.position += 1;
+            }
+        }
+
+        let timing = &self.timing;
+        timing
+            .last_callback
+            .store(started.as_nanos() as u64, Ordering::Relaxed);
+        let duration = self.clock.now().saturating_sub(started);
+        timing
+            .callback_duration
+            .store(duration.as_nanos() as u64, Ordering::Relaxed);
+        timing
+            .buffer_frames
+            .store((out.len() / channels) as u64, Ordering::Relaxed);
+
+        self.emit(PlaybackEvent::PositionChanged(self.position));
+        // backwards the end is the start of the file
+        let at_end = if self.speed < 0.0 {
+            self.position == 0 && self.fraction == 0.0
+        } else {
+            self.position >= self.data.len()
+        };
+        if at_end && !self.ended && !self.wraps() {
+            self.ended = true;
+            self.emit(PlaybackEvent::EndOfStream);
+        }
+    }
+}
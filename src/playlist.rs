@@ -0,0 +1,241 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{error, fs};
+
+// order the expanded files are played in
+#[derive(Clone, Copy)]
+pub enum Sort {
+    Name,
+    // oldest first
+    Mtime,
+    Random,
+}
+
+impl Sort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(Self::Name),
+            "mtime" => Some(Self::Mtime),
+            "random" => Some(Self::Random),
+            _ => None,
+        }
+    }
+}
+
+// the files directories are searched for, and whether this build can read them
+const AUDIO_EXTENSIONS: &[(&str, bool)] = &[
+    ("wav", true),
+    ("aif", true),
+    ("aiff", true),
+    ("aifc", true),
+    ("flac", cfg!(feature = "flac")),
+    ("mp3", cfg!(feature = "mp3")),
+    ("ogg", cfg!(feature = "ogg")),
+    ("oga", cfg!(feature = "ogg")),
+    ("opus", cfg!(feature = "ogg")),
+];
+
+// turns the player's arguments into the files to play. Directories stand for every audio file
+// below them, and patterns with `*`, `?` or `**` are expanded here so they also work quoted or
+// from shells that don't expand `**`. M3U and PLS playlists stand for the files they list.
+// Anything else is taken as a file name as is
+pub fn expand(args: &[String], sort: Sort) -> Result<Vec<String>, Box<dyn error::Error + 'static>> {
+    let mut files = Vec::new();
+    for arg in args {
+        let before = files.len();
+        if is_pattern(arg) {
+            let (root, pattern) = split_pattern(arg);
+            let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+            glob(&root, &components, &mut files);
+        } else if Path::new(arg).is_dir() {
+            wav_files(Path::new(arg), &mut files);
+        } else if is_playlist(Path::new(arg)) {
+            files.extend(read_playlist(Path::new(arg))?);
+        } else {
+            files.push(PathBuf::from(arg));
+        }
+        if files.len() == before {
+            return Err(format!("{arg}: no wav files found").into());
+        }
+    }
+
+    match sort {
+        Sort::Name => files.sort(),
+        Sort::Mtime => files.sort_by_key(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(UNIX_EPOCH)
+        }),
+        Sort::Random => shuffle(&mut files),
+    }
+    Ok(files
+        .into_iter()
+        .map(|file| file.to_string_lossy().into_owned())
+        .collect())
+}
+
+// writes `files` as an extended M3U playlist. Paths are written as they are, relative ones
+// resolve against the current directory, so the playlist is best saved there
+pub fn write_m3u(path: &Path, files: &[String]) -> io::Result<()> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    writeln!(out, "#EXTM3U")?;
+    for file in files {
+        writeln!(out, "{file}")?;
+    }
+    out.flush()
+}
+
+fn is_playlist(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        ["m3u", "m3u8", "pls"]
+            .iter()
+            .any(|playlist| extension.eq_ignore_ascii_case(playlist))
+    })
+}
+
+// the files a playlist lists, in its order. Relative entries are relative to the playlist
+// itself, and urls are skipped since only local files can be played
+fn read_playlist(path: &Path) -> Result<Vec<PathBuf>, Box<dyn error::Error + 'static>> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let contents = String::from_utf8_lossy(&bytes);
+    let pls = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pls"));
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let entries = contents.lines().filter_map(|line| {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if pls {
+            // `File1=path`, everything else in a pls is titles and lengths
+            let (key, value) = line.split_once('=')?;
+            key.trim()
+                .to_ascii_lowercase()
+                .starts_with("file")
+                .then_some(value.trim())
+        } else {
+            // comments and the #EXTINF lines of extended m3u
+            (!line.is_empty() && !line.starts_with('#')).then_some(line)
+        }
+    });
+    Ok(entries
+        .filter(|entry| !entry.contains("://") || entry.starts_with("file://"))
+        .map(|entry| {
+            let entry = Path::new(entry.strip_prefix("file://").unwrap_or(entry));
+            if entry.is_absolute() {
+                entry.to_path_buf()
+            } else {
+                strip_dot(base.join(entry))
+            }
+        })
+        .collect())
+}
+
+fn is_pattern(arg: &str) -> bool {
+    arg.contains(['*', '?'])
+}
+
+// the directory before the first component with a wildcard, and the rest of the pattern
+fn split_pattern(pattern: &str) -> (PathBuf, &str) {
+    let wildcard = pattern.find(['*', '?']).unwrap_or(pattern.len());
+    match pattern[..wildcard].rfind('/') {
+        Some(0) => (PathBuf::from("/"), &pattern[1..]),
+        Some(slash) => (PathBuf::from(&pattern[..slash]), &pattern[slash + 1..]),
+        None => (PathBuf::from("."), pattern),
+    }
+}
+
+// walks `dir` matching one path component per level, `**` matches any number of them
+fn glob(dir: &Path, components: &[&str], files: &mut Vec<PathBuf>) {
+    let Some((&component, rest)) = components.split_first() else {
+        return;
+    };
+    if component == "**" {
+        glob(dir, rest, files);
+        for entry in entries(dir) {
+            if entry.is_dir() {
+                glob(&entry, components, files);
+            }
+        }
+        return;
+    }
+    for entry in entries(dir) {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        // like shells, wildcards don't match hidden files
+        if name.starts_with('.') && !component.starts_with('.') {
+            continue;
+        }
+        if !matches(component.as_bytes(), name.as_bytes()) {
+            continue;
+        }
+        if rest.is_empty() {
+            if entry.is_file() {
+                files.push(strip_dot(entry));
+            }
+        } else if entry.is_dir() {
+            glob(&entry, rest, files);
+        }
+    }
+}
+
+// `*` and `?` matching within a single path component
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+fn wav_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in entries(dir) {
+        if entry.is_dir() {
+            wav_files(&entry, files);
+        } else if is_audio(&entry) {
+            files.push(entry);
+        }
+    }
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        AUDIO_EXTENSIONS
+            .iter()
+            .any(|&(audio, readable)| readable && extension.eq_ignore_ascii_case(audio))
+    })
+}
+
+// unreadable directories are skipped like empty ones
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries.flatten().map(|entry| entry.path()).collect()
+}
+
+// `./a.wav` reads better as `a.wav` in the window title and listings
+fn strip_dot(path: PathBuf) -> PathBuf {
+    match path.strip_prefix(".") {
+        Ok(stripped) => stripped.to_path_buf(),
+        Err(_) => path,
+    }
+}
+
+// fisher-yates with a xorshift generator seeded from the clock, good enough for shuffling a
+// playlist without pulling in a random number crate
+fn shuffle<T>(items: &mut [T]) {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |time| time.as_nanos() as u64)
+        | 1;
+    for i in (1..items.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
@@ -0,0 +1,192 @@
+// third party effects in the playback chain, CLAP plugins hosted by `clap_host` with the
+// `clap` feature and LV2 ones by `lv2_host` with the `lv2` feature. Without either `--plugin`
+// is an error and there's never a plugin to play through
+
+use std::error;
+#[cfg(feature = "clap")]
+use std::path::Path;
+#[cfg(any(feature = "clap", feature = "lv2"))]
+use std::sync::Arc;
+
+#[cfg(feature = "clap")]
+use crate::clap_host;
+#[cfg(feature = "lv2")]
+use crate::lv2_host;
+
+// frames a plugin processes at a time, and so the latency hosting adds on top of its own
+#[cfg(any(feature = "clap", feature = "lv2"))]
+pub const BLOCK: usize = 256;
+
+// a parameter of the plugin as the generic controls show it, hidden and read-only ones are
+// left out
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub id: u32,
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub value: f64,
+    // only takes whole numbers, like a mode switch
+    pub stepped: bool,
+}
+
+impl Parameter {
+    // the value `steps` steps up or down, a fiftieth of the range each or 1 for stepped ones
+    pub fn step(&self, steps: f64) -> f64 {
+        let step = if self.stepped {
+            1.0
+        } else {
+            (self.max - self.min) / 50.0
+        };
+        (self.value + step * steps).clamp(self.min, self.max)
+    }
+}
+
+// a plugin found and loaded, ready to make an instance for every file played
+pub enum PluginLibrary {
+    #[cfg(feature = "clap")]
+    Clap(Arc<clap_host::PluginLibrary>),
+    #[cfg(feature = "lv2")]
+    Lv2(Arc<lv2_host::PluginLibrary>),
+}
+
+// without a backend the types are empty and nothing is done with the arguments
+#[cfg_attr(not(any(feature = "clap", feature = "lv2")), allow(unused_variables))]
+impl PluginLibrary {
+    // LV2 plugins go by their URI, CLAP ones by their file
+    pub fn open(name: &str) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let uri = name.contains("://") || name.starts_with("urn:");
+        #[cfg(feature = "lv2")]
+        if uri {
+            return Ok(Self::Lv2(lv2_host::PluginLibrary::open(name)?));
+        }
+        #[cfg(feature = "clap")]
+        if !uri || Path::new(name).exists() {
+            return Ok(Self::Clap(clap_host::PluginLibrary::open(name)?));
+        }
+        Err(match uri {
+            true => "LV2 plugins need wave built with the lv2 feature",
+            false => "CLAP plugins need wave built with the clap feature",
+        }
+        .into())
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            #[cfg(feature = "clap")]
+            Self::Clap(ref library) => library.name(),
+            #[cfg(feature = "lv2")]
+            Self::Lv2(ref library) => library.name(),
+        }
+    }
+
+    // an instance for a file with `channels` at `sample_rate`, with the parameters it starts
+    // out with
+    pub fn instantiate(
+        &self,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<(Plugin, Vec<Parameter>), Box<dyn error::Error + 'static>> {
+        match *self {
+            #[cfg(feature = "clap")]
+            Self::Clap(ref library) => {
+                let (plugin, parameters) = library.instantiate(sample_rate, channels)?;
+                Ok((Plugin::Clap(plugin), parameters))
+            }
+            #[cfg(feature = "lv2")]
+            Self::Lv2(ref library) => {
+                let (plugin, parameters) = library.instantiate(sample_rate, channels)?;
+                Ok((Plugin::Lv2(plugin), parameters))
+            }
+        }
+    }
+}
+
+// an instance, run a sample at a time like the filters with the output a block late
+pub enum Plugin {
+    #[cfg(feature = "clap")]
+    Clap(clap_host::Plugin),
+    #[cfg(feature = "lv2")]
+    Lv2(lv2_host::Plugin),
+}
+
+#[cfg_attr(not(any(feature = "clap", feature = "lv2")), allow(unused_variables))]
+impl Plugin {
+    // bypassed, the samples pass through as they are
+    pub fn set_enabled(&mut self, enabled: bool) {
+        match *self {
+            #[cfg(feature = "clap")]
+            Self::Clap(ref mut plugin) => plugin.set_enabled(enabled),
+            #[cfg(feature = "lv2")]
+            Self::Lv2(ref mut plugin) => plugin.set_enabled(enabled),
+        }
+    }
+
+    // `id` as in `Parameter::id`, the value takes effect from the next block
+    pub fn set_parameter(&mut self, id: u32, value: f64) {
+        match *self {
+            #[cfg(feature = "clap")]
+            Self::Clap(ref mut plugin) => plugin.set_parameter(id, value),
+            #[cfg(feature = "lv2")]
+            Self::Lv2(ref mut plugin) => plugin.set_parameter(id, value),
+        }
+    }
+
+    pub fn process(&mut self, channel: usize, value: f32) -> f32 {
+        match *self {
+            #[cfg(feature = "clap")]
+            Self::Clap(ref mut plugin) => plugin.process(channel, value),
+            #[cfg(feature = "lv2")]
+            Self::Lv2(ref mut plugin) => plugin.process(channel, value),
+        }
+    }
+}
+
+// the file's samples gathered into blocks for a plugin, and what it made of the previous block
+// handed back in their place. Channels of the file beyond the plugin's inputs are left out of
+// it and extra input channels repeat the file's, the outputs are spread over the file's
+// channels the same way
+#[cfg(any(feature = "clap", feature = "lv2"))]
+pub struct Blocks {
+    channels: usize,
+    // a block per channel of the plugin, -1.0..1.0
+    pub inputs: Vec<Vec<f32>>,
+    pub outputs: Vec<Vec<f32>>,
+    // frames of the current block filled in
+    filled: usize,
+}
+
+#[cfg(any(feature = "clap", feature = "lv2"))]
+impl Blocks {
+    pub fn new(channels: u16, inputs: usize, outputs: usize) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            inputs: vec![vec![0.0; BLOCK]; inputs],
+            outputs: vec![vec![0.0; BLOCK]; outputs],
+            filled: 0,
+        }
+    }
+
+    // takes a sample of `channel` and gives back the plugin's from a block earlier, along with
+    // whether a whole block is now waiting to be processed. There has to be an output
+    pub fn exchange(&mut self, channel: usize, value: f32) -> (f32, bool) {
+        let out = self.outputs[channel % self.outputs.len()][self.filled];
+        for (_, input) in self
+            .inputs
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, _)| i % self.channels == channel)
+        {
+            input[self.filled] = value / 32768.0;
+        }
+        let mut full = false;
+        if channel + 1 == self.channels {
+            self.filled += 1;
+            if self.filled == BLOCK {
+                self.filled = 0;
+                full = true;
+            }
+        }
+        (out * 32768.0, full)
+    }
+}
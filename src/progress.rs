@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::{error, fmt, io};
+
+// how often long operations report, in whatever unit they count (bytes, samples)
+const STEP: usize = 1 << 16;
+const BAR_WIDTH: usize = 30;
+
+// how far a long operation got
+#[derive(Clone, Copy)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn fraction(self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+// called as a long operation goes along, returning Break abandons it and the operation
+// fails with `Cancelled`
+pub type Callback<'a> = &'a mut dyn FnMut(Progress) -> ControlFlow<()>;
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+
+impl error::Error for Cancelled {}
+
+// shared flag telling a long operation on another thread to stop at its next progress report
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// rate limits the callback to every STEP units and once more at the end, checking for
+// cancellation each time
+pub struct Reporter<'a> {
+    callback: Callback<'a>,
+    cancel: &'a CancellationToken,
+    total: usize,
+    next: usize,
+}
+
+impl<'a> Reporter<'a> {
+    pub fn new(callback: Callback<'a>, cancel: &'a CancellationToken, total: usize) -> Self {
+        Self {
+            callback,
+            cancel,
+            total,
+            next: 0,
+        }
+    }
+
+    pub fn update(&mut self, done: usize) -> Result<(), Cancelled> {
+        if done < self.next && done < self.total {
+            return Ok(());
+        }
+        if self.cancel.is_cancelled() {
+            return Err(Cancelled);
+        }
+        self.next = done + STEP;
+        let progress = Progress {
+            done,
+            total: self.total,
+        };
+        match (self.callback)(progress) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(Cancelled),
+        }
+    }
+}
+
+// a long operation running on its own thread. Dropping the job cancels it and waits for the
+// thread to notice, so abandoned work never keeps running in the background
+pub struct Job<T> {
+    cancel: CancellationToken,
+    progress: Arc<Mutex<Progress>>,
+    handle: Option<JoinHandle<Result<T, Cancelled>>>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn(
+        operation: impl FnOnce(Callback, &CancellationToken) -> Result<T, Cancelled> + Send + 'static,
+    ) -> Self {
+        let cancel = CancellationToken::new();
+        let progress = Arc::new(Mutex::new(Progress { done: 0, total: 1 }));
+        let handle = {
+            let cancel = cancel.clone();
+            let progress = progress.clone();
+            thread::spawn(move || {
+                operation(
+                    &mut |update| {
+                        *progress.lock().unwrap() = update;
+                        ControlFlow::Continue(())
+                    },
+                    &cancel,
+                )
+            })
+        };
+        Self {
+            cancel,
+            progress,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        *self.progress.lock().unwrap()
+    }
+
+    // the result once the thread is done, None while it is still running
+    pub fn try_take(&mut self) -> Option<Result<T, Cancelled>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        Some(self.handle.take()?.join().unwrap_or(Err(Cancelled)))
+    }
+}
+
+impl<T> Drop for Job<T> {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+// a callback drawing `label [#####     ]  50%` on stderr
+pub fn terminal_bar(label: &str) -> impl FnMut(Progress) -> ControlFlow<()> + '_ {
+    move |progress| {
+        let filled = (progress.fraction() * BAR_WIDTH as f32) as usize;
+        let mut stderr = io::stderr();
+        write!(
+            stderr,
+            "\r{label} [{}{}] {:>3.0}%",
+            "#".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            progress.fraction() * 100.0
+        )
+        .ok();
+        if progress.done >= progress.total {
+            writeln!(stderr).ok();
+        }
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::{Duration, Instant};
+
+// time the ui may spend drawing a frame, it then sleeps to pace itself at 60 fps
+pub const FRAME_BUDGET: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// consecutive frames over budget before the quality is lowered a step
+const SLOW_FRAMES: u32 = 10;
+// consecutive frames well under budget before it is raised again, slower than lowering so it
+// doesn't flip back and forth
+const FAST_FRAMES: u32 = 120;
+// 1/8 of the waveform samples and spectrogram columns at most
+const LOWEST: u32 = 3;
+
+// how much detail the views draw, lowered while the render loop misses its frame deadline
+// so a slow machine keeps a responsive ui instead of starving everything else. Each level
+// halves the waveform samples drawn and the spectrogram columns transformed
+#[derive(Default)]
+pub struct RenderQuality {
+    level: u32,
+    slow: u32,
+    fast: u32,
+}
+
+impl RenderQuality {
+    // feeds in how long the last frame took to draw, true when the level changed
+    pub fn frame_took(&mut self, elapsed: Duration) -> bool {
+        if elapsed > FRAME_BUDGET {
+            self.slow += 1;
+            self.fast = 0;
+        } else if elapsed < FRAME_BUDGET / 2 {
+            self.fast += 1;
+            self.slow = 0;
+        } else {
+            self.slow = 0;
+            self.fast = 0;
+        }
+
+        if self.slow >= SLOW_FRAMES && self.level < LOWEST {
+            self.level += 1;
+        } else if self.fast >= FAST_FRAMES && self.level > 0 {
+            self.level -= 1;
+        } else {
+            return false;
+        }
+        self.slow = 0;
+        self.fast = 0;
+        true
+    }
+
+    // draw every `step()`th sample or column
+    pub fn step(&self) -> usize {
+        1 << self.level
+    }
+
+    pub fn reduced(&self) -> bool {
+        self.level > 0
+    }
+}
+
+// frames per second actually drawn, smoothed so the readout doesn't flicker
+#[derive(Default)]
+pub struct FrameRate {
+    last: Option<Instant>,
+    fps: f32,
+}
+
+impl FrameRate {
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last.replace(now) {
+            let fps = 1.0 / (now - last).as_secs_f32().max(1e-6);
+            self.fps = if self.fps == 0.0 {
+                fps
+            } else {
+                self.fps * 0.9 + fps * 0.1
+            };
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
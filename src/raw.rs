@@ -0,0 +1,289 @@
+// headerless PCM, what `ffmpeg -f s16le` and DSP code dumping its buffers write. Nothing in
+// the bytes says what they are, so the rate, channels and sample format have to be given
+
+use std::io::{self, BufReader, Read};
+
+use crate::metadata::Metadata;
+use crate::reader::{Dither, read_all_or_nothing};
+use crate::{AudioSource, Header, g711};
+
+/// How the samples of a raw file are stored, named like ffmpeg's formats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RawFormat {
+    U8,
+    S8,
+    S16Le,
+    S16Be,
+    S24Le,
+    S24Be,
+    S32Le,
+    S32Be,
+    F32Le,
+    F32Be,
+    F64Le,
+    F64Be,
+    ALaw,
+    MuLaw,
+}
+
+impl RawFormat {
+    /// `s16le`, `f32be` and so on, or `alaw` and `mulaw`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "u8" => Self::U8,
+            "s8" => Self::S8,
+            "s16le" => Self::S16Le,
+            "s16be" => Self::S16Be,
+            "s24le" => Self::S24Le,
+            "s24be" => Self::S24Be,
+            "s32le" => Self::S32Le,
+            "s32be" => Self::S32Be,
+            "f32le" => Self::F32Le,
+            "f32be" => Self::F32Be,
+            "f64le" => Self::F64Le,
+            "f64be" => Self::F64Be,
+            "alaw" => Self::ALaw,
+            "mulaw" => Self::MuLaw,
+            _ => return None,
+        })
+    }
+
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::U8 | Self::S8 | Self::ALaw | Self::MuLaw => 1,
+            Self::S16Le | Self::S16Be => 2,
+            Self::S24Le | Self::S24Be => 3,
+            Self::S32Le | Self::S32Be | Self::F32Le | Self::F32Be => 4,
+            Self::F64Le | Self::F64Be => 8,
+        }
+    }
+
+    // the wav format code the samples would have
+    fn audio_format(self) -> u16 {
+        match self {
+            Self::F32Le | Self::F32Be | Self::F64Le | Self::F64Be => 3,
+            Self::ALaw => 6,
+            Self::MuLaw => 7,
+            _ => 1,
+        }
+    }
+}
+
+/// What a raw file can't say about itself.
+#[derive(Clone, Copy, Debug)]
+pub struct RawSpec {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub format: RawFormat,
+}
+
+/// Reads headerless samples as described by a [`RawSpec`], like [`crate::WavReader`] reads
+/// a wav file. A partial frame at the end is left out.
+pub struct RawReader<R> {
+    reader: BufReader<R>,
+    header: Header,
+    format: RawFormat,
+    data_size: u64,
+    remaining: u64,
+    dither: Dither,
+    // the reader ran out, possibly partway through a frame
+    ended: bool,
+}
+
+impl<R: Read> RawReader<R> {
+    /// `size` is the number of bytes to read if known, the length of the file, otherwise
+    /// everything up to the end of `reader` is read.
+    pub fn new(reader: R, spec: RawSpec, size: Option<u64>) -> Result<Self, String> {
+        if spec.sample_rate == 0 || spec.channels == 0 {
+            return Err("raw input needs a sample rate and channel count above 0".into());
+        }
+        let width = spec.format.bytes() as u16;
+        // the header fields these end up in are only 16 and 32 bits wide
+        let block_align = spec
+            .channels
+            .checked_mul(width)
+            .ok_or("raw input has too many channels for one frame to fit a wav header")?;
+        let byte_rate = spec
+            .sample_rate
+            .checked_mul(block_align as u32)
+            .ok_or("raw input has too many bytes per second to fit a wav header")?;
+        let data_size = size.map_or(0, |size| size - size % block_align as u64);
+        let header = Header {
+            audio_format: spec.format.audio_format(),
+            num_channels: spec.channels,
+            sample_rate: spec.sample_rate,
+            byte_rate,
+            block_align,
+            bits_per_sample: width * 8,
+            subchunk2_size: data_size.min(u32::MAX as u64) as u32,
+            ..Header::default()
+        };
+        Ok(Self {
+            reader: BufReader::new(reader),
+            header,
+            format: spec.format,
+            data_size,
+            remaining: size.map_or(u64::MAX, |_| data_size),
+            dither: Dither::default(),
+            ended: false,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The size given to [`RawReader::new`] in whole frames, 0 if there was none.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Bytes not read yet, 0 when the size isn't known.
+    pub fn remaining(&self) -> u64 {
+        if self.remaining == u64::MAX {
+            0
+        } else {
+            self.remaining
+        }
+    }
+
+    /// Decodes the next samples into `out`, wider ones dithered down to 16 bits. Always
+    /// whole frames, 0 at the end.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        let width = self.format.bytes();
+        let channels = self.header.num_channels as usize;
+        let frames = (out.len() / channels) as u64;
+        let frames = frames.min(self.remaining / (width * channels) as u64) as usize;
+        if self.ended || frames == 0 {
+            return Ok(0);
+        }
+        let mut bytes = vec![0; frames * channels * width];
+        let mut count = 0;
+        for frame in bytes.chunks_exact_mut(channels * width) {
+            if !read_all_or_nothing(&mut self.reader, frame)? {
+                self.ended = true;
+                break;
+            }
+            count += frame.len();
+        }
+        self.remaining = self.remaining.saturating_sub(count as u64);
+        for (sample, b) in out.iter_mut().zip(bytes[..count].chunks_exact(width)) {
+            *sample = match self.format {
+                RawFormat::U8 => (b[0] as i16 - 128) << 8,
+                RawFormat::S8 => (b[0] as i8 as i16) << 8,
+                RawFormat::S16Le => i16::from_le_bytes([b[0], b[1]]),
+                RawFormat::S16Be => i16::from_be_bytes([b[0], b[1]]),
+                RawFormat::S24Le => {
+                    let value = i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8;
+                    self.dither.quantize(value as f32 / 256.0)
+                }
+                RawFormat::S24Be => {
+                    let value = i32::from_be_bytes([b[0], b[1], b[2], 0]) >> 8;
+                    self.dither.quantize(value as f32 / 256.0)
+                }
+                RawFormat::S32Le => {
+                    let value = i32::from_le_bytes(b.try_into().unwrap());
+                    self.dither.quantize(value as f32 / 65536.0)
+                }
+                RawFormat::S32Be => {
+                    let value = i32::from_be_bytes(b.try_into().unwrap());
+                    self.dither.quantize(value as f32 / 65536.0)
+                }
+                RawFormat::F32Le => {
+                    float(&mut self.dither, f32::from_le_bytes(b.try_into().unwrap()))
+                }
+                RawFormat::F32Be => {
+                    float(&mut self.dither, f32::from_be_bytes(b.try_into().unwrap()))
+                }
+                RawFormat::F64Le => float(
+                    &mut self.dither,
+                    f64::from_le_bytes(b.try_into().unwrap()) as f32,
+                ),
+                RawFormat::F64Be => float(
+                    &mut self.dither,
+                    f64::from_be_bytes(b.try_into().unwrap()) as f32,
+                ),
+                RawFormat::ALaw => g711::alaw_to_linear(b[0]),
+                RawFormat::MuLaw => g711::ulaw_to_linear(b[0]),
+            };
+        }
+        Ok(count / width)
+    }
+
+    /// There's nothing but samples, so no tags either.
+    pub fn finish(self) -> io::Result<Metadata> {
+        Ok(Metadata::default())
+    }
+}
+
+// -1.0..1.0 in 16-bit steps
+fn float(dither: &mut Dither, value: f32) -> i16 {
+    let value = if value.is_nan() { 0.0 } else { value };
+    dither.quantize(value * 32768.0)
+}
+
+impl<R: Read> AudioSource for RawReader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::signal;
+
+    #[test]
+    fn raw_big_endian_samples_drop_a_partial_last_frame() {
+        let samples = signal(2, 8000, 100);
+        let mut bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let spec = RawSpec {
+            sample_rate: 8000,
+            channels: 2,
+            format: RawFormat::S16Be,
+        };
+        let size = bytes.len() as u64;
+        for size in [Some(size), None] {
+            let mut reader = RawReader::new(&bytes[..], spec, size).unwrap();
+            let mut block = [0; 7];
+            let mut read = Vec::new();
+            loop {
+                let count = reader.read_samples(&mut block).unwrap();
+                if count == 0 {
+                    break;
+                }
+                read.extend_from_slice(&block[..count]);
+            }
+            assert_eq!(read, samples);
+        }
+    }
+
+    #[test]
+    fn raw_specs_too_big_for_a_wav_header_are_refused() {
+        for (sample_rate, channels) in [(8000, 32768), (4_000_000_000, 2)] {
+            let spec = RawSpec {
+                sample_rate,
+                channels,
+                format: RawFormat::S16Le,
+            };
+            assert!(RawReader::new(&[0u8; 64][..], spec, Some(64)).is_err());
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use std::io::{self, BufReader, Read};
+use std::{error, str};
+
+use crate::diagnostic::{self, Diagnostic};
+use crate::metadata::Metadata;
+use crate::riff::Chunk;
+use crate::{AudioSource, FORMAT_EXTENSIBLE, Header, bext, cue, g711, id3, info_list, smpl};
+
+// KSDATAFORMAT_SUBTYPE_* GUIDs after their first two bytes
+const GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+/// Reads a wav file front to back without loading it first: the header when it's created,
+/// then the samples a block at a time with [`WavReader::read_samples`], then the metadata
+/// chunks after them with [`WavReader::finish`].
+pub struct WavReader<R> {
+    reader: BufReader<R>,
+    header: Header,
+    // bytes of the data chunk, from the ds64 chunk for RF64 files, and how many aren't read yet
+    data_size: u64,
+    remaining: u64,
+    encoding: Encoding,
+    dither: Dither,
+    // tags from chunks that came before the data
+    metadata: Metadata,
+    id3_metadata: Metadata,
+    // names of cue points from a LIST adtl chunk, which can come before or after the cues
+    cue_labels: Vec<(u32, String)>,
+    // what doesn't follow the spec, so far
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<R: Read> WavReader<R> {
+    /// Reads the header, walking the chunks up to `data` and skipping the ones it doesn't
+    /// know wherever they are. Those and anything else off about the file end up in
+    /// [`Metadata::diagnostics`].
+    pub fn new(reader: R) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let mut reader = BufReader::new(reader);
+        let mut header = Header {
+            chunk_id: read_id(&mut reader)?,
+            chunk_size: read_u32(&mut reader)?,
+            format: read_id(&mut reader)?,
+            ..Header::default()
+        };
+        // RF64 and its successor BW64 are RIFF with the sizes past 4 GB in a ds64 chunk
+        if !matches!(&*header.chunk_id, "RIFF" | "RF64" | "BW64") || &*header.format != "WAVE" {
+            return Err("not a RIFF/WAVE file".into());
+        }
+
+        let mut has_format = false;
+        let mut metadata = Metadata::default();
+        let mut id3_metadata = Metadata::default();
+        let mut cue_labels = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut ds64_data_size = None;
+        let (encoding, data_size) = loop {
+            let mut chunk_header = [0; 8];
+            if !read_all_or_nothing(&mut reader, &mut chunk_header)? {
+                return Err("no data chunk".into());
+            }
+            // an id that isn't text can't be one wave knows, it's skipped like any other
+            let id = String::from_utf8_lossy(&chunk_header[..4]);
+            let id = &*id;
+            let size = u32::from_le_bytes(chunk_header[4..].try_into().unwrap());
+            match id {
+                "fmt " if size >= 16 => {
+                    header.subchunk1_id = id.into();
+                    header.subchunk1_size = size;
+                    header.audio_format = read_u16(&mut reader)?;
+                    header.num_channels = read_u16(&mut reader)?;
+                    header.sample_rate = read_u32(&mut reader)?;
+                    header.byte_rate = read_u32(&mut reader)?;
+                    header.block_align = read_u16(&mut reader)?;
+                    header.bits_per_sample = read_u16(&mut reader)?;
+                    let mut read = 16;
+                    if header.audio_format == FORMAT_EXTENSIBLE && size >= 40 {
+                        // cbSize, always 22 here
+                        read_u16(&mut reader)?;
+                        header.valid_bits_per_sample = read_u16(&mut reader)?;
+                        header.channel_mask = read_u32(&mut reader)?;
+                        let mut guid = [0; 16];
+                        reader.read_exact(&mut guid).map_err(truncated)?;
+                        // the format code followed by the same 14 bytes for every format
+                        // microsoft defined
+                        if guid[2..] != GUID_TAIL {
+                            return Err("unknown extensible subformat".into());
+                        }
+                        header.sub_format = u16::from_le_bytes([guid[0], guid[1]]);
+                        read = 40;
+                    }
+                    // anything else formats carry after their fields
+                    skip(&mut reader, (size - read) as u64 + size as u64 % 2)?;
+                    has_format = true;
+                    check_format(&header, &mut diagnostics)?;
+                }
+                "data" => {
+                    if !has_format {
+                        return Err("data chunk before fmt".into());
+                    }
+                    header.subchunk2_id = id.into();
+                    header.subchunk2_size = size;
+                    // an RF64 file sets the 32-bit size to all ones
+                    let data_size = match ds64_data_size {
+                        Some(ds64) if size == u32::MAX => ds64,
+                        _ => size as u64,
+                    };
+                    break (Encoding::of(&header)?, data_size);
+                }
+                "ds64" => {
+                    let body = read_body(&mut reader, size)?;
+                    // the riff size, then the data size, then the sample count and a table
+                    // for other oversized chunks, which only ever hold audio
+                    ds64_data_size = body
+                        .get(8..16)
+                        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+                }
+                _ => {
+                    let body = read_body(&mut reader, size)?;
+                    check_chunk(id, size, &body, &mut diagnostics);
+                    collect(id, &body, &mut metadata, &mut id3_metadata, &mut cue_labels);
+                    // the sample count of a compressed format doesn't hold for the samples
+                    // once they're decoded
+                    if id != "fact" {
+                        metadata.chunks.push(Chunk {
+                            id: chunk_header[..4].try_into().unwrap(),
+                            data: body,
+                        });
+                    }
+                }
+            }
+        };
+        Ok(Self {
+            reader,
+            data_size,
+            remaining: data_size,
+            encoding,
+            dither: Dither::default(),
+            header,
+            metadata,
+            id3_metadata,
+            cue_labels,
+            diagnostics,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Size of the data chunk in bytes. The same as the header's `subchunk2_size` except
+    /// for RF64 files, whose data chunks can be larger than 4 GB.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Bytes of the data chunk not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Decodes the next samples into `out`, returning how many. 0 means the data chunk is
+    /// done, a file that ends before it is an `UnexpectedEof` error. 24 and 32-bit and float
+    /// samples are dithered down to 16 bits, floats past full scale are clipped. A-law and
+    /// µ-law ones are expanded to 16 bits.
+    pub fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        let width = self.encoding.bytes();
+        let count = (self.remaining / width as u64).min(out.len() as u64) as usize;
+        let mut bytes = vec![0; count * width];
+        self.reader.read_exact(&mut bytes).map_err(truncated)?;
+        self.remaining -= bytes.len() as u64;
+        let samples = out.iter_mut().zip(bytes.chunks_exact(width));
+        match self.encoding {
+            Encoding::Int16 => {
+                for (sample, b) in samples {
+                    *sample = i16::from_le_bytes([b[0], b[1]]);
+                }
+            }
+            Encoding::ALaw => {
+                for (sample, b) in samples {
+                    *sample = g711::alaw_to_linear(b[0]);
+                }
+            }
+            Encoding::MuLaw => {
+                for (sample, b) in samples {
+                    *sample = g711::ulaw_to_linear(b[0]);
+                }
+            }
+            Encoding::Int24 => {
+                for (sample, b) in samples {
+                    let value = i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8;
+                    *sample = self.dither.quantize(value as f32 / 256.0);
+                }
+            }
+            Encoding::Int32 => {
+                for (sample, b) in samples {
+                    let value = i32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                    *sample = self.dither.quantize(value as f32 / 65536.0);
+                }
+            }
+            Encoding::Float32 => {
+                for (sample, b) in samples {
+                    let value = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                    // nan would otherwise turn into a silent 0 anyway
+                    let value = if value.is_nan() { 0.0 } else { value };
+                    *sample = self.dither.quantize(value * 32768.0);
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Steps over the next `count` samples without decoding them, returning how many there
+    /// were.
+    pub fn skip_samples(&mut self, count: u64) -> io::Result<u64> {
+        let width = self.encoding.bytes() as u64;
+        let count = count.min(self.remaining / width);
+        skip(&mut self.reader, count * width)?;
+        self.remaining -= count * width;
+        Ok(count)
+    }
+
+    /// Skips whatever is left of the samples and reads the tags in the chunks after them.
+    pub fn finish(mut self) -> io::Result<Metadata> {
+        // half a sample at the end of an odd sized data chunk, and its padding byte
+        skip(&mut self.reader, self.remaining)?;
+        if self.data_size % 2 == 1 {
+            skip_padding(&mut self.reader)?;
+        }
+
+        // recorders append their metadata chunks once the audio is written, anything that
+        // doesn't look like a chunk is left alone
+        loop {
+            let mut chunk_header = [0; 8];
+            if !read_all_or_nothing(&mut self.reader, &mut chunk_header)? {
+                break;
+            }
+            let id = str::from_utf8(&chunk_header[..4])
+                .ok()
+                .filter(|id| id.bytes().all(|b| b.is_ascii_graphic() || b == b' '));
+            let Some(id) = id else {
+                self.diagnostics.push(Diagnostic::TrailingBytes);
+                break;
+            };
+            let size = u32::from_le_bytes(chunk_header[4..].try_into().unwrap());
+            let body = read_body(&mut self.reader, size)?;
+            check_chunk(id, size, &body, &mut self.diagnostics);
+            collect(
+                id,
+                &body,
+                &mut self.metadata,
+                &mut self.id3_metadata,
+                &mut self.cue_labels,
+            );
+            self.metadata.chunks.push(Chunk {
+                id: chunk_header[..4].try_into().unwrap(),
+                data: body,
+            });
+        }
+        // LIST INFO is what `wave tag` always writes, so it wins over an id3 tag that may not
+        // have been updated along with it
+        let mut metadata = self.metadata;
+        metadata.merge(self.id3_metadata);
+        cue::label(&mut metadata.cues, &self.cue_labels);
+        metadata.diagnostics = self.diagnostics;
+        Ok(metadata)
+    }
+}
+
+impl<R: Read> AudioSource for WavReader<R> {
+    fn header(&self) -> &Header {
+        self.header()
+    }
+
+    fn data_size(&self) -> u64 {
+        self.data_size()
+    }
+
+    fn remaining(&self) -> u64 {
+        self.remaining()
+    }
+
+    fn read_samples(&mut self, out: &mut [i16]) -> io::Result<usize> {
+        self.read_samples(out)
+    }
+
+    fn skip_samples(&mut self, count: u64) -> io::Result<u64> {
+        self.skip_samples(count)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<Metadata> {
+        (*self).finish()
+    }
+}
+
+// how the samples in the data chunk are stored
+#[derive(Clone, Copy)]
+enum Encoding {
+    Int16,
+    Int24,
+    // usually 24 valid bits padded to 32
+    Int32,
+    // IEEE float, full scale at -1.0..1.0
+    Float32,
+    // companded 8-bit telephony samples
+    ALaw,
+    MuLaw,
+}
+
+impl Encoding {
+    fn of(header: &Header) -> Result<Self, Box<dyn error::Error + 'static>> {
+        match (header.sample_format(), header.bits_per_sample) {
+            (3, 32) => Ok(Self::Float32),
+            (3, bits) => Err(format!("{bits}-bit float samples aren't supported").into()),
+            (6, 8) => Ok(Self::ALaw),
+            (7, 8) => Ok(Self::MuLaw),
+            (format @ (6 | 7), bits) => {
+                Err(format!("{bits}-bit samples in format {format} aren't supported").into())
+            }
+            (_, 16) => Ok(Self::Int16),
+            (_, 24) => Ok(Self::Int24),
+            (_, 32) => Ok(Self::Int32),
+            (_, bits) => Err(format!("{bits}-bit samples aren't supported").into()),
+        }
+    }
+
+    fn bytes(self) -> usize {
+        match self {
+            Self::ALaw | Self::MuLaw => 1,
+            Self::Int16 => 2,
+            Self::Int24 => 3,
+            Self::Int32 | Self::Float32 => 4,
+        }
+    }
+}
+
+// triangular dither of one 16-bit step, so the bits below it turn into a little noise
+// instead of distortion on quiet material. Seeded the same every time, a file always decodes
+// to the same samples
+pub(crate) struct Dither(u32);
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self(0x2545_f491)
+    }
+}
+
+impl Dither {
+    // `value` in 16-bit steps
+    pub(crate) fn quantize(&mut self, value: f32) -> i16 {
+        let noise = self.uniform() + self.uniform() - 1.0;
+        (value + noise)
+            .round()
+            .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    fn uniform(&mut self) -> f32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+// the tags, cue points or loops a chunk holds, if it's one of the kinds that hold any
+fn collect(
+    id: &str,
+    body: &[u8],
+    metadata: &mut Metadata,
+    id3_metadata: &mut Metadata,
+    cue_labels: &mut Vec<(u32, String)>,
+) {
+    match id {
+        "id3 " | "ID3 " => id3_metadata.merge(id3::parse(body)),
+        "LIST" => {
+            metadata.merge(info_list::parse(body));
+            cue_labels.extend(cue::labels(body));
+        }
+        "cue " if metadata.cues.is_empty() => metadata.cues = cue::parse(body),
+        "smpl" if metadata.loops.is_empty() => metadata.loops = smpl::parse(body),
+        "iXML" => metadata.ixml = Some(xml_text(body)),
+        "axml" => metadata.axml = Some(xml_text(body)),
+        "bext" if metadata.bext.is_none() => metadata.bext = bext::parse(body),
+        _ => {}
+    }
+}
+
+// the fields of the fmt chunk that follow from the others but were written differently. No
+// channels or no sample rate leave nothing to play, those are errors
+fn check_format(
+    header: &Header,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), Box<dyn error::Error + 'static>> {
+    if header.num_channels == 0 {
+        return Err("fmt chunk has no channels".into());
+    }
+    if header.sample_rate == 0 {
+        return Err("fmt chunk has a sample rate of 0".into());
+    }
+    let bytes = header.bits_per_sample.div_ceil(8);
+    let block_align = header.num_channels.saturating_mul(bytes);
+    if header.block_align != block_align {
+        diagnostics.push(Diagnostic::BlockAlign {
+            stated: header.block_align,
+            expected: block_align,
+        });
+    }
+    let byte_rate = header.sample_rate.saturating_mul(header.block_align as u32);
+    if header.byte_rate != byte_rate {
+        diagnostics.push(Diagnostic::ByteRate {
+            stated: header.byte_rate,
+            expected: byte_rate,
+        });
+    }
+    if header.valid_bits_per_sample > header.bits_per_sample {
+        diagnostics.push(Diagnostic::ValidBits {
+            valid: header.valid_bits_per_sample,
+            bits: header.bits_per_sample,
+        });
+    }
+    Ok(())
+}
+
+// a chunk cut short by the end of the file, or one nobody specified
+fn check_chunk(id: &str, size: u32, body: &[u8], diagnostics: &mut Vec<Diagnostic>) {
+    if body.len() < size as usize {
+        diagnostics.push(Diagnostic::TruncatedChunk {
+            id: id.to_string(),
+            size,
+            available: body.len(),
+        });
+    } else if !diagnostic::known_chunk(id) {
+        diagnostics.push(Diagnostic::UnknownChunk {
+            id: id.to_string(),
+            size,
+        });
+    }
+}
+
+// a chunk's body and its padding, a chunk cut short keeps what's there
+pub(crate) fn read_body(reader: &mut impl Read, size: u32) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader.take(size as u64).read_to_end(&mut body)?;
+    if size % 2 == 1 {
+        skip_padding(reader)?;
+    }
+    Ok(body)
+}
+
+// skips `count` bytes that all have to be there
+pub(crate) fn skip(reader: &mut impl Read, count: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(count), &mut io::sink())?;
+    if skipped < count {
+        return Err(truncated(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+// chunks are padded to an even size, a file ending right before the padding is fine
+pub(crate) fn skip_padding(reader: &mut impl Read) -> io::Result<()> {
+    let mut padding = [0; 1];
+    match reader.read(&mut padding) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => skip_padding(reader),
+        Err(e) => Err(e),
+    }
+}
+
+// fills `buf`, false if the reader was already at its end. Ending partway through counts as
+// the end too, there is nothing to make of half a chunk header
+pub(crate) fn read_all_or_nothing(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => return Ok(false),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+pub(crate) fn truncated(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected end of file")
+    } else {
+        e
+    }
+}
+
+// xml chunks are often padded with nuls to leave room for later edits
+fn xml_text(body: &[u8]) -> String {
+    String::from_utf8_lossy(body)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string()
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_id(reader: &mut impl Read) -> Result<Box<str>, Box<dyn error::Error + 'static>> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(str::from_utf8(&bytes)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::{decode, insert_chunk, signal, wav, wav24};
+
+    #[test]
+    fn reading_in_small_blocks_matches_reading_whole() {
+        let samples = signal(2, 44100, 1000);
+        let bytes = wav(2, 44100, &samples, &[]);
+        let mut reader = WavReader::new(&bytes[..]).unwrap();
+        let mut block = [0; 7];
+        let mut read = Vec::new();
+        loop {
+            let count = reader.read_samples(&mut block).unwrap();
+            if count == 0 {
+                break;
+            }
+            read.extend_from_slice(&block[..count]);
+        }
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn dithered_24_bit_stays_within_the_dither_of_its_top_16_bits() {
+        let samples = signal(2, 48000, 1000);
+        let wav = decode(&wav24(2, 48000, &samples)).unwrap();
+        assert_eq!(wav.header.bits_per_sample, 24);
+        for (&decoded, &original) in wav.data.iter().zip(&samples) {
+            assert!((decoded as i32 - original as i32).abs() <= 2);
+        }
+    }
+
+    #[test]
+    fn a_chunk_id_that_isnt_text_is_skipped_with_a_diagnostic() {
+        let samples = signal(1, 8000, 100);
+        let bytes = insert_chunk(
+            &wav(1, 8000, &samples, &[]),
+            1,
+            [0xff, b'a', 0xfe, b'b'],
+            vec![0; 6],
+        );
+        let wav = decode(&bytes).unwrap();
+        assert_eq!(&wav.data[..], &samples[..]);
+        assert!(matches!(
+            &wav.metadata.diagnostics[..],
+            [Diagnostic::UnknownChunk { size: 6, .. }]
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use crate::i18n;
+use crate::json::{self, Value};
+
+// the session autosaved, so the chapter markers, selection and everything else set up while
+// going through a file isn't lost when sdl or a driver takes wave down. It's written every
+// few seconds while something changed and removed on a clean exit, so finding it at startup
+// means the last run crashed. There's one file for every instance, the last to save wins
+
+// how often the session is checked for changes and written
+const INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub file: String,
+    // the playhead, all positions are in frames
+    pub frame: usize,
+    pub gain: f32,
+    pub loop_range: Option<Range<usize>>,
+    pub markers: Vec<usize>,
+    pub selection: Option<Range<usize>>,
+}
+
+impl Session {
+    fn to_json(&self) -> String {
+        let range = |range: &Option<Range<usize>>| match range {
+            Some(range) => Value::Array(vec![
+                Value::Number(range.start as f64),
+                Value::Number(range.end as f64),
+            ]),
+            None => Value::Null,
+        };
+        Value::Object(vec![
+            ("file".to_string(), Value::String(self.file.clone())),
+            ("frame".to_string(), Value::Number(self.frame as f64)),
+            ("gain".to_string(), Value::Number(self.gain as f64)),
+            ("loop".to_string(), range(&self.loop_range)),
+            (
+                "markers".to_string(),
+                Value::Array(
+                    self.markers
+                        .iter()
+                        .map(|&frame| Value::Number(frame as f64))
+                        .collect(),
+                ),
+            ),
+            ("selection".to_string(), range(&self.selection)),
+        ])
+        .to_string()
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let document = json::parse(text).ok()?;
+        let frame = |value: &Value| value.as_f64().map(|frame| frame as usize);
+        let range = |key: &str| {
+            let range = document.get(key)?.as_array()?;
+            Some(frame(range.first()?)?..frame(range.get(1)?)?)
+        };
+        Some(Self {
+            file: document.get("file")?.as_str()?.to_string(),
+            frame: frame(document.get("frame")?)?,
+            gain: document.get("gain")?.as_f64()? as f32,
+            loop_range: range("loop"),
+            markers: document
+                .get("markers")?
+                .as_array()?
+                .iter()
+                .filter_map(frame)
+                .collect(),
+            selection: range("selection"),
+        })
+    }
+}
+
+// $WAVE_RECOVERY if set, otherwise $XDG_STATE_HOME/wave/recovery.json or
+// ~/.local/state/wave/recovery.json
+fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("WAVE_RECOVERY") {
+        return Some(path.into());
+    }
+    let state = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(state.join("wave").join("recovery.json"))
+}
+
+// what a run that didn't exit cleanly left behind. It stays on disk until the first autosave
+// of this run replaces it
+pub fn leftover() -> Option<Session> {
+    let path = path()?;
+    let text = fs::read_to_string(&path).ok()?;
+    let session = Session::parse(&text);
+    if session.is_none() {
+        eprintln!(
+            "{}: {}",
+            path.display(),
+            i18n::text("recovery.not-a-session")
+        );
+    }
+    session
+}
+
+// on a clean exit, nothing needs recovering
+pub fn clear() {
+    if let Some(path) = path() {
+        fs::remove_file(path).ok();
+    }
+}
+
+pub struct Autosave {
+    last: Instant,
+    saved: Option<Session>,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            saved: None,
+        }
+    }
+
+    // whether it's time to look at the session again
+    pub fn due(&self) -> bool {
+        self.last.elapsed() >= INTERVAL
+    }
+
+    // writes `session` if it changed since the last save. It goes to a temporary file first
+    // and is renamed over the old one, so a crash while writing leaves the previous save
+    pub fn save(&mut self, session: Session) {
+        self.last = Instant::now();
+        if self.saved.as_ref() == Some(&session) {
+            return;
+        }
+        let Some(path) = path() else {
+            return;
+        };
+        let temporary = path.with_extension("json.tmp");
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&temporary, session.to_json() + "\n"))
+            .and_then(|()| fs::rename(&temporary, &path));
+        match written {
+            Ok(()) => self.saved = Some(session),
+            Err(e) => eprintln!("{}: {e}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_session_reads_back_as_written() {
+        let session = Session {
+            file: "take \"2\".wav".to_string(),
+            frame: 48000,
+            gain: 0.5,
+            loop_range: Some(100..2000),
+            markers: vec![0, 4410, 88200],
+            selection: None,
+        };
+        assert_eq!(Session::parse(&session.to_json()), Some(session));
+    }
+}
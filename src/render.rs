@@ -0,0 +1,450 @@
+use sdl2::VideoSubsystem;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+// the drawing the views need, so they can be drawn by something other than the sdl canvas.
+// Everything is in pixels relative to the current viewport, with the color and blending set
+// by the last call to set_color and set_blend
+pub trait Renderer {
+    fn size(&self) -> (u32, u32);
+    // limits drawing to `area` of the window and moves the origin to its corner, None is the
+    // whole window
+    fn set_viewport(&mut self, area: impl Into<Option<Rect>>);
+    fn set_color(&mut self, color: Color);
+    // alpha blending for translucent overlays, off by default
+    fn set_blend(&mut self, blend: bool);
+    fn line(&mut self, from: (i32, i32), to: (i32, i32));
+    fn rect(&mut self, rect: Rect);
+    fn fill_rects(&mut self, rects: &[Rect]);
+    // `image` stretched over `to`, or over the whole viewport
+    fn image(&mut self, image: &Image, to: Option<Rect>);
+
+    fn fill_rect(&mut self, rect: Rect) {
+        self.fill_rects(&[rect]);
+    }
+
+    // draws `layer` over what it drew in the frames before, which fades by `fade` out of 255
+    // every frame, and shows the result in the viewport. A trail that wasn't drawn in the last
+    // frame starts over from black. Renderers that can't keep one just draw the layer
+    fn trail(&mut self, fade: u8, layer: &impl Layer)
+    where
+        Self: Sized,
+    {
+        let _ = fade;
+        layer.draw(self);
+    }
+}
+
+// something drawn into a trail, which the renderer may draw somewhere else than the window
+pub trait Layer {
+    fn draw(&self, canvas: &mut impl Renderer);
+}
+
+// pixels for the views that are computed rather than drawn, rows of 8-bit RGB without padding
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 3],
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, (r, g, b): (u8, u8, u8)) {
+        let offset = (y * self.width as usize + x) * 3;
+        self.pixels[offset..offset + 3].copy_from_slice(&[r, g, b]);
+    }
+
+    #[cfg(feature = "cover-art")]
+    pub fn from_surface(surface: &sdl2::surface::Surface) -> Option<Self> {
+        let surface = surface.convert_format(PixelFormatEnum::RGB24).ok()?;
+        let (width, pitch) = (surface.width(), surface.pitch() as usize);
+        let rows = surface.without_lock()?;
+        let mut image = Self::new(width, surface.height());
+        let row = width as usize * 3;
+        for (y, to) in image.pixels.chunks_exact_mut(row).enumerate() {
+            to.copy_from_slice(&rows[y * pitch..y * pitch + row]);
+        }
+        Some(image)
+    }
+}
+
+// a streaming texture `image` fits in, made by `textures`
+fn image_texture<'r>(
+    textures: &'r TextureCreator<WindowContext>,
+    image: &Image,
+) -> Option<Texture<'r>> {
+    textures
+        .create_texture_streaming(PixelFormatEnum::RGB24, image.width, image.height)
+        .ok()
+}
+
+impl Renderer for Canvas<Window> {
+    fn size(&self) -> (u32, u32) {
+        self.viewport().size()
+    }
+
+    fn set_viewport(&mut self, area: impl Into<Option<Rect>>) {
+        Canvas::set_viewport(self, area);
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.set_draw_color(color);
+    }
+
+    fn set_blend(&mut self, blend: bool) {
+        self.set_blend_mode(if blend {
+            BlendMode::Blend
+        } else {
+            BlendMode::None
+        });
+    }
+
+    // drawing only fails when the renderer itself is gone, which ends the loop anyway
+    fn line(&mut self, from: (i32, i32), to: (i32, i32)) {
+        self.draw_line(from, to).ok();
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        self.draw_rect(rect).ok();
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect]) {
+        Canvas::fill_rects(self, rects).ok();
+    }
+
+    // a texture just for this call, the canvas has nowhere to keep one
+    fn image(&mut self, image: &Image, to: Option<Rect>) {
+        let textures = self.texture_creator();
+        if let Some(mut texture) = image_texture(&textures, image) {
+            texture
+                .update(None, &image.pixels, image.width as usize * 3)
+                .ok();
+            self.copy(&texture, None, to).ok();
+        }
+    }
+}
+
+// the main window drawn by sdl's own renderer, with the textures images and the trail are
+// copied from kept between frames
+pub struct Sdl<'r> {
+    canvas: Canvas<Window>,
+    textures: &'r TextureCreator<WindowContext>,
+    // each with whether it was drawn this frame, the rest are dropped when it's presented
+    images: Vec<(Texture<'r>, bool)>,
+    trail: Option<Texture<'r>>,
+    trail_drawn: bool,
+}
+
+impl<'r> Sdl<'r> {
+    pub fn new(canvas: Canvas<Window>, textures: &'r TextureCreator<WindowContext>) -> Self {
+        Self {
+            canvas,
+            textures,
+            images: Vec::new(),
+            trail: None,
+            trail_drawn: false,
+        }
+    }
+}
+
+impl Renderer for Sdl<'_> {
+    fn size(&self) -> (u32, u32) {
+        self.canvas.size()
+    }
+
+    fn set_viewport(&mut self, area: impl Into<Option<Rect>>) {
+        Renderer::set_viewport(&mut self.canvas, area);
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.canvas.set_color(color);
+    }
+
+    fn set_blend(&mut self, blend: bool) {
+        self.canvas.set_blend(blend);
+    }
+
+    fn line(&mut self, from: (i32, i32), to: (i32, i32)) {
+        self.canvas.line(from, to);
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        self.canvas.rect(rect);
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect]) {
+        Renderer::fill_rects(&mut self.canvas, rects);
+    }
+
+    fn image(&mut self, image: &Image, to: Option<Rect>) {
+        let fits = self.images.iter().position(|(texture, drawn)| {
+            let query = texture.query();
+            !drawn && (query.width, query.height) == (image.width, image.height)
+        });
+        let index = match fits {
+            Some(index) => index,
+            None => match image_texture(self.textures, image) {
+                Some(texture) => {
+                    self.images.push((texture, false));
+                    self.images.len() - 1
+                }
+                None => return,
+            },
+        };
+        let (texture, drawn) = &mut self.images[index];
+        *drawn = true;
+        texture
+            .update(None, &image.pixels, image.width as usize * 3)
+            .ok();
+        self.canvas.copy(texture, None, to).ok();
+    }
+
+    fn trail(&mut self, fade: u8, layer: &impl Layer) {
+        let (width, height) = self.size();
+        self.trail_drawn = true;
+        if self.trail.as_ref().is_none_or(|texture| {
+            let query = texture.query();
+            (query.width, query.height) != (width, height)
+        }) {
+            // render targets aren't supported by every sdl renderer, without one the layer is
+            // just drawn plainly
+            self.trail = self
+                .textures
+                .create_texture_target(None, width, height)
+                .ok();
+            if let Some(texture) = &mut self.trail {
+                texture.set_blend_mode(BlendMode::None);
+                self.canvas
+                    .with_texture_canvas(texture, |target| {
+                        target.set_draw_color(Color::RGB(0, 0, 0));
+                        target.clear();
+                    })
+                    .ok();
+            }
+        }
+        let Some(texture) = &mut self.trail else {
+            layer.draw(&mut self.canvas);
+            return;
+        };
+        self.canvas
+            .with_texture_canvas(texture, |target| {
+                target.set_blend(true);
+                target.set_color(Color::RGBA(0, 0, 0, fade));
+                Renderer::fill_rect(target, Rect::new(0, 0, width, height));
+                target.set_blend(false);
+                layer.draw(target);
+            })
+            .ok();
+        self.canvas.copy(texture, None, None).ok();
+    }
+}
+
+// the main window, drawn by sdl or with the gpu feature by OpenGL shaders
+pub enum Screen<'r> {
+    Sdl(Sdl<'r>),
+    #[cfg(feature = "gpu")]
+    Gpu(Box<crate::gpu::Gpu>),
+}
+
+impl<'r> Screen<'r> {
+    // the gpu renderer in a window of its own, None without the feature or when the driver
+    // can't run it, then sdl draws
+    #[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+    pub fn gpu(video: &VideoSubsystem, title: &str, width: u32, height: u32) -> Option<Self> {
+        #[cfg(feature = "gpu")]
+        match crate::gpu::Gpu::new(video, title, width, height) {
+            Ok(gpu) => return Some(Self::Gpu(Box::new(gpu))),
+            Err(e) => println!("{}: {e}", crate::i18n::text("render.no-gpu")),
+        }
+        None
+    }
+
+    pub fn sdl(canvas: Canvas<Window>, textures: &'r TextureCreator<WindowContext>) -> Self {
+        Self::Sdl(Sdl::new(canvas, textures))
+    }
+
+    pub fn window(&self) -> &Window {
+        match self {
+            Self::Sdl(sdl) => sdl.canvas.window(),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.window(),
+        }
+    }
+
+    pub fn window_mut(&mut self) -> &mut Window {
+        match self {
+            Self::Sdl(sdl) => sdl.canvas.window_mut(),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.window_mut(),
+        }
+    }
+
+    // the whole window, including the viewport
+    pub fn clear(&mut self, color: Color) {
+        match self {
+            Self::Sdl(sdl) => {
+                sdl.canvas.set_viewport(None);
+                sdl.canvas.set_draw_color(color);
+                sdl.canvas.clear();
+            }
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.clear(color),
+        }
+    }
+
+    pub fn present(&mut self) {
+        match self {
+            Self::Sdl(sdl) => {
+                sdl.canvas.present();
+                sdl.images.retain_mut(|(_, drawn)| std::mem::take(drawn));
+                if !std::mem::take(&mut sdl.trail_drawn) {
+                    sdl.trail = None;
+                }
+            }
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.present(),
+        }
+    }
+}
+
+impl Renderer for Screen<'_> {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            Self::Sdl(sdl) => sdl.size(),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.size(),
+        }
+    }
+
+    fn set_viewport(&mut self, area: impl Into<Option<Rect>>) {
+        match self {
+            Self::Sdl(sdl) => sdl.set_viewport(area),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.set_viewport(area),
+        }
+    }
+
+    fn set_color(&mut self, color: Color) {
+        match self {
+            Self::Sdl(sdl) => sdl.set_color(color),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.set_color(color),
+        }
+    }
+
+    fn set_blend(&mut self, blend: bool) {
+        match self {
+            Self::Sdl(sdl) => sdl.set_blend(blend),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.set_blend(blend),
+        }
+    }
+
+    fn line(&mut self, from: (i32, i32), to: (i32, i32)) {
+        match self {
+            Self::Sdl(sdl) => sdl.line(from, to),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.line(from, to),
+        }
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        match self {
+            Self::Sdl(sdl) => sdl.rect(rect),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.rect(rect),
+        }
+    }
+
+    fn fill_rects(&mut self, rects: &[Rect]) {
+        match self {
+            Self::Sdl(sdl) => sdl.fill_rects(rects),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.fill_rects(rects),
+        }
+    }
+
+    fn image(&mut self, image: &Image, to: Option<Rect>) {
+        match self {
+            Self::Sdl(sdl) => sdl.image(image, to),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.image(image, to),
+        }
+    }
+
+    fn trail(&mut self, fade: u8, layer: &impl Layer) {
+        match self {
+            Self::Sdl(sdl) => sdl.trail(fade, layer),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(gpu) => gpu.trail(fade, layer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // counts what's drawn, with no trail of its own
+    #[derive(Default)]
+    struct Count {
+        lines: usize,
+    }
+
+    impl Renderer for Count {
+        fn size(&self) -> (u32, u32) {
+            (100, 50)
+        }
+
+        fn set_viewport(&mut self, _: impl Into<Option<Rect>>) {}
+
+        fn set_color(&mut self, _: Color) {}
+
+        fn set_blend(&mut self, _: bool) {}
+
+        fn line(&mut self, _: (i32, i32), _: (i32, i32)) {
+            self.lines += 1;
+        }
+
+        fn rect(&mut self, _: Rect) {}
+
+        fn fill_rects(&mut self, _: &[Rect]) {}
+
+        fn image(&mut self, _: &Image, _: Option<Rect>) {}
+    }
+
+    struct Cross;
+
+    impl Layer for Cross {
+        fn draw(&self, canvas: &mut impl Renderer) {
+            let (width, height) = canvas.size();
+            canvas.line((0, 0), (width as i32, height as i32));
+            canvas.line((0, height as i32), (width as i32, 0));
+        }
+    }
+
+    #[test]
+    fn without_a_trail_the_layer_is_drawn_plainly() {
+        let mut count = Count::default();
+        count.trail(48, &Cross);
+        assert_eq!(count.lines, 2);
+    }
+
+    #[test]
+    fn image_pixels_are_rows_of_rgb() {
+        let mut image = Image::new(3, 2);
+        image.set(2, 1, (1, 2, 3));
+        assert_eq!(image.pixels.len(), 18);
+        assert_eq!(&image.pixels[15..], &[1, 2, 3]);
+        assert!(image.pixels[..15].iter().all(|&byte| byte == 0));
+    }
+}
@@ -0,0 +1,367 @@
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+use std::{error, fs};
+
+use crate::cli::{self, Args};
+use crate::i18n;
+use crate::riff::{self, Chunk};
+
+const USAGE: &str = "wave repair <broken.wav> <fixed.wav> [--guess] [--channels N] [--rate HZ] \
+     [--bits N] [--strip]";
+// what most field recorders default to, the sample rate can't be told from the samples alone
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+// how much audio the format guess looks at
+const GUESS_BYTES: u64 = 1 << 16;
+
+struct Format {
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+// `wave repair`: rebuilds the chunk sizes and fmt fields of a file a recorder never finished
+// writing, everything that can still be found is kept
+pub fn repair(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["guess", "strip"])?;
+    let (input, output) = match args.positional.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err(i18n::fill("cli.usage", &[("usage", cli::strip_usage(USAGE))]).into()),
+    };
+    let mut file = fs::File::open(input)?;
+    let len = file.metadata()?.len();
+    let scanned = scan(&mut file, len)?;
+    let mut chunks = scanned.chunks;
+    if args.flag("strip") {
+        riff::strip(&mut chunks);
+    }
+
+    let (data_index, mut data) = chunks
+        .iter()
+        .position(|chunk| &chunk.id == b"data")
+        .zip(scanned.data)
+        .ok_or(i18n::text("repair.no-data"))?;
+    let fmt_index = chunks.iter().position(|chunk| &chunk.id == b"fmt ");
+
+    let mut format = match fmt_index {
+        Some(i) if !args.flag("guess") && chunks[i].data.len() >= 16 => {
+            let fmt = &chunks[i].data;
+            Format {
+                channels: u16::from_le_bytes([fmt[2], fmt[3]]),
+                sample_rate: u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]),
+                bits: u16::from_le_bytes([fmt[14], fmt[15]]),
+            }
+        }
+        _ => {
+            let mut start = Vec::new();
+            file.seek(SeekFrom::Start(data.start))?;
+            (&mut file)
+                .take((data.end - data.start).min(GUESS_BYTES))
+                .read_to_end(&mut start)?;
+            let guessed = guess_format(&start);
+            println!(
+                "{}",
+                i18n::fill(
+                    "repair.guessed",
+                    &[
+                        ("channels", guessed.channels.to_string()),
+                        ("bits", guessed.bits.to_string()),
+                    ]
+                )
+            );
+            if args.value("rate").is_none() {
+                println!(
+                    "{}",
+                    i18n::fill(
+                        "repair.assumed-rate",
+                        &[("rate", guessed.sample_rate.to_string())]
+                    )
+                );
+            }
+            guessed
+        }
+    };
+    if let Some(channels) = positive(&args, "channels")? {
+        format.channels = channels;
+    }
+    if let Some(sample_rate) = positive(&args, "rate")? {
+        format.sample_rate = sample_rate;
+    }
+    if let Some(bits) = positive(&args, "bits")? {
+        format.bits = bits;
+    }
+    if format.channels == 0 || format.sample_rate == 0 || !matches!(format.bits, 8 | 16 | 24 | 32) {
+        return Err(i18n::text("repair.unusable-format").into());
+    }
+
+    // block_align and byte_rate always follow from the other fields, which can ask for more
+    // than the header has room for
+    let block_align = format
+        .channels
+        .checked_mul(format.bits.div_ceil(8))
+        .ok_or_else(|| {
+            i18n::fill(
+                "repair.frame-too-big",
+                &[
+                    ("channels", format.channels.to_string()),
+                    ("bits", format.bits.to_string()),
+                ],
+            )
+        })?;
+    let byte_rate = format
+        .sample_rate
+        .checked_mul(block_align as u32)
+        .ok_or_else(|| {
+            i18n::fill(
+                "repair.byte-rate-overflow",
+                &[
+                    ("rate", format.sample_rate.to_string()),
+                    ("bytes", block_align.to_string()),
+                ],
+            )
+        })?;
+    let mut fmt = match fmt_index {
+        Some(i) if chunks[i].data.len() >= 16 => chunks[i].data.clone(),
+        _ => vec![0; 16],
+    };
+    if fmt[0..2] == [0, 0] {
+        fmt[0..2].copy_from_slice(&1u16.to_le_bytes());
+    }
+    let old_align = u16::from_le_bytes([fmt[12], fmt[13]]);
+    let old_rate = u32::from_le_bytes([fmt[8], fmt[9], fmt[10], fmt[11]]);
+    if (old_align, old_rate) != (block_align, byte_rate) {
+        println!(
+            "fmt: block_align {old_align} -> {block_align}, byte_rate {old_rate} -> {byte_rate}"
+        );
+    }
+    fmt[2..4].copy_from_slice(&format.channels.to_le_bytes());
+    fmt[4..8].copy_from_slice(&format.sample_rate.to_le_bytes());
+    fmt[8..12].copy_from_slice(&byte_rate.to_le_bytes());
+    fmt[12..14].copy_from_slice(&block_align.to_le_bytes());
+    fmt[14..16].copy_from_slice(&format.bits.to_le_bytes());
+    match fmt_index {
+        Some(i) => chunks[i].data = fmt,
+        None => chunks.insert(
+            data_index,
+            Chunk {
+                id: *b"fmt ",
+                data: fmt,
+            },
+        ),
+    }
+
+    // a frame cut off by the crash is dropped
+    let size = data.end - data.start;
+    let whole = size / block_align as u64 * block_align as u64;
+    if whole != size {
+        println!(
+            "{}",
+            i18n::fill(
+                "repair.partial-frame",
+                &[("bytes", (size - whole).to_string())]
+            )
+        );
+        data.end = data.start + whole;
+    }
+    let frames = whole / block_align as u64;
+
+    // the samples are copied straight across, however big the file
+    let mut out = BufWriter::new(fs::File::create(output)?);
+    file.seek(SeekFrom::Start(data.start))?;
+    let body = riff::DataBody {
+        source: &mut file,
+        len: whole,
+    };
+    riff::write_form_to(&mut out, b"RIFF", &chunks, Some(body))?;
+    out.flush()?;
+    println!(
+        "{}",
+        i18n::fill(
+            "repair.wrote",
+            &[
+                ("path", output.to_string()),
+                ("frames", frames.to_string()),
+                (
+                    "seconds",
+                    format!("{:.2}", frames as f64 / format.sample_rate as f64)
+                ),
+            ]
+        )
+    );
+    Ok(())
+}
+
+// the value of --`name` as a number above 0 that fits its fmt field
+fn positive<T: FromStr + Default + PartialEq>(
+    args: &Args,
+    name: &str,
+) -> Result<Option<T>, String> {
+    args.value(name)
+        .map(|value| {
+            value
+                .parse::<T>()
+                .ok()
+                .filter(|n| *n != T::default())
+                .ok_or_else(|| {
+                    i18n::fill(
+                        "repair.not-positive",
+                        &[("name", name.to_string()), ("value", value.to_string())],
+                    )
+                })
+        })
+        .transpose()
+}
+
+// the chunks that can still be found in the `len` bytes of `source`, with sizes taken from the
+// actual file length wherever the header disagrees with it. Only the chunks around the samples
+// are read, the body of the data chunk is left in `source` like `riff::read_form_from` does
+pub fn scan(source: &mut (impl Read + Seek), len: u64) -> io::Result<riff::Form> {
+    let mut header = [0; 12];
+    source.seek(SeekFrom::Start(0))?;
+    let mut offset = if len >= 12
+        && source.read_exact(&mut header).is_ok()
+        && &header[0..4] == b"RIFF"
+        && &header[8..12] == b"WAVE"
+    {
+        12
+    } else {
+        // without a header the whole file can only be taken as raw samples
+        println!("{}", i18n::text("repair.no-header"));
+        return Ok(riff::Form {
+            form: *b"RIFF",
+            chunks: vec![Chunk {
+                id: *b"data",
+                data: Vec::new(),
+            }],
+            data: Some(0..len),
+        });
+    };
+
+    let mut chunks = Vec::new();
+    let mut data = None;
+    while offset + 8 <= len {
+        let mut chunk_header = [0; 8];
+        source.seek(SeekFrom::Start(offset))?;
+        source.read_exact(&mut chunk_header)?;
+        let id = [
+            chunk_header[0],
+            chunk_header[1],
+            chunk_header[2],
+            chunk_header[3],
+        ];
+        // past this point it's garbage rather than another chunk
+        if !id.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            println!(
+                "{}",
+                i18n::fill(
+                    "repair.trailing-bytes",
+                    &[("bytes", (len - offset).to_string())]
+                )
+            );
+            break;
+        }
+        let size = u32::from_le_bytes([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]) as u64;
+        let start = offset + 8;
+        let available = len - start;
+        let name = String::from_utf8_lossy(&id);
+        // recorders write the data size last, so a crash leaves it at zero or a placeholder
+        let size = if &id == b"data" && (size == 0 || size == u32::MAX as u64) {
+            println!(
+                "{}",
+                i18n::fill(
+                    "repair.data-to-end",
+                    &[
+                        ("size", size.to_string()),
+                        ("available", available.to_string())
+                    ]
+                )
+            );
+            available
+        } else if size > available {
+            println!(
+                "{}",
+                i18n::fill(
+                    "repair.cut-short",
+                    &[
+                        ("chunk", name.to_string()),
+                        ("size", size.to_string()),
+                        ("available", available.to_string()),
+                    ]
+                )
+            );
+            available
+        } else {
+            size
+        };
+        if &id == b"data" && data.is_none() {
+            data = Some(start..start + size);
+            chunks.push(Chunk {
+                id,
+                data: Vec::new(),
+            });
+        } else {
+            let mut body = vec![0; size as usize];
+            source.read_exact(&mut body)?;
+            chunks.push(Chunk { id, data: body });
+        }
+        offset = start + size + size % 2;
+    }
+    Ok(riff::Form {
+        form: *b"RIFF",
+        chunks,
+        data,
+    })
+}
+
+// tries the common layouts and keeps the one that makes the smoothest signal, correctly
+// decoded audio changes little from one sample to the next while a wrong sample size or
+// channel count turns it into noise
+fn guess_format(data: &[u8]) -> Format {
+    let mut best = (f64::MAX, 2, 16);
+    for bits in [16, 24] {
+        for channels in [1, 2] {
+            let roughness = roughness(data, channels, bits);
+            if roughness < best.0 {
+                best = (roughness, channels, bits);
+            }
+        }
+    }
+    Format {
+        channels: best.1,
+        sample_rate: DEFAULT_SAMPLE_RATE,
+        bits: best.2,
+    }
+}
+
+// mean sample to sample change relative to the mean level, over every channel
+fn roughness(data: &[u8], channels: u16, bits: u16) -> f64 {
+    let width = bits as usize / 8;
+    let samples: Vec<f64> = data
+        .chunks_exact(width)
+        .map(|bytes| match width {
+            2 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            _ => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f64,
+        })
+        .collect();
+    let channels = channels as usize;
+    let (mut change, mut level) = (0.0, 0.0);
+    for channel in 0..channels {
+        let mut previous: Option<f64> = None;
+        for &sample in samples.iter().skip(channel).step_by(channels) {
+            if let Some(previous) = previous {
+                change += (sample - previous).abs();
+            }
+            level += sample.abs();
+            previous = Some(sample);
+        }
+    }
+    if level == 0.0 {
+        f64::MAX
+    } else {
+        change / level
+    }
+}
@@ -0,0 +1,170 @@
+use std::{error, fs};
+
+use rayon::prelude::*;
+
+use crate::cli::Args;
+use crate::playlist::{self, Sort};
+use crate::{WavFile, i18n, id3, loudness, riff};
+
+const USAGE: &str = "wave loudness <file.wav|dir|glob ...> [--target LUFS] [--write-tags] \
+     [--strip]";
+// what ReplayGain 2.0 scanners normalize to
+const DEFAULT_TARGET: f32 = -18.0;
+
+struct Track {
+    path: String,
+    blocks: Vec<f64>,
+    lufs: Option<f32>,
+    // sample peak as a fraction of full scale
+    peak: f32,
+}
+
+// `wave loudness`: integrated loudness of every file, measured in parallel, with the gain that
+// brings each track and the files as an album to the target. The album is gated over the
+// blocks of all tracks together rather than averaging their loudness. --write-tags stores the
+// result as REPLAYGAIN_* user text frames in each file's id3 chunk, --strip removes them again
+// without measuring anything
+pub fn loudness(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["write-tags", "strip"])?;
+    args.input(USAGE)?;
+    if args.flag("strip") {
+        if args.flag("write-tags") {
+            return Err(i18n::text("replaygain.strip-and-write").into());
+        }
+        let files = playlist::expand(&args.positional, Sort::Name)?;
+        for path in &files {
+            strip_tags(path).map_err(|e| format!("{path}: {e}"))?;
+        }
+        println!(
+            "{}",
+            i18n::fill("replaygain.stripped", &[("count", files.len().to_string())])
+        );
+        return Ok(());
+    }
+    let target = match args.value("target") {
+        Some(lufs) => lufs
+            .parse()
+            .map_err(|_| format!("--target expects LUFS, got `{lufs}`"))?,
+        None => DEFAULT_TARGET,
+    };
+    let files = playlist::expand(&args.positional, Sort::Name)?;
+
+    let tracks: Vec<Track> = files
+        .into_par_iter()
+        .map(|path| {
+            let wav = WavFile::open(&path).map_err(|e| format!("{path}: {e}"))?;
+            let channels = wav.header.num_channels.max(1) as usize;
+            let blocks = loudness::blocks(&wav.data, channels, wav.header.sample_rate);
+            let peak = wav
+                .data
+                .iter()
+                .map(|&sample| sample.unsigned_abs())
+                .max()
+                .unwrap_or(0) as f32
+                / 32768.0;
+            Ok(Track {
+                lufs: loudness::gated(&blocks),
+                path,
+                blocks,
+                peak,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    let album_blocks: Vec<f64> = tracks
+        .iter()
+        .flat_map(|track| track.blocks.iter().copied())
+        .collect();
+    let album_lufs = loudness::gated(&album_blocks);
+    let album_peak = tracks.iter().map(|track| track.peak).fold(0.0, f32::max);
+
+    for track in &tracks {
+        println!(
+            "{:>9}  {:>9}  {}  {}",
+            lufs_text(track.lufs),
+            gain_text(track.lufs, target),
+            i18n::fill("replaygain.peak", &[("peak", format!("{:.6}", track.peak))]),
+            track.path
+        );
+    }
+    println!(
+        "{:>9}  {:>9}  {}  {}",
+        lufs_text(album_lufs),
+        gain_text(album_lufs, target),
+        i18n::fill("replaygain.peak", &[("peak", format!("{album_peak:.6}"))]),
+        i18n::fill("replaygain.album", &[("count", tracks.len().to_string())])
+    );
+
+    if args.flag("write-tags") {
+        for track in &tracks {
+            let track_peak = format!("{:.6}", track.peak);
+            let album_peak = format!("{album_peak:.6}");
+            // silent files get their gain removed rather than an arbitrary one
+            let changes = [
+                ("REPLAYGAIN_TRACK_GAIN", gain_tag(track.lufs, target)),
+                ("REPLAYGAIN_TRACK_PEAK", track_peak),
+                ("REPLAYGAIN_ALBUM_GAIN", gain_tag(album_lufs, target)),
+                ("REPLAYGAIN_ALBUM_PEAK", album_peak),
+                ("REPLAYGAIN_REFERENCE_LOUDNESS", format!("{target:.2} LUFS")),
+            ];
+            let changes: Vec<(&str, &str)> = changes
+                .iter()
+                .map(|(name, value)| (*name, value.as_str()))
+                .collect();
+            write_tags(&track.path, &changes)?;
+        }
+        println!(
+            "{}",
+            i18n::fill("replaygain.tagged", &[("count", tracks.len().to_string())])
+        );
+    }
+    Ok(())
+}
+
+fn lufs_text(lufs: Option<f32>) -> String {
+    lufs.map_or("silent".to_string(), |lufs| format!("{lufs:.2} LUFS"))
+}
+
+fn gain_text(lufs: Option<f32>, target: f32) -> String {
+    match gain_tag(lufs, target) {
+        gain if gain.is_empty() => "-".to_string(),
+        gain => gain,
+    }
+}
+
+fn gain_tag(lufs: Option<f32>, target: f32) -> String {
+    lufs.map_or(String::new(), |lufs| format!("{:+.2} dB", target - lufs))
+}
+
+// rewrites the id3 chunk the same way `wave tag --id3` does, everything else is copied as is
+fn write_tags(path: &str, changes: &[(&str, &str)]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let mut file = fs::File::open(path)?;
+    let mut form = riff::read_form_from(&mut file)?;
+    match form
+        .chunks
+        .iter_mut()
+        .find(|chunk| &chunk.id == b"id3 " || &chunk.id == b"ID3 ")
+    {
+        Some(tag) => tag.data = id3::update_user_text(&tag.data, changes),
+        None => form.chunks.push(riff::Chunk {
+            id: *b"id3 ",
+            data: id3::update_user_text(&[], changes),
+        }),
+    }
+    riff::rewrite(path, file, &form)
+}
+
+// drops every REPLAYGAIN_* frame from the id3 chunk, a file without one is left alone
+fn strip_tags(path: &str) -> Result<(), Box<dyn error::Error + 'static>> {
+    let mut file = fs::File::open(path)?;
+    let mut form = riff::read_form_from(&mut file)?;
+    let Some(tag) = form
+        .chunks
+        .iter_mut()
+        .find(|chunk| &chunk.id == b"id3 " || &chunk.id == b"ID3 ")
+    else {
+        return Ok(());
+    };
+    tag.data = id3::remove_user_text(&tag.data, "REPLAYGAIN_");
+    riff::rewrite(path, file, &form)
+}
@@ -0,0 +1,259 @@
+use std::fmt::Write;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::{error, fs};
+
+use crate::classify::{self, Class};
+use crate::cli::Args;
+use crate::dynamics::Dynamics;
+use crate::notes::Notes;
+use crate::overview::Overview;
+use crate::progress::CancellationToken;
+use crate::{WavFile, i18n, loudness, silence, timecode};
+
+const USAGE: &str = "wave report <in.wav> [--output report.html|report.md]";
+// size of the waveform picture
+const SVG_WIDTH: u32 = 1000;
+const SVG_HEIGHT: u32 = 160;
+
+// `wave report`: a QC document for handing to a client, with a picture of the waveform, the
+// chapter markers, the review notes and what the analyses found. HTML with the picture inline
+// by default, Markdown with it in an .svg next to it when the output ends in .md
+pub fn report(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    let input = args.input(USAGE)?;
+    let output = args
+        .value("output")
+        .map_or_else(|| format!("{input}.report.html"), str::to_string);
+    let wav = WavFile::open(input)?;
+
+    let report = Report::new(input, &wav)?;
+    if output.ends_with(".md") {
+        let svg_path = Path::new(&output).with_extension("svg");
+        fs::write(&svg_path, &report.svg)?;
+        let svg_name = svg_path.file_name().unwrap_or_default().to_string_lossy();
+        fs::write(&output, report.markdown(&svg_name))?;
+    } else {
+        fs::write(&output, report.html())?;
+    }
+    println!(
+        "{}",
+        i18n::fill("report.wrote", &[("path", output.to_string())])
+    );
+    Ok(())
+}
+
+struct Report {
+    title: String,
+    // label and value rows of the format and analysis tables
+    format: Vec<(String, String)>,
+    analysis: Vec<(String, String)>,
+    // times and texts
+    chapters: Vec<String>,
+    notes: Vec<(String, String)>,
+    svg: String,
+}
+
+impl Report {
+    fn new(input: &str, wav: &WavFile) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let header = &wav.header;
+        let channels = header.num_channels.max(1) as usize;
+        let frames = wav.data.len() / channels;
+        let time = |frame: usize| timecode::format(frame as f64 / header.sample_rate as f64);
+
+        let mut format = vec![
+            ("channels".to_string(), header.num_channels.to_string()),
+            (
+                "sample rate".to_string(),
+                format!("{} Hz", header.sample_rate),
+            ),
+            ("bits".to_string(), header.bits_per_sample.to_string()),
+            (
+                "duration".to_string(),
+                format!("{} ({frames} frames)", time(frames)),
+            ),
+        ];
+        let speakers = header.speakers();
+        if !speakers.is_empty() {
+            format.insert(1, ("speakers".to_string(), speakers.join(" ")));
+        }
+        format.extend(
+            wav.metadata
+                .fields()
+                .into_iter()
+                .map(|(label, value)| (label.to_string(), value.to_string())),
+        );
+
+        let dynamics = Dynamics::analyze(
+            wav,
+            &mut |_| ControlFlow::Continue(()),
+            &CancellationToken::new(),
+        )
+        .map_err(|_| "analysis was cancelled")?;
+        let loudness = loudness::integrated(&wav.data, channels, header.sample_rate)
+            .map_or("-inf".to_string(), |lufs| format!("{lufs:.1}"));
+        let classes = classify::classify(wav);
+        let share = |class: Class| {
+            let count = classes.iter().filter(|&&c| c == class).count();
+            format!("{:.0}%", count as f64 * 100.0 / classes.len().max(1) as f64)
+        };
+        let analysis = vec![
+            ("peak".to_string(), format!("{:.1} dBFS", dynamics.peak_db)),
+            ("rms".to_string(), format!("{:.1} dBFS", dynamics.rms_db)),
+            (
+                "crest factor".to_string(),
+                format!("{:.1} dB", dynamics.crest_db),
+            ),
+            (
+                "integrated loudness".to_string(),
+                format!("{loudness} LUFS"),
+            ),
+            ("speech".to_string(), share(Class::Speech)),
+            ("music".to_string(), share(Class::Music)),
+            ("silence".to_string(), share(Class::Silence)),
+        ];
+
+        let markers: Vec<usize> = silence::sound_segments(wav)
+            .into_iter()
+            .map(|segment| segment.start)
+            .collect();
+        let notes = Notes::load(input, wav).notes;
+        let svg = svg(
+            &Overview::new(wav, SVG_WIDTH),
+            &markers,
+            &notes.iter().map(|note| note.frame).collect::<Vec<_>>(),
+            frames,
+        );
+        Ok(Self {
+            title: Path::new(input)
+                .file_name()
+                .map_or(input.into(), |name| name.to_string_lossy().into_owned()),
+            format,
+            analysis,
+            chapters: markers.iter().map(|&frame| time(frame)).collect(),
+            notes: notes
+                .iter()
+                .map(|note| (time(note.frame), note.text.clone()))
+                .collect(),
+            svg,
+        })
+    }
+
+    fn html(&self) -> String {
+        let mut html = String::new();
+        let title = escape(&self.title);
+        writeln!(
+            html,
+            "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title} review</title>\n\
+             <style>body {{ font-family: sans-serif; max-width: 60em; margin: 2em auto; }} \
+             td, th {{ text-align: left; padding: 0.2em 1em 0.2em 0; }}</style>\n\
+             </head>\n<body>\n<h1>{title}</h1>"
+        )
+        .unwrap();
+        html.push_str(&self.svg);
+        for (heading, rows) in [("Format", &self.format), ("Analysis", &self.analysis)] {
+            writeln!(html, "<h2>{heading}</h2>\n<table>").unwrap();
+            for (label, value) in rows {
+                writeln!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape(label),
+                    escape(value)
+                )
+                .unwrap();
+            }
+            html.push_str("</table>\n");
+        }
+        writeln!(html, "<h2>Chapters</h2>").unwrap();
+        if self.chapters.is_empty() {
+            html.push_str("<p>none</p>\n");
+        } else {
+            html.push_str("<ol>\n");
+            for chapter in &self.chapters {
+                writeln!(html, "<li>{chapter}</li>").unwrap();
+            }
+            html.push_str("</ol>\n");
+        }
+        writeln!(html, "<h2>Notes</h2>").unwrap();
+        if self.notes.is_empty() {
+            html.push_str("<p>none</p>\n");
+        } else {
+            html.push_str("<table>\n");
+            for (time, text) in &self.notes {
+                writeln!(html, "<tr><th>{time}</th><td>{}</td></tr>", escape(text)).unwrap();
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn markdown(&self, svg_name: &str) -> String {
+        let mut md = format!("# {}\n\n![waveform]({svg_name})\n", self.title);
+        for (heading, rows) in [("Format", &self.format), ("Analysis", &self.analysis)] {
+            writeln!(md, "\n## {heading}\n\n| | |\n|---|---|").unwrap();
+            for (label, value) in rows {
+                writeln!(md, "| {} | {} |", cell(label), cell(value)).unwrap();
+            }
+        }
+        md.push_str("\n## Chapters\n\n");
+        if self.chapters.is_empty() {
+            md.push_str("none\n");
+        }
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            writeln!(md, "{}. {chapter}", i + 1).unwrap();
+        }
+        md.push_str("\n## Notes\n\n");
+        if self.notes.is_empty() {
+            md.push_str("none\n");
+        } else {
+            md.push_str("| time | note |\n|---|---|\n");
+            for (time, text) in &self.notes {
+                writeln!(md, "| {time} | {} |", cell(text)).unwrap();
+            }
+        }
+        md
+    }
+}
+
+// the overview as an svg: the min/max envelope, chapter markers in yellow and notes in pink
+fn svg(overview: &Overview, markers: &[usize], notes: &[usize], frames: usize) -> String {
+    let center = SVG_HEIGHT as f32 / 2.0;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_WIDTH}\" height=\"{SVG_HEIGHT}\" \
+         viewBox=\"0 0 {SVG_WIDTH} {SVG_HEIGHT}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"black\"/>\n<path stroke=\"#3cb4ff\" d=\""
+    );
+    for (x, column) in overview.columns.iter().enumerate() {
+        let top = center - column.max * center;
+        let bottom = (center - column.min * center).max(top + 1.0);
+        write!(svg, "M{x} {top:.1}V{bottom:.1}").unwrap();
+    }
+    svg.push_str("\"/>\n");
+    let x = |frame: usize| frame as f64 / frames.max(1) as f64 * SVG_WIDTH as f64;
+    for (frames, color) in [(markers, "yellow"), (notes, "#ff78c8")] {
+        for &frame in frames {
+            writeln!(
+                svg,
+                "<line x1=\"{0:.1}\" x2=\"{0:.1}\" y1=\"0\" y2=\"{SVG_HEIGHT}\" stroke=\"{color}\"/>",
+                x(frame)
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// a markdown table cell, pipes and line breaks would end it early
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
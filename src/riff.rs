@@ -0,0 +1,461 @@
+use std::io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::{error, fs};
+
+use crate::info_list;
+
+// a RIFF chunk kept as raw bytes, so chunks the player doesn't understand can be written back
+// exactly as they were read
+pub struct Chunk {
+    pub id: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+// the chunks of a RIFF/WAVE file in file order
+pub fn read_chunks(bytes: &[u8]) -> Result<Vec<Chunk>, Box<dyn error::Error + 'static>> {
+    Ok(read_form(bytes)?.1)
+}
+
+// the form (RIFF, or RF64 and its successor BW64 for files past 4 GB) and the chunks of a
+// WAVE file. The ds64 chunk of the larger forms stays in the list, the sizes it holds stand in
+// for the 32-bit ones set to all ones
+pub fn read_form(bytes: &[u8]) -> Result<([u8; 4], Vec<Chunk>), Box<dyn error::Error + 'static>> {
+    let Form {
+        form,
+        mut chunks,
+        data,
+    } = read_form_from(&mut Cursor::new(bytes))?;
+    if let Some(range) = data
+        && let Some(chunk) = chunks.iter_mut().find(|chunk| &chunk.id == b"data")
+    {
+        chunk.data = bytes[range.start as usize..range.end as usize].to_vec();
+    }
+    Ok((form, chunks))
+}
+
+// a file read by `read_form_from`
+pub struct Form {
+    pub form: [u8; 4],
+    pub chunks: Vec<Chunk>,
+    // where the body of the data chunk is in the file, the chunk itself is left empty
+    pub data: Option<Range<u64>>,
+}
+
+// like `read_form`, reading the chunks from `source` but seeking past the samples. The first
+// data chunk comes back empty, with where its body is in `source`, to be copied across with
+// `write_form_to` once the rest has been changed
+pub fn read_form_from(
+    source: &mut (impl Read + Seek),
+) -> Result<Form, Box<dyn error::Error + 'static>> {
+    let len = source.seek(SeekFrom::End(0))?;
+    source.seek(SeekFrom::Start(0))?;
+    let mut header = [0; 12];
+    if len < 12
+        || source.read_exact(&mut header).is_err()
+        || !matches!(&header[0..4], b"RIFF" | b"RF64" | b"BW64")
+        || &header[8..12] != b"WAVE"
+    {
+        return Err("not a RIFF/WAVE file".into());
+    }
+    let form = [header[0], header[1], header[2], header[3]];
+    let mut ds64: Option<Ds64> = None;
+    let mut chunks = Vec::new();
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= len {
+        let mut chunk_header = [0; 8];
+        source.read_exact(&mut chunk_header)?;
+        let id = [
+            chunk_header[0],
+            chunk_header[1],
+            chunk_header[2],
+            chunk_header[3],
+        ];
+        let size = u32::from_le_bytes([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]);
+        let size = match &ds64 {
+            Some(ds64) if size == u32::MAX => ds64.size(&id).ok_or_else(|| {
+                format!("no ds64 size for chunk `{}`", String::from_utf8_lossy(&id))
+            })?,
+            _ => size as u64,
+        };
+        let start = offset + 8;
+        let end = start.saturating_add(size);
+        if end > len {
+            return Err(format!(
+                "chunk `{}` runs past the end of the file",
+                String::from_utf8_lossy(&id)
+            )
+            .into());
+        }
+        if &id == b"data" && data.is_none() {
+            data = Some(start..end);
+            chunks.push(Chunk {
+                id,
+                data: Vec::new(),
+            });
+        } else {
+            let mut body = vec![0; size as usize];
+            source.read_exact(&mut body)?;
+            if &id == b"ds64" && form != *b"RIFF" {
+                ds64 = Some(Ds64::parse(&body)?);
+            }
+            chunks.push(Chunk { id, data: body });
+        }
+        // chunks are padded to an even size
+        offset = end + size % 2;
+        source.seek(SeekFrom::Start(offset))?;
+    }
+    Ok(Form { form, chunks, data })
+}
+
+// the sizes of an RF64 file: the riff size, the data size, the sample count and a table of
+// (chunk id, size) for any other chunk past 4 GB
+struct Ds64 {
+    data_size: u64,
+    sample_count: u64,
+    table: Vec<([u8; 4], u64)>,
+}
+
+impl Ds64 {
+    fn parse(body: &[u8]) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let u64_at = |at: usize| {
+            body.get(at..at + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let short = || "ds64 chunk too short";
+        let data_size = u64_at(8).ok_or_else(short)?;
+        let sample_count = u64_at(16).ok_or_else(short)?;
+        let entries = body
+            .get(24..28)
+            .map_or(0, |bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+        let table = (0..entries as usize)
+            .map(|i| {
+                let at = 28 + i * 12;
+                let id = body.get(at..at + 4).ok_or_else(short)?;
+                let size = u64_at(at + 4).ok_or_else(short)?;
+                Ok(([id[0], id[1], id[2], id[3]], size))
+            })
+            .collect::<Result<_, Box<dyn error::Error + 'static>>>()?;
+        Ok(Self {
+            data_size,
+            sample_count,
+            table,
+        })
+    }
+
+    fn size(&self, id: &[u8; 4]) -> Option<u64> {
+        match id {
+            b"data" => Some(self.data_size),
+            _ => self
+                .table
+                .iter()
+                .find(|(table_id, _)| table_id == id)
+                .map(|&(_, size)| size),
+        }
+    }
+}
+
+// drops every chunk besides fmt, fact and data and the LIST INFO and id3 tags `wave tag`
+// writes itself, for the --strip option of the paths that rewrite files. Markers, loops,
+// bext, iXML, axml and proprietary chunks are otherwise carried through untouched
+pub fn strip(chunks: &mut Vec<Chunk>) {
+    chunks.retain(|chunk| match &chunk.id {
+        b"fmt " | b"data" | b"fact" | b"id3 " | b"ID3 " => true,
+        b"LIST" => info_list::is_info(&chunk.data),
+        _ => false,
+    });
+}
+
+pub fn write_chunks(chunks: &[Chunk]) -> Vec<u8> {
+    write_form(b"RIFF", chunks)
+}
+
+// `chunks` as a `form` file. RF64 and BW64 get a ds64 chunk up front with the real sizes, a
+// RIFF file that no longer fits in 32-bit sizes becomes RF64
+pub fn write_form(form: &[u8; 4], chunks: &[Chunk]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_form_to(&mut bytes, form, chunks, None).expect("writing to memory can't fail");
+    bytes
+}
+
+// the body of the data chunk while a file is rewritten, left where it is and copied across as
+// it's written, so the samples of a large file never have to be in memory
+pub struct DataBody<'a> {
+    pub source: &'a mut dyn Read,
+    pub len: u64,
+}
+
+// like `write_form`, straight into `out`. With `data` the data chunk in `chunks` is only a
+// placeholder, its body is copied from `data` instead
+pub fn write_form_to(
+    out: &mut impl Write,
+    form: &[u8; 4],
+    chunks: &[Chunk],
+    data: Option<DataBody>,
+) -> io::Result<()> {
+    let data_len = data.as_ref().map(|data| data.len);
+    let len = |chunk: &Chunk| match data_len {
+        Some(len) if &chunk.id == b"data" => len,
+        _ => chunk.data.len() as u64,
+    };
+    let body_size = |chunks: &[&Chunk]| {
+        4 + chunks
+            .iter()
+            .map(|chunk| 8 + len(chunk) + len(chunk) % 2)
+            .sum::<u64>()
+    };
+    let all: Vec<_> = chunks.iter().collect();
+    if form == b"RIFF" && body_size(&all) <= u32::MAX as u64 {
+        return write(out, form, false, &all, len, data);
+    }
+
+    let old_ds64 = chunks.iter().find(|chunk| &chunk.id == b"ds64");
+    let chunks: Vec<_> = chunks.iter().filter(|chunk| &chunk.id != b"ds64").collect();
+
+    let form = if form == b"RIFF" { b"RF64" } else { form };
+    let data_size = chunks
+        .iter()
+        .find(|chunk| &chunk.id == b"data")
+        .map_or(0, |chunk| len(chunk));
+    // the sample count is kept from the file, or worked out from the block align of fmt
+    let sample_count = old_ds64
+        .and_then(|chunk| Ds64::parse(&chunk.data).ok())
+        .map(|ds64| ds64.sample_count)
+        .or_else(|| {
+            let fmt = chunks.iter().find(|chunk| &chunk.id == b"fmt ")?;
+            let block_align = u16::from_le_bytes(fmt.data.get(12..14)?.try_into().unwrap());
+            Some(data_size / block_align.max(1) as u64)
+        })
+        .unwrap_or(0);
+    let table: Vec<_> = chunks
+        .iter()
+        .filter(|chunk| &chunk.id != b"data" && len(chunk) >= u32::MAX as u64)
+        .collect();
+
+    let mut ds64_body = Vec::new();
+    let ds64_size = 28 + 12 * table.len() as u64;
+    ds64_body.extend((body_size(&chunks) + 8 + ds64_size).to_le_bytes());
+    ds64_body.extend(data_size.to_le_bytes());
+    ds64_body.extend(sample_count.to_le_bytes());
+    ds64_body.extend((table.len() as u32).to_le_bytes());
+    for chunk in table {
+        ds64_body.extend(chunk.id);
+        ds64_body.extend(len(chunk).to_le_bytes());
+    }
+    let ds64 = Chunk {
+        id: *b"ds64",
+        data: ds64_body,
+    };
+    let with_ds64: Vec<_> = std::iter::once(&ds64).chain(chunks).collect();
+    write(out, form, true, &with_ds64, len, data)
+}
+
+// writes `form` over the file at `path` it was read from, through a temporary file next to it
+// so a failed write can't leave a truncated file. The samples are copied across from `source`,
+// the file still open from reading, which is closed before it's replaced
+pub fn rewrite(
+    path: &str,
+    mut source: fs::File,
+    form: &Form,
+) -> Result<(), Box<dyn error::Error + 'static>> {
+    let mut temporary = Temporary {
+        path: format!("{path}.rewriting"),
+        renamed: false,
+    };
+    let mut out = BufWriter::new(fs::File::create(&temporary.path)?);
+    let data = match &form.data {
+        Some(range) => {
+            source.seek(SeekFrom::Start(range.start))?;
+            Some(DataBody {
+                source: &mut source,
+                len: range.end - range.start,
+            })
+        }
+        None => None,
+    };
+    write_form_to(&mut out, &form.form, &form.chunks, data)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    drop(source);
+    fs::rename(&temporary.path, path)?;
+    temporary.renamed = true;
+    Ok(())
+}
+
+// the file `rewrite` writes into, removed again unless it made it over the original
+struct Temporary {
+    path: String,
+    renamed: bool,
+}
+
+impl Drop for Temporary {
+    fn drop(&mut self) {
+        if !self.renamed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// the form header and the chunks. With `sixty_four` the riff size, the data size and every
+// size too big for 32 bits are all ones, the real ones are in ds64
+fn write(
+    out: &mut impl Write,
+    form: &[u8; 4],
+    sixty_four: bool,
+    chunks: &[&Chunk],
+    len: impl Fn(&Chunk) -> u64,
+    mut data: Option<DataBody>,
+) -> io::Result<()> {
+    let body_size = 4 + chunks
+        .iter()
+        .map(|chunk| 8 + len(chunk) + len(chunk) % 2)
+        .sum::<u64>();
+    let riff_size = match u32::try_from(body_size) {
+        Ok(size) if !sixty_four => size,
+        _ => u32::MAX,
+    };
+    out.write_all(form)?;
+    out.write_all(&riff_size.to_le_bytes())?;
+    out.write_all(b"WAVE")?;
+    for chunk in chunks {
+        out.write_all(&chunk.id)?;
+        let size = match u32::try_from(len(chunk)) {
+            Ok(size) if !(sixty_four && &chunk.id == b"data") && size != u32::MAX => size,
+            _ => u32::MAX,
+        };
+        out.write_all(&size.to_le_bytes())?;
+        match data.take_if(|_| &chunk.id == b"data") {
+            Some(data) => {
+                let copied = io::copy(&mut data.source.take(data.len), out)?;
+                if copied != data.len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the samples ended before the data chunk did",
+                    ));
+                }
+            }
+            None => out.write_all(&chunk.data)?,
+        }
+        if len(chunk) % 2 == 1 {
+            out.write_all(&[0])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Chunk {
+        Chunk {
+            id: *id,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn strip_keeps_the_audio_and_the_tags_only() {
+        let mut chunks = vec![
+            chunk(b"fmt ", &[0; 16]),
+            chunk(b"JUNK", &[0; 4]),
+            chunk(b"bext", &[0; 8]),
+            chunk(b"iXML", b"<BWFXML/>"),
+            chunk(b"axml", b"<ebuCoreMain/>"),
+            chunk(b"cue ", &[0; 4]),
+            chunk(b"smpl", &[0; 36]),
+            chunk(b"LIST", b"adtllabl"),
+            chunk(b"LIST", b"INFOINAM"),
+            chunk(b"id3 ", b"ID3"),
+            chunk(b"fact", &[0; 4]),
+            chunk(b"data", &[1, 2]),
+        ];
+        strip(&mut chunks);
+        let ids: Vec<_> = chunks.iter().map(|chunk| &chunk.id).collect();
+        assert_eq!(ids, [b"fmt ", b"LIST", b"id3 ", b"fact", b"data"]);
+        assert_eq!(chunks[1].data, b"INFOINAM");
+    }
+
+    #[test]
+    fn rf64_and_bw64_are_read_with_their_ds64_sizes_and_written_back_the_same() {
+        let chunks = vec![
+            chunk(
+                b"fmt ",
+                &[1, 0, 2, 0, 0x80, 0xbb, 0, 0, 0, 0xee, 2, 0, 4, 0, 16, 0],
+            ),
+            chunk(b"data", &[0; 12]),
+            chunk(b"LIST", b"INFO"),
+        ];
+        for form in [b"RF64", b"BW64"] {
+            let bytes = write_form(form, &chunks);
+            assert_eq!(&bytes[0..4], form);
+            assert_eq!(bytes[4..8], u32::MAX.to_le_bytes());
+
+            let (read, read_chunks) = read_form(&bytes).unwrap();
+            assert_eq!(&read, form);
+            let ids: Vec<_> = read_chunks.iter().map(|chunk| &chunk.id).collect();
+            assert_eq!(ids, [b"ds64", b"fmt ", b"data", b"LIST"]);
+            assert_eq!(read_chunks[2].data.len(), 12);
+            // riff size, data size and the sample count worked out from the block align
+            let ds64 = Ds64::parse(&read_chunks[0].data).unwrap();
+            assert_eq!(ds64.data_size, 12);
+            assert_eq!(ds64.sample_count, 3);
+            assert_eq!(
+                u64::from_le_bytes(read_chunks[0].data[0..8].try_into().unwrap()),
+                bytes.len() as u64 - 8
+            );
+
+            assert_eq!(write_form(&read, &read_chunks), bytes);
+        }
+    }
+
+    #[test]
+    fn samples_left_in_the_file_are_copied_across_unchanged() {
+        let chunks = vec![
+            chunk(
+                b"fmt ",
+                &[1, 0, 1, 0, 0x40, 0x1f, 0, 0, 0x80, 0x3e, 0, 0, 2, 0, 16, 0],
+            ),
+            chunk(b"data", &[1, 2, 3, 4, 5, 6]),
+            chunk(b"LIST", b"INFO"),
+        ];
+        for form in [b"RIFF", b"RF64"] {
+            let bytes = write_form(form, &chunks);
+            let read = read_form_from(&mut Cursor::new(&bytes)).unwrap();
+            assert!(read.chunks[read.chunks.len() - 2].data.is_empty());
+            let range = read.data.clone().unwrap();
+            let mut samples = &bytes[range.start as usize..range.end as usize];
+            let mut rewritten = Vec::new();
+            let body = DataBody {
+                source: &mut samples,
+                len: range.end - range.start,
+            };
+            write_form_to(&mut rewritten, &read.form, &read.chunks, Some(body)).unwrap();
+            assert_eq!(rewritten, bytes);
+        }
+    }
+
+    #[test]
+    fn a_failed_rewrite_leaves_the_file_and_nothing_else_behind() {
+        let directory = std::env::temp_dir().join(format!("wave-{}-rewrite", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("take.wav");
+        let bytes = write_form(b"RIFF", &[chunk(b"data", &[1, 2, 3, 4])]);
+        fs::write(&path, &bytes).unwrap();
+        let form = Form {
+            form: *b"RIFF",
+            chunks: vec![chunk(b"data", &[])],
+            data: Some(8..12),
+        };
+        // the samples can't be read from a file only open for writing
+        let source = fs::File::create(directory.join("other.wav")).unwrap();
+        assert!(rewrite(path.to_str().unwrap(), source, &form).is_err());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        assert!(!directory.join("take.wav.rewriting").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+//! Where decoded samples are kept: in memory, or once they would go over the memory budget
+//! in a temporary file mapped back in, so a long multichannel recording doesn't get wave
+//! killed for running out of memory. Mapping needs the `spill` feature, without it going
+//! over the budget is an error.
+
+#[cfg(not(feature = "spill"))]
+use std::convert::Infallible;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "spill")]
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+    sync::atomic::AtomicUsize,
+};
+use std::{fmt, fs};
+
+#[cfg(feature = "spill")]
+use memmap2::Mmap;
+
+// bytes of samples a decode may keep in memory, 0 until set
+static BUDGET: AtomicU64 = AtomicU64::new(0);
+
+/// Sets how many bytes of samples a decode may keep in memory before spilling to disk.
+pub fn set_memory_budget(bytes: u64) {
+    BUDGET.store(bytes.max(1), Ordering::Relaxed);
+}
+
+/// The budget set with [`set_memory_budget`], by default half of the physical memory on
+/// Linux and no limit elsewhere.
+pub fn memory_budget() -> u64 {
+    match BUDGET.load(Ordering::Relaxed) {
+        0 => physical_memory().map_or(u64::MAX, |bytes| bytes / 2),
+        budget => budget,
+    }
+}
+
+// MemTotal from /proc/meminfo, in bytes
+fn physical_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Interleaved 16-bit samples, read like a slice. Cloning shares them instead of copying.
+#[derive(Clone)]
+pub struct Samples(Storage);
+
+#[derive(Clone)]
+enum Storage {
+    Memory(Arc<[i16]>),
+    #[cfg(feature = "spill")]
+    Mapped(Arc<Mmap>),
+}
+
+impl Samples {
+    /// Whether the samples are in memory rather than mapped from a spill file.
+    pub fn in_memory(&self) -> bool {
+        matches!(self.0, Storage::Memory(_))
+    }
+}
+
+impl Deref for Samples {
+    type Target = [i16];
+
+    fn deref(&self) -> &[i16] {
+        match &self.0 {
+            Storage::Memory(samples) => samples,
+            // the mapping starts on a page boundary and holds whole native-endian samples
+            #[cfg(feature = "spill")]
+            Storage::Mapped(map) => unsafe {
+                std::slice::from_raw_parts(map.as_ptr().cast(), map.len() / 2)
+            },
+        }
+    }
+}
+
+impl Default for Samples {
+    fn default() -> Self {
+        Vec::new().into()
+    }
+}
+
+impl From<Vec<i16>> for Samples {
+    fn from(samples: Vec<i16>) -> Self {
+        Self(Storage::Memory(samples.into()))
+    }
+}
+
+impl From<Box<[i16]>> for Samples {
+    fn from(samples: Box<[i16]>) -> Self {
+        Self(Storage::Memory(samples.into()))
+    }
+}
+
+impl From<Arc<[i16]>> for Samples {
+    fn from(samples: Arc<[i16]>) -> Self {
+        Self(Storage::Memory(samples))
+    }
+}
+
+impl FromIterator<i16> for Samples {
+    fn from_iter<I: IntoIterator<Item = i16>>(samples: I) -> Self {
+        Self(Storage::Memory(samples.into_iter().collect()))
+    }
+}
+
+impl<'a> IntoIterator for &'a Samples {
+    type Item = &'a i16;
+    type IntoIter = std::slice::Iter<'a, i16>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl PartialEq for Samples {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl fmt::Debug for Samples {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Decoded samples written out to a temporary file as they come in, see [`Samples`].
+pub(crate) struct Spill {
+    #[cfg(feature = "spill")]
+    file: BufWriter<File>,
+    #[cfg(not(feature = "spill"))]
+    never: Infallible,
+}
+
+#[cfg(feature = "spill")]
+impl Spill {
+    // a new file in the temporary directory
+    pub(crate) fn create() -> io::Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("wave-{}-{count}.samples", process::id()));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // unix keeps the file around as long as it's open or mapped, elsewhere it's left in
+        // the temporary directory
+        fs::remove_file(&path).ok();
+        Ok(Self {
+            file: BufWriter::new(file),
+        })
+    }
+
+    // a little at a time, the samples written at once can be the whole budget
+    pub(crate) fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let mut bytes = [0; 8192];
+        for chunk in samples.chunks(bytes.len() / 2) {
+            for (pair, sample) in bytes.chunks_exact_mut(2).zip(chunk) {
+                pair.copy_from_slice(&sample.to_ne_bytes());
+            }
+            self.file.write_all(&bytes[..chunk.len() * 2])?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> io::Result<Samples> {
+        let file = self.file.into_inner().map_err(|e| e.into_error())?;
+        // nothing else writes to the file, it was made for this and isn't linked anywhere
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Samples(Storage::Mapped(Arc::new(map))))
+    }
+}
+
+#[cfg(not(feature = "spill"))]
+impl Spill {
+    pub(crate) fn create() -> Result<Self, String> {
+        Err(
+            "the decoded samples would go over the memory budget, spilling them to disk \
+             needs wave built with the spill feature"
+                .into(),
+        )
+    }
+
+    pub(crate) fn write(&mut self, _: &[i16]) -> Result<(), String> {
+        match self.never {}
+    }
+
+    pub(crate) fn finish(self) -> Result<Samples, String> {
+        match self.never {}
+    }
+}
+
+#[cfg(all(test, feature = "spill"))]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use crate::conformance::{signal, wav};
+    use crate::progress::CancellationToken;
+    use crate::{BLOCK_SAMPLES, WavFile, open_source};
+
+    #[test]
+    fn decodes_over_the_memory_budget_spill_to_disk_unchanged() {
+        let samples = signal(2, 48000, 100_000);
+        let bytes = wav(2, 48000, &samples, &[]);
+        let source = open_source(&bytes[..]).unwrap();
+        // a few blocks fit before the rest has to go to the spill file
+        let budget = 3 * BLOCK_SAMPLES as u64 * 2;
+        let spilled = WavFile::decode(
+            source,
+            budget,
+            &mut |_| ControlFlow::Continue(()),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+        assert!(!spilled.data.in_memory());
+        assert_eq!(&*spilled.data, &samples[..]);
+    }
+}
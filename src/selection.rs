@@ -0,0 +1,132 @@
+use std::ops::Range;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::render::Renderer;
+use crate::{WavFile, dynamics, font, loudness, timecode};
+
+// how close to an edge of the selection a right click has to land to drag that edge instead
+// of starting a new selection, in pixels
+pub const EDGE_GRAB: i32 = 4;
+pub const BAR_HEIGHT: u32 = 24;
+
+// a range of frames picked by right-dragging along the overview. The anchor stays where the
+// drag started and the edge follows the mouse, so it can be dragged past the anchor
+#[derive(Clone, Copy)]
+pub struct Selection {
+    anchor: usize,
+    edge: usize,
+}
+
+impl Selection {
+    pub fn new(frame: usize) -> Self {
+        Self {
+            anchor: frame,
+            edge: frame,
+        }
+    }
+
+    pub fn drag(&mut self, frame: usize) {
+        self.edge = frame;
+    }
+
+    // picks up the end of the selection nearest `frame` to drag it, the other one stays put
+    pub fn grab(&mut self, frame: usize) {
+        let Range { start, end } = self.range();
+        self.anchor = if frame.abs_diff(start) < frame.abs_diff(end) {
+            end
+        } else {
+            start
+        };
+        self.edge = frame;
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.edge)..self.anchor.max(self.edge)
+    }
+}
+
+pub struct Stats {
+    pub frames: Range<usize>,
+    pub seconds: f64,
+    pub peak_db: f32,
+    pub rms_db: f32,
+    // None when the selection is silent
+    pub loudness: Option<f32>,
+}
+
+impl Stats {
+    pub fn measure(wav: &WavFile, frames: Range<usize>) -> Self {
+        let channels = wav.header.num_channels.max(1) as usize;
+        let samples = wav
+            .data
+            .get(frames.start * channels..frames.end * channels)
+            .unwrap_or(&[]);
+        let peak = samples
+            .iter()
+            .map(|&sample| sample.unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        let square: f64 = samples
+            .iter()
+            .map(|&sample| (sample as f64 / 32768.0).powi(2))
+            .sum();
+        let rms = (square / samples.len().max(1) as f64).sqrt();
+        Self {
+            seconds: frames.len() as f64 / wav.header.sample_rate as f64,
+            frames,
+            peak_db: dynamics::to_db(peak as f32 / 32768.0),
+            rms_db: dynamics::to_db(rms as f32),
+            loudness: loudness::integrated(samples, channels, wav.header.sample_rate),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let loudness = self
+            .loudness
+            .map_or("-inf".to_string(), |lufs| format!("{lufs:.1}"));
+        format!(
+            "{}  peak {:.1} dBFS  rms {:.1} dBFS  {loudness} LUFS",
+            timecode::format(self.seconds),
+            self.peak_db,
+            self.rms_db
+        )
+    }
+}
+
+// the selection shaded over the overview
+pub fn draw_range(canvas: &mut impl Renderer, frames: &Range<usize>, total_frames: usize) {
+    let (width, height) = canvas.size();
+    let x = |frame: usize| (frame as f64 / total_frames.max(1) as f64 * width as f64) as i32;
+    let (left, right) = (x(frames.start), x(frames.end));
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(80, 160, 255, 90));
+    canvas.fill_rect(Rect::new(left, 0, (right - left).max(1) as u32, height));
+    canvas.set_blend(false);
+    canvas.set_color(Color::RGB(80, 160, 255));
+    for x in [left, right] {
+        canvas.line((x, 0), (x, height as i32));
+    }
+}
+
+// the info bar above the overview
+pub fn draw_bar(canvas: &mut impl Renderer, stats: &Stats) {
+    let (width, height) = canvas.size();
+    canvas.set_color(Color::RGB(24, 32, 48));
+    canvas.fill_rect(Rect::new(0, 0, width, height));
+    canvas.set_color(Color::RGB(255, 255, 255));
+    let text = format!(
+        "selection {}..{}  {}",
+        stats.frames.start,
+        stats.frames.end,
+        stats.summary()
+    );
+    font::draw_text(
+        canvas,
+        8,
+        (height as i32 - font::GLYPH_HEIGHT * 2) / 2,
+        2,
+        &text,
+    );
+}
@@ -0,0 +1,42 @@
+use std::ops::Range;
+
+use crate::WavFile;
+use crate::dynamics::to_db;
+
+// windows quieter than this count as silence
+pub const THRESHOLD_DB: f32 = -50.0;
+// pauses shorter than this are just gaps between words or notes, not chapter breaks
+pub const MIN_SILENCE_SECONDS: f32 = 1.0;
+const WINDOW_SECONDS: f32 = 0.02;
+
+// frame ranges of everything that isn't silence, in file order
+pub fn sound_segments(wav: &WavFile) -> Vec<Range<usize>> {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let window = ((wav.header.sample_rate as f32 * WINDOW_SECONDS) as usize).max(1);
+    let min_silent_windows = (MIN_SILENCE_SECONDS / WINDOW_SECONDS) as usize;
+
+    let mut segments: Vec<Range<usize>> = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    let mut silent_windows = 0;
+    for (i, chunk) in wav.data.chunks(window * channels).enumerate() {
+        let square: f32 = chunk.iter().map(|&s| (s as f32 / 32768.0).powi(2)).sum();
+        let loud = to_db((square / chunk.len() as f32).sqrt()) > THRESHOLD_DB;
+        let start = i * window;
+        let end = start + chunk.len() / channels;
+
+        if loud {
+            silent_windows = 0;
+            match current.as_mut() {
+                Some(segment) => segment.end = end,
+                None => current = Some(start..end),
+            }
+        } else if current.is_some() {
+            silent_windows += 1;
+            if silent_windows >= min_silent_windows {
+                segments.extend(current.take());
+            }
+        }
+    }
+    segments.extend(current);
+    segments
+}
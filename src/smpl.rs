@@ -0,0 +1,58 @@
+// https://www.recordingblogs.com/wiki/sample-chunk-of-a-wave-file, the `smpl` chunk samplers
+// read their root note and sustain loops from
+
+/// A sustain loop of a sampler instrument.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleLoop {
+    /// First frame of the loop.
+    pub start: usize,
+    /// Frame after the last one, the chunk itself stores the last frame.
+    pub end: usize,
+    /// 0 forward, 1 alternating, 2 backward, anything else is sampler specific.
+    pub kind: u32,
+    /// How often to play it, 0 for forever.
+    pub play_count: u32,
+}
+
+// the loops of a `smpl` chunk body, an empty list when it's too short for its header. A loop
+// count larger than the chunk is cut short
+pub fn parse(body: &[u8]) -> Vec<SampleLoop> {
+    // manufacturer, product, sample period, unity note, pitch fraction, smpte format, smpte
+    // offset, loop count and sampler data size, then 24 bytes a loop
+    if body.len() < 36 {
+        return Vec::new();
+    }
+    let count = le_u32(&body[28..32]) as usize;
+    body[36..]
+        .chunks_exact(24)
+        .take(count)
+        .map(|point| SampleLoop {
+            start: le_u32(&point[8..12]) as usize,
+            end: le_u32(&point[12..16]) as usize + 1,
+            kind: le_u32(&point[4..8]),
+            play_count: le_u32(&point[20..24]),
+        })
+        .filter(|sample_loop| sample_loop.start < sample_loop.end)
+        .collect()
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conformance::{decode, signal, wav};
+
+    #[test]
+    fn sampler_loop_end_is_made_exclusive() {
+        let mut smpl = vec![0; 36];
+        smpl[28..32].copy_from_slice(&1u32.to_le_bytes());
+        for field in [0u32, 0, 100, 299, 0, 0] {
+            smpl.extend(field.to_le_bytes());
+        }
+        let wav = decode(&wav(1, 8000, &signal(1, 8000, 400), &[(*b"smpl", smpl)])).unwrap();
+        let sample_loop = &wav.loops()[0];
+        assert_eq!((sample_loop.start, sample_loop.end), (100, 300));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::WavFile;
+use crate::fft::{fft, hann};
+
+pub const FFT_SIZE: usize = 1024;
+pub const HOP: usize = 512;
+pub const BINS: usize = FFT_SIZE / 2;
+
+pub struct Spectrogram {
+    window: Vec<f32>,
+    // magnitude of every bin in dBFS, computed lazily the first time a column scrolls into
+    // view so opening a long file doesn't require transforming all of it up front
+    columns: Vec<Option<Box<[f32]>>>,
+}
+
+impl Spectrogram {
+    pub fn new(wav: &WavFile) -> Self {
+        let frames = wav.data.len() / wav.header.num_channels.max(1) as usize;
+        Self {
+            window: hann(FFT_SIZE),
+            columns: vec![None; frames / HOP + 1],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn column(&mut self, wav: &WavFile, index: usize) -> &[f32] {
+        if self.columns[index].is_none() {
+            self.columns[index] = Some(self.compute(wav, index));
+        }
+        self.columns[index].as_deref().unwrap()
+    }
+
+    fn compute(&self, wav: &WavFile, index: usize) -> Box<[f32]> {
+        let channels = wav.header.num_channels.max(1) as usize;
+        let mut re = vec![0.0f32; FFT_SIZE];
+        let mut im = vec![0.0f32; FFT_SIZE];
+
+        // mix every channel down to mono, frames past the end of the file stay silent
+        let start = index * HOP;
+        for (i, value) in re.iter_mut().enumerate() {
+            let offset = (start + i) * channels;
+            if let Some(frame) = wav.data.get(offset..offset + channels) {
+                let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+                *value = sum as f32 / channels as f32 / 32768.0 * self.window[i];
+            }
+        }
+
+        fft(&mut re, &mut im);
+
+        // a full scale sine through a hann window peaks at FFT_SIZE / 4
+        let scale = 4.0 / FFT_SIZE as f32;
+        (0..BINS)
+            .map(|bin| {
+                let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() * scale;
+                20.0 * magnitude.max(1e-10).log10()
+            })
+            .collect()
+    }
+}
+
+pub fn bin_frequency(bin: usize, sample_rate: u32) -> f32 {
+    bin as f32 * sample_rate as f32 / FFT_SIZE as f32
+}
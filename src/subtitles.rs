@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::Path;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::render::Renderer;
+use crate::{font, i18n, timecode};
+
+// a subtitle of an SRT file, in frames of the file it's shown over
+pub struct Subtitle {
+    pub start: usize,
+    pub end: usize,
+    pub lines: Vec<String>,
+}
+
+// the subtitles of `--srt`, or of the .srt file next to `path` with the same name (what video
+// editors export alongside a clip). Empty when there's neither
+pub fn load(srt: Option<&str>, path: &str, sample_rate: u32) -> Result<Vec<Subtitle>, String> {
+    let sidecar = Path::new(path).with_extension("srt");
+    let srt = match srt {
+        Some(srt) => Path::new(srt),
+        None if sidecar.is_file() => sidecar.as_path(),
+        None => return Ok(Vec::new()),
+    };
+    let text = fs::read_to_string(srt).map_err(|e| format!("{}: {e}", srt.display()))?;
+    let subtitles = parse(&text, sample_rate).map_err(|e| format!("{}: {e}", srt.display()))?;
+    println!(
+        "{}",
+        i18n::fill(
+            "subtitles.loaded",
+            &[
+                ("count", subtitles.len().to_string()),
+                ("path", srt.display().to_string())
+            ]
+        )
+    );
+    Ok(subtitles)
+}
+
+// blocks separated by blank lines: a counter, `00:01:02,500 --> 00:01:04,000`, then the text.
+// The counter is optional since hand-edited files often get it wrong, and the subtitles come
+// back sorted by start
+pub fn parse(text: &str, sample_rate: u32) -> Result<Vec<Subtitle>, String> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut subtitles = Vec::new();
+    for block in text.split("\n\n").map(str::trim).filter(|b| !b.is_empty()) {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            return Err(format!(
+                "no `-->` timing in `{}`",
+                block.lines().next().unwrap()
+            ));
+        };
+        let (start, end) = timing.split_once("-->").unwrap();
+        // some writers use a dot instead of the comma, and the timing line can carry
+        // positioning after the end time
+        let time = |time: &str| {
+            let time = time.split_whitespace().next().unwrap_or_default();
+            timecode::parse(&time.replace(',', "."), sample_rate)
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        subtitles.push(Subtitle {
+            start,
+            end: end.max(start),
+            lines: lines.map(|line| strip_tags(line.trim())).collect(),
+        });
+    }
+    subtitles.sort_by_key(|subtitle| subtitle.start);
+    Ok(subtitles)
+}
+
+// drops `<i>`-style formatting tags, the font has no italics to show them with
+fn strip_tags(line: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+// the subtitle on screen at `frame`, the later one where two overlap
+pub fn at(subtitles: &[Subtitle], frame: usize) -> Option<&Subtitle> {
+    subtitles
+        .iter()
+        .rev()
+        .find(|subtitle| (subtitle.start..subtitle.end).contains(&frame))
+}
+
+// the text of the current subtitle centered at the bottom of the views, like a player would
+// show it
+pub fn draw_caption(canvas: &mut impl Renderer, subtitle: &Subtitle) {
+    let (width, height) = canvas.size();
+    let bottom = height as i32 - 12;
+    let top = bottom - subtitle.lines.len() as i32 * 20;
+    canvas.set_blend(true);
+    for (i, line) in subtitle.lines.iter().enumerate() {
+        let text_width = font::text_width(line, 2);
+        let left = (width as i32 - text_width) / 2;
+        let y = top + i as i32 * 20;
+        canvas.set_color(Color::RGBA(0, 0, 0, 180));
+        canvas.fill_rect(Rect::new(left - 4, y - 2, text_width as u32 + 8, 20));
+        canvas.set_color(Color::RGB(255, 255, 255));
+        font::draw_text(canvas, left, y + 2, 2, line);
+    }
+    canvas.set_blend(false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_read_with_either_decimal_separator() {
+        let srt = "\u{feff}2\r\n00:00:02.000 --> 00:00:03,500 X1:0\r\n<i>second</i>\r\n\r\n\
+                   1\r\n00:00:00,500 --> 00:00:01,000\r\nfirst\r\nline two\r\n";
+        let subtitles = parse(srt, 1000).unwrap();
+        assert_eq!(subtitles.len(), 2);
+        assert_eq!((subtitles[0].start, subtitles[0].end), (500, 1000));
+        assert_eq!(subtitles[0].lines, ["first", "line two"]);
+        assert_eq!((subtitles[1].start, subtitles[1].end), (2000, 3500));
+        assert_eq!(subtitles[1].lines, ["second"]);
+        assert!(at(&subtitles, 1500).is_none());
+        assert_eq!(at(&subtitles, 2500).unwrap().lines, ["second"]);
+    }
+}
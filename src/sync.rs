@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::error;
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use sdl2::audio::AudioSpecDesired;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use wave::Header;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::player::{PlaybackEvent, Player};
+use crate::render::Renderer;
+use crate::{AUDIO_BUFFER_FRAMES, WavFile, font};
+
+const SAMPLE_RATE: u32 = 48000;
+// one beep a second, long enough to see the flash
+const PERIOD_FRAMES: usize = SAMPLE_RATE as usize;
+const BEEP_FRAMES: usize = SAMPLE_RATE as usize / 20;
+const BEEP_HZ: f32 = 1000.0;
+// flashes the reported offset is averaged over
+const AVERAGE_OVER: usize = 8;
+// step of the up and down keys
+const DELAY_STEP: Duration = Duration::from_millis(5);
+
+// `wave sync-test`: a beep every second with the window flashing white while the latency
+// model says it is being heard. If they don't line up, up and down change the output delay
+// until they do, and the value to put in the config is shown
+pub fn sync_test(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    Args::parse(args, &[])?;
+    let config = Config::load();
+    let wav = beeps();
+    let (mut player, callback) = Player::new(&wav, None);
+    let mut output_delay = config.output_delay;
+    player.set_output_delay(output_delay);
+    player.set_loop(Some(0..wav.data.len()));
+    let events = player.subscribe();
+    player.play();
+
+    let sdl_context = sdl2::init()?;
+    let desired_spec = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE as i32),
+        channels: Some(1),
+        samples: Some(AUDIO_BUFFER_FRAMES),
+    };
+    let device = sdl_context
+        .audio()?
+        .open_playback(None, &desired_spec, |_spec| callback)?;
+    device.resume();
+    let window = sdl_context
+        .video()?
+        .window("wave sync test", 480, 240)
+        .position_centered()
+        .build()?;
+    let mut canvas = window.into_canvas().present_vsync().build()?;
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let mut handed_samples = 0;
+    let mut flashing = false;
+    // how long after the estimated start of a beep its flash reached the screen
+    let mut offsets: VecDeque<Duration> = VecDeque::new();
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return Ok(()),
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Up | Keycode::Down)),
+                    ..
+                } => {
+                    output_delay = if keycode == Keycode::Up {
+                        output_delay + DELAY_STEP
+                    } else {
+                        output_delay.saturating_sub(DELAY_STEP)
+                    };
+                    player.set_output_delay(output_delay);
+                    offsets.clear();
+                }
+                _ => {}
+            }
+        }
+        player.pump();
+        for event in events.try_iter() {
+            if let PlaybackEvent::PositionChanged(position) = event {
+                handed_samples = position;
+            }
+        }
+
+        let into_period = player.audible_position(handed_samples) % PERIOD_FRAMES;
+        let beeping = into_period < BEEP_FRAMES;
+        canvas.set_color(if beeping {
+            Color::RGB(255, 255, 255)
+        } else {
+            Color::RGB(0, 0, 0)
+        });
+        canvas.clear();
+        let (width, height) = canvas.size();
+        draw_status(&mut canvas, beeping, &offsets, output_delay);
+        // a bar along the bottom showing where in the period the beep is heard
+        canvas.set_color(Color::RGB(255, 0, 0));
+        let x = (into_period * width as usize / PERIOD_FRAMES) as i32;
+        canvas.fill_rect(Rect::new(x, height as i32 - 8, 4, 8)).ok();
+        canvas.present();
+
+        // the frame that just went up is the first one showing this beep
+        if beeping && !flashing {
+            let since_onset = Duration::from_secs_f64(into_period as f64 / SAMPLE_RATE as f64);
+            if offsets.len() == AVERAGE_OVER {
+                offsets.pop_front();
+            }
+            offsets.push_back(since_onset);
+        }
+        flashing = beeping;
+    }
+}
+
+fn draw_status(
+    canvas: &mut impl Renderer,
+    beeping: bool,
+    offsets: &VecDeque<Duration>,
+    output_delay: Duration,
+) {
+    let mut lines = vec![match offsets.len() {
+        0 => "waiting for a beep".to_string(),
+        count => {
+            let average = offsets.iter().sum::<Duration>() / count as u32;
+            format!(
+                "flash {:.1} ms after the beep",
+                average.as_secs_f64() * 1000.0
+            )
+        }
+    }];
+    lines.push(format!(
+        "output delay {} ms, up/down to adjust",
+        output_delay.as_millis()
+    ));
+    lines.push(format!(
+        "config: output_delay_ms = {}",
+        output_delay.as_millis()
+    ));
+    canvas.set_color(if beeping {
+        Color::RGB(0, 0, 0)
+    } else {
+        Color::RGB(255, 255, 255)
+    });
+    for (i, line) in lines.iter().enumerate() {
+        font::draw_text(canvas, 8, 8 + i as i32 * 20, 2, line);
+    }
+}
+
+// a second of a short sine beep followed by silence, played on a loop
+fn beeps() -> WavFile {
+    let data = (0..PERIOD_FRAMES)
+        .map(|frame| {
+            if frame >= BEEP_FRAMES {
+                return 0;
+            }
+            let t = frame as f32 / SAMPLE_RATE as f32;
+            ((TAU * BEEP_HZ * t).sin() * 16000.0) as i16
+        })
+        .collect();
+    WavFile {
+        header: Header {
+            num_channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            ..Header::default()
+        },
+        data,
+        ..WavFile::default()
+    }
+}
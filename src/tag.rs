@@ -0,0 +1,85 @@
+use std::{error, fs};
+
+use crate::cli::{self, Args};
+use crate::{i18n, id3, info_list, riff};
+
+const USAGE: &str = "wave tag <file.wav> [--title X] [--artist X] [--album X] [--date X] \
+     [--comment X] [--id3] [--strip]";
+
+// (option, LIST INFO id, id3 frame)
+const TAGS: [(&str, [u8; 4], [u8; 4]); 5] = [
+    ("title", *b"INAM", *b"TIT2"),
+    ("artist", *b"IART", *b"TPE1"),
+    ("album", *b"IPRD", *b"TALB"),
+    ("date", *b"ICRD", *b"TDRC"),
+    ("comment", *b"ICMT", *b"COMM"),
+];
+
+// `wave tag`: rewrites the LIST INFO chunk (and with --id3 the id3 chunk) in place, every other
+// chunk is copied byte for byte unless --strip drops everything but the audio and the tags,
+// an empty value removes the tag
+pub fn tag(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &["id3", "strip"])?;
+    let path = args.input(&cli::strip_usage(USAGE))?;
+    let given: Vec<_> = TAGS
+        .iter()
+        .filter_map(|&(option, info, frame)| Some((info, frame, args.value(option)?)))
+        .collect();
+    if given.is_empty() && !args.flag("strip") {
+        return Err(i18n::fill(
+            "tag.nothing-to-change",
+            &[("usage", cli::strip_usage(USAGE))],
+        )
+        .into());
+    }
+
+    // only the chunks around the samples are read, the samples are copied across when the
+    // file is written back
+    let mut file = fs::File::open(path)?;
+    let mut form = riff::read_form_from(&mut file)?;
+    let chunks = &mut form.chunks;
+    if args.flag("strip") {
+        riff::strip(chunks);
+    }
+
+    if !given.is_empty() {
+        let info_changes: Vec<_> = given
+            .iter()
+            .map(|&(info, _, value)| (info, value))
+            .collect();
+        match chunks
+            .iter_mut()
+            .find(|chunk| &chunk.id == b"LIST" && info_list::is_info(&chunk.data))
+        {
+            Some(list) => list.data = info_list::update(&list.data, &info_changes),
+            None => chunks.push(riff::Chunk {
+                id: *b"LIST",
+                data: info_list::update(&[], &info_changes),
+            }),
+        }
+    }
+
+    if args.flag("id3") && !given.is_empty() {
+        let frame_changes: Vec<_> = given
+            .iter()
+            .map(|&(_, frame, value)| (frame, value))
+            .collect();
+        match chunks
+            .iter_mut()
+            .find(|chunk| &chunk.id == b"id3 " || &chunk.id == b"ID3 ")
+        {
+            Some(tag) => tag.data = id3::update(&tag.data, &frame_changes),
+            None => chunks.push(riff::Chunk {
+                id: *b"id3 ",
+                data: id3::update(&[], &frame_changes),
+            }),
+        }
+    }
+
+    riff::rewrite(path, file, &form)?;
+    println!(
+        "{}",
+        i18n::fill("tag.tagged", &[("path", path.to_string())])
+    );
+    Ok(())
+}
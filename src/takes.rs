@@ -0,0 +1,300 @@
+use std::error;
+use std::path::Path;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use crate::metadata::Metadata;
+use crate::render::Renderer;
+use crate::samples::Samples;
+use crate::{WavFile, align, dynamics, font};
+
+// furthest apart stems are looked for when lining them up
+const MAX_ALIGN_SECONDS: f64 = 1.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pan {
+    Left,
+    Right,
+    Center,
+}
+
+struct Track {
+    name: String,
+    samples: Samples,
+    channels: usize,
+    pan: Pan,
+    gain_db: f32,
+    muted: bool,
+    // frames the track is played ahead by, set by lining it up with the first one
+    offset: isize,
+}
+
+// the files of a take recorded one per track, played together as a single stereo file. Mono
+// tracks named like `_L` and `_R` go hard left and right and everything else sits in the
+// middle, stereo ones keep their own sides. Gains and mutes only change what's heard, the
+// views go on showing every track at unity
+pub struct TakeGroup {
+    tracks: Vec<Track>,
+    frames: usize,
+    sample_rate: u32,
+}
+
+// splits `files` into take groups: neighbours whose names only differ after the last `_`,
+// `-` or space, like take_01_L.wav and take_01_R.wav or song_kick.wav and song_snare.wav.
+// Everything else stays a group of one
+pub fn group(files: &[String]) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for file in files {
+        match groups.last_mut() {
+            Some(group)
+                if take_key(&group[0]).is_some() && take_key(&group[0]) == take_key(file) =>
+            {
+                group.push(file.clone())
+            }
+            _ => groups.push(vec![file.clone()]),
+        }
+    }
+    groups
+}
+
+// the directory and the name without its track suffix
+fn take_key(file: &str) -> Option<(String, String)> {
+    let path = Path::new(file);
+    let stem = path.file_stem()?.to_string_lossy();
+    let (base, _) = stem.rsplit_once(['_', '-', ' '])?;
+    let directory = path
+        .parent()
+        .map(|parent| parent.to_string_lossy().into_owned());
+    Some((directory.unwrap_or_default(), base.to_string()))
+}
+
+fn track_suffix(file: &str) -> String {
+    let stem = Path::new(file)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    match stem.rsplit_once(['_', '-', ' ']) {
+        Some((_, suffix)) => suffix.to_string(),
+        None => stem.into_owned(),
+    }
+}
+
+impl TakeGroup {
+    // loads every track, they all need the same sample rate and the shorter ones are padded
+    // with silence
+    pub fn open(files: &[String]) -> Result<(Self, WavFile), Box<dyn error::Error + 'static>> {
+        let mut tracks = Vec::new();
+        let mut header = None;
+        for file in files {
+            let wav = WavFile::open(file)?;
+            let first = header.get_or_insert_with(|| wav.header.clone());
+            if wav.header.sample_rate != first.sample_rate {
+                return Err(format!(
+                    "{file} is at {} Hz, the rest of its take at {} Hz",
+                    wav.header.sample_rate, first.sample_rate
+                )
+                .into());
+            }
+            let name = track_suffix(file);
+            let pan = match name.to_lowercase().as_str() {
+                "l" | "left" => Pan::Left,
+                "r" | "right" => Pan::Right,
+                _ => Pan::Center,
+            };
+            tracks.push(Track {
+                name,
+                channels: wav.header.num_channels.max(1) as usize,
+                samples: wav.data,
+                pan,
+                gain_db: 0.0,
+                muted: false,
+                offset: 0,
+            });
+        }
+        let Some(mut header) = header else {
+            return Err("an empty take group".into());
+        };
+        let frames = tracks
+            .iter()
+            .map(|track| track.samples.len() / track.channels)
+            .max()
+            .unwrap_or(0);
+        let group = Self {
+            tracks,
+            frames,
+            sample_rate: header.sample_rate,
+        };
+
+        let data = group.mix();
+        header.num_channels = 2;
+        header.bits_per_sample = 16;
+        header.block_align = 4;
+        header.byte_rate = header.sample_rate * 4;
+        header.subchunk2_size = (data.len() * 2) as u32;
+        let wav = WavFile {
+            header,
+            data_size: (data.len() * 2) as u64,
+            data: data.to_vec().into(),
+            metadata: Metadata::default(),
+        };
+        Ok((group, wav))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    // every track summed into interleaved stereo with its gain, pan and mute
+    pub fn mix(&self) -> Samples {
+        let mut mix = vec![0.0f32; self.frames * 2];
+        for track in self.tracks.iter().filter(|track| !track.muted) {
+            let gain = 10f32.powf(track.gain_db / 20.0);
+            // a centered mono track is spread over both sides at -3 dB so it sounds as loud
+            let (left, right) = match track.pan {
+                Pan::Left => (gain, 0.0),
+                Pan::Right => (0.0, gain),
+                Pan::Center => (gain * 0.707, gain * 0.707),
+            };
+            // a track played ahead skips its start, one held back starts after silence
+            let skip = track.offset.max(0) as usize;
+            let delay = (-track.offset).max(0) as usize * 2;
+            for (frame, out) in track
+                .samples
+                .chunks_exact(track.channels)
+                .skip(skip)
+                .zip(mix[delay.min(self.frames * 2)..].chunks_exact_mut(2))
+            {
+                if track.channels == 1 {
+                    out[0] += frame[0] as f32 * left;
+                    out[1] += frame[0] as f32 * right;
+                } else {
+                    out[0] += frame[0] as f32 * gain;
+                    out[1] += frame[1] as f32 * gain;
+                }
+            }
+        }
+        mix.into_iter()
+            .map(|sample| sample.clamp(-32768.0, 32767.0) as i16)
+            .collect()
+    }
+
+    // lines every track up with the first one by cross-correlating their starts
+    pub fn align(&mut self) {
+        let mono = |track: &Track| -> Vec<f32> {
+            track
+                .samples
+                .chunks_exact(track.channels)
+                .map(|frame| frame.iter().map(|&s| s as f32).sum::<f32>() / 32768.0)
+                .collect()
+        };
+        let Some(reference) = self.tracks.first().map(mono) else {
+            return;
+        };
+        let max_lag = (MAX_ALIGN_SECONDS * self.sample_rate as f64) as usize;
+        for track in self.tracks.iter_mut().skip(1) {
+            track.offset = align::lag(&reference, &mono(track), max_lag);
+        }
+    }
+
+    pub fn toggle_mute(&mut self, track: usize) {
+        if let Some(track) = self.tracks.get_mut(track) {
+            track.muted = !track.muted;
+        }
+    }
+
+    pub fn set_gain(&mut self, track: usize, db: f32) {
+        if let Some(track) = self.tracks.get_mut(track) {
+            track.gain_db = db;
+        }
+    }
+
+    // one line per track for the panel and the terminal, with how far it was moved
+    pub fn lines(&self) -> Vec<String> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                let state = if track.muted {
+                    "muted".to_string()
+                } else {
+                    format!("{:+.1} dB", track.gain_db)
+                };
+                let mut line = format!("{} {:<8} {state}", i + 1, track.name);
+                if track.offset != 0 {
+                    line.push_str(&format!("  {:+} frames", -track.offset));
+                }
+                line
+            })
+            .collect()
+    }
+}
+
+// the tracks of the group in the top right corner, muted ones greyed out
+pub fn draw(canvas: &mut impl Renderer, group: &TakeGroup) {
+    let lines = group.lines();
+    let (width, _) = canvas.size();
+    let box_width = lines
+        .iter()
+        .map(|line| font::text_width(line, 2))
+        .max()
+        .unwrap_or(0)
+        + 8;
+    let left = width as i32 - box_width - 4;
+    let top = 36;
+    canvas.set_blend(true);
+    canvas.set_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(Rect::new(
+        left,
+        top,
+        box_width as u32,
+        lines.len() as u32 * 20 + 8,
+    ));
+    canvas.set_blend(false);
+    for (i, (line, track)) in lines.iter().zip(&group.tracks).enumerate() {
+        canvas.set_color(if track.muted {
+            Color::RGB(110, 110, 110)
+        } else {
+            Color::RGB(255, 255, 255)
+        });
+        font::draw_text(canvas, left + 4, top + 4 + i as i32 * 20, 2, line);
+    }
+}
+
+// `track dB` as typed into the gain prompt, tracks counted from 1
+pub fn parse_gain(text: &str, tracks: usize) -> Result<(usize, f32), String> {
+    let invalid = || format!("`{text}` is not a track and a gain (try `2 -6`)");
+    let (track, db) = text.trim().split_once(' ').ok_or_else(invalid)?;
+    let track: usize = track.parse().map_err(|_| invalid())?;
+    let db: f32 = db
+        .trim()
+        .trim_end_matches("dB")
+        .trim()
+        .parse()
+        .map_err(|_| invalid())?;
+    if track == 0 || track > tracks {
+        return Err(format!("there are tracks 1 to {tracks}"));
+    }
+    Ok((track - 1, db.clamp(dynamics::FLOOR_DB, 24.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbours_differing_only_in_the_suffix_form_a_take() {
+        let files: Vec<String> = [
+            "a/take_01_L.wav",
+            "a/take_01_R.wav",
+            "a/take_02_L.wav",
+            "b/solo.wav",
+        ]
+        .map(String::from)
+        .to_vec();
+        let groups = group(&files);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].len(), 2);
+        assert_eq!(track_suffix(&groups[0][1]), "R");
+    }
+}
@@ -0,0 +1,182 @@
+use std::{env, fs, path::PathBuf};
+
+use sdl2::pixels::Color;
+
+use crate::colormap::Colormap;
+use crate::i18n;
+
+// user adjustable look of the views, read from a plain `key = value` file:
+//
+//   # lines starting with '#' are comments
+//   spectrogram_colormap = magma
+//   spectrogram_db_floor = -90
+//   spectrogram_db_ceiling = 0
+//   overview_colormap = magma
+//   palette = deuteranopia
+pub struct Theme {
+    pub spectrogram_colormap: Colormap,
+    // levels at or below the floor get the first color of the map, at or above the ceiling the last
+    pub spectrogram_db_floor: f32,
+    pub spectrogram_db_ceiling: f32,
+    // colors the full-file overview by rms level
+    pub overview_colormap: Colormap,
+    // colors of the waveform and the level meters
+    pub palette: Palette,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            spectrogram_colormap: Colormap::Viridis,
+            spectrogram_db_floor: -100.0,
+            spectrogram_db_ceiling: 0.0,
+            overview_colormap: Colormap::Magma,
+            palette: Palette::Standard,
+        }
+    }
+}
+
+impl Theme {
+    // $WAVE_THEME if set, otherwise $XDG_CONFIG_HOME/wave/theme or ~/.config/wave/theme
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("WAVE_THEME") {
+            return Some(path.into());
+        }
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("wave").join("theme"))
+    }
+
+    // a missing theme file just means the defaults, malformed lines are reported and skipped
+    pub fn load() -> Self {
+        let mut theme = Self::default();
+        let Some(contents) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return theme;
+        };
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Err(e) = theme.set(line) {
+                eprintln!(
+                    "{}",
+                    i18n::fill(
+                        "theme.bad-line",
+                        &[("line", (number + 1).to_string()), ("error", e.to_string())]
+                    )
+                );
+            }
+        }
+        theme.clamp_db_range();
+        theme
+    }
+
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key = value`, got `{line}`"))?;
+        let (key, value) = (key.trim(), value.trim());
+        let number = || {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("`{value}` is not a number"))
+        };
+        let colormap =
+            || Colormap::from_name(value).ok_or_else(|| format!("unknown colormap `{value}`"));
+
+        match key {
+            "spectrogram_colormap" => self.spectrogram_colormap = colormap()?,
+            "spectrogram_db_floor" => self.spectrogram_db_floor = number()?,
+            "spectrogram_db_ceiling" => self.spectrogram_db_ceiling = number()?,
+            "overview_colormap" => self.overview_colormap = colormap()?,
+            "palette" => {
+                self.palette = Palette::from_name(value).ok_or_else(|| {
+                    format!(
+                        "unknown palette `{value}`, expected standard, deuteranopia or protanopia"
+                    )
+                })?
+            }
+            _ => return Err(format!("unknown key `{key}`")),
+        }
+        Ok(())
+    }
+
+    // keeps at least 10 dB between floor and ceiling so the map never collapses
+    pub fn clamp_db_range(&mut self) {
+        self.spectrogram_db_ceiling = self.spectrogram_db_ceiling.clamp(-150.0, 20.0);
+        self.spectrogram_db_floor = self
+            .spectrogram_db_floor
+            .clamp(-160.0, self.spectrogram_db_ceiling - 10.0);
+    }
+
+    pub fn spectrogram_color(&self, db: f32) -> (u8, u8, u8) {
+        let range = self.spectrogram_db_ceiling - self.spectrogram_db_floor;
+        self.spectrogram_colormap
+            .color((db - self.spectrogram_db_floor) / range)
+    }
+}
+
+// the waveform and meter colors. The standard one goes from green through yellow to red like
+// most meters, which is the axis red-green color blindness can't tell apart, so the others
+// take their colors from the Okabe-Ito set instead and keep the levels apart by brightness too.
+// Deuteranopia and protanopia both confuse red and green, protanopes also see red as dark, so
+// theirs tops out in white rather than orange
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Standard,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl Palette {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Self::Standard),
+            "deuteranopia" => Some(Self::Deuteranopia),
+            "protanopia" => Some(Self::Protanopia),
+            _ => None,
+        }
+    }
+
+    pub fn waveform(self) -> Color {
+        match self {
+            Self::Standard => Color::RGB(0, 255, 0),
+            Self::Deuteranopia => Color::RGB(86, 180, 233),
+            Self::Protanopia => Color::RGB(240, 228, 66),
+        }
+    }
+
+    // meter bars up to -18 dB, up to -6 dB and above
+    pub fn meter(self) -> [Color; 3] {
+        match self {
+            Self::Standard => [
+                Color::RGB(0, 220, 0),
+                Color::RGB(255, 220, 0),
+                Color::RGB(255, 64, 64),
+            ],
+            Self::Deuteranopia => [
+                Color::RGB(0, 114, 178),
+                Color::RGB(240, 228, 66),
+                Color::RGB(230, 159, 0),
+            ],
+            Self::Protanopia => [
+                Color::RGB(0, 114, 178),
+                Color::RGB(86, 180, 233),
+                Color::RGB(255, 255, 255),
+            ],
+        }
+    }
+
+    // full-scale samples, always drawn as a mark of their own too so the color isn't the only
+    // thing that says so
+    pub fn clip(self) -> Color {
+        match self {
+            Self::Standard => Color::RGB(255, 64, 64),
+            Self::Deuteranopia => Color::RGB(230, 159, 0),
+            Self::Protanopia => Color::RGB(255, 255, 255),
+        }
+    }
+}
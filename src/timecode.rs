@@ -0,0 +1,57 @@
+use std::ops::Range;
+
+// formats a position in seconds as `mm:ss.mmm`, or `h:mm:ss.mmm` past the first hour
+pub fn format(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, minutes) = (millis / 3_600_000, millis / 60_000 % 60);
+    let (secs, millis) = (millis / 1000 % 60, millis % 1000);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}.{millis:03}")
+    } else {
+        format!("{minutes:02}:{secs:02}.{millis:03}")
+    }
+}
+
+// parses a position into a frame index: `1.5s` in seconds, `12:34.5` or `1:02:03` as a clock
+// time, and a bare integer as a frame number
+pub fn parse(text: &str, sample_rate: u32) -> Result<usize, String> {
+    let text = text.trim();
+    let invalid = || format!("`{text}` is not a position (try 1.5s, 12:34.5 or a frame number)");
+
+    let seconds = if let Some(seconds) = text.strip_suffix('s') {
+        seconds.parse::<f64>().map_err(|_| invalid())?
+    } else if text.contains(':') {
+        let mut seconds = 0.0;
+        for part in text.split(':') {
+            let value = part.parse::<f64>().map_err(|_| invalid())?;
+            seconds = seconds * 60.0 + value;
+        }
+        seconds
+    } else {
+        return text.parse::<usize>().map_err(|_| invalid());
+    };
+
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(invalid());
+    }
+    Ok((seconds * sample_rate as f64).round() as usize)
+}
+
+// `start..end` with either side optional, each side in any form `parse` accepts
+pub fn parse_range(text: &str, sample_rate: u32, frames: usize) -> Result<Range<usize>, String> {
+    let (start, end) = text
+        .split_once("..")
+        .ok_or_else(|| format!("`{text}` is not a range (try 1.0s..2.0s)"))?;
+    let start = match start {
+        "" => 0,
+        start => parse(start, sample_rate)?,
+    };
+    let end = match end {
+        "" => frames,
+        end => parse(end, sample_rate)?.min(frames),
+    };
+    if start >= end {
+        return Err(format!("`{text}` is an empty range"));
+    }
+    Ok(start..end)
+}
@@ -0,0 +1,66 @@
+use std::f32::consts::TAU;
+
+// reference frequencies worth matching by ear: concert pitch, a 1 kHz test tone and mains hum
+pub const PRESETS: [f32; 6] = [440.0, 1000.0, 50.0, 60.0, 100.0, 120.0];
+pub const LEVELS_DB: [f32; 5] = [-30.0, -24.0, -18.0, -12.0, -6.0];
+
+// what the guide tone should sound like, changed from the ui and sent to the audio thread
+#[derive(Clone, Copy)]
+pub struct ToneSettings {
+    pub enabled: bool,
+    pub frequency: f32,
+    pub level_db: f32,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: PRESETS[0],
+            level_db: -18.0,
+        }
+    }
+}
+
+impl ToneSettings {
+    pub fn next_preset(&mut self) {
+        self.frequency = next_after(&PRESETS, self.frequency);
+    }
+
+    pub fn next_level(&mut self) {
+        self.level_db = next_after(&LEVELS_DB, self.level_db);
+    }
+}
+
+// sine oscillator mixed under playback to identify tonal content in the file
+pub struct GuideTone {
+    pub settings: ToneSettings,
+    sample_rate: f32,
+    phase: f32,
+}
+
+impl GuideTone {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            settings: ToneSettings::default(),
+            sample_rate: sample_rate as f32,
+            phase: 0.0,
+        }
+    }
+
+    // one sample of the tone in the -1.0..1.0 range, silent while disabled
+    pub fn next_sample(&mut self) -> f32 {
+        let settings = self.settings;
+        if !settings.enabled {
+            return 0.0;
+        }
+        let value = self.phase.sin() * 10f32.powf(settings.level_db / 20.0);
+        self.phase = (self.phase + TAU * settings.frequency / self.sample_rate) % TAU;
+        value
+    }
+}
+
+fn next_after(values: &[f32], current: f32) -> f32 {
+    let index = values.iter().position(|&v| v == current).unwrap_or(0);
+    values[(index + 1) % values.len()]
+}
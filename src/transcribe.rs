@@ -0,0 +1,96 @@
+use std::{error, fs};
+
+use crate::cli::Args;
+use crate::dynamics::to_db;
+use crate::midi::{self, Note};
+use crate::{WavFile, i18n, onset, pitch, timecode};
+
+const USAGE: &str = "wave transcribe <in.wav> <out.mid>";
+// skip the attack transient, its noise throws off pitch detection
+const PITCH_DELAY_SECONDS: f32 = 0.02;
+const PITCH_BLOCK: usize = 2048;
+// a note ends early once its level falls this far below the quietest note worth keeping
+const RELEASE_DB: f32 = -45.0;
+const LEVEL_WINDOW_SECONDS: f32 = 0.01;
+// frames downmixed at once for the onset detector
+const READ_BLOCK: usize = 1 << 16;
+
+// `wave transcribe`: a rough monophonic midi sketch from the onset and pitch detectors
+pub fn transcribe(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    let (input, output) = match args.positional.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err(i18n::fill("cli.usage", &[("usage", USAGE.to_string())]).into()),
+    };
+    let wav = WavFile::open(input)?;
+    let notes = detect_notes(&wav);
+
+    for note in &notes {
+        println!(
+            "{}  {}",
+            timecode::format(note.start),
+            i18n::fill(
+                "transcribe.note",
+                &[
+                    ("key", format!("{:>3}", note.key)),
+                    ("velocity", format!("{:>3}", note.velocity)),
+                ]
+            )
+        );
+    }
+    fs::write(output, midi::write_smf(&notes))?;
+    println!(
+        "{}",
+        i18n::fill(
+            "transcribe.wrote",
+            &[
+                ("count", notes.len().to_string()),
+                ("path", output.to_string())
+            ]
+        )
+    );
+    Ok(())
+}
+
+// the file is downmixed a block at a time, so a long recording isn't copied into memory whole
+fn detect_notes(wav: &WavFile) -> Vec<Note> {
+    let sample_rate = wav.header.sample_rate;
+    let frames = wav.frames();
+    let onsets = onset::detect(wav.mono_blocks(READ_BLOCK), sample_rate);
+    let delay = (PITCH_DELAY_SECONDS * sample_rate as f32) as usize;
+    let level_window = ((LEVEL_WINDOW_SECONDS * sample_rate as f32) as usize).max(1);
+
+    let mut notes = Vec::new();
+    for (i, &start) in onsets.iter().enumerate() {
+        let limit = onsets.get(i + 1).copied().unwrap_or(frames);
+        let block_start = (start + delay).min(limit);
+        let block = wav.mono_range(block_start..block_start + PITCH_BLOCK);
+        let Some(frequency) = pitch::yin(&block, sample_rate) else {
+            continue;
+        };
+
+        // the note lasts until the next onset or until it has decayed into the noise
+        let mut end = limit;
+        let mut peak_db = f32::MIN;
+        for (j, from) in (start..limit).step_by(level_window).enumerate() {
+            let window = wav.mono_range(from..(from + level_window).min(limit));
+            let rms = (window.iter().map(|s| s * s).sum::<f32>() / window.len() as f32).sqrt();
+            let db = to_db(rms);
+            peak_db = peak_db.max(db);
+            if db < RELEASE_DB && j * level_window > delay {
+                end = from;
+                break;
+            }
+        }
+
+        // -60..0 dBFS spread over the velocity range
+        let velocity = ((peak_db + 60.0) / 60.0 * 127.0).clamp(1.0, 127.0) as u8;
+        notes.push(Note {
+            start: start as f64 / sample_rate as f64,
+            end: end as f64 / sample_rate as f64,
+            key: pitch::midi_note(frequency),
+            velocity,
+        });
+    }
+    notes
+}
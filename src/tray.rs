@@ -0,0 +1,151 @@
+// the icon in the system tray, with the `tray` feature, over the freedesktop
+// StatusNotifierItem protocol most Linux desktops show. Its menu plays and pauses, skips to the
+// next track, brings the window back and quits, and its tooltip is what's playing. While it's
+// up, closing the window only hides it and playback carries on. Without the feature, or without a
+// desktop that shows tray icons, there's no icon and closing the window quits as before
+
+use std::sync::mpsc::{self, Receiver};
+
+// what a click in the tray asks of the event loop
+#[cfg_attr(not(feature = "tray"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrayAction {
+    PlayPause,
+    NextTrack,
+    ShowWindow,
+    Quit,
+}
+
+pub struct Tray {
+    #[cfg(feature = "tray")]
+    handle: Option<ksni::blocking::Handle<Icon>>,
+    actions: Receiver<TrayAction>,
+    // the title and playing state last shown, the icon is only updated when they change
+    shown: Option<(String, bool)>,
+}
+
+impl Tray {
+    pub fn new() -> Self {
+        let (sender, actions) = mpsc::channel();
+        #[cfg(feature = "tray")]
+        let handle = {
+            use ksni::blocking::TrayMethods;
+
+            let icon = Icon {
+                title: String::new(),
+                playing: false,
+                actions: sender,
+            };
+            match icon.spawn() {
+                Ok(handle) => Some(handle),
+                Err(e) => {
+                    println!("{}: {e}", crate::i18n::text("tray.no-icon"));
+                    None
+                }
+            }
+        };
+        #[cfg(not(feature = "tray"))]
+        drop(sender);
+        Self {
+            #[cfg(feature = "tray")]
+            handle,
+            actions,
+            shown: None,
+        }
+    }
+
+    // whether there's an icon to get the window back from
+    pub fn is_shown(&self) -> bool {
+        #[cfg(feature = "tray")]
+        return self
+            .handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_closed());
+        #[cfg(not(feature = "tray"))]
+        false
+    }
+
+    // the clicks since the last call
+    pub fn actions(&self) -> impl Iterator<Item = TrayAction> + '_ {
+        self.actions.try_iter()
+    }
+
+    pub fn update(&mut self, title: &str, playing: bool) {
+        if self
+            .shown
+            .as_ref()
+            .is_some_and(|(shown, was_playing)| shown == title && *was_playing == playing)
+        {
+            return;
+        }
+        self.shown = Some((title.to_string(), playing));
+        #[cfg(feature = "tray")]
+        if let Some(handle) = &self.handle {
+            handle.update(|icon| {
+                icon.title = title.to_string();
+                icon.playing = playing;
+            });
+        }
+    }
+}
+
+#[cfg(feature = "tray")]
+struct Icon {
+    title: String,
+    playing: bool,
+    actions: mpsc::Sender<TrayAction>,
+}
+
+#[cfg(feature = "tray")]
+impl ksni::Tray for Icon {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").into()
+    }
+
+    fn title(&self) -> String {
+        format!("wave  {}", self.title)
+    }
+
+    fn icon_name(&self) -> String {
+        if self.playing {
+            "media-playback-start".into()
+        } else {
+            "media-playback-pause".into()
+        }
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        ksni::ToolTip {
+            title: self.title.clone(),
+            ..ksni::ToolTip::default()
+        }
+    }
+
+    // a left click brings the window back
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.actions.send(TrayAction::ShowWindow).ok();
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        use crate::i18n;
+        use ksni::menu::StandardItem;
+
+        let item = |key: &str, action: TrayAction| {
+            StandardItem {
+                label: i18n::text(key).to_string(),
+                activate: Box::new(move |icon: &mut Self| {
+                    icon.actions.send(action).ok();
+                }),
+                ..StandardItem::default()
+            }
+            .into()
+        };
+        vec![
+            item("action.play-pause", TrayAction::PlayPause),
+            item("action.next-track", TrayAction::NextTrack),
+            item("tray.show-window", TrayAction::ShowWindow),
+            ksni::MenuItem::Separator,
+            item("action.quit", TrayAction::Quit),
+        ]
+    }
+}
@@ -0,0 +1,51 @@
+use std::error;
+use std::fs::File;
+
+use crate::cli::Args;
+use crate::i18n;
+use crate::playlist::{self, Sort};
+
+const USAGE: &str = "wave validate <in.wav|dir|glob ...>";
+
+// `wave validate`: how each file deviates from the spec, without decoding the samples. Fails
+// if any of them does or can't be read at all
+pub fn validate(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    args.input(USAGE)?;
+    let files = playlist::expand(&args.positional, Sort::Name)?;
+
+    let mut failed = 0;
+    for path in &files {
+        let metadata = File::open(path)
+            .map_err(Into::into)
+            .and_then(wave::open_source)
+            .and_then(|source| Ok(source.finish()?));
+        match metadata {
+            Ok(metadata) if metadata.diagnostics.is_empty() => {
+                println!("{path}: {}", i18n::text("validate.ok"))
+            }
+            Ok(metadata) => {
+                failed += 1;
+                println!("{path}:");
+                for diagnostic in &metadata.diagnostics {
+                    println!("  {diagnostic}");
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{path}: {e}");
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(i18n::fill(
+            "validate.failed",
+            &[
+                ("failed", failed.to_string()),
+                ("files", files.len().to_string()),
+            ],
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::error;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::samples::Samples;
+use crate::{Header, WavFile, i18n};
+
+const EXTENSIONS: [&str; 7] = ["mp4", "m4v", "mov", "mkv", "webm", "avi", "mxf"];
+
+pub fn is_video(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|extension| {
+        EXTENSIONS
+            .iter()
+            .any(|video| extension.eq_ignore_ascii_case(video))
+    })
+}
+
+// the first audio track of a video file as a 16-bit wav, decoded by ffmpeg the same way the
+// desktop notifications go through the tool the desktop already has: no codec library gets
+// linked in for a preview. ffprobe finds the rate and channel count so nothing is resampled
+// or downmixed
+pub fn open(path: &str) -> Result<WavFile, Box<dyn error::Error + 'static>> {
+    let missing = |e| format!("{path}: video files need ffmpeg and ffprobe on the PATH ({e})");
+    let probe = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "a:0"])
+        .args([
+            "-show_entries",
+            "stream=sample_rate,channels",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(path)
+        .output()
+        .map_err(missing)?;
+    if !probe.status.success() {
+        return Err(format!("{path}: {}", String::from_utf8_lossy(&probe.stderr).trim()).into());
+    }
+    // `sample_rate,channels`, nothing at all when there's no audio track
+    let probed = String::from_utf8_lossy(&probe.stdout);
+    let (sample_rate, channels) = probed
+        .trim()
+        .split_once(',')
+        .and_then(|(rate, channels)| {
+            Some((rate.parse::<u32>().ok()?, channels.parse::<u16>().ok()?))
+        })
+        .ok_or_else(|| format!("{path}: no audio track"))?;
+
+    println!(
+        "{}",
+        i18n::fill("video.extracting", &[("path", path.to_string())])
+    );
+    let decoded = Command::new("ffmpeg")
+        .args(["-v", "error", "-i", path, "-map", "0:a:0", "-vn"])
+        .args(["-f", "s16le", "-acodec", "pcm_s16le", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(missing)?;
+    if !decoded.status.success() {
+        return Err(format!(
+            "{path}: {}",
+            String::from_utf8_lossy(&decoded.stderr).trim()
+        )
+        .into());
+    }
+
+    let data: Samples = decoded
+        .stdout
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    let data_size = data.len() as u64 * 2;
+    let header = Header {
+        chunk_id: "RIFF".into(),
+        format: "WAVE".into(),
+        subchunk1_id: "fmt ".into(),
+        subchunk1_size: 16,
+        audio_format: 1,
+        num_channels: channels,
+        sample_rate,
+        byte_rate: sample_rate * channels as u32 * 2,
+        block_align: channels * 2,
+        bits_per_sample: 16,
+        subchunk2_id: "data".into(),
+        subchunk2_size: data_size.min(u32::MAX as u64) as u32,
+        ..Header::default()
+    };
+    Ok(WavFile {
+        header,
+        data_size,
+        data,
+        metadata: Default::default(),
+    })
+}
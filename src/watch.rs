@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{error, fs, thread};
+
+use crate::classify::{self, Class};
+use crate::cli::Args;
+use crate::dynamics::Dynamics;
+use crate::json::Value;
+use crate::progress::CancellationToken;
+use crate::{WavFile, i18n, loudness, playlist, silence};
+
+const USAGE: &str =
+    "wave watch <dir> [--run stats,loudness,chapters,classes] [--out DIR] [--interval SECONDS]";
+const ANALYSES: [&str; 4] = ["stats", "loudness", "chapters", "classes"];
+
+// `wave watch`: an ingestion service for a drop folder. Every wav file that shows up in it gets
+// analyzed once it stops growing and a json report goes to the output directory, named after
+// the file. Files that already have a report are left alone, so a restart picks up where it
+// stopped. Runs until killed
+pub fn watch(args: &[String]) -> Result<(), Box<dyn error::Error + 'static>> {
+    let args = Args::parse(args, &[])?;
+    let dir = PathBuf::from(args.input(USAGE)?);
+    let out = args.value("out").map_or_else(|| dir.clone(), PathBuf::from);
+    let run: Vec<&str> = args
+        .value("run")
+        .unwrap_or("stats,loudness")
+        .split(',')
+        .map(str::trim)
+        .collect();
+    if let Some(unknown) = run.iter().find(|name| !ANALYSES.contains(name)) {
+        return Err(format!(
+            "unknown analysis `{unknown}`, expected some of {}",
+            ANALYSES.join(",")
+        )
+        .into());
+    }
+    let interval = match args.value("interval") {
+        Some(seconds) => Duration::from_secs_f64(
+            seconds
+                .parse()
+                .map_err(|_| format!("--interval expects seconds, got `{seconds}`"))?,
+        ),
+        None => Duration::from_secs(2),
+    };
+    fs::create_dir_all(&out)?;
+    println!(
+        "{}",
+        i18n::fill(
+            "watch.watching",
+            &[
+                ("dir", dir.display().to_string()),
+                ("interval", format!("{interval:?}"))
+            ]
+        )
+    );
+
+    // size and modification time of the files waiting to settle, as of the last look
+    let mut pending: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+    loop {
+        for path in wav_files(&dir)? {
+            let report = report_path(&out, &path);
+            if report.exists() {
+                continue;
+            }
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let seen = (metadata.len(), metadata.modified()?);
+            // a file is only complete once it looked the same twice in a row
+            if pending.insert(path.clone(), seen) != Some(seen) {
+                continue;
+            }
+            pending.remove(&path);
+            match analyze(&path, &run) {
+                Ok(value) => {
+                    fs::write(&report, value.to_string() + "\n")?;
+                    println!("{} -> {}", path.display(), report.display());
+                }
+                // written anyway so a broken file isn't retried forever
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    let error =
+                        Value::Object(vec![("error".to_string(), Value::String(e.to_string()))]);
+                    fs::write(&report, error.to_string() + "\n")?;
+                }
+            }
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn wav_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn error::Error + 'static>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| playlist::is_audio(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn report_path(out: &Path, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    out.join(format!("{name}.json"))
+}
+
+fn analyze(path: &Path, run: &[&str]) -> Result<Value, Box<dyn error::Error + 'static>> {
+    let wav = WavFile::open(&path.to_string_lossy())?;
+    let header = &wav.header;
+    let channels = header.num_channels.max(1) as usize;
+    let frames = wav.data.len() / channels;
+    let seconds = |frame: usize| Value::Number(frame as f64 / header.sample_rate as f64);
+
+    let mut fields = vec![
+        (
+            "file".to_string(),
+            Value::String(path.to_string_lossy().into_owned()),
+        ),
+        ("channels".to_string(), Value::Number(channels as f64)),
+        (
+            "sample_rate".to_string(),
+            Value::Number(header.sample_rate as f64),
+        ),
+        ("duration".to_string(), seconds(frames)),
+    ];
+    for &name in run {
+        let value = match name {
+            "stats" => {
+                let dynamics = Dynamics::analyze(
+                    &wav,
+                    &mut |_| ControlFlow::Continue(()),
+                    &CancellationToken::new(),
+                )
+                .map_err(|_| "analysis was cancelled")?;
+                Value::Object(vec![
+                    (
+                        "peak_db".to_string(),
+                        Value::Number(dynamics.peak_db as f64),
+                    ),
+                    ("rms_db".to_string(), Value::Number(dynamics.rms_db as f64)),
+                    (
+                        "crest_db".to_string(),
+                        Value::Number(dynamics.crest_db as f64),
+                    ),
+                ])
+            }
+            "loudness" => loudness::integrated(&wav.data, channels, header.sample_rate)
+                .map_or(Value::Null, |lufs| Value::Number(lufs as f64)),
+            "chapters" => Value::Array(
+                silence::sound_segments(&wav)
+                    .into_iter()
+                    .map(|segment| seconds(segment.start))
+                    .collect(),
+            ),
+            _ => {
+                let classes = classify::classify(&wav);
+                let share = |class: Class| {
+                    let count = classes.iter().filter(|&&c| c == class).count();
+                    Value::Number(count as f64 / classes.len().max(1) as f64)
+                };
+                Value::Object(vec![
+                    ("speech".to_string(), share(Class::Speech)),
+                    ("music".to_string(), share(Class::Music)),
+                    ("silence".to_string(), share(Class::Silence)),
+                ])
+            }
+        };
+        fields.push((name.to_string(), value));
+    }
+    Ok(Value::Object(fields))
+}
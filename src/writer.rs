@@ -0,0 +1,262 @@
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::{error, fs};
+
+/// The format of a file written by [`WavWriter`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WavSpec {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 16, 24 or 32, integer PCM like the reader takes.
+    pub bits_per_sample: u16,
+}
+
+impl WavSpec {
+    fn block_align(&self) -> u16 {
+        self.channels * (self.bits_per_sample / 8)
+    }
+}
+
+// the RIFF size field and the data size field, from the start of the file
+const RIFF_SIZE_OFFSET: u64 = 4;
+const DATA_SIZE_OFFSET: u64 = 40;
+
+/// Writes a canonical 44 byte header wav file: the header first with the sizes left at zero,
+/// then the samples as they come with [`WavWriter::write_samples`], then the real sizes and
+/// the padding byte of an odd sized data chunk (24-bit with an odd number of samples) once [`WavWriter::finish`] is called.
+/// Chunks the writer knows nothing about, such as `bext` or `iXML` read from another file,
+/// can follow the samples with [`WavWriter::write_chunk`].
+///
+/// ```no_run
+/// use wave::writer::{WavSpec, WavWriter};
+///
+/// let spec = WavSpec { sample_rate: 48000, channels: 2, bits_per_sample: 24 };
+/// let mut writer = WavWriter::create("out.wav", spec)?;
+/// writer.write_samples(&[0, 0, 1000, -1000])?;
+/// writer.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    spec: WavSpec,
+    // bytes of samples written so far
+    data_size: u64,
+    // bytes of the chunks after the data, None until the first one ends the samples
+    trailing: Option<u64>,
+}
+
+impl WavWriter<BufWriter<fs::File>> {
+    /// Creates the file at `path`, replacing whatever was there.
+    pub fn create(path: &str, spec: WavSpec) -> Result<Self, Box<dyn error::Error + 'static>> {
+        let file = fs::File::create(path).map_err(|e| format!("{path}: {e}"))?;
+        Self::new(BufWriter::new(file), spec)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Writes the header to `writer`, which should be at the start of where the file goes.
+    pub fn new(mut writer: W, spec: WavSpec) -> Result<Self, Box<dyn error::Error + 'static>> {
+        if !matches!(spec.bits_per_sample, 16 | 24 | 32) {
+            return Err(format!("can't write {}-bit samples", spec.bits_per_sample).into());
+        }
+        if spec.channels == 0 || spec.sample_rate == 0 {
+            return Err("a wav file needs at least one channel and a sample rate".into());
+        }
+        let block_align = spec.block_align();
+        let mut header = Vec::with_capacity(44);
+        header.extend(b"RIFF");
+        header.extend(0u32.to_le_bytes());
+        header.extend(b"WAVE");
+        header.extend(b"fmt ");
+        header.extend(16u32.to_le_bytes());
+        header.extend(1u16.to_le_bytes());
+        header.extend(spec.channels.to_le_bytes());
+        header.extend(spec.sample_rate.to_le_bytes());
+        header.extend((spec.sample_rate * block_align as u32).to_le_bytes());
+        header.extend(block_align.to_le_bytes());
+        header.extend(spec.bits_per_sample.to_le_bytes());
+        header.extend(b"data");
+        header.extend(0u32.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer,
+            spec,
+            data_size: 0,
+            trailing: None,
+        })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Appends interleaved 16-bit samples, widened to the bit depth of the spec.
+    pub fn write_samples(
+        &mut self,
+        samples: &[i16],
+    ) -> Result<(), Box<dyn error::Error + 'static>> {
+        if self.trailing.is_some() {
+            return Err("samples can't follow the chunks after the data".into());
+        }
+        let bytes = (self.spec.bits_per_sample / 8) as usize;
+        let mut encoded = Vec::with_capacity(samples.len() * bytes);
+        for &sample in samples {
+            match self.spec.bits_per_sample {
+                16 => encoded.extend(sample.to_le_bytes()),
+                24 => encoded.extend(&((sample as i32) << 8).to_le_bytes()[..3]),
+                _ => encoded.extend(((sample as i32) << 16).to_le_bytes()),
+            }
+        }
+        // the sizes are 32-bit, past that it would have to be an RF64 file
+        if self.data_size + encoded.len() as u64 > u32::MAX as u64 - 36 {
+            return Err("too many samples for a RIFF file".into());
+        }
+        self.writer.write_all(&encoded)?;
+        self.data_size += encoded.len() as u64;
+        Ok(())
+    }
+
+    /// Appends a chunk as it is after the samples, padded to an even size. No more samples
+    /// can be written once one is.
+    pub fn write_chunk(
+        &mut self,
+        id: [u8; 4],
+        data: &[u8],
+    ) -> Result<(), Box<dyn error::Error + 'static>> {
+        if matches!(&id, b"fmt " | b"data" | b"ds64") {
+            return Err(
+                format!("the writer writes the `{}` chunk itself", id.escape_ascii()).into(),
+            );
+        }
+        let padding = data.len() as u64 % 2;
+        let trailing = self.end_data()? + 8 + data.len() as u64 + padding;
+        if self.data_size + trailing > u32::MAX as u64 - 36 {
+            return Err("too many chunks for a RIFF file".into());
+        }
+        self.writer.write_all(&id)?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(data)?;
+        if padding == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        self.trailing = Some(trailing);
+        Ok(())
+    }
+
+    /// Pads the data chunk to an even size and fills in the sizes of the header, giving back
+    /// the writer positioned at the end of the file.
+    pub fn finish(mut self) -> Result<W, Box<dyn error::Error + 'static>> {
+        let trailing = self.end_data()?;
+        let padding = self.data_size % 2;
+        self.patch(
+            RIFF_SIZE_OFFSET,
+            (36 + self.data_size + padding + trailing) as u32,
+        )?;
+        self.patch(DATA_SIZE_OFFSET, self.data_size as u32)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    // the data chunk ends in whole frames and is padded once, giving the bytes of the chunks
+    // after it so far
+    fn end_data(&mut self) -> Result<u64, Box<dyn error::Error + 'static>> {
+        if let Some(trailing) = self.trailing {
+            return Ok(trailing);
+        }
+        let frame = self.spec.block_align() as u64;
+        if !self.data_size.is_multiple_of(frame) {
+            return Err(format!(
+                "{} samples aren't whole frames of {} channels",
+                self.data_size / (self.spec.bits_per_sample / 8) as u64,
+                self.spec.channels
+            )
+            .into());
+        }
+        if self.data_size % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        self.trailing = Some(0);
+        Ok(0)
+    }
+
+    fn patch(&mut self, offset: u64, value: u32) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(offset))?;
+        self.writer.write_all(&value.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::conformance::{decode, signal, wav};
+    use crate::riff;
+
+    #[test]
+    fn written_files_read_back_at_every_bit_depth() {
+        let samples = signal(2, 22050, 999);
+        for bits_per_sample in [16, 24, 32] {
+            let spec = WavSpec {
+                sample_rate: 22050,
+                channels: 2,
+                bits_per_sample,
+            };
+            let mut writer = WavWriter::new(Cursor::new(Vec::new()), spec).unwrap();
+            writer.write_samples(&samples).unwrap();
+            let bytes = writer.finish().unwrap().into_inner();
+            assert_eq!(riff::read_chunks(&bytes).unwrap().len(), 2);
+            let wav = decode(&bytes).unwrap();
+            assert_eq!(wav.header.bits_per_sample, bits_per_sample);
+            assert_eq!(wav.data.len(), samples.len());
+            // exact up to the dither of going back down to 16 bits
+            for (&read, &written) in wav.data.iter().zip(&samples) {
+                assert!((read as i32 - written as i32).abs() <= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_sized_data_is_padded() {
+        let spec = WavSpec {
+            sample_rate: 8000,
+            channels: 1,
+            bits_per_sample: 24,
+        };
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), spec).unwrap();
+        writer.write_samples(&[0, 1000, -1000]).unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+        assert_eq!(bytes.len(), 44 + 10);
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 10);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 9);
+        assert_eq!(decode(&bytes).unwrap().data.len(), 3);
+    }
+
+    #[test]
+    fn chunks_read_with_the_samples_are_written_back() {
+        let samples = signal(1, 8000, 100);
+        let bytes = wav(
+            1,
+            8000,
+            &samples,
+            &[
+                (*b"bext", vec![7; 602]),
+                (*b"iXML", b"<BWFXML/>".to_vec()),
+                (*b"wxyz", vec![1, 2, 3]),
+            ],
+        );
+        let wav = decode(&bytes).unwrap();
+        let spec = WavSpec {
+            sample_rate: 8000,
+            channels: 1,
+            bits_per_sample: 16,
+        };
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), spec).unwrap();
+        writer.write_samples(&wav.data).unwrap();
+        for chunk in &wav.metadata.chunks {
+            writer.write_chunk(chunk.id, &chunk.data).unwrap();
+        }
+        assert!(writer.write_samples(&[0]).is_err());
+        assert_eq!(writer.finish().unwrap().into_inner(), bytes);
+    }
+}
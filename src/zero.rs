@@ -0,0 +1,63 @@
+use crate::WavFile;
+
+// how far a position may move to reach a zero crossing, about half a period of 50 Hz
+const SEARCH_SECONDS: f64 = 0.01;
+
+// the frame near `frame` where playback can start or loop without a click: the one closest to
+// a zero crossing on every channel at once, which is where the loudest channel is quietest.
+// Ties go to the nearest frame
+pub fn nearest(wav: &WavFile, frame: usize) -> usize {
+    let channels = wav.header.num_channels.max(1) as usize;
+    let frames = wav.data.len() / channels;
+    if frames == 0 {
+        return frame;
+    }
+    let frame = frame.min(frames - 1);
+    let radius = (SEARCH_SECONDS * wav.header.sample_rate as f64) as usize;
+
+    (0..=radius)
+        .flat_map(|distance| [frame.checked_sub(distance), Some(frame + distance)])
+        .flatten()
+        .filter(|&candidate| candidate < frames)
+        .min_by_key(|&candidate| {
+            wav.data[candidate * channels..(candidate + 1) * channels]
+                .iter()
+                .map(|sample| sample.unsigned_abs())
+                .max()
+        })
+        .unwrap_or(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wave::Header;
+
+    fn stereo(data: Vec<i16>) -> WavFile {
+        WavFile {
+            header: Header {
+                num_channels: 2,
+                sample_rate: 1000,
+                bits_per_sample: 16,
+                ..Header::default()
+            },
+            data: data.into(),
+            ..WavFile::default()
+        }
+    }
+
+    #[test]
+    fn settles_where_every_channel_is_near_zero() {
+        // the left channel crosses zero at frame 1, the right one at frame 3, frame 2 is
+        // closest to both
+        let wav = stereo(vec![-900, 800, 1, 600, 90, 70, 700, -2, 900, -600]);
+        assert_eq!(nearest(&wav, 0), 2);
+        assert_eq!(nearest(&wav, 4), 2);
+    }
+
+    #[test]
+    fn ties_go_to_the_nearest_frame() {
+        let wav = stereo(vec![0, 0, 500, 500, 500, 500, 0, 0]);
+        assert_eq!(nearest(&wav, 2), 3);
+    }
+}
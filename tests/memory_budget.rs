@@ -0,0 +1,63 @@
+// what opening a file over the memory budget costs, in a test binary of its own because it
+// counts every allocation the process makes
+
+#![cfg(feature = "spill")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use wave::{WavFile, WavSpec, WavWriter};
+
+// the bytes allocated right now and the most there were at once
+static LIVE: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+
+struct Tracking;
+
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live =
+            LIVE.fetch_add(layout.size() as isize, Ordering::Relaxed) + layout.size() as isize;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Tracking = Tracking;
+
+#[test]
+fn a_file_over_the_memory_budget_opens_and_downmixes_within_it() {
+    // five minutes of stereo at 8 kHz, almost 10 MB of samples against a 1 MB budget
+    let budget = 1 << 20;
+    let spec = WavSpec {
+        sample_rate: 8000,
+        channels: 2,
+        bits_per_sample: 16,
+    };
+    let mut writer = WavWriter::new(Cursor::new(Vec::new()), spec).unwrap();
+    let samples: Vec<i16> = (0..8000 * 300 * 2)
+        .map(|i| (i % 200 * 100) as i16)
+        .collect();
+    writer.write_samples(&samples).unwrap();
+    drop(samples);
+    let bytes = writer.finish().unwrap().into_inner();
+
+    let before = LIVE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let wav = WavFile::from_reader_with_budget(&bytes[..], budget).unwrap();
+    // a second at a time, the way the analyses go through a file
+    let blocks = wav.mono_blocks(8000).count();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+
+    assert!(!wav.data.in_memory());
+    assert_eq!(blocks, 300);
+    // the budget is for the samples, decoding and downmixing take a block on top
+    assert!(peak < 2 * budget as isize, "{peak} bytes at once");
+}